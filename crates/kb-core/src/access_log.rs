@@ -18,6 +18,9 @@ pub struct AccessLogEntry {
     pub query: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub entry_id: Option<String>,
+    /// IDs of the records returned by a retrieval (query, search).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_ids: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result_count: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -104,6 +107,7 @@ mod tests {
             domain: domain.map(|s| s.to_string()),
             query: None,
             entry_id: None,
+            entry_ids: None,
             result_count: None,
             signal: None,
         }
@@ -0,0 +1,631 @@
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::diff::changed_fields;
+use crate::error::Result;
+use crate::filter;
+use crate::format::get_record_summary;
+use crate::id::generate_record_id;
+use crate::interchange::{ImportAction, ImportChange, import_change};
+use crate::types::{Classification, Evidence, ExpertiseRecord, RecordType};
+
+/// Front-matter key linking an ADR file to its decision record.
+const KB_ID_KEY: &str = "kb-id";
+
+/// Numbering before an ADR title: "ADR-7: ", "0007 ", "2. ".
+static TITLE_PREFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?i:adr)?[-\s]?\d+[.:]?\s+").expect("valid regex"));
+
+/// `Status:` / `Date:` lines before the first section (MADR 2, Nygard).
+static META_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[*-]?\s*(?i:(status|date|deciders|decision-makers))\s*:\s*(.*)$")
+        .expect("valid regex")
+});
+
+/// An Architecture Decision Record parsed from a MADR or Nygard-style file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Adr {
+    pub file_name: String,
+    pub number: Option<u32>,
+    pub title: String,
+    pub status: Option<String>,
+    pub date: Option<String>,
+    pub rationale: String,
+    pub kb_id: Option<String>,
+    /// The file contents, with line endings normalized.
+    pub source: String,
+}
+
+/// A file `kb export adr` writes, or would write.
+#[derive(Debug, Clone)]
+pub struct AdrWrite {
+    pub file_name: String,
+    pub contents: String,
+    pub id: String,
+    pub title: String,
+    /// False when an existing ADR file is rewritten.
+    pub created: bool,
+    /// Why an existing ADR cannot be updated in place; the file is left alone
+    /// and `contents` is empty.
+    pub conflict: Option<String>,
+}
+
+fn leading_number(file_name: &str) -> Option<u32> {
+    let digits: String = file_name.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// Split optional YAML front-matter from a Markdown document.
+fn split_front_matter(text: &str) -> (Option<serde_yaml::Mapping>, &str) {
+    let Some((yaml, body)) = text
+        .strip_prefix("---\n")
+        .and_then(|rest| rest.split_once("\n---\n"))
+    else {
+        return (None, text);
+    };
+    match serde_yaml::from_str(yaml) {
+        Ok(front) => (Some(front), body),
+        Err(_) => (None, text),
+    }
+}
+
+/// Index of the decision section among lowercased `## ` headings: "Decision
+/// Outcome" or "Decision", else the first one starting with "decision".
+fn decision_section<'a>(headings: impl Iterator<Item = &'a str> + Clone) -> Option<usize> {
+    headings
+        .clone()
+        .position(|h| h == "decision outcome" || h == "decision")
+        .or_else(|| headings.into_iter().position(|h| h.starts_with("decision")))
+}
+
+fn front_str(front: Option<&serde_yaml::Mapping>, key: &str) -> Option<String> {
+    match front?.get(key)? {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Parse an ADR file. Returns `None` when the document has no `# Title`.
+///
+/// Status and date come from front-matter (MADR 3), `* Status:` / `Date:`
+/// lines (MADR 2, Nygard) or a `## Status` section. The rationale is the
+/// decision section ("Decision", "Decision Outcome"), falling back to the
+/// context section and then to the text under the title.
+pub fn parse_adr(file_name: &str, text: &str) -> Option<Adr> {
+    let text = text.replace("\r\n", "\n");
+    let (front, body) = split_front_matter(&text);
+    let front = front.as_ref();
+
+    let mut title = None;
+    let mut preamble = Vec::new();
+    let mut sections: Vec<(String, Vec<&str>)> = Vec::new();
+    let mut status = front_str(front, "status");
+    let mut date = front_str(front, "date");
+
+    for line in body.lines() {
+        if title.is_none() {
+            if let Some(t) = line.strip_prefix("# ") {
+                title = Some(TITLE_PREFIX.replace(t.trim(), "").to_string());
+            }
+            continue;
+        }
+        if let Some(heading) = line.strip_prefix("## ") {
+            sections.push((heading.trim().to_lowercase(), Vec::new()));
+            continue;
+        }
+        match sections.last_mut() {
+            Some((_, lines)) => lines.push(line),
+            None => {
+                if let Some(caps) = META_LINE.captures(line) {
+                    let value = caps[2].trim().to_string();
+                    match caps[1].to_lowercase().as_str() {
+                        "status" => status = status.or(Some(value)),
+                        "date" => date = date.or(Some(value)),
+                        _ => {}
+                    }
+                } else {
+                    preamble.push(line);
+                }
+            }
+        }
+    }
+
+    let section_text =
+        |idx: usize| Some(sections[idx].1.join("\n").trim().to_string()).filter(|s| !s.is_empty());
+    let section = |prefix: &str| {
+        sections
+            .iter()
+            .position(|(h, _)| h.starts_with(prefix))
+            .and_then(section_text)
+    };
+    if status.is_none() {
+        status = section("status").and_then(|s| s.lines().next().map(str::to_string));
+    }
+    let rationale = decision_section(sections.iter().map(|(h, _)| h.as_str()))
+        .and_then(section_text)
+        .or_else(|| section("context"))
+        .unwrap_or_else(|| preamble.join("\n").trim().to_string());
+
+    Some(Adr {
+        file_name: file_name.to_string(),
+        number: leading_number(file_name),
+        title: title?,
+        status,
+        date: date.filter(|d| !d.is_empty()),
+        rationale,
+        kb_id: front_str(front, KB_ID_KEY),
+        source: text.clone(),
+    })
+}
+
+/// Read every numbered `*.md` ADR in a directory, ordered by file name.
+/// Templates, READMEs and indexes (no leading number) are skipped.
+pub fn read_adr_dir(dir: &Path) -> Result<Vec<Adr>> {
+    let mut names: Vec<String> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|n| n.ends_with(".md") && leading_number(n).is_some())
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    names.sort();
+
+    let mut adrs = Vec::new();
+    for name in names {
+        let text = fs::read_to_string(dir.join(&name))?;
+        adrs.extend(parse_adr(&name, &text));
+    }
+    Ok(adrs)
+}
+
+fn slug(title: &str) -> String {
+    let mut out = String::new();
+    for c in title.to_lowercase().chars() {
+        if c.is_alphanumeric() {
+            out.push(c);
+        } else if !out.is_empty() && !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    let mut slug: String = out.trim_end_matches('-').chars().take(60).collect();
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+    if slug.is_empty() {
+        slug.push_str("decision");
+    }
+    slug
+}
+
+/// File name for a new ADR: `0007-use-jsonl-storage.md`.
+pub fn adr_file_name(number: u32, title: &str) -> String {
+    format!("{number:04}-{}.md", slug(title))
+}
+
+/// Render a decision record as a MADR file. Returns `None` for other types.
+pub fn render_adr(record: &ExpertiseRecord, status: Option<&str>) -> Option<String> {
+    let ExpertiseRecord::Decision {
+        id,
+        title,
+        rationale,
+        date,
+        ..
+    } = record
+    else {
+        return None;
+    };
+
+    let mut front = serde_yaml::Mapping::new();
+    front.insert("status".into(), status.unwrap_or("accepted").into());
+    if let Some(date) = date {
+        front.insert("date".into(), date.as_str().into());
+    }
+    if let Some(id) = id {
+        front.insert(KB_ID_KEY.into(), id.as_str().into());
+    }
+    let front = serde_yaml::to_string(&front).unwrap_or_default();
+
+    Some(format!(
+        "---\n{front}---\n# {title}\n\n## Decision Outcome\n\n{}\n",
+        rationale.trim()
+    ))
+}
+
+/// Update an existing ADR from a decision record, keeping everything but the
+/// front-matter (`date`, `kb-id`; `status` is left as is), the title heading
+/// and the decision section verbatim, so Context, Consequences and
+/// Considered Options survive. Errors with the reason when the file cannot
+/// be updated that way.
+fn update_adr(adr: &Adr, record: &ExpertiseRecord) -> std::result::Result<String, String> {
+    let ExpertiseRecord::Decision {
+        id,
+        title,
+        rationale,
+        date,
+        ..
+    } = record
+    else {
+        return Err("not a decision record".to_string());
+    };
+
+    let (front, body) = split_front_matter(&adr.source);
+    if front.is_none() && adr.source.starts_with("---\n") {
+        return Err("front-matter is not valid YAML".to_string());
+    }
+    let mut front = front.unwrap_or_default();
+    if let Some(date) = date {
+        front.insert("date".into(), date.as_str().into());
+    }
+    if let Some(id) = id {
+        front.insert(KB_ID_KEY.into(), id.as_str().into());
+    }
+    let front = if front.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "---\n{}---\n",
+            serde_yaml::to_string(&front).unwrap_or_default()
+        )
+    };
+
+    let mut lines: Vec<String> = body.lines().map(str::to_string).collect();
+    let Some(title_idx) = lines.iter().position(|l| l.starts_with("# ")) else {
+        return Err("no title heading".to_string());
+    };
+    let old_title = lines[title_idx][2..].trim().to_string();
+    let prefix = TITLE_PREFIX.find(&old_title).map_or("", |m| m.as_str());
+    lines[title_idx] = format!("# {prefix}{title}");
+
+    let headings: Vec<(usize, String)> = lines
+        .iter()
+        .enumerate()
+        .skip(title_idx + 1)
+        .filter_map(|(i, l)| l.strip_prefix("## ").map(|h| (i, h.trim().to_lowercase())))
+        .collect();
+    let Some(section) = decision_section(headings.iter().map(|(_, h)| h.as_str())) else {
+        return Err("no Decision section to update".to_string());
+    };
+    let start = headings[section].0 + 1;
+    let end = headings.get(section + 1).map_or(lines.len(), |(i, _)| *i);
+    let mut replacement = vec![String::new(), rationale.trim().to_string()];
+    if end < lines.len() {
+        replacement.push(String::new());
+    }
+    lines.splice(start..end, replacement);
+
+    let contents = format!("{front}{}\n", lines.join("\n"));
+    match parse_adr(&adr.file_name, &contents) {
+        Some(updated) if adr_matches(&updated, record) => Ok(contents),
+        _ => Err("the updated decision section would not read back unchanged".to_string()),
+    }
+}
+
+/// True when the ADR already says what the record says.
+fn adr_matches(adr: &Adr, record: &ExpertiseRecord) -> bool {
+    match record {
+        ExpertiseRecord::Decision {
+            title,
+            rationale,
+            date,
+            ..
+        } => adr.title == *title && adr.rationale == rationale.trim() && adr.date == *date,
+        _ => false,
+    }
+}
+
+/// Index of the record an ADR belongs to: by `kb-id`, then by evidence
+/// pointing at the ADR file, then by title.
+fn find_record(records: &[ExpertiseRecord], adr: &Adr, file: &str) -> Option<usize> {
+    if let Some(idx) = adr
+        .kb_id
+        .as_deref()
+        .and_then(|id| records.iter().position(|r| r.id() == Some(id)))
+    {
+        return Some(idx);
+    }
+    let by_file = records.iter().position(|r| {
+        r.record_type() == RecordType::Decision
+            && r.evidence().and_then(|e| e.file.as_deref()) == Some(file)
+    });
+    by_file.or_else(|| {
+        filter::find_duplicate(records, &decision_from_adr(adr, file)).map(|(idx, _)| idx)
+    })
+}
+
+fn decision_from_adr(adr: &Adr, file: &str) -> ExpertiseRecord {
+    ExpertiseRecord::Decision {
+        id: adr.kb_id.clone(),
+        title: adr.title.clone(),
+        rationale: adr.rationale.clone(),
+        date: adr.date.clone(),
+        classification: Classification::Foundational,
+        recorded_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        updated_at: None,
+        branch: None,
+        evidence: Some(Evidence {
+            file: Some(file.to_string()),
+            ..Default::default()
+        }),
+        tags: None,
+        relates_to: None,
+        supersedes: None,
+        outcomes: None,
+    }
+}
+
+/// Point a decision record's `evidence.file` at its ADR.
+pub fn link_adr(record: &mut ExpertiseRecord, file: String) {
+    if let ExpertiseRecord::Decision { evidence, .. } = record {
+        evidence.get_or_insert_with(Evidence::default).file = Some(file);
+    }
+}
+
+/// Merge ADRs into a domain's records. `file_path` maps an ADR file name to
+/// the path stored in `evidence.file`.
+///
+/// Unmatched ADRs become foundational decision records. A matched record
+/// takes the ADR's title, rationale and date when `update_existing` is set
+/// (other fields are kept) and is linked to the ADR file either way.
+pub fn merge_adrs(
+    existing: &mut Vec<ExpertiseRecord>,
+    adrs: &[Adr],
+    file_path: impl Fn(&str) -> String,
+    update_existing: bool,
+) -> Result<Vec<ImportChange>> {
+    let mut changes = Vec::new();
+
+    for adr in adrs {
+        let file = file_path(&adr.file_name);
+        let Some(idx) = find_record(existing, adr, &file) else {
+            let mut record = decision_from_adr(adr, &file);
+            if record.id().is_none() {
+                record.set_id(generate_record_id(&record));
+            }
+            changes.push(import_change(ImportAction::Create, &record, Vec::new()));
+            existing.push(record);
+            continue;
+        };
+
+        let mut updated = existing[idx].clone();
+        if let ExpertiseRecord::Decision {
+            title,
+            rationale,
+            date,
+            ..
+        } = &mut updated
+        {
+            if update_existing {
+                *title = adr.title.clone();
+                *rationale = adr.rationale.clone();
+                if adr.date.is_some() {
+                    *date = adr.date.clone();
+                }
+            }
+        }
+        link_adr(&mut updated, file);
+
+        let fields = changed_fields(&existing[idx], &updated)?;
+        if fields.is_empty() {
+            changes.push(import_change(ImportAction::Unchanged, &updated, fields));
+        } else {
+            changes.push(import_change(ImportAction::Update, &updated, fields));
+            updated.touch();
+            existing[idx] = updated;
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Plan the ADR files for a set of decision records.
+///
+/// A record with an ADR in `adrs` (by `kb-id` or by `evidence.file` naming
+/// the file) updates it in place only when the title, rationale or date
+/// differ (see `update_adr`), or reports a conflict; other records get new
+/// files numbered after the highest existing one.
+pub fn plan_adr_export(records: &[&ExpertiseRecord], adrs: &[Adr]) -> Vec<AdrWrite> {
+    let mut next = adrs.iter().filter_map(|a| a.number).max().unwrap_or(0) + 1;
+    let mut writes = Vec::new();
+
+    for record in records {
+        let Some(id) = record.id() else {
+            continue;
+        };
+        let evidence_name = record
+            .evidence()
+            .and_then(|e| e.file.as_deref())
+            .and_then(|f| Path::new(f).file_name())
+            .and_then(|n| n.to_str());
+        let existing = adrs
+            .iter()
+            .find(|a| a.kb_id.as_deref() == Some(id))
+            .or_else(|| {
+                adrs.iter()
+                    .find(|a| Some(a.file_name.as_str()) == evidence_name)
+            });
+
+        let (file_name, contents, created, conflict) = match existing {
+            Some(adr) if adr_matches(adr, record) => continue,
+            Some(adr) => match update_adr(adr, record) {
+                Ok(contents) => (adr.file_name.clone(), contents, false, None),
+                Err(reason) => (adr.file_name.clone(), String::new(), false, Some(reason)),
+            },
+            None => {
+                let Some(contents) = render_adr(record, None) else {
+                    continue;
+                };
+                let name = adr_file_name(next, &get_record_summary(record));
+                next += 1;
+                (name, contents, true, None)
+            }
+        };
+        writes.push(AdrWrite {
+            file_name,
+            contents,
+            id: id.to_string(),
+            title: get_record_summary(record),
+            created,
+            conflict,
+        });
+    }
+
+    writes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NYGARD: &str = "# 2. Use JSONL for storage\n\nDate: 2024-03-01\n\n## Status\n\nAccepted\n\n## Context\n\nWe need line-based diffs.\n\n## Decision\n\nStore one record per line.\n\n## Consequences\n\nEasy merges.\n";
+
+    const MADR: &str = "---\nstatus: proposed\ndate: 2024-04-02\n---\n# Use minijinja for templates\n\n## Context and Problem Statement\n\nUsers want custom output.\n\n## Decision Outcome\n\nChosen option: minijinja, because it is small.\n";
+
+    fn decision(id: &str, title: &str, rationale: &str) -> ExpertiseRecord {
+        ExpertiseRecord::Decision {
+            id: Some(id.to_string()),
+            title: title.to_string(),
+            rationale: rationale.to_string(),
+            date: None,
+            classification: Classification::Tactical,
+            recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: None,
+            tags: Some(vec!["storage".to_string()]),
+            relates_to: None,
+            supersedes: None,
+            outcomes: None,
+        }
+    }
+
+    #[test]
+    fn parses_nygard_and_madr() {
+        let nygard = parse_adr("0002-use-jsonl.md", NYGARD).unwrap();
+        assert_eq!(nygard.number, Some(2));
+        assert_eq!(nygard.title, "Use JSONL for storage");
+        assert_eq!(nygard.status.as_deref(), Some("Accepted"));
+        assert_eq!(nygard.date.as_deref(), Some("2024-03-01"));
+        assert_eq!(nygard.rationale, "Store one record per line.");
+
+        let madr = parse_adr("0003-templates.md", MADR).unwrap();
+        assert_eq!(madr.title, "Use minijinja for templates");
+        assert_eq!(madr.status.as_deref(), Some("proposed"));
+        assert_eq!(madr.date.as_deref(), Some("2024-04-02"));
+        assert!(madr.rationale.starts_with("Chosen option: minijinja"));
+
+        assert!(parse_adr("0004-empty.md", "no heading here").is_none());
+    }
+
+    #[test]
+    fn rendered_adr_parses_back() {
+        let record = decision("mx-abc123", "Use JSONL", "Line-based diffs.");
+        let text = render_adr(&record, None).unwrap();
+        let adr = parse_adr("0001-use-jsonl.md", &text).unwrap();
+        assert_eq!(adr.kb_id.as_deref(), Some("mx-abc123"));
+        assert_eq!(adr.status.as_deref(), Some("accepted"));
+        assert!(adr_matches(&adr, &record));
+        assert_eq!(adr_file_name(12, "Use JSONL (v2)!"), "0012-use-jsonl-v2.md");
+    }
+
+    #[test]
+    fn merge_links_by_file_and_keeps_other_fields() {
+        let mut records = vec![decision("mx-000001", "Old title", "Old")];
+        if let ExpertiseRecord::Decision { evidence, .. } = &mut records[0] {
+            *evidence = Some(Evidence {
+                file: Some("docs/adr/0002-use-jsonl.md".to_string()),
+                ..Default::default()
+            });
+        }
+        let adrs = vec![
+            parse_adr("0002-use-jsonl.md", NYGARD).unwrap(),
+            parse_adr("0003-templates.md", MADR).unwrap(),
+        ];
+        let path = |name: &str| format!("docs/adr/{name}");
+
+        let changes = merge_adrs(&mut records, &adrs, path, true).unwrap();
+        assert_eq!(changes[0].action, ImportAction::Update);
+        assert_eq!(changes[1].action, ImportAction::Create);
+        assert_eq!(records.len(), 2);
+        assert_eq!(get_record_summary(&records[0]), "Use JSONL for storage");
+        assert_eq!(records[0].tags().unwrap(), ["storage"]);
+        assert_eq!(records[0].classification(), Classification::Tactical);
+        assert_eq!(
+            records[1].evidence().unwrap().file.as_deref(),
+            Some("docs/adr/0003-templates.md")
+        );
+
+        let again = merge_adrs(&mut records, &adrs, path, true).unwrap();
+        assert!(again.iter().all(|c| c.action == ImportAction::Unchanged));
+    }
+
+    #[test]
+    fn export_numbers_new_files_and_skips_in_sync_ones() {
+        let synced = decision("mx-000001", "Use JSONL", "Line-based diffs.");
+        let changed = decision("mx-000002", "Use YAML config", "Readable.");
+        let fresh = decision("mx-000003", "Use clap", "Derive API.");
+        let adrs = vec![
+            parse_adr("0004-use-jsonl.md", &render_adr(&synced, None).unwrap()).unwrap(),
+            parse_adr(
+                "0007-config.md",
+                "---\nkb-id: mx-000002\nstatus: superseded\n---\n# Use YAML config\n\n## Decision\n\nOld text.\n",
+            )
+            .unwrap(),
+        ];
+
+        let writes = plan_adr_export(&[&synced, &changed, &fresh], &adrs);
+        assert_eq!(writes.len(), 2);
+        assert_eq!(writes[0].file_name, "0007-config.md");
+        assert!(!writes[0].created);
+        assert!(writes[0].contents.contains("status: superseded"));
+        assert_eq!(writes[1].file_name, "0008-use-clap.md");
+        assert!(writes[1].created);
+    }
+
+    #[test]
+    fn export_updates_existing_adr_in_place() {
+        let mut record = decision(
+            "mx-000002",
+            "Use JSONL",
+            "Store one record per line, sorted.",
+        );
+        if let ExpertiseRecord::Decision { evidence, date, .. } = &mut record {
+            *evidence = Some(Evidence {
+                file: Some("docs/adr/0002-use-jsonl.md".to_string()),
+                ..Default::default()
+            });
+            *date = Some("2024-05-01".to_string());
+        }
+        let adrs = vec![parse_adr("0002-use-jsonl.md", NYGARD).unwrap()];
+
+        let writes = plan_adr_export(&[&record], &adrs);
+        assert_eq!(writes.len(), 1);
+        assert!(writes[0].conflict.is_none());
+        let text = &writes[0].contents;
+        assert!(text.starts_with("---\ndate: 2024-05-01\nkb-id: mx-000002\n---\n# 2. Use JSONL\n"));
+        assert!(text.contains("## Context\n\nWe need line-based diffs.\n"));
+        assert!(text.contains(
+            "## Decision\n\nStore one record per line, sorted.\n\n## Consequences\n\nEasy merges.\n"
+        ));
+        let adr = parse_adr("0002-use-jsonl.md", text).unwrap();
+        assert_eq!(adr.status.as_deref(), Some("Accepted"));
+        assert!(adr_matches(&adr, &record));
+
+        // Without a decision section the file is left alone
+        let context_only = "# 3. Use clap\n\n## Context\n\nWe need a CLI.\n";
+        let adrs = vec![parse_adr("0003-use-clap.md", context_only).unwrap()];
+        let mut record = decision("mx-000003", "Use clap", "Derive API.");
+        link_adr(&mut record, "docs/adr/0003-use-clap.md".to_string());
+        let writes = plan_adr_export(&[&record], &adrs);
+        assert_eq!(
+            writes[0].conflict.as_deref(),
+            Some("no Decision section to update")
+        );
+        assert!(writes[0].contents.is_empty());
+    }
+}
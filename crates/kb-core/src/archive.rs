@@ -0,0 +1,214 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{KbError, Result};
+use crate::types::ExpertiseRecord;
+use crate::{config, lock, storage};
+
+/// Why, when and by what a record was removed from its domain file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Removal {
+    pub reason: String,
+    /// The command that removed the record, e.g. `delete`, `prune`, `compact`.
+    pub command: String,
+    pub removed_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+impl Removal {
+    pub fn new(command: &str, reason: impl Into<String>, session_id: Option<&str>) -> Self {
+        Self {
+            reason: reason.into(),
+            command: command.to_string(),
+            removed_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            session_id: session_id.map(|s| s.to_string()),
+        }
+    }
+}
+
+/// A line of `.kb/archive/<domain>.jsonl`: a removed record and its removal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedRecord {
+    pub record: ExpertiseRecord,
+    pub removal: Removal,
+}
+
+/// Read an archive file. Returns an empty vec if the file doesn't exist.
+pub fn read_archive(file_path: &Path) -> Result<Vec<ArchivedRecord>> {
+    let content = match fs::read_to_string(file_path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+/// Atomically rewrite an archive file.
+pub fn write_archive(file_path: &Path, entries: &[ArchivedRecord]) -> Result<()> {
+    let dir = file_path.parent().unwrap_or(Path::new("."));
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    for entry in entries {
+        writeln!(tmp, "{}", serde_json::to_string(entry)?)?;
+    }
+    tmp.flush()?;
+    tmp.persist(file_path).map_err(std::io::Error::other)?;
+    Ok(())
+}
+
+/// Append removed records to the archive of `domain`.
+pub fn archive_records(cwd: &Path, domain: &str, entries: &[ArchivedRecord]) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let file_path = config::get_archive_path(domain, cwd)?;
+    fs::create_dir_all(config::get_archive_dir(cwd))?;
+    lock::with_file_lock(&file_path, || {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&file_path)?;
+        for entry in entries {
+            let mut line = serde_json::to_string(entry)?;
+            line.push('\n');
+            file.write_all(line.as_bytes())?;
+        }
+        Ok(())
+    })
+}
+
+/// Index of the latest archive entry for `identifier` (full ID, hash or
+/// prefix, as in `kb delete`). A record archived more than once resolves to
+/// its most recent removal.
+pub fn resolve_archived(entries: &[ArchivedRecord], identifier: &str) -> Result<usize> {
+    let hash = identifier.strip_prefix("mx-").unwrap_or(identifier);
+    let full_id = format!("mx-{hash}");
+    let latest = |id: &str| entries.iter().rposition(|e| e.record.id() == Some(id));
+
+    if let Some(i) = latest(&full_id) {
+        return Ok(i);
+    }
+    let mut ids: Vec<&str> = entries
+        .iter()
+        .filter_map(|e| e.record.id())
+        .filter(|id| id.starts_with(&full_id))
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    match ids.as_slice() {
+        [id] => Ok(latest(id).expect("matched ID is archived")),
+        [] => Err(KbError::RecordNotFound(identifier.to_string())),
+        _ => Err(KbError::AmbiguousId {
+            id: identifier.to_string(),
+            count: ids.len(),
+            ids: ids.join(", "),
+        }),
+    }
+}
+
+/// Move an archived record back into its domain file and drop it from the
+/// archive. Fails if a record with the same ID is active again.
+pub fn restore_record(cwd: &Path, domain: &str, identifier: &str) -> Result<ArchivedRecord> {
+    let archive_path = config::get_archive_path(domain, cwd)?;
+    let file_path = config::get_expertise_path(domain, cwd)?;
+
+    // Domain file first, then archive: the order removals lock them in
+    lock::with_file_lock(&file_path, || {
+        lock::with_file_lock(&archive_path, || {
+            let mut entries = read_archive(&archive_path)?;
+            let idx = resolve_archived(&entries, identifier)?;
+            let restored = entries.remove(idx);
+            let id = restored.record.id().unwrap_or_default();
+
+            let mut records = storage::read_expertise_file(&file_path)?;
+            if records.iter().any(|r| r.id() == Some(id)) {
+                return Err(KbError::ValidationError(format!(
+                    "record {id} already exists in {domain}"
+                )));
+            }
+            records.push(restored.record.clone());
+            storage::write_expertise_file(&file_path, &mut records)?;
+
+            write_archive(&archive_path, &entries)?;
+            Ok(restored)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Classification;
+
+    fn archived(id: &str, reason: &str) -> ArchivedRecord {
+        ArchivedRecord {
+            record: ExpertiseRecord::Convention {
+                id: Some(id.to_string()),
+                content: format!("Convention {id}"),
+                classification: Classification::Tactical,
+                recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+                updated_at: None,
+                branch: None,
+                evidence: None,
+                tags: None,
+                relates_to: None,
+                supersedes: None,
+                outcomes: None,
+            },
+            removal: Removal::new("delete", reason, None),
+        }
+    }
+
+    #[test]
+    fn resolve_archived_prefers_latest_removal() {
+        let entries = vec![
+            archived("mx-abc123", "first"),
+            archived("mx-abd456", "other"),
+            archived("mx-abc123", "second"),
+        ];
+        assert_eq!(resolve_archived(&entries, "abc").unwrap(), 2);
+        assert_eq!(resolve_archived(&entries, "mx-abd456").unwrap(), 1);
+        assert!(matches!(
+            resolve_archived(&entries, "ab"),
+            Err(KbError::AmbiguousId { count: 2, .. })
+        ));
+        assert!(matches!(
+            resolve_archived(&entries, "fff"),
+            Err(KbError::RecordNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn archive_and_restore_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(config::get_expertise_dir(dir.path())).unwrap();
+
+        archive_records(dir.path(), "backend", &[archived("mx-abc123", "deleted")]).unwrap();
+        let path = config::get_archive_path("backend", dir.path()).unwrap();
+        assert_eq!(read_archive(&path).unwrap().len(), 1);
+
+        let restored = restore_record(dir.path(), "backend", "abc").unwrap();
+        assert_eq!(restored.removal.reason, "deleted");
+        assert!(read_archive(&path).unwrap().is_empty());
+        let records = storage::read_expertise_file(
+            &config::get_expertise_path("backend", dir.path()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(records[0].id(), Some("mx-abc123"));
+
+        // Restoring over an active record is refused
+        archive_records(dir.path(), "backend", &[archived("mx-abc123", "again")]).unwrap();
+        assert!(matches!(
+            restore_record(dir.path(), "backend", "abc123"),
+            Err(KbError::ValidationError(_))
+        ));
+        assert_eq!(read_archive(&path).unwrap().len(), 1);
+    }
+}
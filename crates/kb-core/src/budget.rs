@@ -1,5 +1,11 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::format::format_overflow_line;
 use crate::scoring::compute_confirmation_score;
-use crate::types::{Classification, ExpertiseRecord, RecordType};
+use crate::tokenizer::{self, TokenEstimator};
+use crate::types::{BudgetPolicy, Classification, ExpertiseRecord, RecordType};
 
 pub const DEFAULT_BUDGET: usize = 4000;
 
@@ -25,15 +31,93 @@ pub struct DomainRecords<'a> {
     pub records: Vec<&'a ExpertiseRecord>,
 }
 
+/// Why a record was left out of the budgeted output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropReason {
+    /// The global token budget was exhausted.
+    Budget,
+    /// The domain reached its `max_share` of the budget.
+    DomainMaxShare,
+    /// The domain already holds `type_caps[type]` records of this type.
+    TypeCap,
+}
+
+impl DropReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Budget => "budget",
+            Self::DomainMaxShare => "domain_max_share",
+            Self::TypeCap => "type_cap",
+        }
+    }
+}
+
+pub struct DroppedRecord<'a> {
+    pub domain: String,
+    pub record: &'a ExpertiseRecord,
+    pub reason: DropReason,
+}
+
 pub struct BudgetResult<'a> {
     pub kept: Vec<DomainRecords<'a>>,
     pub dropped_count: usize,
     pub dropped_domain_count: usize,
+    /// Dropped records in priority order, with the quota that excluded each.
+    pub dropped: Vec<DroppedRecord<'a>>,
+    /// Estimated tokens spent on kept records.
+    pub used_tokens: usize,
 }
 
-/// Estimate token count from character count (chars / 4).
+/// Estimate token count with the built-in heuristic (see `tokenizer::heuristic_tokens`).
 pub fn estimate_tokens(text: &str) -> usize {
-    text.len().div_ceil(4)
+    tokenizer::heuristic_tokens(text)
+}
+
+/// Fixed per-record overhead for list markers, type labels and separators.
+const RECORD_OVERHEAD_TOKENS: usize = 4;
+
+/// Estimate the prime cost of a record from its content fields, without
+/// rendering it.
+pub fn record_cost(record: &ExpertiseRecord, estimator: &dyn TokenEstimator) -> usize {
+    let count = |text: &str| estimator.count_tokens(text);
+    let count_files =
+        |files: &Option<Vec<String>>| files.iter().flatten().map(|f| count(f)).sum::<usize>();
+    let content = match record {
+        ExpertiseRecord::Convention { content, .. } => count(content),
+        ExpertiseRecord::Pattern {
+            name,
+            description,
+            files,
+            ..
+        } => count(name) + count(description) + count_files(files),
+        ExpertiseRecord::Failure {
+            description,
+            resolution,
+            ..
+        } => count(description) + count(resolution),
+        ExpertiseRecord::Decision {
+            title, rationale, ..
+        } => count(title) + count(rationale),
+        ExpertiseRecord::Reference {
+            name,
+            description,
+            files,
+            ..
+        } => {
+            // References render their files instead of the description when present
+            let detail = if files.as_ref().is_some_and(|f| !f.is_empty()) {
+                count_files(files)
+            } else {
+                count(description)
+            };
+            count(name) + detail
+        }
+        ExpertiseRecord::Guide {
+            name, description, ..
+        } => count(name) + count(description),
+    };
+    content + RECORD_OVERHEAD_TOKENS
 }
 
 fn type_priority_index(rt: RecordType) -> usize {
@@ -60,77 +144,244 @@ fn record_sort_key(r: &ExpertiseRecord) -> (usize, usize, i64, i64) {
     (type_idx, class_idx, -(cs * 1000.0) as i64, -time)
 }
 
+/// Running totals while selecting records.
+struct Selection {
+    kept: Vec<bool>,
+    used_tokens: usize,
+    domain_used: Vec<usize>,
+    type_counts: Vec<HashMap<RecordType, usize>>,
+}
+
+impl Selection {
+    fn take(&mut self, i: usize, domain: usize, rt: RecordType, cost: usize) {
+        self.kept[i] = true;
+        self.used_tokens += cost;
+        self.domain_used[domain] += cost;
+        *self.type_counts[domain].entry(rt).or_default() += 1;
+    }
+
+    fn type_cap_reached(&self, policy: &BudgetPolicy, domain: usize, rt: RecordType) -> bool {
+        policy
+            .type_caps
+            .get(&rt)
+            .is_some_and(|cap| self.type_counts[domain].get(&rt).copied().unwrap_or(0) >= *cap)
+    }
+}
+
 /// Apply a token budget to records across multiple domains.
 /// Records are prioritized by type, classification, confirmation score, then recency.
-pub fn apply_budget<'a, F>(
+///
+/// Selection runs in three passes over the prioritized list:
+/// 1. up to `foundational_slots` foundational records per domain (ignoring shares and caps),
+/// 2. records filling each domain's `min_share` reservation,
+/// 3. everything else, subject to type caps, `max_share` and the global budget.
+///
+/// Runs in O(n log n) for the priority sort and linear time otherwise; costs
+/// come from [`record_cost`] so no record is rendered.
+pub fn apply_budget<'a>(
     domains: &[DomainRecords<'a>],
     budget: usize,
-    format_record: F,
-) -> BudgetResult<'a>
-where
-    F: Fn(&ExpertiseRecord, &str) -> String,
-{
-    // Flatten and tag
-    let mut tagged: Vec<(&str, &'a ExpertiseRecord)> = Vec::new();
-    for d in domains {
-        for r in &d.records {
-            tagged.push((d.domain.as_str(), r));
+    policy: &BudgetPolicy,
+    estimator: &dyn TokenEstimator,
+) -> BudgetResult<'a> {
+    // Flatten into (domain index, record index) pairs
+    let mut tagged: Vec<(usize, usize)> = Vec::new();
+    for (di, d) in domains.iter().enumerate() {
+        for ri in 0..d.records.len() {
+            tagged.push((di, ri));
         }
     }
 
     // Sort by priority
-    tagged.sort_by(|a, b| {
-        let ka = record_sort_key(a.1);
-        let kb = record_sort_key(b.1);
-        ka.cmp(&kb)
-    });
-
-    let total_records = tagged.len();
-    let mut used_tokens = 0;
-    let mut kept_indices: std::collections::HashSet<usize> = std::collections::HashSet::new();
-
-    for (i, (domain, record)) in tagged.iter().enumerate() {
-        let formatted = format_record(record, domain);
-        let cost = estimate_tokens(&formatted);
-        if used_tokens + cost <= budget {
-            used_tokens += cost;
-            kept_indices.insert(i);
+    tagged.sort_by_cached_key(|&(di, ri)| record_sort_key(domains[di].records[ri]));
+
+    let costs: Vec<usize> = tagged
+        .iter()
+        .map(|&(di, ri)| record_cost(domains[di].records[ri], estimator))
+        .collect();
+
+    let limits: Vec<(usize, usize)> = domains
+        .iter()
+        .map(|d| {
+            let (min, max) = policy.shares_for(&d.domain);
+            (
+                (min * budget as f64).round() as usize,
+                (max * budget as f64).round() as usize,
+            )
+        })
+        .collect();
+
+    let record_at = |i: usize| {
+        let (di, ri) = tagged[i];
+        domains[di].records[ri]
+    };
+    let mut sel = Selection {
+        kept: vec![false; tagged.len()],
+        used_tokens: 0,
+        domain_used: vec![0; domains.len()],
+        type_counts: vec![HashMap::new(); domains.len()],
+    };
+
+    // Pass 1: guaranteed foundational slots
+    if policy.foundational_slots > 0 {
+        let mut slots_used = vec![0usize; domains.len()];
+        for (i, &(di, _)) in tagged.iter().enumerate() {
+            let record = record_at(i);
+            if record.classification() == Classification::Foundational
+                && slots_used[di] < policy.foundational_slots
+                && sel.used_tokens + costs[i] <= budget
+            {
+                slots_used[di] += 1;
+                sel.take(i, di, record.record_type(), costs[i]);
+            }
         }
     }
 
-    // Rebuild domain groups preserving original order
-    let mut result = Vec::new();
-    let mut dropped_domains = std::collections::HashSet::new();
+    // Pass 2: minimum share reservations
+    for (i, &(di, _)) in tagged.iter().enumerate() {
+        let rt = record_at(i).record_type();
+        if sel.kept[i]
+            || sel.domain_used[di] >= limits[di].0
+            || sel.domain_used[di] + costs[i] > limits[di].1
+            || sel.used_tokens + costs[i] > budget
+            || sel.type_cap_reached(policy, di, rt)
+        {
+            continue;
+        }
+        sel.take(i, di, rt, costs[i]);
+    }
 
-    for domain_rec in domains {
-        let mut kept_records = Vec::new();
-        for record in &domain_rec.records {
-            let idx = tagged
-                .iter()
-                .position(|(d, r)| *d == domain_rec.domain.as_str() && std::ptr::eq(*r, *record));
-            if let Some(idx) = idx {
-                if kept_indices.contains(&idx) {
-                    kept_records.push(*record);
-                } else {
-                    dropped_domains.insert(domain_rec.domain.clone());
-                }
+    // Pass 3: global greedy fill
+    let mut dropped: Vec<DroppedRecord<'a>> = Vec::new();
+    let mut reasons: Vec<Vec<Option<DropReason>>> = domains
+        .iter()
+        .map(|d| vec![None; d.records.len()])
+        .collect();
+    for (i, &(di, ri)) in tagged.iter().enumerate() {
+        if sel.kept[i] {
+            continue;
+        }
+        let record = record_at(i);
+        let rt = record.record_type();
+        let reason = if sel.type_cap_reached(policy, di, rt) {
+            Some(DropReason::TypeCap)
+        } else if sel.used_tokens + costs[i] > budget {
+            Some(DropReason::Budget)
+        } else if sel.domain_used[di] + costs[i] > limits[di].1 {
+            Some(DropReason::DomainMaxShare)
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => {
+                reasons[di][ri] = Some(reason);
+                dropped.push(DroppedRecord {
+                    domain: domains[di].domain.clone(),
+                    record,
+                    reason,
+                });
             }
+            None => sel.take(i, di, rt, costs[i]),
+        }
+    }
+
+    // Rebuild domain groups preserving original order
+    let mut result = Vec::new();
+    let mut dropped_domain_count = 0;
+    for (di, domain_rec) in domains.iter().enumerate() {
+        let kept_records: Vec<&'a ExpertiseRecord> = domain_rec
+            .records
+            .iter()
+            .zip(&reasons[di])
+            .filter(|(_, reason)| reason.is_none())
+            .map(|(r, _)| *r)
+            .collect();
+        if kept_records.len() < domain_rec.records.len() {
+            dropped_domain_count += 1;
         }
         if !kept_records.is_empty() {
             result.push(DomainRecords {
                 domain: domain_rec.domain.clone(),
                 records: kept_records,
             });
-        } else if !domain_rec.records.is_empty() {
-            dropped_domains.insert(domain_rec.domain.clone());
         }
     }
 
     BudgetResult {
         kept: result,
-        dropped_count: total_records - kept_indices.len(),
-        dropped_domain_count: dropped_domains.len(),
+        dropped_count: dropped.len(),
+        dropped_domain_count,
+        dropped,
+        used_tokens: sel.used_tokens,
+    }
+}
+
+/// Per-domain record and token accounting for a budgeted selection.
+#[derive(Debug, Clone, Serialize)]
+pub struct DomainBudgetStats {
+    pub domain: String,
+    pub records: usize,
+    pub kept: usize,
+    pub dropped: usize,
+    /// Estimated tokens of every record in the domain.
+    pub tokens: usize,
+    /// Estimated tokens of the kept records.
+    pub kept_tokens: usize,
+}
+
+/// Stats for each domain in `all`, counting the records that survived in `kept`
+/// (a budget result, or `all` itself when no budget applies).
+pub fn domain_stats(
+    all: &[DomainRecords<'_>],
+    kept: &[DomainRecords<'_>],
+    estimator: &dyn TokenEstimator,
+) -> Vec<DomainBudgetStats> {
+    let cost = |r: &&ExpertiseRecord| record_cost(r, estimator);
+    let kept_by_domain: HashMap<&str, &[&ExpertiseRecord]> = kept
+        .iter()
+        .map(|dr| (dr.domain.as_str(), dr.records.as_slice()))
+        .collect();
+    all.iter()
+        .map(|dr| {
+            let kept = kept_by_domain
+                .get(dr.domain.as_str())
+                .copied()
+                .unwrap_or_default();
+            DomainBudgetStats {
+                domain: dr.domain.clone(),
+                records: dr.records.len(),
+                kept: kept.len(),
+                dropped: dr.records.len() - kept.len(),
+                tokens: dr.records.iter().map(cost).sum(),
+                kept_tokens: kept.iter().map(cost).sum(),
+            }
+        })
+        .collect()
+}
+
+/// Tokens for the overflow index heading and hint line.
+const OVERFLOW_HEADER_TOKENS: usize = 24;
+
+/// Number of dropped records (a prefix of `dropped`, so highest priority first)
+/// whose index lines fit in `budget` tokens.
+pub fn fit_overflow_index(
+    dropped: &[DroppedRecord<'_>],
+    budget: usize,
+    estimator: &dyn TokenEstimator,
+) -> usize {
+    let Some(mut remaining) = budget.checked_sub(OVERFLOW_HEADER_TOKENS) else {
+        return 0;
+    };
+    let mut fitted = 0;
+    for d in dropped {
+        let cost = estimator.count_tokens(&format_overflow_line(&d.domain, d.record));
+        if cost > remaining {
+            break;
+        }
+        remaining -= cost;
+        fitted += 1;
     }
+    fitted
 }
 
 /// Format the truncation summary line.
@@ -155,6 +406,303 @@ pub fn format_budget_summary(dropped_count: usize, dropped_domain_count: usize)
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tokenizer::HeuristicEstimator;
+    use crate::types::DomainQuota;
+
+    /// A record costing exactly 14 heuristic tokens (10 content + overhead).
+    fn record(rt: RecordType, cls: Classification, n: usize) -> ExpertiseRecord {
+        let mut json = match rt {
+            RecordType::Decision => {
+                serde_json::json!({"type": "decision", "title": format!("{n:0>36}"), "rationale": "rrrr"})
+            }
+            _ => serde_json::json!({"type": "convention", "content": format!("{n:0>40}")}),
+        };
+        json["classification"] = serde_json::json!(cls.as_str());
+        json["recorded_at"] = serde_json::json!("2024-01-01T00:00:00Z");
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn run<'a>(
+        domains: &[DomainRecords<'a>],
+        budget: usize,
+        policy: &BudgetPolicy,
+    ) -> BudgetResult<'a> {
+        apply_budget(domains, budget, policy, &HeuristicEstimator)
+    }
+
+    fn kept_in(result: &BudgetResult<'_>, domain: &str) -> usize {
+        result
+            .kept
+            .iter()
+            .find(|d| d.domain == domain)
+            .map_or(0, |d| d.records.len())
+    }
+
+    #[test]
+    fn record_cost_uses_content_fields() {
+        let r = record(RecordType::Convention, Classification::Tactical, 0);
+        assert_eq!(
+            record_cost(&r, &HeuristicEstimator),
+            10 + RECORD_OVERHEAD_TOKENS
+        );
+        let r = record(RecordType::Decision, Classification::Tactical, 0);
+        assert_eq!(
+            record_cost(&r, &HeuristicEstimator),
+            10 + RECORD_OVERHEAD_TOKENS
+        );
+    }
+
+    /// Counts estimator calls, to check the work done per record.
+    #[derive(Default)]
+    struct CountingEstimator(std::cell::Cell<usize>);
+
+    impl TokenEstimator for CountingEstimator {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn count_tokens(&self, text: &str) -> usize {
+            self.0.set(self.0.get() + 1);
+            HeuristicEstimator.count_tokens(text)
+        }
+    }
+
+    #[test]
+    fn large_knowledge_base_costs_each_record_once() {
+        let records: Vec<ExpertiseRecord> = (0..50_000)
+            .map(|n| {
+                let rt = if n % 3 == 0 {
+                    RecordType::Decision
+                } else {
+                    RecordType::Convention
+                };
+                record(rt, Classification::Tactical, n)
+            })
+            .collect();
+        let domains: Vec<DomainRecords<'_>> = records
+            .chunks(5_000)
+            .enumerate()
+            .map(|(i, chunk)| DomainRecords {
+                domain: format!("d{i}"),
+                records: chunk.iter().collect(),
+            })
+            .collect();
+        let policy = BudgetPolicy {
+            max_share: Some(0.2),
+            ..Default::default()
+        };
+
+        let single = CountingEstimator::default();
+        for r in &records {
+            record_cost(r, &single);
+        }
+        let estimator = CountingEstimator::default();
+        let result = apply_budget(&domains, 100_000, &policy, &estimator);
+
+        let kept: usize = result.kept.iter().map(|d| d.records.len()).sum();
+        assert_eq!(kept + result.dropped_count, 50_000);
+        assert_eq!(kept, 100_000 / 14);
+        // Every record is costed exactly once, however the budget is shared out
+        assert_eq!(estimator.0.get(), single.0.get());
+    }
+
+    #[test]
+    fn overflow_index_fits_prefix_of_dropped() {
+        let recs: Vec<ExpertiseRecord> = (0..20)
+            .map(|n| record(RecordType::Convention, Classification::Tactical, n))
+            .collect();
+        let domains = vec![DomainRecords {
+            domain: "d".into(),
+            records: recs.iter().collect(),
+        }];
+        let result = run(&domains, 28, &BudgetPolicy::default());
+        assert_eq!(result.used_tokens, 28);
+        assert_eq!(result.dropped.len(), 18);
+
+        assert_eq!(
+            fit_overflow_index(&result.dropped, 10, &HeuristicEstimator),
+            0
+        );
+        let line = estimate_tokens(&format_overflow_line("d", result.dropped[0].record));
+        let n = fit_overflow_index(
+            &result.dropped,
+            OVERFLOW_HEADER_TOKENS + 3 * line,
+            &HeuristicEstimator,
+        );
+        assert_eq!(n, 3);
+        assert_eq!(
+            fit_overflow_index(&result.dropped, 100_000, &HeuristicEstimator),
+            18
+        );
+    }
+
+    #[test]
+    fn unconstrained_budget_is_greedy() {
+        let big: Vec<ExpertiseRecord> = (0..10)
+            .map(|n| record(RecordType::Convention, Classification::Tactical, n))
+            .collect();
+        let small = [record(RecordType::Decision, Classification::Tactical, 0)];
+        let domains = vec![
+            DomainRecords {
+                domain: "big".into(),
+                records: big.iter().collect(),
+            },
+            DomainRecords {
+                domain: "small".into(),
+                records: small.iter().collect(),
+            },
+        ];
+        // Conventions outrank decisions, so "big" takes the whole budget
+        let result = run(&domains, 70, &BudgetPolicy::default());
+        assert_eq!(kept_in(&result, "big"), 5);
+        assert_eq!(kept_in(&result, "small"), 0);
+        assert_eq!(result.dropped_count, 6);
+        assert!(
+            result
+                .dropped
+                .iter()
+                .all(|d| d.reason == DropReason::Budget)
+        );
+    }
+
+    #[test]
+    fn max_share_leaves_room_for_other_domains() {
+        let big: Vec<ExpertiseRecord> = (0..10)
+            .map(|n| record(RecordType::Convention, Classification::Tactical, n))
+            .collect();
+        let small = [record(RecordType::Decision, Classification::Tactical, 0)];
+        let domains = vec![
+            DomainRecords {
+                domain: "big".into(),
+                records: big.iter().collect(),
+            },
+            DomainRecords {
+                domain: "small".into(),
+                records: small.iter().collect(),
+            },
+        ];
+        let policy = BudgetPolicy {
+            max_share: Some(0.6),
+            ..Default::default()
+        };
+        let result = run(&domains, 70, &policy);
+        assert_eq!(kept_in(&result, "big"), 3);
+        assert_eq!(kept_in(&result, "small"), 1);
+        assert!(
+            result
+                .dropped
+                .iter()
+                .any(|d| d.domain == "big" && d.reason == DropReason::DomainMaxShare)
+        );
+    }
+
+    #[test]
+    fn min_share_reserves_budget() {
+        let big: Vec<ExpertiseRecord> = (0..10)
+            .map(|n| record(RecordType::Convention, Classification::Tactical, n))
+            .collect();
+        let small: Vec<ExpertiseRecord> = (0..3)
+            .map(|n| record(RecordType::Decision, Classification::Tactical, n))
+            .collect();
+        let domains = vec![
+            DomainRecords {
+                domain: "big".into(),
+                records: big.iter().collect(),
+            },
+            DomainRecords {
+                domain: "small".into(),
+                records: small.iter().collect(),
+            },
+        ];
+        let mut policy = BudgetPolicy::default();
+        policy.domains.insert(
+            "small".into(),
+            DomainQuota {
+                min_share: Some(0.4),
+                max_share: None,
+            },
+        );
+        let result = run(&domains, 70, &policy);
+        assert_eq!(kept_in(&result, "small"), 2);
+        assert_eq!(kept_in(&result, "big"), 3);
+    }
+
+    #[test]
+    fn type_cap_reports_reason() {
+        let recs: Vec<ExpertiseRecord> = (0..4)
+            .map(|n| record(RecordType::Convention, Classification::Tactical, n))
+            .collect();
+        let domains = vec![DomainRecords {
+            domain: "d".into(),
+            records: recs.iter().collect(),
+        }];
+        let mut policy = BudgetPolicy::default();
+        policy.type_caps.insert(RecordType::Convention, 1);
+        let result = run(&domains, 1000, &policy);
+        assert_eq!(kept_in(&result, "d"), 1);
+        assert_eq!(result.dropped.len(), 3);
+        assert!(
+            result
+                .dropped
+                .iter()
+                .all(|d| d.reason == DropReason::TypeCap)
+        );
+    }
+
+    #[test]
+    fn foundational_slots_bypass_quotas() {
+        let recs = [
+            record(RecordType::Convention, Classification::Tactical, 0),
+            record(RecordType::Decision, Classification::Foundational, 1),
+        ];
+        let domains = vec![DomainRecords {
+            domain: "d".into(),
+            records: recs.iter().collect(),
+        }];
+        let mut policy = BudgetPolicy {
+            foundational_slots: 1,
+            ..Default::default()
+        };
+        policy.type_caps.insert(RecordType::Decision, 0);
+        // Budget fits one record: the guaranteed foundational decision wins
+        let result = run(&domains, 14, &policy);
+        assert_eq!(result.kept[0].records.len(), 1);
+        assert_eq!(
+            result.kept[0].records[0].record_type(),
+            RecordType::Decision
+        );
+        assert_eq!(result.dropped[0].reason, DropReason::Budget);
+    }
+
+    #[test]
+    fn domain_stats_counts_kept_and_dropped() {
+        let recs: Vec<ExpertiseRecord> = (0..3)
+            .map(|n| record(RecordType::Convention, Classification::Tactical, n))
+            .collect();
+        let decision = record(RecordType::Decision, Classification::Tactical, 9);
+        let domains = vec![
+            DomainRecords {
+                domain: "a".into(),
+                records: recs.iter().collect(),
+            },
+            DomainRecords {
+                domain: "b".into(),
+                records: vec![&decision],
+            },
+        ];
+        // Conventions outrank decisions, so "b" is squeezed out
+        let result = run(&domains, 42, &BudgetPolicy::default());
+        let stats = domain_stats(&domains, &result.kept, &HeuristicEstimator);
+
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            (stats[0].records, stats[0].kept, stats[0].dropped),
+            (3, 3, 0)
+        );
+        assert_eq!((stats[0].tokens, stats[0].kept_tokens), (42, 42));
+        assert_eq!((stats[1].kept, stats[1].dropped), (0, 1));
+        assert_eq!((stats[1].tokens, stats[1].kept_tokens), (14, 0));
+    }
 
     #[test]
     fn estimate_tokens_basic() {
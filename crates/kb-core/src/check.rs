@@ -18,7 +18,7 @@ pub struct CheckResult {
 
 /// Extract file path-like strings from text.
 /// Matches patterns like `src/foo/bar.rs`, `*.ts`, `crates/kb/Cargo.toml`.
-fn extract_paths(text: &str) -> Vec<String> {
+pub(crate) fn extract_paths(text: &str) -> Vec<String> {
     let re =
         Regex::new(r#"(?:^|[\s`"',(])([a-zA-Z0-9_.*][\w.*/-]*\.[a-zA-Z0-9]+)(?:[\s`"',):]|$)"#)
             .unwrap();
@@ -39,7 +39,7 @@ fn extract_paths(text: &str) -> Vec<String> {
 }
 
 /// Get all searchable text from a record.
-fn record_text(record: &ExpertiseRecord) -> String {
+pub(crate) fn record_text(record: &ExpertiseRecord) -> String {
     let mut parts = Vec::new();
 
     match record {
@@ -213,6 +213,8 @@ mod tests {
             content: "Always check src/nonexistent/file.rs before deploying".to_string(),
             classification: Classification::Tactical,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::check::{extract_paths, record_text};
+use crate::error::Result;
+use crate::format::get_record_summary;
+use crate::git::{self, FileChange};
+use crate::types::{ChurnPolicy, ExpertiseRecord, KbConfig, RecordType};
+use crate::{config, storage};
+
+/// Changes to one referenced path since the record was written.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChurn {
+    pub path: String,
+    pub commits: usize,
+    pub lines: u64,
+}
+
+/// Git churn in the files a record refers to.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordChurn {
+    pub domain: String,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    pub summary: String,
+    /// `updated_at`, or `recorded_at` for records never edited.
+    pub since: String,
+    /// Distinct commits touching any of the files.
+    pub commits: usize,
+    pub lines: u64,
+    /// Only files changed since the record was written.
+    pub files: Vec<FileChurn>,
+}
+
+impl RecordChurn {
+    pub fn is_stale(&self, policy: &ChurnPolicy) -> bool {
+        self.commits >= policy.min_commits && self.lines >= policy.min_lines
+    }
+}
+
+/// Paths a record refers to: pattern/reference `files`, `evidence.file` and
+/// path-like strings in its text. Globs are skipped.
+pub fn record_paths(record: &ExpertiseRecord) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for path in extract_paths(&record_text(record))
+        .into_iter()
+        .chain(record.files().into_iter().flatten().cloned())
+    {
+        let path = path
+            .trim_start_matches("./")
+            .trim_end_matches('/')
+            .to_string();
+        if !path.is_empty() && !path.contains('*') && !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+fn timestamp(iso: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(iso)
+        .ok()
+        .map(|dt| dt.timestamp())
+}
+
+/// A file change touches `path` if it is the file or lies under the directory.
+fn touches(change: &FileChange, path: &str) -> bool {
+    change.path == path
+        || change
+            .path
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn record_churn(
+    domain: &str,
+    record: &ExpertiseRecord,
+    changes: &[FileChange],
+) -> Option<RecordChurn> {
+    let paths = record_paths(record);
+    if paths.is_empty() {
+        return None;
+    }
+    let since = record.last_modified().to_string();
+    let since_ts = timestamp(&since)?;
+    let recent: Vec<&FileChange> = changes.iter().filter(|c| c.timestamp > since_ts).collect();
+
+    let mut all_commits: HashSet<&str> = HashSet::new();
+    let mut files = Vec::new();
+    for path in paths {
+        let mut commits: HashSet<&str> = HashSet::new();
+        let mut lines = 0;
+        for change in recent.iter().filter(|c| touches(c, &path)) {
+            commits.insert(&change.commit);
+            lines += change.lines;
+        }
+        if commits.is_empty() {
+            continue;
+        }
+        all_commits.extend(&commits);
+        files.push(FileChurn {
+            path,
+            commits: commits.len(),
+            lines,
+        });
+    }
+
+    Some(RecordChurn {
+        domain: domain.to_string(),
+        id: record.id().unwrap_or("(no id)").to_string(),
+        record_type: record.record_type(),
+        summary: get_record_summary(record),
+        since,
+        commits: all_commits.len(),
+        lines: files.iter().map(|f| f.lines).sum(),
+        files,
+    })
+}
+
+/// Churn for every record that references at least one path, computed from
+/// a single `git log` over the period since the oldest record.
+pub fn compute_churn(cwd: &Path, domains: &[(String, Vec<ExpertiseRecord>)]) -> Vec<RecordChurn> {
+    let oldest = domains
+        .iter()
+        .flat_map(|(_, records)| records.iter())
+        .map(|r| r.last_modified())
+        .min();
+    let Some(oldest) = oldest else {
+        return Vec::new();
+    };
+    if !git::is_git_repo(cwd) {
+        return Vec::new();
+    }
+    let changes = git::file_changes_since(cwd, oldest);
+
+    domains
+        .iter()
+        .flat_map(|(domain, records)| {
+            records
+                .iter()
+                .filter_map(|r| record_churn(domain, r, &changes))
+        })
+        .collect()
+}
+
+/// Records whose referenced files changed past the configured churn
+/// thresholds, most changed lines first.
+pub fn stale_records(cwd: &Path, cfg: &KbConfig, domain: Option<&str>) -> Result<Vec<RecordChurn>> {
+    let mut domains = Vec::new();
+    for name in &cfg.domains {
+        if domain.is_some_and(|d| d != name) {
+            continue;
+        }
+        let path = config::get_expertise_path(name, cwd)?;
+        domains.push((name.clone(), storage::read_expertise_file(&path)?));
+    }
+
+    let mut stale: Vec<RecordChurn> = compute_churn(cwd, &domains)
+        .into_iter()
+        .filter(|c| c.is_stale(&cfg.churn))
+        .collect();
+    stale.sort_by(|a, b| b.lines.cmp(&a.lines).then(b.commits.cmp(&a.commits)));
+    Ok(stale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Classification, Evidence};
+
+    fn pattern(files: &[&str], description: &str) -> ExpertiseRecord {
+        ExpertiseRecord::Pattern {
+            id: Some("mx-000001".to_string()),
+            name: "Storage".to_string(),
+            description: description.to_string(),
+            files: Some(files.iter().map(|f| f.to_string()).collect()),
+            classification: Classification::Foundational,
+            recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: Some(Evidence {
+                file: Some("docs/storage.md".to_string()),
+                ..Default::default()
+            }),
+            tags: None,
+            relates_to: None,
+            supersedes: None,
+            outcomes: None,
+        }
+    }
+
+    fn change(commit: &str, timestamp: i64, path: &str, lines: u64) -> FileChange {
+        FileChange {
+            commit: commit.to_string(),
+            timestamp,
+            path: path.to_string(),
+            lines,
+        }
+    }
+
+    #[test]
+    fn record_paths_collects_files_evidence_and_text() {
+        let record = pattern(&["src/storage.rs", "src/*.rs"], "See ./src/lock.rs too");
+        assert_eq!(
+            record_paths(&record),
+            vec!["src/lock.rs", "src/storage.rs", "docs/storage.md"]
+        );
+    }
+
+    #[test]
+    fn record_churn_counts_changes_after_recording() {
+        let record = pattern(&["src/storage"], "");
+        let recorded = timestamp("2024-01-01T00:00:00.000Z").unwrap();
+        let changes = vec![
+            change("a", recorded - 10, "src/storage/mod.rs", 500),
+            change("b", recorded + 10, "src/storage/mod.rs", 40),
+            change("b", recorded + 10, "src/storage/io.rs", 20),
+            change("c", recorded + 20, "src/storage/io.rs", 50),
+            change("d", recorded + 30, "src/storage_old.rs", 70),
+            change("e", recorded + 40, "docs/storage.md", 5),
+        ];
+        let churn = record_churn("d", &record, &changes).unwrap();
+        assert_eq!(churn.commits, 3);
+        assert_eq!(churn.lines, 115);
+        assert_eq!(churn.files.len(), 2);
+        assert!(churn.is_stale(&ChurnPolicy {
+            min_commits: 3,
+            min_lines: 100,
+        }));
+        assert!(!churn.is_stale(&ChurnPolicy {
+            min_commits: 4,
+            min_lines: 100,
+        }));
+    }
+}
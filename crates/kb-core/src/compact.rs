@@ -0,0 +1,445 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::check::record_text;
+use crate::error::{KbError, Result};
+use crate::gaps::{content_tokens, jaccard};
+use crate::types::{Classification, Evidence, ExpertiseRecord};
+
+/// Default token-set similarity above which two records are near-duplicates.
+pub const DEFAULT_SIMILARITY: f64 = 0.6;
+
+/// A group of near-duplicate records and the record proposed to replace them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactCluster {
+    pub domain: String,
+    /// IDs of the clustered records. The first keeps its ID in the merge.
+    pub ids: Vec<String>,
+    /// Lowest similarity between any two members.
+    pub similarity: f64,
+    pub merged: ExpertiseRecord,
+    /// When the merge was proposed. It no longer applies once a member has
+    /// changed after this.
+    pub planned_at: String,
+}
+
+impl CompactCluster {
+    /// IDs removed when the cluster is applied.
+    pub fn absorbed(&self) -> &[String] {
+        &self.ids[1..]
+    }
+}
+
+/// A reviewable compaction plan, as written by `kb compact --plan` and
+/// executed by `kb compact --apply`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompactPlan {
+    pub clusters: Vec<CompactCluster>,
+}
+
+/// The field that identifies a record: content, name, description or title.
+pub fn headline(record: &ExpertiseRecord) -> &str {
+    match record {
+        ExpertiseRecord::Convention { content, .. } => content,
+        ExpertiseRecord::Pattern { name, .. }
+        | ExpertiseRecord::Reference { name, .. }
+        | ExpertiseRecord::Guide { name, .. } => name,
+        ExpertiseRecord::Failure { description, .. } => description,
+        ExpertiseRecord::Decision { title, .. } => title,
+    }
+}
+
+/// Replace the field returned by [`headline`].
+pub fn set_headline(record: &mut ExpertiseRecord, text: String) {
+    match record {
+        ExpertiseRecord::Convention { content, .. } => *content = text,
+        ExpertiseRecord::Pattern { name, .. }
+        | ExpertiseRecord::Reference { name, .. }
+        | ExpertiseRecord::Guide { name, .. } => *name = text,
+        ExpertiseRecord::Failure { description, .. } => *description = text,
+        ExpertiseRecord::Decision { title, .. } => *title = text,
+    }
+}
+
+fn similarity(
+    a: &ExpertiseRecord,
+    b: &ExpertiseRecord,
+    tokens: (&HashSet<String>, &HashSet<String>),
+) -> f64 {
+    if headline(a) == headline(b) {
+        return 1.0;
+    }
+    jaccard(tokens.0, tokens.1)
+}
+
+fn union_into(target: &mut Vec<String>, values: Option<&[String]>) {
+    for value in values.into_iter().flatten() {
+        if !target.contains(value) {
+            target.push(value.clone());
+        }
+    }
+}
+
+fn non_empty(values: Vec<String>) -> Option<Vec<String>> {
+    (!values.is_empty()).then_some(values)
+}
+
+fn merge_evidence(target: &mut Evidence, other: &Evidence) {
+    for (field, value) in [
+        (&mut target.commit, &other.commit),
+        (&mut target.date, &other.date),
+        (&mut target.issue, &other.issue),
+        (&mut target.file, &other.file),
+        (&mut target.bead, &other.bead),
+    ] {
+        if field.is_none() {
+            field.clone_from(value);
+        }
+    }
+}
+
+fn durability(classification: Classification) -> u8 {
+    match classification {
+        Classification::Foundational => 2,
+        Classification::Tactical => 1,
+        Classification::Observational => 0,
+    }
+}
+
+/// Merge `members` into one record. The most recently modified member
+/// provides the text and ID; tags, files, evidence, outcomes and links are
+/// unioned, the most durable classification wins, and the merged record
+/// supersedes the other members.
+pub fn merge_records(members: &[&ExpertiseRecord]) -> ExpertiseRecord {
+    let base = members
+        .iter()
+        .copied()
+        .max_by(|a, b| a.last_modified().cmp(b.last_modified()))
+        .expect("cluster has members");
+    let base_id = base.id().unwrap_or_default();
+    let mut merged = base.clone();
+    let ids: Vec<&str> = members.iter().filter_map(|r| r.id()).collect();
+
+    let mut tags = Vec::new();
+    let mut files = Vec::new();
+    let mut relates_to = Vec::new();
+    let mut supersedes = Vec::new();
+    let mut outcomes = Vec::new();
+    let mut evidence: Option<Evidence> = None;
+    let mut classification = base.classification();
+    let recorded_at = members
+        .iter()
+        .map(|r| r.recorded_at())
+        .min()
+        .unwrap_or_default();
+
+    for record in
+        std::iter::once(base).chain(members.iter().copied().filter(|r| r.id() != base.id()))
+    {
+        union_into(&mut tags, record.tags());
+        union_into(&mut files, record.files());
+        union_into(&mut relates_to, record.relates_to());
+        union_into(&mut supersedes, record.supersedes());
+        outcomes.extend(record.outcomes().into_iter().flatten().cloned());
+        if let Some(ev) = record.evidence() {
+            merge_evidence(evidence.get_or_insert_with(Evidence::default), ev);
+        }
+        if durability(record.classification()) > durability(classification) {
+            classification = record.classification();
+        }
+        if let Some(id) = record.id()
+            && id != base_id
+            && !supersedes.iter().any(|s| s == id)
+        {
+            supersedes.push(id.to_string());
+        }
+    }
+    relates_to.retain(|id| !ids.contains(&id.as_str()));
+    supersedes.retain(|id| id != base_id);
+
+    merged.set_tags(non_empty(tags));
+    merged.set_files(non_empty(files));
+    merged.set_relates_to(non_empty(relates_to));
+    merged.set_supersedes(non_empty(supersedes));
+    merged.set_outcomes((!outcomes.is_empty()).then_some(outcomes));
+    merged.set_evidence(evidence);
+    merged.set_classification(classification);
+    merged.set_recorded_at(recorded_at.to_string());
+    merged
+}
+
+/// Cluster near-duplicate records of the same type. A record joins a
+/// cluster only when it is at least `threshold` similar to every member
+/// (complete linkage), so a chain of pairwise-similar records cannot pull
+/// unrelated records together. Records with the same headline always
+/// cluster; with no `threshold` only they do. Records without an ID are
+/// skipped.
+pub fn find_clusters(
+    domain: &str,
+    records: &[ExpertiseRecord],
+    threshold: Option<f64>,
+) -> Vec<CompactCluster> {
+    let candidates: Vec<(&ExpertiseRecord, HashSet<String>)> = records
+        .iter()
+        .filter(|r| r.id().is_some())
+        .map(|r| (r, content_tokens(&record_text(r))))
+        .collect();
+
+    let planned_at = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+    // (member indices into `candidates`, lowest pairwise similarity)
+    let mut groups: Vec<(Vec<usize>, f64)> = Vec::new();
+    for (i, (record, tokens)) in candidates.iter().enumerate() {
+        let best = groups
+            .iter()
+            .enumerate()
+            .filter_map(|(g, (members, _))| {
+                let scores = members.iter().map(|&m| {
+                    let (other, other_tokens) = &candidates[m];
+                    (other.record_type() == record.record_type())
+                        .then(|| similarity(record, other, (tokens, other_tokens)))
+                        .filter(|score| *score >= threshold.unwrap_or(1.0))
+                        .filter(|_| threshold.is_some() || headline(record) == headline(other))
+                });
+                let lowest = scores.collect::<Option<Vec<f64>>>()?;
+                Some((g, lowest.into_iter().fold(1.0, f64::min)))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        match best {
+            Some((g, score)) => {
+                groups[g].0.push(i);
+                groups[g].1 = groups[g].1.min(score);
+            }
+            None => groups.push((vec![i], 1.0)),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(members, _)| members.len() > 1)
+        .map(|(members, similarity)| {
+            let members: Vec<&ExpertiseRecord> = members.iter().map(|&m| candidates[m].0).collect();
+            let merged = merge_records(&members);
+            let keep = merged.id().unwrap_or_default().to_string();
+            let mut ids = vec![keep.clone()];
+            ids.extend(
+                members
+                    .iter()
+                    .filter_map(|r| r.id())
+                    .filter(|id| *id != keep)
+                    .map(str::to_string),
+            );
+            CompactCluster {
+                domain: domain.to_string(),
+                ids,
+                similarity,
+                merged,
+                planned_at: planned_at.clone(),
+            }
+        })
+        .collect()
+}
+
+/// True when `record` was modified after `timestamp`.
+fn modified_after(record: &ExpertiseRecord, timestamp: &str) -> bool {
+    let parse = |ts: &str| chrono::DateTime::parse_from_rfc3339(ts).ok();
+    match (parse(record.last_modified()), parse(timestamp)) {
+        (Some(modified), Some(at)) => modified > at,
+        _ => record.last_modified() > timestamp,
+    }
+}
+
+/// Replace the records of `cluster` with its merged record, which takes the
+/// position of the first member. Links to absorbed records are redirected
+/// to the merged one. Returns the absorbed records, for archiving. Fails
+/// when a clustered ID no longer exists or a member changed after the merge
+/// was planned, since the merge would drop that change.
+pub fn apply_cluster(
+    records: &mut Vec<ExpertiseRecord>,
+    cluster: &CompactCluster,
+) -> Result<Vec<ExpertiseRecord>> {
+    if cluster.ids.is_empty() || cluster.merged.id() != Some(cluster.ids[0].as_str()) {
+        return Err(KbError::ValidationError(format!(
+            "Merged record in {} must keep the ID of the first clustered record",
+            cluster.domain
+        )));
+    }
+    for id in &cluster.ids {
+        let Some(record) = records.iter().find(|r| r.id() == Some(id.as_str())) else {
+            return Err(KbError::RecordNotFound(id.clone()));
+        };
+        if modified_after(record, &cluster.planned_at) {
+            return Err(KbError::ValidationError(format!(
+                "Record {id} changed after the merge into {} was planned; propose the merge again",
+                cluster.ids[0]
+            )));
+        }
+    }
+
+    let keep = &cluster.ids[0];
+    let absorbed = cluster.absorbed();
+    let position = records
+        .iter()
+        .position(|r| r.id().is_some_and(|id| cluster.ids.iter().any(|c| c == id)))
+        .unwrap_or(records.len());
+    let removed: Vec<ExpertiseRecord> = records
+        .iter()
+        .filter(|r| r.id().is_some_and(|id| absorbed.iter().any(|a| a == id)))
+        .cloned()
+        .collect();
+    records.retain(|r| !r.id().is_some_and(|id| cluster.ids.iter().any(|c| c == id)));
+    records.insert(position.min(records.len()), cluster.merged.clone());
+
+    for record in records.iter_mut() {
+        let Some(links) = record.relates_to() else {
+            continue;
+        };
+        if !links.iter().any(|l| absorbed.contains(l)) {
+            continue;
+        }
+        let mut relinked = Vec::new();
+        for link in links {
+            let target = if absorbed.contains(link) { keep } else { link };
+            if record.id() != Some(target.as_str()) && !relinked.contains(target) {
+                relinked.push(target.clone());
+            }
+        }
+        record.set_relates_to(non_empty(relinked));
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convention(id: &str, content: &str, recorded_at: &str, tags: &[&str]) -> ExpertiseRecord {
+        ExpertiseRecord::Convention {
+            id: Some(id.to_string()),
+            content: content.to_string(),
+            classification: Classification::Tactical,
+            recorded_at: recorded_at.to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: None,
+            tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+            relates_to: None,
+            supersedes: None,
+            outcomes: None,
+        }
+    }
+
+    #[test]
+    fn find_clusters_groups_near_duplicates() {
+        let records = vec![
+            convention(
+                "mx-000001",
+                "Use anyhow for errors in the CLI crate",
+                "2024-01-01T00:00:00.000Z",
+                &["errors"],
+            ),
+            convention(
+                "mx-000002",
+                "Run cargo fmt before committing",
+                "2024-01-02T00:00:00.000Z",
+                &[],
+            ),
+            convention(
+                "mx-000003",
+                "In the CLI crate, use anyhow for errors",
+                "2024-02-01T00:00:00.000Z",
+                &["cli"],
+            ),
+        ];
+        // Exact duplicates only
+        assert!(find_clusters("backend", &records, None).is_empty());
+
+        let clusters = find_clusters("backend", &records, Some(DEFAULT_SIMILARITY));
+        assert_eq!(clusters.len(), 1);
+        let cluster = &clusters[0];
+        assert_eq!(cluster.ids, vec!["mx-000003", "mx-000001"]);
+        assert_eq!(
+            headline(&cluster.merged),
+            "In the CLI crate, use anyhow for errors"
+        );
+        assert_eq!(cluster.merged.tags().unwrap(), ["cli", "errors"]);
+        assert_eq!(cluster.merged.supersedes().unwrap(), ["mx-000001"]);
+        assert_eq!(cluster.merged.recorded_at(), "2024-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn find_clusters_requires_similarity_to_every_member() {
+        // a~b and b~c, but a and c share little: no chain through b
+        let records = vec![
+            convention(
+                "mx-000001",
+                "alpha beta gamma delta",
+                "2024-01-01T00:00:00.000Z",
+                &[],
+            ),
+            convention(
+                "mx-000002",
+                "beta gamma delta epsilon",
+                "2024-01-02T00:00:00.000Z",
+                &[],
+            ),
+            convention(
+                "mx-000003",
+                "gamma delta epsilon zeta",
+                "2024-01-03T00:00:00.000Z",
+                &[],
+            ),
+        ];
+        let clusters = find_clusters("backend", &records, Some(0.6));
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].ids, vec!["mx-000002", "mx-000001"]);
+        assert!(clusters[0].similarity >= 0.6);
+    }
+
+    #[test]
+    fn apply_cluster_replaces_members_and_relinks() {
+        let mut records = vec![
+            convention("mx-000001", "Use anyhow", "2024-01-01T00:00:00.000Z", &[]),
+            convention("mx-000002", "Use anyhow", "2024-02-01T00:00:00.000Z", &[]),
+            convention(
+                "mx-000003",
+                "Prefer thiserror in libraries",
+                "2024-01-01T00:00:00.000Z",
+                &[],
+            ),
+        ];
+        records[2].set_relates_to(Some(vec!["mx-000001".to_string()]));
+        let cluster = find_clusters("backend", &records, None).remove(0);
+        let removed = apply_cluster(&mut records, &cluster).unwrap();
+        assert_eq!(removed[0].id(), Some("mx-000001"));
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id(), Some("mx-000002"));
+        assert_eq!(records[1].relates_to().unwrap(), ["mx-000002"]);
+
+        // Applying again fails: the absorbed record is gone
+        assert!(matches!(
+            apply_cluster(&mut records, &cluster),
+            Err(KbError::RecordNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn apply_cluster_rejects_members_changed_since_planning() {
+        let mut records = vec![
+            convention("mx-000001", "Use anyhow", "2024-01-01T00:00:00.000Z", &[]),
+            convention("mx-000002", "Use anyhow", "2024-02-01T00:00:00.000Z", &[]),
+        ];
+        let mut cluster = find_clusters("backend", &records, None).remove(0);
+        cluster.planned_at = "2024-03-01T00:00:00.000Z".to_string();
+        records[1].set_updated_at(Some("2024-03-02T00:00:00.000Z".to_string()));
+
+        assert!(matches!(
+            apply_cluster(&mut records, &cluster),
+            Err(KbError::ValidationError(_))
+        ));
+        assert_eq!(records.len(), 2);
+    }
+}
@@ -7,8 +7,12 @@ use crate::types::KbConfig;
 const KB_DIR: &str = ".kb";
 const CONFIG_FILE: &str = "kb.config.yaml";
 const EXPERTISE_DIR: &str = "expertise";
+const ARCHIVE_DIR: &str = "archive";
 
-pub const GITATTRIBUTES_LINE: &str = ".kb/expertise/*.jsonl merge=union";
+pub const GITATTRIBUTES_LINES: &[&str] = &[
+    ".kb/expertise/*.jsonl merge=union",
+    ".kb/archive/*.jsonl merge=union",
+];
 
 pub fn get_kb_dir(cwd: &Path) -> PathBuf {
     cwd.join(KB_DIR)
@@ -27,6 +31,15 @@ pub fn get_expertise_path(domain: &str, cwd: &Path) -> Result<PathBuf> {
     Ok(get_expertise_dir(cwd).join(format!("{domain}.jsonl")))
 }
 
+pub fn get_archive_dir(cwd: &Path) -> PathBuf {
+    get_kb_dir(cwd).join(ARCHIVE_DIR)
+}
+
+pub fn get_archive_path(domain: &str, cwd: &Path) -> Result<PathBuf> {
+    validate_domain_name(domain)?;
+    Ok(get_archive_dir(cwd).join(format!("{domain}.jsonl")))
+}
+
 pub fn validate_domain_name(domain: &str) -> Result<()> {
     let re = regex::Regex::new(r"^[a-zA-Z0-9][a-zA-Z0-9_-]*$").unwrap();
     if !re.is_match(domain) {
@@ -39,6 +52,13 @@ pub fn read_config(cwd: &Path) -> Result<KbConfig> {
     let config_path = get_config_path(cwd);
     let content = fs::read_to_string(&config_path)?;
     let config: KbConfig = serde_yaml::from_str(&content)?;
+    if let Some(share) = config.budget.overflow_share
+        && !(0.0..=1.0).contains(&share)
+    {
+        return Err(KbError::ValidationError(format!(
+            "budget.overflow_share must be between 0.0 and 1.0, got {share}"
+        )));
+    }
     Ok(config)
 }
 
@@ -88,7 +108,14 @@ pub fn init_kb_dir(cwd: &Path) -> Result<()> {
     // Create or append .gitattributes
     let gitattributes_path = cwd.join(".gitattributes");
     let existing = fs::read_to_string(&gitattributes_path).unwrap_or_default();
-    if !existing.contains(GITATTRIBUTES_LINE) {
+    let mut attributes = String::new();
+    for line in GITATTRIBUTES_LINES {
+        if !existing.contains(line) {
+            attributes.push_str(line);
+            attributes.push('\n');
+        }
+    }
+    if !attributes.is_empty() {
         let separator = if !existing.is_empty() && !existing.ends_with('\n') {
             "\n"
         } else {
@@ -96,7 +123,7 @@ pub fn init_kb_dir(cwd: &Path) -> Result<()> {
         };
         fs::write(
             &gitattributes_path,
-            format!("{existing}{separator}{GITATTRIBUTES_LINE}\n"),
+            format!("{existing}{separator}{attributes}"),
         )?;
     }
 
@@ -154,7 +181,9 @@ mod tests {
         assert!(get_kb_dir(tmp.path()).is_dir());
         assert!(get_config_path(tmp.path()).exists());
         assert!(get_expertise_dir(tmp.path()).is_dir());
-        assert!(tmp.path().join(".gitattributes").exists());
+        let attributes = fs::read_to_string(tmp.path().join(".gitattributes")).unwrap();
+        assert!(attributes.contains(".kb/expertise/*.jsonl merge=union"));
+        assert!(attributes.contains(".kb/archive/*.jsonl merge=union"));
 
         let config = read_config(tmp.path()).unwrap();
         assert_eq!(config.version, "1");
@@ -175,4 +204,17 @@ mod tests {
         let config = read_config(tmp.path()).unwrap();
         assert_eq!(config.domains, vec!["test"]);
     }
+
+    #[test]
+    fn read_config_rejects_out_of_range_overflow_share() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_kb_dir(tmp.path()).unwrap();
+        let mut config = read_config(tmp.path()).unwrap();
+        config.budget.overflow_share = Some(1.5);
+        write_config(&config, tmp.path()).unwrap();
+        assert!(matches!(
+            read_config(tmp.path()),
+            Err(KbError::ValidationError(_))
+        ));
+    }
 }
@@ -0,0 +1,249 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{KbError, Result};
+use crate::format::get_record_summary;
+use crate::id::generate_record_id;
+use crate::template::SECTIONS;
+use crate::types::{ExpertiseRecord, RecordType};
+
+/// A top-level field that differs between two versions of a record.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+/// Compare two records field by field.
+pub fn changed_fields(old: &ExpertiseRecord, new: &ExpertiseRecord) -> Result<Vec<FieldChange>> {
+    let as_map = |r: &ExpertiseRecord| -> Result<HashMap<String, Value>> {
+        match serde_json::to_value(r)? {
+            Value::Object(map) => Ok(map.into_iter().collect()),
+            _ => Ok(HashMap::new()),
+        }
+    };
+    let old = as_map(old)?;
+    let new = as_map(new)?;
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+
+    Ok(keys
+        .into_iter()
+        .filter(|k| old.get(*k) != new.get(*k))
+        .map(|k| FieldChange {
+            field: k.clone(),
+            old: old.get(k).cloned(),
+            new: new.get(k).cloned(),
+        })
+        .collect())
+}
+
+/// The two sides of a diff. `to: None` means the working tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffRange {
+    pub from: String,
+    pub to: Option<String>,
+}
+
+impl DiffRange {
+    /// Parse `a..b`, `a..` (to HEAD), `..b` (from HEAD) or a single ref
+    /// (compared against the working tree).
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        let Some((from, to)) = spec.split_once("..") else {
+            if spec.is_empty() {
+                return Err(KbError::InvalidRange(spec.to_string()));
+            }
+            return Ok(Self {
+                from: spec.to_string(),
+                to: None,
+            });
+        };
+        // `a...b` (symmetric difference) and `..` have no meaningful sides
+        if to.starts_with('.') || (from.is_empty() && to.is_empty()) {
+            return Err(KbError::InvalidRange(spec.to_string()));
+        }
+        let side = |s: &str| if s.is_empty() { "HEAD" } else { s }.to_string();
+        Ok(Self {
+            from: side(from),
+            to: Some(side(to)),
+        })
+    }
+
+    /// Human label for the target side.
+    pub fn to_label(&self) -> &str {
+        self.to.as_deref().unwrap_or("working tree")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Removed,
+}
+
+/// One record that differs between the two sides of a diff.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordDiff {
+    pub domain: String,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    pub kind: ChangeKind,
+    pub summary: String,
+    /// Field-level changes; empty for added and removed records.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldChange>,
+    /// The new record, or the old one when removed.
+    pub record: ExpertiseRecord,
+}
+
+/// Parse JSONL content into records, skipping lines that don't parse.
+pub fn parse_jsonl(content: &str) -> Vec<ExpertiseRecord> {
+    content
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect()
+}
+
+/// Key used to match versions of a record: its ID, or the content hash if it has none.
+pub(crate) fn record_key(record: &ExpertiseRecord) -> String {
+    record
+        .id()
+        .map(str::to_string)
+        .unwrap_or_else(|| generate_record_id(record))
+}
+
+fn section_index(record_type: RecordType) -> usize {
+    SECTIONS
+        .iter()
+        .position(|(t, _)| *t == record_type)
+        .unwrap_or(SECTIONS.len())
+}
+
+/// Diff two versions of a domain's records.
+///
+/// Records are matched by ID (records without one fall back to their content
+/// hash), so edits show up as modifications. Results are ordered by record
+/// type, then added, modified, removed.
+pub fn diff_records(
+    domain: &str,
+    old: &[ExpertiseRecord],
+    new: &[ExpertiseRecord],
+) -> Result<Vec<RecordDiff>> {
+    let old_by_key: HashMap<String, &ExpertiseRecord> =
+        old.iter().map(|r| (record_key(r), r)).collect();
+    let mut seen = HashSet::new();
+    let mut diffs = Vec::new();
+
+    let entry = |id: String, kind, record: &ExpertiseRecord, fields| RecordDiff {
+        domain: domain.to_string(),
+        id,
+        record_type: record.record_type(),
+        kind,
+        summary: get_record_summary(record),
+        fields,
+        record: record.clone(),
+    };
+
+    for record in new {
+        let key = record_key(record);
+        if !seen.insert(key.clone()) {
+            continue;
+        }
+        match old_by_key.get(&key) {
+            None => diffs.push(entry(key, ChangeKind::Added, record, Vec::new())),
+            Some(prev) => {
+                let fields = changed_fields(prev, record)?;
+                if !fields.is_empty() {
+                    diffs.push(entry(key, ChangeKind::Modified, record, fields));
+                }
+            }
+        }
+    }
+
+    let mut removed_seen = HashSet::new();
+    for record in old {
+        let key = record_key(record);
+        if !seen.contains(&key) && removed_seen.insert(key.clone()) {
+            diffs.push(entry(key, ChangeKind::Removed, record, Vec::new()));
+        }
+    }
+
+    diffs.sort_by_key(|d| (section_index(d.record_type), d.kind));
+    Ok(diffs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Classification;
+
+    fn convention(id: &str, content: &str) -> ExpertiseRecord {
+        ExpertiseRecord::Convention {
+            id: Some(id.to_string()),
+            content: content.to_string(),
+            classification: Classification::Tactical,
+            recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: None,
+            tags: None,
+            relates_to: None,
+            supersedes: None,
+            outcomes: None,
+        }
+    }
+
+    #[test]
+    fn diff_records_detects_modifications() {
+        let old = vec![convention("mx-1", "Use tabs"), convention("mx-2", "Gone")];
+        let new = vec![convention("mx-1", "Use spaces"), convention("mx-3", "New")];
+
+        let diffs = diff_records("style", &old, &new).unwrap();
+        let kinds: Vec<(&str, ChangeKind)> =
+            diffs.iter().map(|d| (d.id.as_str(), d.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("mx-3", ChangeKind::Added),
+                ("mx-1", ChangeKind::Modified),
+                ("mx-2", ChangeKind::Removed),
+            ]
+        );
+
+        let modified = &diffs[1];
+        assert_eq!(modified.fields.len(), 1);
+        assert_eq!(modified.fields[0].field, "content");
+        assert_eq!(modified.fields[0].old, Some(Value::from("Use tabs")));
+        assert_eq!(modified.fields[0].new, Some(Value::from("Use spaces")));
+    }
+
+    #[test]
+    fn diff_records_unchanged_is_empty() {
+        let records = vec![convention("mx-1", "Same")];
+        assert!(diff_records("d", &records, &records).unwrap().is_empty());
+    }
+
+    #[test]
+    fn diff_range_parse() {
+        let range = |from: &str, to: Option<&str>| DiffRange {
+            from: from.to_string(),
+            to: to.map(str::to_string),
+        };
+        assert_eq!(DiffRange::parse("HEAD~1").unwrap(), range("HEAD~1", None));
+        assert_eq!(
+            DiffRange::parse("main..feature").unwrap(),
+            range("main", Some("feature"))
+        );
+        assert_eq!(DiffRange::parse("v1..").unwrap(), range("v1", Some("HEAD")));
+        assert_eq!(DiffRange::parse("..v2").unwrap(), range("HEAD", Some("v2")));
+        assert!(DiffRange::parse("a...b").is_err());
+        assert!(DiffRange::parse("..").is_err());
+    }
+}
@@ -0,0 +1,513 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::git::CommitPatch;
+use crate::types::RecordType;
+
+/// Lint, format and tool configuration files whose settings are conventions.
+const CONFIG_FILES: &[&str] = &[
+    "rustfmt.toml",
+    ".rustfmt.toml",
+    "clippy.toml",
+    ".clippy.toml",
+    "deny.toml",
+    ".editorconfig",
+    ".prettierrc",
+    ".eslintrc",
+    "eslint.config.js",
+    "eslint.config.mjs",
+    "biome.json",
+    "tsconfig.json",
+    "ruff.toml",
+    ".flake8",
+    "mypy.ini",
+    ".golangci.yml",
+    ".golangci.yaml",
+    ".pre-commit-config.yaml",
+    ".markdownlint.json",
+    ".stylelintrc",
+];
+
+/// Extensions of source files that make up modules.
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "rs", "ts", "tsx", "js", "jsx", "mjs", "py", "go", "java", "kt", "rb", "c", "cc", "cpp", "h",
+    "hpp", "cs", "swift", "scala", "ex", "exs", "php",
+];
+
+/// Extensions of documentation files.
+const DOC_EXTENSIONS: &[&str] = &["md", "mdx", "rst", "adoc", "txt"];
+
+/// Directories holding design documents and ADRs.
+const DOC_DIRS: &[&str] = &["docs", "doc", "adr", "adrs", "decisions", "rfcs"];
+
+/// Commit subject words that mark a fix.
+const FIX_WORDS: &[&str] = &["fix", "flak", "regression", "broken", "repair"];
+
+/// Added lines kept in a convention drafted from a config change.
+const MAX_SETTINGS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// One file of a patch and the lines it adds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePatch {
+    pub path: String,
+    pub status: FileStatus,
+    pub added: Vec<String>,
+}
+
+/// Files changed together, by a commit or (without `commit`) in the working
+/// tree.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeSet {
+    pub commit: Option<String>,
+    pub subject: String,
+    pub body: String,
+    pub files: Vec<FilePatch>,
+}
+
+impl ChangeSet {
+    pub fn from_commit(commit: &CommitPatch) -> Self {
+        Self {
+            commit: Some(commit.hash.clone()),
+            subject: commit.subject.clone(),
+            body: commit.body.clone(),
+            files: parse_patch(&commit.patch),
+        }
+    }
+}
+
+/// A record proposed from a change, in the shape `kb record --batch` accepts.
+#[derive(Debug, Clone, Serialize)]
+pub struct Draft {
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    /// Which heuristic produced the draft.
+    pub reason: String,
+    /// Changed files the draft is based on.
+    pub files: Vec<String>,
+    pub record: serde_json::Value,
+}
+
+/// Split a unified diff (`git diff -U0` / `git log -p`) into files.
+pub fn parse_patch(patch: &str) -> Vec<FilePatch> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    // Headers only come before a file's first hunk; inside a hunk, `+++ x`
+    // is an added line reading `++ x`
+    let mut in_hunk = false;
+    for line in patch.lines() {
+        if line.starts_with("diff --git ") {
+            files.push(FilePatch {
+                path: String::new(),
+                status: FileStatus::Modified,
+                added: Vec::new(),
+            });
+            in_hunk = false;
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if in_hunk {
+            if let Some(added) = line.strip_prefix('+') {
+                file.added.push(added.to_string());
+            }
+        } else if line.starts_with("@@") {
+            in_hunk = true;
+        } else if line.starts_with("new file mode") {
+            file.status = FileStatus::Added;
+        } else if line.starts_with("deleted file mode") {
+            file.status = FileStatus::Deleted;
+        } else if let Some(path) = line.strip_prefix("--- a/") {
+            file.path = path.to_string();
+        } else if let Some(path) = line.strip_prefix("+++ b/") {
+            file.path = path.to_string();
+        }
+        // Anything else is the /dev/null side of an added or deleted file, or
+        // another extended header line
+    }
+    files.retain(|f| !f.path.is_empty());
+    files
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn extension(path: &str) -> &str {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+}
+
+pub fn is_test_file(path: &str) -> bool {
+    let name = file_name(path).to_lowercase();
+    let lower = path.to_lowercase();
+    lower.starts_with("tests/")
+        || lower.starts_with("test/")
+        || lower.contains("/tests/")
+        || lower.contains("/test/")
+        || lower.contains("__tests__/")
+        || name.starts_with("test_")
+        || name.contains("_test.")
+        || name.contains(".test.")
+        || name.contains(".spec.")
+}
+
+fn is_config_file(path: &str) -> bool {
+    let name = file_name(path);
+    CONFIG_FILES
+        .iter()
+        .any(|c| name == *c || (c.starts_with('.') && name.starts_with(&format!("{c}."))))
+}
+
+fn is_source_file(path: &str) -> bool {
+    SOURCE_EXTENSIONS.contains(&extension(path))
+}
+
+/// ADRs and documents under a docs directory; changelogs and READMEs are not
+/// decisions.
+fn is_decision_doc(path: &str) -> bool {
+    let name = file_name(path).to_lowercase();
+    if !DOC_EXTENSIONS.contains(&extension(path)) || name.starts_with("changelog") {
+        return false;
+    }
+    let in_doc_dir = path
+        .split('/')
+        .rev()
+        .skip(1)
+        .any(|dir| DOC_DIRS.contains(&dir.to_lowercase().as_str()));
+    in_doc_dir || name.starts_with("adr")
+}
+
+fn is_fix(subject: &str) -> bool {
+    let subject = subject.to_lowercase();
+    FIX_WORDS.iter().any(|w| subject.contains(w))
+}
+
+/// Added lines that carry settings rather than comments or punctuation.
+fn setting_lines(added: &[String]) -> Vec<String> {
+    added
+        .iter()
+        .map(|l| l.trim())
+        .filter(|l| {
+            !l.is_empty()
+                && !l.starts_with('#')
+                && !l.starts_with("//")
+                && !l.starts_with(';')
+                && l.chars().any(char::is_alphanumeric)
+        })
+        .map(|l| l.trim_end_matches(',').to_string())
+        .take(MAX_SETTINGS)
+        .collect()
+}
+
+/// First line of a module doc comment or docstring among the added lines.
+fn module_doc(added: &[String]) -> Option<String> {
+    added.iter().find_map(|l| {
+        let l = l.trim();
+        ["//!", "///", "/**", "\"\"\"", "# ", "* "]
+            .iter()
+            .find_map(|p| l.strip_prefix(p))
+            .map(|rest| rest.trim().trim_end_matches("\"\"\"").trim().to_string())
+            .filter(|rest| rest.len() > 3)
+    })
+}
+
+/// First heading and first prose line of an added document.
+fn doc_title_and_text(added: &[String]) -> (Option<String>, Option<String>) {
+    let title = added
+        .iter()
+        .find_map(|l| l.strip_prefix('#'))
+        .map(|h| h.trim_start_matches('#').trim().to_string())
+        .filter(|h| !h.is_empty());
+    let text = added
+        .iter()
+        .map(|l| l.trim())
+        .find(|l| {
+            !l.is_empty()
+                && !l.starts_with('#')
+                && !l.starts_with("---")
+                && l.chars().next().is_some_and(char::is_alphabetic)
+        })
+        .map(str::to_string);
+    (title, text)
+}
+
+fn evidence(change: &ChangeSet, file: Option<&str>) -> serde_json::Value {
+    let mut evidence = serde_json::Map::new();
+    if let Some(ref commit) = change.commit {
+        evidence.insert("commit".to_string(), commit.clone().into());
+    }
+    if let Some(file) = file {
+        evidence.insert("file".to_string(), file.into());
+    }
+    serde_json::Value::Object(evidence)
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+fn draft(
+    record_type: RecordType,
+    reason: &str,
+    files: Vec<String>,
+    mut record: serde_json::Value,
+    evidence: serde_json::Value,
+) -> Draft {
+    record["type"] = record_type.as_str().into();
+    record["evidence"] = evidence;
+    Draft {
+        record_type,
+        reason: reason.to_string(),
+        files,
+        record,
+    }
+}
+
+/// Draft records from one change set.
+///
+/// A fix commit touching only tests becomes a failure; changed lint and
+/// tool configs become conventions; added source files become references;
+/// ADRs and design docs become decisions. `.kb/` is ignored.
+pub fn draft_change(change: &ChangeSet) -> Vec<Draft> {
+    let files: Vec<&FilePatch> = change
+        .files
+        .iter()
+        .filter(|f| !f.path.starts_with(".kb/"))
+        .collect();
+    if files.is_empty() {
+        return Vec::new();
+    }
+    let mut drafts = Vec::new();
+
+    if files.iter().all(|f| is_test_file(&f.path)) {
+        if change.commit.is_some() && is_fix(&change.subject) {
+            let paths: Vec<String> = files.iter().map(|f| f.path.clone()).collect();
+            let resolution = non_empty(&change.body).unwrap_or_else(|| {
+                format!("TODO: describe the fix (changed {})", paths.join(", "))
+            });
+            drafts.push(draft(
+                RecordType::Failure,
+                "test-only fix",
+                paths,
+                serde_json::json!({
+                    "description": change.subject,
+                    "resolution": resolution,
+                    "tags": ["tests"],
+                }),
+                evidence(change, None),
+            ));
+        }
+        return drafts;
+    }
+
+    for file in files {
+        let path = file.path.as_str();
+        if file.status == FileStatus::Deleted || is_test_file(path) {
+            continue;
+        }
+        if is_config_file(path) {
+            let settings = setting_lines(&file.added);
+            if settings.is_empty() {
+                continue;
+            }
+            drafts.push(draft(
+                RecordType::Convention,
+                "config change",
+                vec![path.to_string()],
+                serde_json::json!({
+                    "content": format!("Follow {}: {}", file_name(path), settings.join("; ")),
+                    "classification": "foundational",
+                }),
+                evidence(change, Some(path)),
+            ));
+        } else if is_decision_doc(path) {
+            let (title, text) = doc_title_and_text(&file.added);
+            let title = title
+                .or_else(|| non_empty(&change.subject))
+                .unwrap_or_else(|| file_name(path).to_string());
+            let rationale = text
+                .or_else(|| non_empty(&change.body))
+                .unwrap_or_else(|| format!("TODO: why (see {path})"));
+            drafts.push(draft(
+                RecordType::Decision,
+                "design document",
+                vec![path.to_string()],
+                serde_json::json!({
+                    "title": title,
+                    "rationale": rationale,
+                    "classification": "foundational",
+                }),
+                evidence(change, Some(path)),
+            ));
+        } else if file.status == FileStatus::Added && is_source_file(path) {
+            let description = module_doc(&file.added)
+                .or_else(|| non_empty(&change.subject))
+                .unwrap_or_else(|| "TODO: what this module is for".to_string());
+            drafts.push(draft(
+                RecordType::Reference,
+                "new module",
+                vec![path.to_string()],
+                serde_json::json!({
+                    "name": path,
+                    "description": description,
+                    "files": [path],
+                }),
+                evidence(change, Some(path)),
+            ));
+        }
+    }
+    drafts
+}
+
+/// Draft records from every change set, dropping repeats of the same record.
+pub fn draft_records(changes: &[ChangeSet]) -> Vec<Draft> {
+    let mut drafts: Vec<Draft> = Vec::new();
+    for draft in changes.iter().flat_map(draft_change) {
+        let key = |d: &Draft| {
+            let mut r = d.record.clone();
+            if let Some(obj) = r.as_object_mut() {
+                obj.remove("evidence");
+            }
+            r
+        };
+        if !drafts.iter().any(|d| key(d) == key(&draft)) {
+            drafts.push(draft);
+        }
+    }
+    drafts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ExpertiseRecord;
+
+    const PATCH: &str = "\
+diff --git a/rustfmt.toml b/rustfmt.toml
+new file mode 100644
+--- /dev/null
++++ b/rustfmt.toml
+@@ -0,0 +1,2 @@
++# formatting
++max_width = 100
+diff --git a/src/cache.rs b/src/cache.rs
+new file mode 100644
+--- /dev/null
++++ b/src/cache.rs
+@@ -0,0 +1,2 @@
++//! In-memory cache for parsed expertise files.
++pub struct Cache;
+diff --git a/docs/adr/0002-use-jsonl.md b/docs/adr/0002-use-jsonl.md
+--- a/docs/adr/0002-use-jsonl.md
++++ b/docs/adr/0002-use-jsonl.md
+@@ -1,0 +2,2 @@
++# Use JSONL for storage
++Line-based files merge cleanly in git.
+diff --git a/src/old.rs b/src/old.rs
+deleted file mode 100644
+--- a/src/old.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-pub fn old() {}
+";
+
+    fn change(commit: Option<&str>, subject: &str, patch: &str) -> ChangeSet {
+        ChangeSet {
+            commit: commit.map(str::to_string),
+            subject: subject.to_string(),
+            body: String::new(),
+            files: parse_patch(patch),
+        }
+    }
+
+    #[test]
+    fn parse_patch_reads_status_and_added_lines() {
+        let files = parse_patch(PATCH);
+        assert_eq!(files.len(), 4);
+        assert_eq!(files[0].status, FileStatus::Added);
+        assert_eq!(files[0].added, vec!["# formatting", "max_width = 100"]);
+        assert_eq!(files[2].status, FileStatus::Modified);
+        assert_eq!(files[3].path, "src/old.rs");
+        assert_eq!(files[3].status, FileStatus::Deleted);
+    }
+
+    #[test]
+    fn parse_patch_keeps_added_lines_that_look_like_headers() {
+        let files = parse_patch(
+            "\
+diff --git a/src/counter.c b/src/counter.c
+--- a/src/counter.c
++++ b/src/counter.c
+@@ -1,2 +1,2 @@
+--- count;
++++ count;
++++ b/not-a-header
+",
+        );
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "src/counter.c");
+        assert_eq!(files[0].added, vec!["++ count;", "++ b/not-a-header"]);
+    }
+
+    #[test]
+    fn drafts_follow_file_heuristics() {
+        let drafts = draft_change(&change(Some("abc123"), "Add cache", PATCH));
+        let types: Vec<RecordType> = drafts.iter().map(|d| d.record_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                RecordType::Convention,
+                RecordType::Reference,
+                RecordType::Decision
+            ]
+        );
+        assert_eq!(
+            drafts[0].record["content"],
+            "Follow rustfmt.toml: max_width = 100"
+        );
+        assert_eq!(
+            drafts[1].record["description"],
+            "In-memory cache for parsed expertise files."
+        );
+        assert_eq!(drafts[2].record["title"], "Use JSONL for storage");
+        assert_eq!(drafts[2].record["evidence"]["commit"], "abc123");
+
+        // Every draft is a valid record once defaults are filled in
+        for d in drafts {
+            let mut record = d.record.clone();
+            record["recorded_at"] = "2024-01-01T00:00:00.000Z".into();
+            record["classification"] = "tactical".into();
+            serde_json::from_value::<ExpertiseRecord>(record).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_only_fix_becomes_failure() {
+        let patch = "\
+diff --git a/tests/lock.rs b/tests/lock.rs
+--- a/tests/lock.rs
++++ b/tests/lock.rs
+@@ -3 +3 @@
++    let dir = tempdir();
+";
+        let drafts = draft_change(&change(Some("abc"), "Fix flaky lock test", patch));
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].record_type, RecordType::Failure);
+        assert_eq!(drafts[0].record["description"], "Fix flaky lock test");
+
+        assert!(draft_change(&change(Some("abc"), "Add lock tests", patch)).is_empty());
+        assert!(draft_change(&change(None, "", patch)).is_empty());
+    }
+}
@@ -33,6 +33,31 @@ pub enum KbError {
     )]
     LockTimeout(String),
 
+    #[error("Tokenizer \"{0}\" is not available. Rebuild kb with the `bpe` feature.")]
+    TokenizerUnavailable(String),
+
+    #[error("Template \"{name}\" not found. Available templates: {available}")]
+    TemplateNotFound { name: String, available: String },
+
+    #[error("Template error: {0}")]
+    Template(String),
+
+    #[error("Import error: {0}")]
+    Import(String),
+
+    #[error("Invalid git range \"{0}\". Use <ref>, <from>..<to>, <from>.. or ..<to>.")]
+    InvalidRange(String),
+
+    #[error(
+        "Domain \"{domain}\" has {count} records; adding {adding} would exceed its hard limit of {limit}. Split it with `kb split {domain}` or force the write."
+    )]
+    HardLimit {
+        domain: String,
+        count: usize,
+        adding: usize,
+        limit: usize,
+    },
+
     #[error("Schema validation failed: {0}")]
     ValidationError(String),
 
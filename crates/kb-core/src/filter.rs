@@ -86,6 +86,8 @@ mod tests {
             content: content.to_string(),
             classification: Classification::Foundational,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
@@ -102,6 +104,8 @@ mod tests {
             files: Some(vec!["src/main.rs".to_string()]),
             classification: Classification::Tactical,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
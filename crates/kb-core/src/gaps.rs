@@ -0,0 +1,273 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::access_log::AccessLogEntry;
+use crate::search::tokenize;
+
+/// Words ignored when comparing questions.
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "can", "do", "does", "for", "how", "i", "in", "is", "it", "of", "on",
+    "or", "should", "the", "to", "we", "what", "when", "where", "which", "why", "with",
+];
+
+/// Default token-set similarity above which two questions share a cluster.
+pub const DEFAULT_SIMILARITY: f64 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct GapOptions {
+    /// Ignore entries older than this.
+    pub since: Option<DateTime<Utc>>,
+    /// Jaccard similarity threshold for clustering.
+    pub similarity: f64,
+}
+
+impl Default for GapOptions {
+    fn default() -> Self {
+        Self {
+            since: None,
+            similarity: DEFAULT_SIMILARITY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GapQuery {
+    pub query: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GapCluster {
+    /// Most frequently asked phrasing in the cluster.
+    pub representative: String,
+    pub count: usize,
+    pub oracle_count: usize,
+    pub search_count: usize,
+    pub sessions: usize,
+    pub last_seen: DateTime<Utc>,
+    pub queries: Vec<GapQuery>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DomainGaps {
+    /// `None` for gaps logged without a domain.
+    pub domain: Option<String>,
+    pub count: usize,
+    pub clusters: Vec<GapCluster>,
+}
+
+/// Whether an access log entry signals a knowledge gap: an oracle call, or a
+/// search that returned nothing.
+pub fn is_gap_entry(entry: &AccessLogEntry) -> bool {
+    entry.query.is_some()
+        && (entry.tool == "oracle" || (entry.tool == "search" && entry.result_count == Some(0)))
+}
+
+/// Lowercased tokens of `text`, without stopwords.
+pub(crate) fn content_tokens(text: &str) -> HashSet<String> {
+    tokenize(text)
+        .into_iter()
+        .filter(|t| !STOPWORDS.contains(&t.as_str()))
+        .collect()
+}
+
+pub(crate) fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let inter = a.intersection(b).count();
+    let union = a.union(b).count();
+    inter as f64 / union as f64
+}
+
+struct ClusterBuilder<'a> {
+    tokens: HashSet<String>,
+    entries: Vec<&'a AccessLogEntry>,
+}
+
+impl ClusterBuilder<'_> {
+    fn build(self) -> GapCluster {
+        let mut phrasing: HashMap<String, GapQuery> = HashMap::new();
+        let mut sessions: HashSet<&str> = HashSet::new();
+        let mut oracle_count = 0;
+        let mut last_seen = self.entries[0].timestamp;
+
+        for entry in &self.entries {
+            let query = entry.query.as_deref().unwrap_or_default().trim();
+            phrasing
+                .entry(query.to_lowercase())
+                .or_insert_with(|| GapQuery {
+                    query: query.to_string(),
+                    count: 0,
+                })
+                .count += 1;
+            sessions.insert(entry.session_id.as_str());
+            if entry.tool == "oracle" {
+                oracle_count += 1;
+            }
+            last_seen = last_seen.max(entry.timestamp);
+        }
+
+        let mut queries: Vec<GapQuery> = phrasing.into_values().collect();
+        queries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.query.cmp(&b.query)));
+
+        GapCluster {
+            representative: queries[0].query.clone(),
+            count: self.entries.len(),
+            oracle_count,
+            search_count: self.entries.len() - oracle_count,
+            sessions: sessions.len(),
+            last_seen,
+            queries,
+        }
+    }
+}
+
+/// Greedy single-pass clustering: each question joins the first cluster whose
+/// token set is similar enough, and that cluster absorbs its tokens.
+fn cluster_entries<'a>(entries: &[&'a AccessLogEntry], similarity: f64) -> Vec<GapCluster> {
+    let mut builders: Vec<ClusterBuilder<'a>> = Vec::new();
+
+    for entry in entries {
+        let tokens = content_tokens(entry.query.as_deref().unwrap_or_default());
+        let best = builders
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i, jaccard(&c.tokens, &tokens)))
+            .filter(|(_, score)| *score >= similarity)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        match best {
+            Some((i, _)) => {
+                builders[i].tokens.extend(tokens);
+                builders[i].entries.push(entry);
+            }
+            None => builders.push(ClusterBuilder {
+                tokens,
+                entries: vec![entry],
+            }),
+        }
+    }
+
+    let mut clusters: Vec<GapCluster> = builders.into_iter().map(|b| b.build()).collect();
+    clusters.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| b.last_seen.cmp(&a.last_seen))
+    });
+    clusters
+}
+
+/// Collect knowledge gaps from the access log, clustered per domain and
+/// ranked by frequency.
+pub fn collect_gaps(entries: &[AccessLogEntry], opts: &GapOptions) -> Vec<DomainGaps> {
+    let mut by_domain: HashMap<Option<&str>, Vec<&AccessLogEntry>> = HashMap::new();
+    for entry in entries {
+        if !is_gap_entry(entry) || opts.since.is_some_and(|since| entry.timestamp < since) {
+            continue;
+        }
+        by_domain
+            .entry(entry.domain.as_deref())
+            .or_default()
+            .push(entry);
+    }
+
+    let mut domains: Vec<DomainGaps> = by_domain
+        .into_iter()
+        .map(|(domain, entries)| DomainGaps {
+            domain: domain.map(|d| d.to_string()),
+            count: entries.len(),
+            clusters: cluster_entries(&entries, opts.similarity),
+        })
+        .collect();
+    domains.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.domain.cmp(&b.domain)));
+    domains
+}
+
+/// Draft record stub for a gap cluster, in the shape `kb record --batch`
+/// accepts. The content is a placeholder for a human to replace.
+pub fn draft_stub(cluster: &GapCluster) -> serde_json::Value {
+    serde_json::json!({
+        "type": "convention",
+        "content": format!("TODO: answer \"{}\"", cluster.representative),
+        "classification": "tactical",
+        "tags": ["gap"],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(tool: &str, domain: Option<&str>, query: &str, results: usize) -> AccessLogEntry {
+        AccessLogEntry {
+            session_id: "s1".to_string(),
+            timestamp: Utc::now(),
+            tool: tool.to_string(),
+            domain: domain.map(|d| d.to_string()),
+            query: Some(query.to_string()),
+            entry_id: None,
+            entry_ids: None,
+            result_count: Some(results),
+            signal: None,
+        }
+    }
+
+    #[test]
+    fn only_oracle_and_empty_searches_are_gaps() {
+        assert!(is_gap_entry(&entry("oracle", None, "x", 0)));
+        assert!(is_gap_entry(&entry("search", None, "x", 0)));
+        assert!(!is_gap_entry(&entry("search", None, "x", 3)));
+        assert!(!is_gap_entry(&entry("query", None, "x", 0)));
+    }
+
+    #[test]
+    fn clusters_similar_questions() {
+        let entries = vec![
+            entry("oracle", Some("db"), "How do we run migrations?", 0),
+            entry("search", Some("db"), "run migrations", 0),
+            entry("oracle", Some("db"), "What is the connection pool size?", 0),
+        ];
+        let gaps = collect_gaps(&entries, &GapOptions::default());
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].count, 3);
+        assert_eq!(gaps[0].clusters.len(), 2);
+
+        let top = &gaps[0].clusters[0];
+        assert_eq!(top.count, 2);
+        assert_eq!(top.oracle_count, 1);
+        assert_eq!(top.search_count, 1);
+    }
+
+    #[test]
+    fn groups_by_domain_ranked_by_frequency() {
+        let entries = vec![
+            entry("oracle", Some("api"), "auth tokens", 0),
+            entry("oracle", None, "release process", 0),
+            entry("oracle", Some("api"), "rate limits", 0),
+        ];
+        let gaps = collect_gaps(&entries, &GapOptions::default());
+        assert_eq!(gaps[0].domain.as_deref(), Some("api"));
+        assert_eq!(gaps[0].count, 2);
+        assert_eq!(gaps[1].domain, None);
+    }
+
+    #[test]
+    fn representative_is_most_common_phrasing() {
+        let entries = vec![
+            entry("oracle", None, "deploy steps", 0),
+            entry("oracle", None, "Deploy steps", 0),
+            entry("oracle", None, "deploy steps staging", 0),
+        ];
+        let gaps = collect_gaps(&entries, &GapOptions::default());
+        let cluster = &gaps[0].clusters[0];
+        assert_eq!(cluster.count, 3);
+        assert_eq!(cluster.queries[0].count, 2);
+
+        let stub = draft_stub(cluster);
+        assert_eq!(stub["type"], "convention");
+        assert!(stub["content"].as_str().unwrap().contains("deploy steps"));
+    }
+}
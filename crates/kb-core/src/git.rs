@@ -2,8 +2,34 @@ use std::collections::BTreeSet;
 use std::path::Path;
 use std::process::Command;
 
+use serde::Serialize;
+
 use crate::types::ExpertiseRecord;
 
+/// A commit as listed by `git log`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommitInfo {
+    pub hash: String,
+    #[serde(skip)]
+    pub parents: Vec<String>,
+    pub author: String,
+    pub email: String,
+    /// Author date, ISO 8601.
+    pub date: String,
+    pub subject: String,
+}
+
+impl CommitInfo {
+    pub fn short_hash(&self) -> &str {
+        &self.hash[..self.hash.len().min(7)]
+    }
+
+    /// Calendar date part of `date`.
+    pub fn day(&self) -> &str {
+        self.date.split('T').next().unwrap_or(&self.date)
+    }
+}
+
 /// Check if the given directory is inside a git repository.
 pub fn is_git_repo(cwd: &Path) -> bool {
     Command::new("git")
@@ -74,6 +100,320 @@ pub fn get_changed_files(cwd: &Path, since: &str) -> Vec<String> {
     files.into_iter().collect()
 }
 
+fn git_output(cwd: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(cwd)
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !text.is_empty()).then_some(text)
+}
+
+/// Name of the checked-out branch, or `None` on a detached HEAD.
+pub fn current_branch(cwd: &Path) -> Option<String> {
+    git_output(cwd, &["symbolic-ref", "--short", "-q", "HEAD"])
+}
+
+/// The repository's mainline branch: `origin/HEAD` if set, otherwise a local
+/// `main` or `master`.
+pub fn default_branch(cwd: &Path) -> Option<String> {
+    if let Some(remote_head) = git_output(
+        cwd,
+        &["symbolic-ref", "--short", "-q", "refs/remotes/origin/HEAD"],
+    ) {
+        return remote_head
+            .strip_prefix("origin/")
+            .map(str::to_string)
+            .or(Some(remote_head));
+    }
+    ["main", "master"]
+        .into_iter()
+        .find(|b| ref_exists(cwd, &format!("refs/heads/{b}")))
+        .map(str::to_string)
+}
+
+/// Branch new records should be scoped to: the current branch unless it is
+/// the mainline (`main_branch` if configured, otherwise detected).
+pub fn scoped_branch(cwd: &Path, main_branch: Option<&str>) -> Option<String> {
+    let current = current_branch(cwd)?;
+    let main = main_branch
+        .map(str::to_string)
+        .or_else(|| default_branch(cwd));
+    // Without a known mainline (e.g. before the first commit) nothing is scoped
+    match main {
+        Some(main) if main != current => Some(current),
+        _ => None,
+    }
+}
+
+/// Check whether a ref resolves to a commit.
+pub fn ref_exists(cwd: &Path, git_ref: &str) -> bool {
+    Command::new("git")
+        .args([
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{git_ref}^{{commit}}"),
+        ])
+        .current_dir(cwd)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Check whether `branch` has been merged into `HEAD`.
+pub fn is_merged(cwd: &Path, branch: &str) -> bool {
+    Command::new("git")
+        .args(["merge-base", "--is-ancestor", branch, "HEAD"])
+        .current_dir(cwd)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Read a file's content at a git ref. Returns `None` if the ref or path does not exist.
+pub fn read_file_at_ref(cwd: &Path, git_ref: &str, rel_path: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["show", &format!("{git_ref}:{rel_path}")])
+        .current_dir(cwd)
+        .output()
+        .ok()?;
+
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        None
+    }
+}
+
+/// List file names directly under `rel_dir` at a git ref.
+pub fn list_files_at_ref(cwd: &Path, git_ref: &str, rel_dir: &str) -> Vec<String> {
+    let Ok(output) = Command::new("git")
+        .args(["ls-tree", "--name-only", git_ref, &format!("{rel_dir}/")])
+        .current_dir(cwd)
+        .output()
+    else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| l.trim().rsplit('/').next().map(str::to_string))
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+/// Commits that touched `rel_path`, oldest first. Merges are included when
+/// they differ from every parent (conflict resolutions, merge driver output).
+pub fn file_commits(cwd: &Path, rel_path: &str) -> Vec<CommitInfo> {
+    const SEP: char = '\u{1f}';
+    let Some(text) = git_output(
+        cwd,
+        &[
+            "log",
+            "--reverse",
+            "--format=%H%x1f%P%x1f%an%x1f%ae%x1f%aI%x1f%s",
+            "--",
+            rel_path,
+        ],
+    ) else {
+        return Vec::new();
+    };
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(SEP);
+            Some(CommitInfo {
+                hash: fields.next()?.to_string(),
+                parents: fields
+                    .next()?
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect(),
+                author: fields.next()?.to_string(),
+                email: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                subject: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The trailers (`Key: value` lines closing the message) of one commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitTrailers {
+    pub hash: String,
+    /// Author date, ISO 8601.
+    pub date: String,
+    pub trailers: Vec<(String, String)>,
+}
+
+/// Trailers of every commit in `range`, oldest first. A range without `..`
+/// selects just that commit. `None` if git rejects the range.
+pub fn commit_trailers(cwd: &Path, range: &str) -> Option<Vec<CommitTrailers>> {
+    let mut cmd = Command::new("git");
+    cmd.args([
+        "log",
+        "--reverse",
+        "--format=%x1e%H%x1f%aI%x1f%(trailers:only,unfold)",
+    ]);
+    if !range.contains("..") {
+        cmd.arg("--no-walk");
+    }
+    let output = cmd
+        .arg(range)
+        .arg("--")
+        .current_dir(cwd)
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let commits = text
+        .split('\u{1e}')
+        .filter_map(|block| {
+            let mut fields = block.splitn(3, '\u{1f}');
+            let hash = fields.next()?.trim().to_string();
+            let date = fields.next()?.to_string();
+            let trailers = fields
+                .next()
+                .unwrap_or_default()
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect();
+            (!hash.is_empty()).then_some(CommitTrailers {
+                hash,
+                date,
+                trailers,
+            })
+        })
+        .collect();
+    Some(commits)
+}
+
+/// A commit's message and its patch (no context lines, no rename detection).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitPatch {
+    pub hash: String,
+    pub subject: String,
+    pub body: String,
+    pub patch: String,
+}
+
+/// Every non-merge commit in `range`, oldest first.
+pub fn commit_patches(cwd: &Path, range: &str) -> Vec<CommitPatch> {
+    let Some(text) = git_output(
+        cwd,
+        &[
+            "log",
+            "--reverse",
+            "--no-merges",
+            "--no-renames",
+            "-p",
+            "-U0",
+            "--format=%x1e%H%x1f%s%x1f%b%x1f",
+            range,
+            "--",
+        ],
+    ) else {
+        return Vec::new();
+    };
+    text.split('\u{1e}')
+        .filter_map(|block| {
+            let mut fields = block.splitn(4, '\u{1f}');
+            Some(CommitPatch {
+                hash: fields.next()?.trim().to_string(),
+                subject: fields.next()?.to_string(),
+                body: fields.next()?.trim().to_string(),
+                patch: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .filter(|c| !c.hash.is_empty())
+        .collect()
+}
+
+/// Patch of uncommitted changes to tracked files (staged and unstaged).
+pub fn working_tree_patch(cwd: &Path) -> String {
+    git_output(cwd, &["diff", "-U0", "--no-renames", "HEAD", "--"]).unwrap_or_default()
+}
+
+/// Untracked files that are not ignored.
+pub fn untracked_files(cwd: &Path) -> Vec<String> {
+    git_output(cwd, &["ls-files", "--others", "--exclude-standard"])
+        .map(|text| text.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Lines changed in one file by one commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub commit: String,
+    /// Committer date, Unix seconds.
+    pub timestamp: i64,
+    /// Path relative to the working directory.
+    pub path: String,
+    /// Lines added plus removed (0 for binary files).
+    pub lines: u64,
+}
+
+/// Per-file changes of every non-merge commit since `since` (any date git
+/// understands), limited to files under `cwd`.
+pub fn file_changes_since(cwd: &Path, since: &str) -> Vec<FileChange> {
+    let since_arg = format!("--since={since}");
+    let Some(text) = git_output(
+        cwd,
+        &[
+            "log",
+            "--relative",
+            "--no-merges",
+            "--no-renames",
+            "--numstat",
+            "--format=%x1e%H %ct",
+            &since_arg,
+        ],
+    ) else {
+        return Vec::new();
+    };
+
+    let mut changes = Vec::new();
+    for block in text.split('\u{1e}').filter(|b| !b.trim().is_empty()) {
+        let mut lines = block.lines();
+        let Some((commit, timestamp)) = lines.next().and_then(|h| h.split_once(' ')) else {
+            continue;
+        };
+        let Ok(timestamp) = timestamp.trim().parse::<i64>() else {
+            continue;
+        };
+        for line in lines {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(added), Some(removed), Some(path)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let count = |n: &str| n.parse::<u64>().unwrap_or(0);
+            changes.push(FileChange {
+                commit: commit.to_string(),
+                timestamp,
+                path: path.to_string(),
+                lines: count(added) + count(removed),
+            });
+        }
+    }
+    changes
+}
+
 /// Check if a file matches any of the changed files (exact or substring).
 pub fn file_matches_any(file: &str, changed_files: &[String]) -> bool {
     changed_files.iter().any(|changed| {
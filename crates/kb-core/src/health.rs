@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 
-use crate::types::{Classification, ExpertiseRecord, RecordType, ShelfLife};
+use crate::error::{KbError, Result};
+use crate::types::{
+    Classification, ExpertiseRecord, Governance, KbConfig, RecordType, ShelfLifeRule,
+};
 
 #[derive(Debug)]
 pub struct DomainHealth {
@@ -12,38 +15,141 @@ pub struct DomainHealth {
     pub newest_timestamp: Option<String>,
 }
 
-/// Check if a record is stale based on classification and shelf life.
-pub fn is_record_stale(
+/// The shelf life that applies to a record, and where it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedShelfLife {
+    pub days: u32,
+    /// Index into `shelf_life_rules`; `None` for `classification_defaults`.
+    pub rule: Option<usize>,
+}
+
+/// Shelf life for `record` in `domain`: the most specific matching rule in
+/// `shelf_life_rules` that sets days for the record's classification (the
+/// first such rule on ties), else the classification default. `None` for
+/// foundational records, which never go stale.
+pub fn shelf_life_for(
     record: &ExpertiseRecord,
-    now: chrono::DateTime<chrono::Utc>,
-    shelf_life: &ShelfLife,
-) -> bool {
+    domain: &str,
+    config: &KbConfig,
+) -> Option<AppliedShelfLife> {
     let classification = record.classification();
+    let default = &config.classification_defaults.shelf_life;
+    let default_days = match classification {
+        Classification::Foundational => return None,
+        Classification::Tactical => default.tactical,
+        Classification::Observational => default.observational,
+    };
 
-    if classification == Classification::Foundational {
-        return false;
+    let mut best: Option<(usize, &ShelfLifeRule, u32)> = None;
+    for (i, rule) in config.shelf_life_rules.iter().enumerate() {
+        let Some(days) = rule.days(classification) else {
+            continue;
+        };
+        if !rule.matches(domain, record) {
+            continue;
+        }
+        if best.is_none_or(|(_, b, _)| rule.specificity() > b.specificity()) {
+            best = Some((i, rule, days));
+        }
     }
 
-    let recorded_at = match chrono::DateTime::parse_from_rfc3339(record.recorded_at()) {
-        Ok(dt) => dt.with_timezone(&chrono::Utc),
-        Err(_) => return false,
-    };
+    Some(match best {
+        Some((i, _, days)) => AppliedShelfLife {
+            days,
+            rule: Some(i),
+        },
+        None => AppliedShelfLife {
+            days: default_days,
+            rule: None,
+        },
+    })
+}
+
+/// Whole days since the record was last modified (edited, given an outcome
+/// or reclassified), if its timestamp parses.
+pub fn record_age_days(
+    record: &ExpertiseRecord,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(record.last_modified())
+        .ok()
+        .map(|dt| (now - dt.with_timezone(&chrono::Utc)).num_days())
+}
+
+/// Why a record is past its shelf life.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Staleness {
+    pub age_days: i64,
+    pub shelf_life: AppliedShelfLife,
+}
+
+impl Staleness {
+    /// The rule behind the shelf life, e.g. `rule 2 (domain=frontend)` or
+    /// `classification default`.
+    pub fn source(&self, config: &KbConfig) -> String {
+        match self
+            .shelf_life
+            .rule
+            .and_then(|i| config.shelf_life_rules.get(i).map(|r| (i, r)))
+        {
+            Some((i, rule)) => format!("rule {} ({})", i + 1, rule.criteria()),
+            None => "classification default".to_string(),
+        }
+    }
+}
+
+/// How a record is stale, or `None` if it is within its shelf life.
+pub fn staleness(
+    record: &ExpertiseRecord,
+    domain: &str,
+    now: chrono::DateTime<chrono::Utc>,
+    config: &KbConfig,
+) -> Option<Staleness> {
+    let shelf_life = shelf_life_for(record, domain, config)?;
+    let age_days = record_age_days(record, now)?;
+    (age_days > shelf_life.days as i64).then_some(Staleness {
+        age_days,
+        shelf_life,
+    })
+}
 
-    let age_days = (now - recorded_at).num_days();
+/// Check if a record is stale based on classification and shelf life.
+pub fn is_record_stale(
+    record: &ExpertiseRecord,
+    domain: &str,
+    now: chrono::DateTime<chrono::Utc>,
+    config: &KbConfig,
+) -> bool {
+    staleness(record, domain, now, config).is_some()
+}
 
-    match classification {
-        Classification::Tactical => age_days > shelf_life.tactical as i64,
-        Classification::Observational => age_days > shelf_life.observational as i64,
-        Classification::Foundational => false,
+/// Refuse to add `adding` records to a domain holding `count` if that would
+/// take it past `governance.hard_limit`.
+pub fn check_hard_limit(
+    governance: &Governance,
+    domain: &str,
+    count: usize,
+    adding: usize,
+) -> Result<()> {
+    let limit = governance.hard_limit as usize;
+    if adding > 0 && count + adding > limit {
+        return Err(KbError::HardLimit {
+            domain: domain.to_string(),
+            count,
+            adding,
+            limit,
+        });
     }
+    Ok(())
 }
 
 /// Calculate comprehensive health metrics for a domain.
 pub fn calculate_domain_health(
+    domain: &str,
     records: &[ExpertiseRecord],
-    max_entries: u32,
-    shelf_life: &ShelfLife,
+    config: &KbConfig,
 ) -> DomainHealth {
+    let max_entries = config.governance.max_entries;
     let now = chrono::Utc::now();
 
     let mut type_dist: HashMap<RecordType, usize> = HashMap::new();
@@ -56,7 +162,7 @@ pub fn calculate_domain_health(
         *type_dist.entry(record.record_type()).or_default() += 1;
         *class_dist.entry(record.classification()).or_default() += 1;
 
-        if is_record_stale(record, now, shelf_life) {
+        if is_record_stale(record, domain, now, config) {
             stale_count += 1;
         }
 
@@ -84,3 +190,91 @@ pub fn calculate_domain_health(
         newest_timestamp: newest,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(classification: &str, tags: &[&str], days_old: i64) -> ExpertiseRecord {
+        let recorded_at = (chrono::Utc::now() - chrono::Duration::days(days_old))
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        serde_json::from_value(serde_json::json!({
+            "type": "failure",
+            "description": "Build cache goes stale",
+            "resolution": "Clear it",
+            "classification": classification,
+            "recorded_at": recorded_at,
+            "tags": tags,
+        }))
+        .unwrap()
+    }
+
+    fn config() -> KbConfig {
+        KbConfig {
+            shelf_life_rules: serde_yaml::from_str(
+                "- domain: frontend\n  tactical: 7\n- tag: security\n  tactical: 90\n- domain: frontend\n  tag: security\n  tactical: 30\n- type: failure\n  observational: 60\n- domain: billing\n  observational: 365\n",
+            )
+            .unwrap(),
+            ..KbConfig::default()
+        }
+    }
+
+    #[test]
+    fn shelf_life_rules_precedence() {
+        let config = config();
+        let days = |domain: &str, cls: &str, tags: &[&str]| {
+            shelf_life_for(&record(cls, tags, 0), domain, &config).map(|s| (s.days, s.rule))
+        };
+        // Default when no rule matches
+        assert_eq!(days("api", "tactical", &[]), Some((14, None)));
+        // Single criterion
+        assert_eq!(days("frontend", "tactical", &[]), Some((7, Some(0))));
+        // Tag beats domain on equal criteria count
+        assert_eq!(days("api", "tactical", &["Security"]), Some((90, Some(1))));
+        // More criteria win
+        assert_eq!(
+            days("frontend", "tactical", &["security"]),
+            Some((30, Some(2)))
+        );
+        // Rules without days for the classification are skipped
+        assert_eq!(days("frontend", "observational", &[]), Some((60, Some(3))));
+        // Domain beats type
+        assert_eq!(days("billing", "observational", &[]), Some((365, Some(4))));
+        assert_eq!(days("frontend", "foundational", &[]), None);
+    }
+
+    #[test]
+    fn staleness_reports_rule() {
+        let config = config();
+        let stale = staleness(
+            &record("tactical", &[], 10),
+            "frontend",
+            chrono::Utc::now(),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(stale.age_days, 10);
+        assert_eq!(stale.source(&config), "rule 1 (domain=frontend)");
+        assert!(!is_record_stale(
+            &record("tactical", &[], 10),
+            "api",
+            chrono::Utc::now(),
+            &config
+        ));
+    }
+
+    #[test]
+    fn hard_limit_refuses_records_past_the_limit() {
+        let governance = KbConfig::default().governance;
+        assert!(check_hard_limit(&governance, "api", 199, 1).is_ok());
+        assert!(check_hard_limit(&governance, "api", 250, 0).is_ok());
+        assert!(matches!(
+            check_hard_limit(&governance, "api", 198, 3),
+            Err(KbError::HardLimit {
+                count: 198,
+                limit: 200,
+                ..
+            })
+        ));
+    }
+}
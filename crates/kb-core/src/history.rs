@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::diff::{self, ChangeKind, RecordDiff, record_key};
+use crate::error::{KbError, Result};
+use crate::format::get_record_summary;
+use crate::git::{self, CommitInfo};
+use crate::types::{ExpertiseRecord, RecordType};
+use crate::{config, storage};
+
+/// Changes to a domain file made by one commit, or by the working tree when
+/// `commit` is `None`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub commit: Option<CommitInfo>,
+    pub changes: Vec<RecordDiff>,
+}
+
+/// Where a record came from: the commit that added it and the one that last
+/// changed it. `None` means the change is not committed yet.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlameEntry {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    pub summary: String,
+    pub introduced: Option<CommitInfo>,
+    pub last_changed: Option<CommitInfo>,
+}
+
+fn expertise_rel_path(domain: &str) -> String {
+    format!(".kb/expertise/{domain}.jsonl")
+}
+
+fn records_at(cwd: &Path, git_ref: &str, rel_path: &str) -> Vec<ExpertiseRecord> {
+    git::read_file_at_ref(cwd, git_ref, rel_path)
+        .map(|content| diff::parse_jsonl(&content))
+        .unwrap_or_default()
+}
+
+/// Whether `change` is already present in another parent of a merge, i.e.
+/// was brought in by the merged branch rather than made by the merge.
+fn inherited(change: &RecordDiff, other: &HashMap<String, &ExpertiseRecord>) -> bool {
+    let same = |a: &ExpertiseRecord, b: &ExpertiseRecord| {
+        serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+    };
+    match (change.kind, other.get(&change.id)) {
+        (ChangeKind::Removed, None) => true,
+        (ChangeKind::Removed, Some(_)) => false,
+        (_, Some(r)) => same(r, &change.record),
+        (_, None) => false,
+    }
+}
+
+/// Record-level history of a domain file, oldest first. Each commit is
+/// compared with its first parent; merges only report what differs from
+/// every parent. Uncommitted changes come last.
+pub fn domain_history(cwd: &Path, domain: &str) -> Result<Vec<HistoryEntry>> {
+    let rel_path = expertise_rel_path(domain);
+    let mut entries = Vec::new();
+
+    for commit in git::file_commits(cwd, &rel_path) {
+        let new = records_at(cwd, &commit.hash, &rel_path);
+        let old = match commit.parents.first() {
+            Some(parent) => records_at(cwd, parent, &rel_path),
+            None => Vec::new(),
+        };
+        let mut changes = diff::diff_records(domain, &old, &new)?;
+        for parent in commit.parents.iter().skip(1) {
+            let other_records = records_at(cwd, parent, &rel_path);
+            let other: HashMap<String, &ExpertiseRecord> =
+                other_records.iter().map(|r| (record_key(r), r)).collect();
+            changes.retain(|c| !inherited(c, &other));
+        }
+        if !changes.is_empty() {
+            entries.push(HistoryEntry {
+                commit: Some(commit),
+                changes,
+            });
+        }
+    }
+
+    let head = records_at(cwd, "HEAD", &rel_path);
+    let path = config::get_expertise_path(domain, cwd)?;
+    let working = storage::read_expertise_file(&path)?;
+    let changes = diff::diff_records(domain, &head, &working)?;
+    if !changes.is_empty() {
+        entries.push(HistoryEntry {
+            commit: None,
+            changes,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Resolve an ID or prefix (with or without `mx-`) against every ID that
+/// appears in `history`, so deleted records can be found too.
+pub fn resolve_history_id(
+    history: &[(String, Vec<HistoryEntry>)],
+    identifier: &str,
+) -> Result<(String, String)> {
+    let hash = identifier.strip_prefix("mx-").unwrap_or(identifier);
+    let prefix = format!("mx-{hash}");
+
+    let mut matches: Vec<(String, String)> = Vec::new();
+    for (domain, entries) in history {
+        for change in entries.iter().flat_map(|e| &e.changes) {
+            let candidate = (domain.clone(), change.id.clone());
+            if change.id.starts_with(&prefix) && !matches.contains(&candidate) {
+                matches.push(candidate);
+            }
+        }
+    }
+
+    // An exact ID wins over longer IDs sharing the prefix
+    if let Some(exact) = matches.iter().find(|(_, id)| *id == prefix) {
+        return Ok(exact.clone());
+    }
+    match matches.len() {
+        1 => Ok(matches.remove(0)),
+        0 => Err(KbError::RecordNotFound(identifier.to_string())),
+        n => Err(KbError::AmbiguousId {
+            id: identifier.to_string(),
+            count: n,
+            ids: matches
+                .iter()
+                .map(|(d, id)| format!("{id} ({d})"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        }),
+    }
+}
+
+/// Every change to one record, oldest first.
+pub fn record_log(entries: &[HistoryEntry], id: &str) -> Vec<(Option<CommitInfo>, RecordDiff)> {
+    entries
+        .iter()
+        .flat_map(|e| {
+            e.changes
+                .iter()
+                .filter(|c| c.id == id)
+                .map(|c| (e.commit.clone(), c.clone()))
+        })
+        .collect()
+}
+
+/// Annotate current records with the commit that introduced them and the
+/// one that last changed them.
+pub fn blame(entries: &[HistoryEntry], records: &[ExpertiseRecord]) -> Vec<BlameEntry> {
+    records
+        .iter()
+        .map(|record| {
+            let id = record_key(record);
+            let mut introduced = None;
+            let mut last_changed = None;
+            for entry in entries {
+                for change in entry.changes.iter().filter(|c| c.id == id) {
+                    match change.kind {
+                        // A re-added record starts a new life
+                        ChangeKind::Added => {
+                            introduced = Some(entry.commit.clone());
+                            last_changed = Some(entry.commit.clone());
+                        }
+                        ChangeKind::Modified => last_changed = Some(entry.commit.clone()),
+                        ChangeKind::Removed => {}
+                    }
+                }
+            }
+            BlameEntry {
+                id,
+                record_type: record.record_type(),
+                summary: get_record_summary(record),
+                introduced: introduced.flatten(),
+                last_changed: last_changed.flatten(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Classification;
+
+    fn convention(id: &str, content: &str) -> ExpertiseRecord {
+        ExpertiseRecord::Convention {
+            id: Some(id.to_string()),
+            content: content.to_string(),
+            classification: Classification::Tactical,
+            recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: None,
+            tags: None,
+            relates_to: None,
+            supersedes: None,
+            outcomes: None,
+        }
+    }
+
+    fn commit(hash: &str) -> CommitInfo {
+        CommitInfo {
+            hash: hash.to_string(),
+            parents: Vec::new(),
+            author: "dev".to_string(),
+            email: "dev@example.com".to_string(),
+            date: "2024-01-01T00:00:00+00:00".to_string(),
+            subject: hash.to_string(),
+        }
+    }
+
+    /// mx-aaa1 added in c1 and edited in c2; mx-aaa2 added in c2 and
+    /// removed in the working tree; mx-bbb1 added in the working tree.
+    fn history() -> Vec<HistoryEntry> {
+        let v1 = vec![convention("mx-aaa1", "One")];
+        let v2 = vec![convention("mx-aaa1", "One!"), convention("mx-aaa2", "Two")];
+        let v3 = vec![
+            convention("mx-aaa1", "One!"),
+            convention("mx-bbb1", "Three"),
+        ];
+        let entry = |commit, old: &[ExpertiseRecord], new: &[ExpertiseRecord]| HistoryEntry {
+            commit,
+            changes: diff::diff_records("d", old, new).unwrap(),
+        };
+        vec![
+            entry(Some(commit("c1")), &[], &v1),
+            entry(Some(commit("c2")), &v1, &v2),
+            entry(None, &v2, &v3),
+        ]
+    }
+
+    #[test]
+    fn record_log_and_blame() {
+        let entries = history();
+
+        let log = record_log(&entries, "mx-aaa2");
+        let kinds: Vec<(Option<&str>, ChangeKind)> = log
+            .iter()
+            .map(|(c, d)| (c.as_ref().map(|c| c.hash.as_str()), d.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![(Some("c2"), ChangeKind::Added), (None, ChangeKind::Removed)]
+        );
+
+        let current = vec![
+            convention("mx-aaa1", "One!"),
+            convention("mx-bbb1", "Three"),
+        ];
+        let blamed = blame(&entries, &current);
+        assert_eq!(blamed[0].introduced.as_ref().unwrap().hash, "c1");
+        assert_eq!(blamed[0].last_changed.as_ref().unwrap().hash, "c2");
+        assert!(blamed[1].introduced.is_none());
+    }
+
+    #[test]
+    fn resolve_history_id_finds_deleted_records() {
+        let history = vec![("d".to_string(), history())];
+        assert_eq!(
+            resolve_history_id(&history, "aaa2").unwrap(),
+            ("d".to_string(), "mx-aaa2".to_string())
+        );
+        assert!(matches!(
+            resolve_history_id(&history, "aaa"),
+            Err(KbError::AmbiguousId { count: 2, .. })
+        ));
+        assert!(resolve_history_id(&history, "zzz").is_err());
+    }
+}
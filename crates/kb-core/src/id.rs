@@ -37,6 +37,8 @@ mod tests {
             content: "Always use snake_case".to_string(),
             classification: Classification::Foundational,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
@@ -57,6 +59,8 @@ mod tests {
             content: "Use snake_case".to_string(),
             classification: Classification::Foundational,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
@@ -68,6 +72,8 @@ mod tests {
             content: "Use camelCase".to_string(),
             classification: Classification::Foundational,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
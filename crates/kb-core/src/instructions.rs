@@ -0,0 +1,458 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::markers::{MARKER_END, MARKER_START};
+use crate::types::RecordType;
+
+/// Bullets shorter than this are usually labels, not rules.
+const MIN_TEXT_LEN: usize = 12;
+
+/// Phrases that mark a bullet as a decision rather than a rule.
+const DECISION_HINTS: &[&str] = &[
+    "because",
+    "decided",
+    "we chose",
+    "chosen",
+    "instead of",
+    "rather than",
+    "in favor of",
+    "in favour of",
+];
+
+/// Heading words for sections that record decisions.
+const DECISION_HEADINGS: &[&str] = &["decision", "architecture", "rationale", "why"];
+
+/// Heading words for sections that describe procedures.
+const GUIDE_HEADINGS: &[&str] = &[
+    "how to",
+    "workflow",
+    "setup",
+    "set up",
+    "release",
+    "deploy",
+    "steps",
+    "getting started",
+];
+
+/// Words that make a prose sentence a rule worth proposing.
+const DIRECTIVE_WORDS: &[&str] = &[
+    "always", "never", "must", "should", "prefer", "avoid", "don't", "do not",
+];
+
+/// A record proposed from an instruction file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    /// Convention content, decision title or guide name.
+    pub text: String,
+    /// Decision rationale or guide steps.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Headings above the candidate, outermost first.
+    pub headings: Vec<String>,
+    /// 1-based line in the source file.
+    pub line: usize,
+    /// Suggested domain: an existing one mentioned nearby, or a new one
+    /// named after the section.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// Whether `domain` has to be created first.
+    pub new_domain: bool,
+}
+
+impl Candidate {
+    /// Record stub in the shape `kb record --batch` accepts.
+    pub fn to_record_json(&self, source: &str) -> serde_json::Value {
+        let mut record = match self.record_type {
+            RecordType::Decision => serde_json::json!({
+                "type": "decision",
+                "title": self.text,
+                "rationale": self.detail.as_deref().unwrap_or(&self.text),
+            }),
+            RecordType::Guide => serde_json::json!({
+                "type": "guide",
+                "name": self.text,
+                "description": self.detail.as_deref().unwrap_or(&self.text),
+            }),
+            _ => serde_json::json!({
+                "type": "convention",
+                "content": self.text,
+            }),
+        };
+        // Hand-written team rules are long-lived: keep them out of shelf-life pruning
+        record["classification"] = "foundational".into();
+        record["evidence"] = serde_json::json!({ "file": source });
+        record
+    }
+}
+
+/// Blank out the kb marker section and any YAML front-matter (Cursor `.mdc`
+/// rules) so line numbers stay intact.
+fn without_marker_section(content: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = content.lines().collect();
+    if lines.first() == Some(&"---") {
+        if let Some(end) = lines.iter().skip(1).position(|l| *l == "---") {
+            lines[..end + 2].fill("");
+        }
+    }
+
+    let mut inside = false;
+    for line in lines.iter_mut() {
+        if line.contains(MARKER_START) {
+            inside = true;
+        }
+        let keep = !inside;
+        if line.contains(MARKER_END) {
+            inside = false;
+        }
+        if !keep {
+            *line = "";
+        }
+    }
+    lines
+}
+
+fn heading_level(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = line.get(level..)?;
+    (1..=6)
+        .contains(&level)
+        .then(|| rest.strip_prefix(' '))
+        .flatten()
+        .map(|title| (level, title.trim()))
+}
+
+/// Bullet or numbered item: (indent, numbered, text).
+fn list_item(line: &str) -> Option<(usize, bool, &str)> {
+    let indent = line.len() - line.trim_start().len();
+    let trimmed = line.trim_start();
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(text) = trimmed.strip_prefix(marker) {
+            return Some((indent, false, text.trim()));
+        }
+    }
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        let rest = &trimmed[digits..];
+        if let Some(text) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return Some((indent, true, text.trim()));
+        }
+    }
+    None
+}
+
+fn contains_any(text: &str, words: &[&str]) -> bool {
+    let lower = text.to_lowercase();
+    words.iter().any(|w| lower.contains(w))
+}
+
+/// Trim whitespace and surrounding bold markers from an item.
+fn clean(text: &str) -> String {
+    text.trim().trim_matches('*').trim().to_string()
+}
+
+/// Decision title: the part before "because", or the first sentence.
+fn decision_title(text: &str) -> String {
+    static BECAUSE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?i) because").expect("valid regex"));
+    let cut = BECAUSE
+        .find(text)
+        .map(|m| m.start())
+        .or_else(|| text.find(". "))
+        .unwrap_or(text.len());
+    text[..cut]
+        .trim()
+        .trim_end_matches([',', '.', ':', ';'])
+        .to_string()
+}
+
+struct Item {
+    line: usize,
+    numbered: bool,
+    text: String,
+}
+
+/// Turn the list items collected under the current heading into candidates.
+fn flush(
+    headings: &[(usize, String)],
+    items: &mut Vec<Item>,
+    candidates: &mut Vec<Candidate>,
+    domains: &[String],
+) {
+    if items.is_empty() {
+        return;
+    }
+    let titles: Vec<String> = headings.iter().map(|(_, t)| t.clone()).collect();
+    let section = titles.last().cloned().unwrap_or_default();
+    let is_guide = items.iter().all(|i| i.numbered) || contains_any(&section, GUIDE_HEADINGS);
+
+    if is_guide && !section.is_empty() {
+        let steps: Vec<String> = items
+            .iter()
+            .enumerate()
+            .map(|(n, i)| format!("{}. {}", n + 1, i.text))
+            .collect();
+        candidates.push(candidate(
+            RecordType::Guide,
+            section.clone(),
+            Some(steps.join("\n")),
+            &titles,
+            items[0].line,
+            domains,
+        ));
+    } else {
+        let decision_section = contains_any(&section, DECISION_HEADINGS);
+        for item in items.iter() {
+            if decision_section || contains_any(&item.text, DECISION_HINTS) {
+                candidates.push(candidate(
+                    RecordType::Decision,
+                    decision_title(&item.text),
+                    Some(item.text.clone()),
+                    &titles,
+                    item.line,
+                    domains,
+                ));
+            } else {
+                candidates.push(candidate(
+                    RecordType::Convention,
+                    item.text.clone(),
+                    None,
+                    &titles,
+                    item.line,
+                    domains,
+                ));
+            }
+        }
+    }
+    items.clear();
+}
+
+/// Parse an agent instruction file (CLAUDE.md, AGENTS.md, Cursor rules, ...)
+/// into candidate records. The kb marker section and code blocks are skipped.
+///
+/// Numbered lists, and lists under procedure headings ("Release", "How to
+/// ..."), become one guide per section. Other bullets become conventions, or
+/// decisions when they explain a choice ("because", "instead of") or sit under
+/// a decision heading. Prose sentences with directive words ("always",
+/// "never", "prefer") become conventions.
+pub fn extract_candidates(content: &str, domains: &[String]) -> Vec<Candidate> {
+    let lines = without_marker_section(content);
+    let mut candidates = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut items: Vec<Item> = Vec::new();
+    let mut in_code = false;
+
+    for (idx, raw) in lines.iter().enumerate() {
+        let line_no = idx + 1;
+        if raw.trim_start().starts_with("```") || raw.trim_start().starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+
+        if let Some((level, title)) = heading_level(raw) {
+            flush(&headings, &mut items, &mut candidates, domains);
+            headings.retain(|(l, _)| *l < level);
+            headings.push((level, title.to_string()));
+            continue;
+        }
+
+        if let Some((indent, numbered, text)) = list_item(raw) {
+            match items.last_mut() {
+                // Nested items elaborate on their parent
+                Some(parent) if indent > 0 => {
+                    parent.text.push_str("; ");
+                    parent.text.push_str(&clean(text));
+                }
+                _ => items.push(Item {
+                    line: line_no,
+                    numbered,
+                    text: clean(text),
+                }),
+            }
+            continue;
+        }
+
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('>') || trimmed.starts_with('|') {
+            continue;
+        }
+        // Wrapped continuation of the previous list item
+        if raw.starts_with([' ', '\t']) {
+            if let Some(last) = items.last_mut() {
+                last.text.push(' ');
+                last.text.push_str(trimmed);
+                continue;
+            }
+        }
+
+        flush(&headings, &mut items, &mut candidates, domains);
+        let titles: Vec<String> = headings.iter().map(|(_, t)| t.clone()).collect();
+        for sentence in trimmed.split_inclusive(". ") {
+            let sentence = sentence.trim();
+            if contains_any(sentence, DIRECTIVE_WORDS) {
+                candidates.push(candidate(
+                    RecordType::Convention,
+                    sentence.to_string(),
+                    None,
+                    &titles,
+                    line_no,
+                    domains,
+                ));
+            }
+        }
+    }
+    flush(&headings, &mut items, &mut candidates, domains);
+
+    candidates.retain(|c| c.text.chars().count() >= MIN_TEXT_LEN || c.detail.is_some());
+    candidates
+}
+
+fn candidate(
+    record_type: RecordType,
+    text: String,
+    detail: Option<String>,
+    headings: &[String],
+    line: usize,
+    domains: &[String],
+) -> Candidate {
+    let (domain, new_domain) = match suggest_domain(&text, headings, domains) {
+        Some(d) => (Some(d), false),
+        None => (
+            headings.get(1).or(headings.first()).map(|h| domain_slug(h)),
+            true,
+        ),
+    };
+    Candidate {
+        record_type,
+        text,
+        detail,
+        headings: headings.to_vec(),
+        line,
+        domain: domain.filter(|d| !d.is_empty()),
+        new_domain,
+    }
+}
+
+/// An existing domain named in the nearest heading, or else in the text.
+fn suggest_domain(text: &str, headings: &[String], domains: &[String]) -> Option<String> {
+    let mentions = |haystack: &str| {
+        let words: Vec<String> = haystack
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric() && c != '-' && c != '_')
+            .map(str::to_string)
+            .collect();
+        domains
+            .iter()
+            .find(|d| words.contains(&d.to_lowercase()))
+            .cloned()
+    };
+    headings
+        .iter()
+        .rev()
+        .find_map(|h| mentions(h))
+        .or_else(|| mentions(text))
+}
+
+/// Domain name derived from a heading: "Code Style" -> "code-style".
+fn domain_slug(heading: &str) -> String {
+    let mut out = String::new();
+    for c in heading.to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c);
+        } else if !out.is_empty() && !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    out.trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLAUDE_MD: &str = "# Project\n\nAlways run `cargo fmt` before committing. This repo is a CLI.\n\n## Testing\n\n- Use assert_cmd for CLI tests\n- Keep unit tests next to the code\n  - in a `tests` module\n- ok\n\n## Architecture\n\n- JSONL storage, because line-based diffs merge cleanly\n\n## Release\n\n1. Bump the version\n2. Tag and push\n\n```sh\n- not a bullet\n```\n\n<!-- kb:start -->\n- Generated by kb, never import me\n<!-- kb:end -->\n";
+
+    #[test]
+    fn extracts_conventions_decisions_and_guides() {
+        let candidates = extract_candidates(CLAUDE_MD, &["testing".to_string()]);
+        let summary: Vec<(RecordType, &str)> = candidates
+            .iter()
+            .map(|c| (c.record_type, c.text.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    RecordType::Convention,
+                    "Always run `cargo fmt` before committing."
+                ),
+                (RecordType::Convention, "Use assert_cmd for CLI tests"),
+                (
+                    RecordType::Convention,
+                    "Keep unit tests next to the code; in a `tests` module"
+                ),
+                (RecordType::Decision, "JSONL storage"),
+                (RecordType::Guide, "Release"),
+            ]
+        );
+        assert_eq!(
+            candidates[4].detail.as_deref(),
+            Some("1. Bump the version\n2. Tag and push")
+        );
+        assert_eq!(candidates[1].line, 7);
+    }
+
+    #[test]
+    fn decision_title_handles_non_ascii() {
+        // "İ" lowercases to more bytes than it takes in the original
+        assert_eq!(
+            decision_title("İİİ Use SQLite Because it is simple"),
+            "İİİ Use SQLite"
+        );
+        assert_eq!(
+            decision_title("Prefer JSONL. It diffs well"),
+            "Prefer JSONL"
+        );
+    }
+
+    #[test]
+    fn skips_front_matter() {
+        let mdc = "---\ndescription: Rust rules\nglobs: *.rs\n---\n- Prefer `?` over unwrap in library code\n";
+        let candidates = extract_candidates(mdc, &[]);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].line, 5);
+        assert_eq!(candidates[0].domain, None);
+    }
+
+    #[test]
+    fn suggests_existing_or_new_domains() {
+        let candidates = extract_candidates(CLAUDE_MD, &["testing".to_string()]);
+        assert_eq!(candidates[1].domain.as_deref(), Some("testing"));
+        assert!(!candidates[1].new_domain);
+        assert_eq!(candidates[3].domain.as_deref(), Some("architecture"));
+        assert!(candidates[3].new_domain);
+    }
+
+    #[test]
+    fn record_json_matches_batch_shape() {
+        let candidates = extract_candidates(CLAUDE_MD, &[]);
+        let decision = candidates[3].to_record_json("CLAUDE.md");
+        assert_eq!(decision["type"], "decision");
+        assert_eq!(decision["title"], "JSONL storage");
+        assert_eq!(
+            decision["rationale"],
+            "JSONL storage, because line-based diffs merge cleanly"
+        );
+        assert_eq!(decision["evidence"]["file"], "CLAUDE.md");
+        let mut value = decision.clone();
+        value["recorded_at"] = "2024-01-01T00:00:00.000Z".into();
+        let record: crate::types::ExpertiseRecord = serde_json::from_value(value).unwrap();
+        assert_eq!(record.record_type(), RecordType::Decision);
+    }
+}
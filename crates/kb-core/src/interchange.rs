@@ -0,0 +1,709 @@
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::diff::{FieldChange, changed_fields};
+use crate::error::{KbError, Result};
+use crate::filter;
+use crate::format::get_record_summary;
+use crate::id::generate_record_id;
+use crate::types::{ExpertiseRecord, RecordType};
+
+/// Columns written and read by the CSV format, in order.
+const CSV_COLUMNS: &[&str] = &[
+    "domain",
+    "id",
+    "type",
+    "classification",
+    "recorded_at",
+    "updated_at",
+    "branch",
+    "name",
+    "title",
+    "content",
+    "description",
+    "resolution",
+    "rationale",
+    "date",
+    "files",
+    "tags",
+    "relates_to",
+    "supersedes",
+    "evidence_commit",
+    "evidence_date",
+    "evidence_issue",
+    "evidence_file",
+    "evidence_bead",
+    "outcomes",
+];
+
+/// List-valued fields, joined with `LIST_SEPARATOR` in a single CSV cell.
+/// A `;` or `\` inside an item is escaped with a backslash.
+const LIST_FIELDS: &[&str] = &["files", "tags", "relates_to", "supersedes"];
+const LIST_SEPARATOR: &str = "; ";
+
+/// Exchange formats supported by `kb export --format` and `kb import --format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterchangeFormat {
+    /// Records as Markdown sections with YAML front-matter.
+    Markdown,
+    Csv,
+    Yaml,
+    Ndjson,
+}
+
+impl InterchangeFormat {
+    pub const ALL: &[Self] = &[Self::Markdown, Self::Csv, Self::Yaml, Self::Ndjson];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+            Self::Csv => "csv",
+            Self::Yaml => "yaml",
+            Self::Ndjson => "ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Csv => "csv",
+            Self::Yaml => "yaml",
+            Self::Ndjson => "ndjson",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Some(Self::Markdown),
+            "csv" => Some(Self::Csv),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "ndjson" | "jsonl" => Some(Self::Ndjson),
+            _ => None,
+        }
+    }
+
+    /// Guess the format from a file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(Self::parse)
+    }
+}
+
+impl std::fmt::Display for InterchangeFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A record tagged with its domain, as it appears in exported files.
+#[derive(Serialize)]
+struct Row<'a> {
+    domain: &'a str,
+    #[serde(flatten)]
+    record: &'a ExpertiseRecord,
+}
+
+/// A record read from an exchange file. `domain` is `None` when the input
+/// didn't name one (the caller supplies a default).
+#[derive(Debug, Clone)]
+pub struct ImportedRecord {
+    pub domain: Option<String>,
+    pub record: ExpertiseRecord,
+}
+
+/// Field holding the free-text body of a record in the Markdown format.
+fn body_field(record_type: RecordType) -> &'static str {
+    match record_type {
+        RecordType::Convention => "content",
+        RecordType::Decision => "rationale",
+        _ => "description",
+    }
+}
+
+// ── Export ──────────────────────────────────────────────────────────────────
+
+/// Serialize records of one or more domains into a single document.
+pub fn export_records(
+    format: InterchangeFormat,
+    domains: &[(String, Vec<ExpertiseRecord>)],
+) -> Result<String> {
+    let rows: Vec<Row> = domains
+        .iter()
+        .flat_map(|(domain, records)| records.iter().map(move |record| Row { domain, record }))
+        .collect();
+
+    match format {
+        InterchangeFormat::Markdown => export_markdown(&rows),
+        InterchangeFormat::Csv => export_csv(&rows),
+        InterchangeFormat::Yaml => Ok(serde_yaml::to_string(&rows)?),
+        InterchangeFormat::Ndjson => {
+            let mut out = String::new();
+            for row in &rows {
+                out.push_str(&serde_json::to_string(row)?);
+                out.push('\n');
+            }
+            Ok(out)
+        }
+    }
+}
+
+fn export_markdown(rows: &[Row]) -> Result<String> {
+    let mut out = String::new();
+    for row in rows {
+        let serde_yaml::Value::Mapping(mut fields) = serde_yaml::to_value(row)? else {
+            continue;
+        };
+        let body = fields
+            .shift_remove(body_field(row.record.record_type()))
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+
+        // `type` leads the front-matter: it is what marks the start of a record.
+        let mut front = serde_yaml::Mapping::new();
+        if let Some(t) = fields.shift_remove("type") {
+            front.insert("type".into(), t);
+        }
+        front.extend(fields);
+
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str("---\n");
+        out.push_str(&serde_yaml::to_string(&front)?);
+        out.push_str("---\n");
+        out.push_str(body.trim_end());
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn export_csv(rows: &[Row]) -> Result<String> {
+    let mut out = String::new();
+    write_csv_line(&mut out, CSV_COLUMNS.iter().copied());
+
+    for row in rows {
+        let value = serde_json::to_value(row)?;
+        let cells: Vec<String> = CSV_COLUMNS
+            .iter()
+            .map(|col| csv_cell(&value, col))
+            .collect();
+        write_csv_line(&mut out, cells.iter().map(String::as_str));
+    }
+    Ok(out)
+}
+
+fn csv_cell(value: &Value, column: &str) -> String {
+    let field = match column.strip_prefix("evidence_") {
+        Some(key) => value.get("evidence").and_then(|e| e.get(key)),
+        None => value.get(column),
+    };
+    match field {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(items)) if LIST_FIELDS.contains(&column) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|item| item.replace('\\', "\\\\").replace(';', "\\;"))
+            .collect::<Vec<_>>()
+            .join(LIST_SEPARATOR),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn write_csv_line<'a>(out: &mut String, cells: impl Iterator<Item = &'a str>) {
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if cell.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&cell.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(cell);
+        }
+    }
+    out.push('\n');
+}
+
+// ── Import ──────────────────────────────────────────────────────────────────
+
+/// Parse an exchange document into records. A missing `classification`
+/// defaults to tactical; a missing `recorded_at` is left empty for
+/// `merge_records` to fill in.
+pub fn parse_records(format: InterchangeFormat, input: &str) -> Result<Vec<ImportedRecord>> {
+    let rows = match format {
+        InterchangeFormat::Markdown => parse_markdown(input)?,
+        InterchangeFormat::Csv => parse_csv(input)?,
+        InterchangeFormat::Yaml => {
+            let value: serde_yaml::Value = serde_yaml::from_str(input)?;
+            match serde_json::to_value(value)? {
+                Value::Array(items) => items,
+                Value::Null => Vec::new(),
+                single => vec![single],
+            }
+        }
+        InterchangeFormat::Ndjson => input
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<std::result::Result<_, _>>()?,
+    };
+
+    rows.into_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            row_to_record(row).map_err(|e| KbError::Import(format!("record {}: {e}", i + 1)))
+        })
+        .collect()
+}
+
+fn row_to_record(mut row: Value) -> Result<ImportedRecord> {
+    let Some(obj) = row.as_object_mut() else {
+        return Err(KbError::Import("expected an object".to_string()));
+    };
+    let domain = match obj.remove("domain") {
+        Some(Value::String(d)) if !d.is_empty() => Some(d),
+        _ => None,
+    };
+    if !obj.contains_key("recorded_at") {
+        obj.insert("recorded_at".to_string(), Value::String(String::new()));
+    }
+    if !obj.contains_key("classification") {
+        obj.insert(
+            "classification".to_string(),
+            Value::String("tactical".to_string()),
+        );
+    }
+    let record = serde_json::from_value(row)?;
+    Ok(ImportedRecord { domain, record })
+}
+
+/// Split a Markdown export into records. A record starts at a `---` line
+/// directly followed by a `type:` line and runs until the next record.
+fn parse_markdown(input: &str) -> Result<Vec<Value>> {
+    let lines: Vec<&str> = input.lines().collect();
+    let starts_record =
+        |i: usize| lines[i] == "---" && lines.get(i + 1).is_some_and(|l| l.starts_with("type:"));
+
+    let mut rows = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if !starts_record(i) {
+            i += 1;
+            continue;
+        }
+        let front_start = i + 1;
+        let Some(front_len) = lines[front_start..].iter().position(|l| *l == "---") else {
+            return Err(KbError::Import(
+                "unterminated front-matter block".to_string(),
+            ));
+        };
+        let front_end = front_start + front_len;
+        let body_start = front_end + 1;
+        let body_end = (body_start..lines.len())
+            .find(|&j| starts_record(j))
+            .unwrap_or(lines.len());
+
+        let front: serde_yaml::Value =
+            serde_yaml::from_str(&lines[front_start..front_end].join("\n"))?;
+        let mut row = serde_json::to_value(front)?;
+        let body = lines[body_start..body_end].join("\n").trim().to_string();
+        if let Some(obj) = row.as_object_mut() {
+            let record_type = obj
+                .get("type")
+                .cloned()
+                .and_then(|t| serde_json::from_value::<RecordType>(t).ok());
+            if let Some(t) = record_type {
+                obj.insert(body_field(t).to_string(), Value::String(body));
+            }
+        }
+        rows.push(row);
+        i = body_end;
+    }
+    Ok(rows)
+}
+
+fn parse_csv(input: &str) -> Result<Vec<Value>> {
+    let mut table = split_csv(input).into_iter();
+    let Some(header) = table.next() else {
+        return Ok(Vec::new());
+    };
+
+    let mut rows = Vec::new();
+    for cells in table {
+        if cells.iter().all(|c| c.trim().is_empty()) {
+            continue;
+        }
+        let mut obj = serde_json::Map::new();
+        let mut evidence = serde_json::Map::new();
+        for (column, cell) in header.iter().zip(cells) {
+            let column = column.trim();
+            if cell.is_empty() {
+                continue;
+            }
+            if let Some(key) = column.strip_prefix("evidence_") {
+                evidence.insert(key.to_string(), Value::String(cell));
+            } else if LIST_FIELDS.contains(&column) {
+                let items = split_list(&cell).into_iter().map(Value::String).collect();
+                obj.insert(column.to_string(), Value::Array(items));
+            } else if column == "outcomes" {
+                obj.insert(column.to_string(), serde_json::from_str(&cell)?);
+            } else {
+                obj.insert(column.to_string(), Value::String(cell));
+            }
+        }
+        if !evidence.is_empty() {
+            obj.insert("evidence".to_string(), Value::Object(evidence));
+        }
+        rows.push(Value::Object(obj));
+    }
+    Ok(rows)
+}
+
+/// Split a list cell on unescaped `;`, trimming items and dropping empty ones.
+fn split_list(cell: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut chars = cell.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => item.push(chars.next().unwrap_or('\\')),
+            ';' => items.push(std::mem::take(&mut item)),
+            _ => item.push(c),
+        }
+    }
+    items.push(item);
+    items
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Split RFC 4180 CSV text into rows of cells.
+fn split_csv(input: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    cell.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => cell.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut cell)),
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => cell.push(c),
+        }
+    }
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+    rows
+}
+
+// ── Merge ───────────────────────────────────────────────────────────────────
+
+/// What importing a record did (or would do) to a domain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportAction {
+    Create,
+    Update,
+    Unchanged,
+    Skip,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportChange {
+    pub action: ImportAction,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    pub summary: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldChange>,
+}
+
+/// Merge imported records into a domain's records.
+///
+/// A record whose ID exists is replaced in place. Otherwise a record that
+/// duplicates an existing one (same content, name, title or description) updates
+/// named types under the stored ID and is skipped for conventions and failures,
+/// unless `force` is set. Everything else is appended, keeping its ID when it has one.
+/// Records without `recorded_at` keep the stored timestamp, or get the current time.
+pub fn merge_records(
+    existing: &mut Vec<ExpertiseRecord>,
+    incoming: Vec<ExpertiseRecord>,
+    force: bool,
+) -> Result<Vec<ImportChange>> {
+    let mut changes = Vec::with_capacity(incoming.len());
+
+    for mut record in incoming {
+        let id_match = record
+            .id()
+            .and_then(|id| existing.iter().position(|r| r.id() == Some(id)));
+        let target = match id_match {
+            Some(idx) => Some(idx),
+            None if force => None,
+            None => filter::find_duplicate(existing, &record).map(|(idx, _)| idx),
+        };
+
+        let Some(idx) = target else {
+            if record.recorded_at().is_empty() {
+                record.set_recorded_at(
+                    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                );
+            }
+            if record.id().is_none() {
+                record.set_id(generate_record_id(&record));
+            }
+            changes.push(import_change(ImportAction::Create, &record, Vec::new()));
+            existing.push(record);
+            continue;
+        };
+
+        if let Some(id) = existing[idx].id() {
+            record.set_id(id.to_string());
+        }
+        if record.recorded_at().is_empty() {
+            record.set_recorded_at(existing[idx].recorded_at().to_string());
+        }
+        if id_match.is_none() && !record.is_named_type() {
+            changes.push(import_change(ImportAction::Skip, &record, Vec::new()));
+            continue;
+        }
+
+        let fields = changed_fields(&existing[idx], &record)?;
+        if fields.is_empty() {
+            changes.push(import_change(ImportAction::Unchanged, &record, fields));
+        } else {
+            changes.push(import_change(ImportAction::Update, &record, fields));
+            record.touch();
+            existing[idx] = record;
+        }
+    }
+
+    Ok(changes)
+}
+
+pub(crate) fn import_change(
+    action: ImportAction,
+    record: &ExpertiseRecord,
+    fields: Vec<FieldChange>,
+) -> ImportChange {
+    ImportChange {
+        action,
+        id: record.id().unwrap_or_default().to_string(),
+        record_type: record.record_type(),
+        summary: get_record_summary(record),
+        fields,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Classification, Evidence, Outcome, OutcomeStatus};
+
+    fn sample() -> Vec<(String, Vec<ExpertiseRecord>)> {
+        let conv = ExpertiseRecord::Convention {
+            id: Some("mx-000001".to_string()),
+            content: "Use \"anyhow\", not unwrap\n\n---\nEven in tests".to_string(),
+            classification: Classification::Foundational,
+            recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: Some(Evidence {
+                commit: Some("abc123".to_string()),
+                ..Default::default()
+            }),
+            tags: Some(vec!["errors".to_string(), "style".to_string()]),
+            relates_to: None,
+            supersedes: None,
+            outcomes: Some(vec![Outcome {
+                status: OutcomeStatus::Success,
+                duration: Some(1.5),
+                test_results: None,
+                agent: Some("ci".to_string()),
+                notes: None,
+                recorded_at: None,
+            }]),
+        };
+        let pattern = ExpertiseRecord::Pattern {
+            id: Some("mx-000002".to_string()),
+            name: "Loader".to_string(),
+            description: "Loads config, then env".to_string(),
+            files: Some(vec!["src/config.rs".to_string()]),
+            classification: Classification::Tactical,
+            recorded_at: "2024-01-02T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: None,
+            tags: None,
+            relates_to: Some(vec!["mx-000001".to_string()]),
+            supersedes: None,
+            outcomes: None,
+        };
+        let decision = ExpertiseRecord::Decision {
+            id: Some("mx-000003".to_string()),
+            title: "Use JSONL".to_string(),
+            rationale: "Line-based diffs".to_string(),
+            date: Some("2024-01-03".to_string()),
+            classification: Classification::Tactical,
+            recorded_at: "2024-01-03T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: None,
+            tags: None,
+            relates_to: None,
+            supersedes: None,
+            outcomes: None,
+        };
+        vec![
+            ("api".to_string(), vec![conv, pattern]),
+            ("db".to_string(), vec![decision]),
+        ]
+    }
+
+    fn as_values(domains: &[(String, Vec<ExpertiseRecord>)]) -> Vec<(String, Value)> {
+        domains
+            .iter()
+            .flat_map(|(d, rs)| {
+                rs.iter()
+                    .map(move |r| (d.clone(), serde_json::to_value(r).unwrap()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let domains = sample();
+        for &format in InterchangeFormat::ALL {
+            let text = export_records(format, &domains).unwrap();
+            let parsed = parse_records(format, &text).unwrap();
+            let got: Vec<(String, Value)> = parsed
+                .iter()
+                .map(|r| {
+                    (
+                        r.domain.clone().unwrap(),
+                        serde_json::to_value(&r.record).unwrap(),
+                    )
+                })
+                .collect();
+            assert_eq!(got, as_values(&domains), "{format} did not round-trip");
+        }
+    }
+
+    #[test]
+    fn markdown_puts_body_after_front_matter() {
+        let text = export_records(InterchangeFormat::Markdown, &sample()).unwrap();
+        assert!(text.starts_with("---\ntype: convention\n"));
+        assert!(text.contains("---\nLoads config, then env\n"));
+        assert!(text.contains("domain: db\n"));
+    }
+
+    #[test]
+    fn csv_fills_defaults_and_splits_lists() {
+        let input = "domain,type,content,tags\napi,convention,\"Say \"\"hi\"\"\",a; b\n";
+        let parsed = parse_records(InterchangeFormat::Csv, input).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].domain.as_deref(), Some("api"));
+        let record = &parsed[0].record;
+        assert_eq!(record.classification(), Classification::Tactical);
+        assert_eq!(record.recorded_at(), "");
+        assert_eq!(record.tags().unwrap(), ["a", "b"]);
+        assert_eq!(get_record_summary(record), "Say \"hi\"");
+    }
+
+    #[test]
+    fn csv_escapes_separators_in_list_items() {
+        let mut domains = sample();
+        if let ExpertiseRecord::Pattern { tags, files, .. } = &mut domains[0].1[1] {
+            *tags = Some(vec!["a;b".to_string(), "c".to_string()]);
+            *files = Some(vec!["C:\\src\\x;y.rs".to_string()]);
+        }
+        let text = export_records(InterchangeFormat::Csv, &domains).unwrap();
+        assert!(text.contains("a\\;b; c"));
+        let parsed = parse_records(InterchangeFormat::Csv, &text).unwrap();
+        assert_eq!(parsed[1].record.tags().unwrap(), ["a;b", "c"]);
+        assert_eq!(parsed[1].record.files().unwrap(), ["C:\\src\\x;y.rs"]);
+    }
+
+    #[test]
+    fn parse_reports_record_number() {
+        let input = "{\"type\":\"convention\",\"content\":\"ok\"}\n{\"type\":\"pattern\"}\n";
+        let err = parse_records(InterchangeFormat::Ndjson, input).unwrap_err();
+        assert!(err.to_string().contains("record 2"));
+    }
+
+    #[test]
+    fn merge_preserves_ids_and_dedupes() {
+        let (_, mut existing) = sample().remove(0);
+
+        let mut edited = existing[1].clone();
+        if let ExpertiseRecord::Pattern { description, .. } = &mut edited {
+            *description = "Loads config only".to_string();
+        }
+        let mut renamed_dup = existing[0].clone();
+        renamed_dup.set_id("mx-ffffff".to_string());
+        // No timestamp in the input: the stored one is kept
+        let mut unchanged = existing[0].clone();
+        unchanged.set_recorded_at(String::new());
+        let new = ExpertiseRecord::Guide {
+            id: Some("mx-abcdef".to_string()),
+            name: "Release".to_string(),
+            description: "Tag and push".to_string(),
+            classification: Classification::Tactical,
+            recorded_at: "2024-02-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: None,
+            tags: None,
+            relates_to: None,
+            supersedes: None,
+            outcomes: None,
+        };
+
+        let changes = merge_records(
+            &mut existing,
+            vec![edited, renamed_dup, unchanged, new],
+            false,
+        )
+        .unwrap();
+        let actions: Vec<ImportAction> = changes.iter().map(|c| c.action).collect();
+        assert_eq!(
+            actions,
+            [
+                ImportAction::Update,
+                ImportAction::Skip,
+                ImportAction::Unchanged,
+                ImportAction::Create
+            ]
+        );
+        assert_eq!(changes[0].fields.len(), 1);
+        assert_eq!(changes[0].fields[0].field, "description");
+        assert_eq!(existing.len(), 3);
+        assert_eq!(existing[2].id(), Some("mx-abcdef"));
+    }
+}
@@ -1,19 +1,38 @@
 pub mod access_log;
+pub mod adr;
+pub mod archive;
 pub mod budget;
 pub mod changelog;
 pub mod check;
+pub mod churn;
+pub mod compact;
 pub mod config;
+pub mod diff;
+pub mod draft;
 pub mod error;
 pub mod filter;
 pub mod format;
+pub mod gaps;
 pub mod git;
 pub mod health;
+pub mod history;
 pub mod id;
+pub mod instructions;
+pub mod interchange;
 pub mod lock;
 pub mod markers;
+pub mod merge;
+pub mod promotion;
+pub mod relevance;
 pub mod resolve;
 pub mod scoring;
 pub mod search;
 pub mod session;
+pub mod site;
+pub mod split;
 pub mod storage;
+pub mod template;
+pub mod tokenizer;
+pub mod trailers;
 pub mod types;
+pub mod usage;
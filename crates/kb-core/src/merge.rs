@@ -0,0 +1,304 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde_json::{Map, Value};
+
+use crate::diff::record_key;
+use crate::error::Result;
+use crate::storage::parse_expertise;
+use crate::types::ExpertiseRecord;
+
+/// Git's default conflict marker length.
+const MARKER_SIZE: usize = 7;
+
+/// Fields merged specially rather than compared side by side.
+const OUTCOMES: &str = "outcomes";
+const UPDATED_AT: &str = "updated_at";
+
+type Fields = Map<String, Value>;
+
+/// Result of a three-way merge of one expertise file.
+#[derive(Debug, Default)]
+pub struct MergeResult {
+    /// Merged JSONL, with conflict markers around unresolved records.
+    pub content: String,
+    /// IDs of records left in conflict.
+    pub conflicts: Vec<String>,
+    /// IDs of concurrent edits resolved by the later `updated_at`.
+    pub resolved: Vec<String>,
+}
+
+enum Merged {
+    Clean(Fields),
+    ByTimestamp(Fields),
+    Conflict,
+}
+
+fn to_fields(record: &ExpertiseRecord) -> Result<Fields> {
+    match serde_json::to_value(record)? {
+        Value::Object(map) => Ok(map),
+        _ => Ok(Fields::new()),
+    }
+}
+
+fn last_modified(fields: &Fields) -> &str {
+    fields
+        .get(UPDATED_AT)
+        .or_else(|| fields.get("recorded_at"))
+        .and_then(Value::as_str)
+        .unwrap_or("")
+}
+
+/// Outcomes from both sides, ours first, without duplicates.
+fn union_outcomes(ours: Option<&Value>, theirs: Option<&Value>) -> Option<Value> {
+    let mut merged: Vec<Value> = Vec::new();
+    for side in [ours, theirs].into_iter().flatten() {
+        for outcome in side.as_array().into_iter().flatten() {
+            if !merged.contains(outcome) {
+                merged.push(outcome.clone());
+            }
+        }
+    }
+    (!merged.is_empty()).then_some(Value::Array(merged))
+}
+
+/// Merge two versions of a record field by field against their common base.
+///
+/// A field changed on one side only takes that side's value. Fields changed
+/// differently on both sides are taken from the side with the later
+/// `updated_at` (or `recorded_at`); if neither is later the record conflicts.
+fn merge_fields(base: Option<&Fields>, ours: &Fields, theirs: &Fields) -> Merged {
+    let keys: BTreeSet<&String> = ours
+        .keys()
+        .chain(theirs.keys())
+        .chain(base.into_iter().flat_map(|b| b.keys()))
+        .filter(|k| *k != OUTCOMES && *k != UPDATED_AT)
+        .collect();
+
+    let newer = match last_modified(ours).cmp(last_modified(theirs)) {
+        std::cmp::Ordering::Greater => Some(ours),
+        std::cmp::Ordering::Less => Some(theirs),
+        std::cmp::Ordering::Equal => None,
+    };
+
+    let mut merged = Fields::new();
+    let mut by_timestamp = false;
+    for key in keys {
+        let (o, t) = (ours.get(key), theirs.get(key));
+        let b = base.and_then(|b| b.get(key));
+        let value = if o == t || t == b {
+            o
+        } else if o == b {
+            t
+        } else {
+            let Some(side) = newer else {
+                return Merged::Conflict;
+            };
+            by_timestamp = true;
+            side.get(key)
+        };
+        if let Some(value) = value {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+
+    if let Some(outcomes) = union_outcomes(ours.get(OUTCOMES), theirs.get(OUTCOMES)) {
+        merged.insert(OUTCOMES.to_string(), outcomes);
+    }
+    let updated_at = [ours.get(UPDATED_AT), theirs.get(UPDATED_AT)]
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .max();
+    if let Some(updated_at) = updated_at {
+        merged.insert(UPDATED_AT.to_string(), Value::from(updated_at));
+    }
+
+    if by_timestamp {
+        Merged::ByTimestamp(merged)
+    } else {
+        Merged::Clean(merged)
+    }
+}
+
+/// Index one side by record key, in file order. Duplicate keys (left behind
+/// by earlier `merge=union` merges) are folded into one record.
+fn index(records: &[ExpertiseRecord]) -> Result<(Vec<String>, HashMap<String, Fields>)> {
+    let mut order = Vec::new();
+    let mut by_key: HashMap<String, Fields> = HashMap::new();
+    for record in records {
+        let key = record_key(record);
+        let fields = to_fields(record)?;
+        match by_key.get(&key) {
+            Some(prev) => {
+                // Unresolvable copies keep the first one
+                let folded = merge_fields(None, prev, &fields);
+                if let Merged::Clean(m) | Merged::ByTimestamp(m) = folded {
+                    by_key.insert(key, m);
+                }
+            }
+            None => {
+                order.push(key.clone());
+                by_key.insert(key, fields);
+            }
+        }
+    }
+    Ok((order, by_key))
+}
+
+fn to_line(fields: Fields) -> Result<String> {
+    let record: ExpertiseRecord = serde_json::from_value(Value::Object(fields))?;
+    Ok(serde_json::to_string(&record)?)
+}
+
+/// Three-way merge of expertise JSONL content by record ID.
+///
+/// Records added on either side are kept, edits made on one side are
+/// applied, and records deleted on one side stay deleted unless the other
+/// side edited them. `outcomes` arrays are unioned.
+pub fn merge_expertise(base: &str, ours: &str, theirs: &str) -> Result<MergeResult> {
+    let (_, base) = index(&parse_expertise(base)?)?;
+    let (ours_order, ours) = index(&parse_expertise(ours)?)?;
+    let (theirs_order, theirs) = index(&parse_expertise(theirs)?)?;
+
+    let mut result = MergeResult::default();
+    let mut lines: Vec<String> = Vec::new();
+
+    let keys = ours_order
+        .iter()
+        .chain(theirs_order.iter().filter(|k| !ours.contains_key(*k)));
+    for key in keys {
+        let b = base.get(key);
+        let fields = match (ours.get(key), theirs.get(key)) {
+            (Some(o), Some(t)) => match merge_fields(b, o, t) {
+                Merged::Clean(m) => m,
+                Merged::ByTimestamp(m) => {
+                    result.resolved.push(key.clone());
+                    m
+                }
+                Merged::Conflict => {
+                    result.conflicts.push(key.clone());
+                    let marker = |c: char| c.to_string().repeat(MARKER_SIZE);
+                    lines.push(format!("{} ours", marker('<')));
+                    lines.push(to_line(o.clone())?);
+                    lines.push(marker('='));
+                    lines.push(to_line(t.clone())?);
+                    lines.push(format!("{} theirs", marker('>')));
+                    continue;
+                }
+            },
+            // Deleted on the other side: keep only if this side edited it
+            (Some(side), None) | (None, Some(side)) => match b {
+                Some(b) if b == side => continue,
+                _ => side.clone(),
+            },
+            (None, None) => continue,
+        };
+        lines.push(to_line(fields)?);
+    }
+
+    result.content = lines.iter().map(|l| format!("{l}\n")).collect();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::get_record_summary;
+
+    fn line(id: &str, content: &str, extra: &str) -> String {
+        format!(
+            r#"{{"type":"convention","id":"{id}","content":"{content}","classification":"tactical","recorded_at":"2024-01-01T00:00:00.000Z"{extra}}}"#
+        )
+    }
+
+    fn jsonl(lines: &[String]) -> String {
+        lines.iter().map(|l| format!("{l}\n")).collect()
+    }
+
+    fn merged(base: &[String], ours: &[String], theirs: &[String]) -> MergeResult {
+        merge_expertise(&jsonl(base), &jsonl(ours), &jsonl(theirs)).unwrap()
+    }
+
+    fn records(result: &MergeResult) -> Vec<ExpertiseRecord> {
+        parse_expertise(&result.content).unwrap()
+    }
+
+    #[test]
+    fn merge_applies_one_sided_edits_and_additions() {
+        let base = [line("mx-1", "A", ""), line("mx-2", "B", "")];
+        let ours = [line("mx-1", "A edited", ""), line("mx-2", "B", "")];
+        let theirs = [
+            line("mx-1", "A", ""),
+            line("mx-2", "B", r#","tags":["x"]"#),
+            line("mx-3", "C", ""),
+        ];
+        let result = merged(&base, &ours, &theirs);
+        assert!(result.conflicts.is_empty());
+
+        let recs = records(&result);
+        assert_eq!(recs.len(), 3);
+        assert_eq!(get_record_summary(&recs[0]), "A edited");
+        assert_eq!(recs[1].tags(), Some(&["x".to_string()][..]));
+        assert_eq!(recs[2].id(), Some("mx-3"));
+    }
+
+    #[test]
+    fn merge_respects_deletions() {
+        let base = [line("mx-1", "A", ""), line("mx-2", "B", "")];
+        let ours = [line("mx-2", "B edited", "")];
+        let theirs = [line("mx-1", "A", "")];
+        // mx-1 deleted by ours; mx-2 deleted by theirs but edited by ours
+        let recs = records(&merged(&base, &ours, &theirs));
+        assert_eq!(recs.len(), 1);
+        assert_eq!(get_record_summary(&recs[0]), "B edited");
+    }
+
+    #[test]
+    fn merge_concurrent_edits_use_latest_updated_at() {
+        let base = [line("mx-1", "A", "")];
+        let ours = [line(
+            "mx-1",
+            "Ours",
+            r#","updated_at":"2024-02-01T00:00:00.000Z""#,
+        )];
+        let theirs = [line(
+            "mx-1",
+            "Theirs",
+            r#","updated_at":"2024-03-01T00:00:00.000Z""#,
+        )];
+        let result = merged(&base, &ours, &theirs);
+        assert_eq!(result.resolved, vec!["mx-1".to_string()]);
+        let recs = records(&result);
+        assert_eq!(get_record_summary(&recs[0]), "Theirs");
+        assert_eq!(recs[0].updated_at(), Some("2024-03-01T00:00:00.000Z"));
+    }
+
+    #[test]
+    fn merge_conflict_markers_without_timestamp_order() {
+        let base = [line("mx-1", "A", "")];
+        let result = merged(
+            &base,
+            &[line("mx-1", "Ours", "")],
+            &[line("mx-1", "Theirs", "")],
+        );
+        assert_eq!(result.conflicts, vec!["mx-1".to_string()]);
+        assert!(result.content.starts_with("<<<<<<< ours\n"));
+        assert!(result.content.contains("\n=======\n"));
+        assert!(result.content.ends_with(">>>>>>> theirs\n"));
+    }
+
+    #[test]
+    fn merge_unions_outcomes_and_folds_duplicates() {
+        let success = r#","outcomes":[{"status":"success"}]"#;
+        let failure = r#","outcomes":[{"status":"failure"}]"#;
+        let base = [line("mx-1", "A", "")];
+        let ours = [line("mx-1", "A", success)];
+        // A previous union merge left two copies of the record
+        let theirs = [line("mx-1", "A", failure), line("mx-1", "A", "")];
+        let result = merged(&base, &ours, &theirs);
+        assert!(result.conflicts.is_empty());
+        let recs = records(&result);
+        assert_eq!(recs.len(), 1);
+        assert_eq!(recs[0].outcomes().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::access_log::AccessLogEntry;
+use crate::changelog::ChangelogEntry;
+use crate::types::{Classification, ExpertiseRecord, OutcomeStatus, PromotionPolicy};
+
+/// Outcome and feedback counts for a record.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Evidence {
+    pub successes: u32,
+    pub failures: u32,
+    pub helpful: u32,
+    pub not_helpful: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Promote,
+    Demote,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Promote => "promote",
+            Self::Demote => "demote",
+        }
+    }
+}
+
+/// A classification change proposed by the promotion policy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClassificationChange {
+    pub direction: Direction,
+    pub from: Classification,
+    pub to: Classification,
+    /// 1-based index into `promotion.promote` or `promotion.demote`.
+    pub rule: usize,
+    pub evidence: Evidence,
+}
+
+impl ClassificationChange {
+    /// E.g. "3 successes, 0 failures, 1 helpful, 0 not-helpful (promote rule 1)".
+    pub fn reason(&self) -> String {
+        let e = &self.evidence;
+        format!(
+            "{} successes, {} failures, {} helpful, {} not-helpful ({} rule {})",
+            e.successes,
+            e.failures,
+            e.helpful,
+            e.not_helpful,
+            self.direction.as_str(),
+            self.rule
+        )
+    }
+}
+
+/// When each record's classification last changed, per record ID, from the
+/// changelog entries that carry a `classification` diff.
+pub fn reclassified_at(entries: &[ChangelogEntry]) -> HashMap<String, DateTime<Utc>> {
+    let mut times: HashMap<String, DateTime<Utc>> = HashMap::new();
+    for entry in entries.iter().filter(|e| {
+        e.diff
+            .as_ref()
+            .is_some_and(|d| d.contains_key("classification"))
+    }) {
+        let slot = times
+            .entry(entry.entry_id.clone())
+            .or_insert(entry.timestamp);
+        *slot = (*slot).max(entry.timestamp);
+    }
+    times
+}
+
+/// `kb_feedback` signals per record ID, as (helpful, not-helpful). Signals
+/// given before a record's last reclassification are skipped: they were
+/// already acted on.
+pub fn feedback_counts(
+    entries: &[AccessLogEntry],
+    reclassified: &HashMap<String, DateTime<Utc>>,
+) -> HashMap<String, (u32, u32)> {
+    let mut counts: HashMap<String, (u32, u32)> = HashMap::new();
+    for entry in entries.iter().filter(|e| e.tool == "feedback") {
+        let (Some(id), Some(signal)) = (entry.entry_id.as_deref(), entry.signal.as_deref()) else {
+            continue;
+        };
+        let id = if id.starts_with("mx-") {
+            id.to_string()
+        } else {
+            format!("mx-{id}")
+        };
+        if reclassified.get(&id).is_some_and(|&t| entry.timestamp <= t) {
+            continue;
+        }
+        let slot = counts.entry(id).or_default();
+        match signal.trim().to_lowercase().as_str() {
+            "helpful" => slot.0 += 1,
+            "not-helpful" | "not_helpful" | "unhelpful" => slot.1 += 1,
+            _ => {}
+        }
+    }
+    counts
+}
+
+/// Count the outcomes of `record` and the feedback given on it. Once a record
+/// has been reclassified (`since`), only outcomes recorded after that count,
+/// so the same evidence cannot move it again.
+pub fn evidence_for(
+    record: &ExpertiseRecord,
+    feedback: &HashMap<String, (u32, u32)>,
+    since: Option<DateTime<Utc>>,
+) -> Evidence {
+    let mut evidence = Evidence::default();
+    for outcome in record.outcomes().unwrap_or_default() {
+        if let Some(since) = since {
+            let after = outcome
+                .recorded_at
+                .as_deref()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .is_some_and(|t| t.with_timezone(&Utc) > since);
+            if !after {
+                continue;
+            }
+        }
+        match outcome.status {
+            OutcomeStatus::Success => evidence.successes += 1,
+            OutcomeStatus::Failure => evidence.failures += 1,
+            OutcomeStatus::Partial => {}
+        }
+    }
+    if let Some(&(helpful, not_helpful)) = record.id().and_then(|id| feedback.get(id)) {
+        evidence.helpful = helpful;
+        evidence.not_helpful = not_helpful;
+    }
+    evidence
+}
+
+fn reached(count: u32, threshold: Option<u32>) -> bool {
+    threshold.is_some_and(|t| count >= t)
+}
+
+/// The first demotion rule, or failing that the first promotion rule, that
+/// applies to a record of `classification` with `evidence`.
+pub fn evaluate(
+    classification: Classification,
+    evidence: Evidence,
+    policy: &PromotionPolicy,
+) -> Option<ClassificationChange> {
+    let demotion = policy.demote.iter().position(|rule| {
+        rule.from == classification
+            && rule.to != classification
+            && (reached(evidence.failures, rule.min_failures)
+                || reached(evidence.not_helpful, rule.min_not_helpful))
+    });
+    if let Some(i) = demotion {
+        return Some(ClassificationChange {
+            direction: Direction::Demote,
+            from: classification,
+            to: policy.demote[i].to,
+            rule: i + 1,
+            evidence,
+        });
+    }
+
+    let promotion = policy.promote.iter().position(|rule| {
+        rule.from == classification
+            && rule.to != classification
+            && evidence.failures <= rule.max_failures
+            && (reached(evidence.successes, rule.min_successes)
+                || reached(evidence.helpful, rule.min_helpful))
+    })?;
+    Some(ClassificationChange {
+        direction: Direction::Promote,
+        from: classification,
+        to: policy.promote[promotion].to,
+        rule: promotion + 1,
+        evidence,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evidence(successes: u32, failures: u32, helpful: u32, not_helpful: u32) -> Evidence {
+        Evidence {
+            successes,
+            failures,
+            helpful,
+            not_helpful,
+        }
+    }
+
+    #[test]
+    fn default_policy_promotes_and_demotes() {
+        let policy = PromotionPolicy::default();
+        let change = evaluate(Classification::Tactical, evidence(3, 0, 0, 0), &policy).unwrap();
+        assert_eq!(change.direction, Direction::Promote);
+        assert_eq!(change.to, Classification::Foundational);
+        assert_eq!(
+            change.reason(),
+            "3 successes, 0 failures, 0 helpful, 0 not-helpful (promote rule 1)"
+        );
+
+        // Not enough successes, or any failure, blocks promotion
+        assert!(evaluate(Classification::Tactical, evidence(2, 0, 0, 0), &policy).is_none());
+        assert!(evaluate(Classification::Tactical, evidence(5, 1, 0, 0), &policy).is_none());
+
+        let change = evaluate(Classification::Foundational, evidence(4, 2, 0, 0), &policy).unwrap();
+        assert_eq!(change.direction, Direction::Demote);
+        assert_eq!(change.to, Classification::Tactical);
+        assert!(evaluate(Classification::Foundational, evidence(9, 1, 0, 0), &policy).is_none());
+    }
+
+    #[test]
+    fn feedback_thresholds_and_counts() {
+        let policy: PromotionPolicy = serde_yaml::from_str(
+            "promote:\n- from: observational\n  to: tactical\n  min_helpful: 2\ndemote:\n- from: tactical\n  to: observational\n  min_not_helpful: 3\n",
+        )
+        .unwrap();
+        let entry = |id: &str, signal: &str| AccessLogEntry {
+            session_id: "s1".to_string(),
+            timestamp: chrono::Utc::now(),
+            tool: "feedback".to_string(),
+            domain: None,
+            query: None,
+            entry_id: Some(id.to_string()),
+            entry_ids: None,
+            result_count: None,
+            signal: Some(signal.to_string()),
+        };
+        let entries = [
+            entry("mx-abc123", "helpful"),
+            entry("abc123", "helpful"),
+            entry("mx-def456", "not-helpful"),
+        ];
+        let counts = feedback_counts(&entries, &HashMap::new());
+        assert_eq!(counts["mx-abc123"], (2, 0));
+        assert_eq!(counts["mx-def456"], (0, 1));
+
+        // Feedback given before a reclassification no longer counts
+        let counts = feedback_counts(
+            &entries,
+            &HashMap::from([("mx-abc123".to_string(), chrono::Utc::now())]),
+        );
+        assert!(!counts.contains_key("mx-abc123"));
+        assert_eq!(counts["mx-def456"], (0, 1));
+
+        let change =
+            evaluate(Classification::Observational, evidence(0, 0, 2, 0), &policy).unwrap();
+        assert_eq!(
+            (change.direction, change.to),
+            (Direction::Promote, Classification::Tactical)
+        );
+        let change = evaluate(Classification::Tactical, evidence(0, 0, 5, 3), &policy).unwrap();
+        assert_eq!(change.direction, Direction::Demote);
+        assert!(evaluate(Classification::Foundational, evidence(9, 0, 9, 0), &policy).is_none());
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::churn::record_paths;
+use crate::git::file_matches_any;
+use crate::types::ExpertiseRecord;
+
+/// Why a record relates to a set of changed files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Relevance {
+    /// Changed files matching the record's `files`, evidence or in-text paths.
+    pub files: Vec<String>,
+    /// Record tags naming a directory or file stem of a changed file.
+    pub tags: Vec<String>,
+}
+
+impl Relevance {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.tags.is_empty()
+    }
+}
+
+/// Path components and file stems of a changed file, lowercased.
+fn path_words(file: &str) -> Vec<String> {
+    let path = Path::new(file);
+    let mut words: Vec<String> = path
+        .parent()
+        .into_iter()
+        .flat_map(|p| p.components())
+        .filter_map(|c| c.as_os_str().to_str())
+        .map(str::to_lowercase)
+        .collect();
+    if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+        words.push(stem.to_lowercase());
+    }
+    words
+}
+
+/// How `record` relates to `changed_files`. A referenced path matches a
+/// changed file by suffix (as in `kb prime --files`) or as its directory.
+pub fn relevance(record: &ExpertiseRecord, changed_files: &[String]) -> Relevance {
+    let paths = record_paths(record);
+    let files = changed_files
+        .iter()
+        .filter(|changed| {
+            file_matches_any(changed, &paths)
+                || paths.iter().any(|p| {
+                    changed
+                        .strip_prefix(p.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+                })
+        })
+        .cloned()
+        .collect();
+
+    let words: Vec<String> = changed_files.iter().flat_map(|f| path_words(f)).collect();
+    let tags = record
+        .tags()
+        .into_iter()
+        .flatten()
+        .filter(|tag| words.contains(&tag.to_lowercase()))
+        .cloned()
+        .collect();
+
+    Relevance { files, tags }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Classification;
+
+    fn convention(content: &str, tags: &[&str]) -> ExpertiseRecord {
+        ExpertiseRecord::Convention {
+            id: Some("mx-000001".to_string()),
+            content: content.to_string(),
+            classification: Classification::Tactical,
+            recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: None,
+            tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+            relates_to: None,
+            supersedes: None,
+            outcomes: None,
+        }
+    }
+
+    #[test]
+    fn relevance_matches_paths_and_tags() {
+        let changed = vec![
+            "crates/kb-core/src/storage.rs".to_string(),
+            "docs/intro.md".to_string(),
+        ];
+        let record = convention("Keep src/storage.rs free of locking", &["Storage", "cli"]);
+        let rel = relevance(&record, &changed);
+        assert_eq!(rel.files, vec!["crates/kb-core/src/storage.rs"]);
+        assert_eq!(rel.tags, vec!["Storage"]);
+
+        let unrelated = convention("Use tabs", &["formatting"]);
+        assert!(relevance(&unrelated, &changed).is_empty());
+    }
+
+    #[test]
+    fn relevance_matches_directories() {
+        let changed = vec!["crates/kb/src/commands/learn.rs".to_string()];
+        let record = ExpertiseRecord::Pattern {
+            id: Some("mx-000002".to_string()),
+            name: "Command modules".to_string(),
+            description: "One file per command".to_string(),
+            files: Some(vec!["crates/kb/src/commands/".to_string()]),
+            classification: Classification::Foundational,
+            recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: None,
+            tags: None,
+            relates_to: None,
+            supersedes: None,
+            outcomes: None,
+        };
+        assert_eq!(relevance(&record, &changed).files, changed);
+    }
+}
@@ -54,6 +54,8 @@ mod tests {
             content: format!("content for {id}"),
             classification: Classification::Foundational,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
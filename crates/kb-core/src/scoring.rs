@@ -100,6 +100,8 @@ mod tests {
             content: "test".to_string(),
             classification: Classification::Tactical,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
@@ -115,6 +117,8 @@ mod tests {
             content: "test".to_string(),
             classification: Classification::Tactical,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
@@ -165,6 +169,8 @@ mod tests {
             content: "test".to_string(),
             classification: Classification::Tactical,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
@@ -122,6 +122,11 @@ fn extract_record_text(record: &ExpertiseRecord) -> (String, HashMap<String, Str
         add_array_field("tags", tags, &mut field_texts, &mut all_parts);
     }
 
+    // Index the ID so records listed by ID (e.g. the prime overflow index) can be looked up
+    if let Some(id) = record.id() {
+        add_field("id", id, &mut field_texts, &mut all_parts);
+    }
+
     (all_parts.join(" "), field_texts)
 }
 
@@ -265,6 +270,8 @@ mod tests {
             content: content.to_string(),
             classification: Classification::Foundational,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
@@ -281,6 +288,8 @@ mod tests {
             files: None,
             classification: Classification::Tactical,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
@@ -335,4 +344,15 @@ mod tests {
             assert!(window[0].score >= window[1].score);
         }
     }
+
+    #[test]
+    fn finds_record_by_id() {
+        let mut records = vec![convention("First rule"), convention("Second rule")];
+        for (i, record) in records.iter_mut().enumerate() {
+            record.set_id(format!("mx-00000{i}"));
+        }
+        let results = search_records(&records, "mx-000001");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id(), Some("mx-000001"));
+    }
 }
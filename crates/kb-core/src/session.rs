@@ -100,7 +100,7 @@ pub fn list_sessions(cwd: &Path) -> Result<Vec<Session>> {
             sessions.push(session);
         }
     }
-    sessions.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.started_at));
     Ok(sessions)
 }
 
@@ -0,0 +1,532 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use minijinja::Environment;
+use serde_json::{Value, json};
+
+use crate::error::{KbError, Result};
+use crate::format::get_record_summary;
+use crate::template::SECTIONS;
+use crate::types::ExpertiseRecord;
+
+const PAGES: &[(&str, &str)] = &[
+    ("base.html", include_str!("../templates/site/base.html")),
+    ("index.html", include_str!("../templates/site/index.html")),
+    ("domain.html", include_str!("../templates/site/domain.html")),
+    ("record.html", include_str!("../templates/site/record.html")),
+    ("tags.html", include_str!("../templates/site/tags.html")),
+];
+
+const STYLE_CSS: &str = include_str!("../templates/site/style.css");
+const SEARCH_JS: &str = include_str!("../templates/site/search.js");
+
+/// A generated file, relative to the output directory.
+#[derive(Debug, Clone)]
+pub struct SiteFile {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// Filesystem-safe page name for a domain, record ID or tag.
+fn slug(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// `base`, or `base-2`, `base-3`, ... if that name is already taken. Names are
+/// compared case-insensitively since they become file names and anchors.
+fn unique_slug(base: String, taken: &mut HashSet<String>) -> String {
+    let mut name = base.clone();
+    let mut n = 2;
+    while !taken.insert(name.to_lowercase()) {
+        name = format!("{base}-{n}");
+        n += 1;
+    }
+    name
+}
+
+/// Labelled content fields of a record, in display order.
+fn record_fields(record: &ExpertiseRecord) -> Vec<(&'static str, &str)> {
+    match record {
+        ExpertiseRecord::Convention { content, .. } => vec![("Content", content)],
+        ExpertiseRecord::Pattern {
+            name, description, ..
+        }
+        | ExpertiseRecord::Reference {
+            name, description, ..
+        }
+        | ExpertiseRecord::Guide {
+            name, description, ..
+        } => vec![("Name", name), ("Description", description)],
+        ExpertiseRecord::Failure {
+            description,
+            resolution,
+            ..
+        } => vec![("Description", description), ("Resolution", resolution)],
+        ExpertiseRecord::Decision {
+            title,
+            rationale,
+            date,
+            ..
+        } => {
+            let mut fields = vec![("Title", title.as_str()), ("Rationale", rationale.as_str())];
+            if let Some(date) = date {
+                fields.push(("Date", date));
+            }
+            fields
+        }
+    }
+}
+
+struct Page<'a> {
+    domain: &'a str,
+    record: &'a ExpertiseRecord,
+    /// Page name without extension: the record ID when present, else domain
+    /// and position, with a numeric suffix if another record already has it.
+    name: String,
+}
+
+/// Link to another record by ID, resolved against the exported records.
+fn link_value(id: &str, pages: &HashMap<&str, &Page<'_>>) -> Value {
+    match pages.get(id) {
+        Some(page) => json!({
+            "id": id,
+            "href": format!("{}.html", page.name),
+            "title": get_record_summary(page.record),
+        }),
+        None => json!({ "id": id }),
+    }
+}
+
+fn record_value(
+    page: &Page<'_>,
+    pages: &HashMap<&str, &Page<'_>>,
+    backlinks: &[&Page<'_>],
+    tag_anchors: &HashMap<&str, String>,
+) -> Value {
+    let r = page.record;
+    let links = |ids: Option<&[String]>| -> Vec<Value> {
+        ids.unwrap_or_default()
+            .iter()
+            .map(|id| link_value(id, pages))
+            .collect()
+    };
+    let fields: Vec<Value> = record_fields(r)
+        .into_iter()
+        .map(|(label, value)| json!({ "label": label, "value": value }))
+        .collect();
+    json!({
+        "id": r.id(),
+        "page": format!("{}.html", page.name),
+        "domain": page.domain,
+        "domain_page": format!("{}.html", slug(page.domain)),
+        "type": r.record_type().as_str(),
+        "classification": r.classification().as_str(),
+        "recorded_at": r.recorded_at(),
+        "title": get_record_summary(r),
+        "fields": fields,
+        "files": r.files().unwrap_or_default(),
+        "tags": r.tags().unwrap_or_default().iter().map(|t| json!({
+            "name": t,
+            "anchor": tag_anchors[t.as_str()],
+        })).collect::<Vec<_>>(),
+        "evidence": r.evidence(),
+        "outcomes": r.outcomes().unwrap_or_default(),
+        "relates_to": links(r.relates_to()),
+        "supersedes": links(r.supersedes()),
+        "referenced_by": backlinks.iter().map(|p| json!({
+            "id": p.record.id(),
+            "href": format!("{}.html", p.name),
+            "title": get_record_summary(p.record),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// Searchable text of a record: every content field plus files.
+fn search_text(record: &ExpertiseRecord) -> String {
+    let mut parts: Vec<&str> = record_fields(record).into_iter().map(|(_, v)| v).collect();
+    parts.extend(
+        record
+            .files()
+            .unwrap_or_default()
+            .iter()
+            .map(String::as_str),
+    );
+    parts.join(" ")
+}
+
+/// Embed JSON in a `<script>`-loaded file without closing any surrounding tag.
+fn js_assign(name: &str, value: &Value) -> String {
+    format!(
+        "window.{name} = {};\n",
+        value.to_string().replace("</", "<\\/")
+    )
+}
+
+fn render_error(err: minijinja::Error) -> KbError {
+    KbError::Template(err.to_string())
+}
+
+/// Build a self-contained static site for browsing a knowledge base: an
+/// index with client-side search, one page per domain and per record, and a
+/// tag index. All assets are local; pages work from `file://`.
+pub fn build_site(
+    domains: &[(String, Vec<ExpertiseRecord>)],
+    title: &str,
+) -> Result<Vec<SiteFile>> {
+    let mut env = Environment::new();
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+    for (name, source) in PAGES {
+        env.add_template(name, source).map_err(render_error)?;
+    }
+
+    let mut pages: Vec<Page<'_>> = Vec::new();
+    let mut page_names = HashSet::new();
+    let mut tag_anchors: HashMap<&str, String> = HashMap::new();
+    let mut anchor_names = HashSet::new();
+    for (domain, records) in domains {
+        for (i, record) in records.iter().enumerate() {
+            let base = match record.id() {
+                Some(id) => slug(id),
+                None => format!("{}-{}", slug(domain), i + 1),
+            };
+            pages.push(Page {
+                domain,
+                record,
+                name: unique_slug(base, &mut page_names),
+            });
+            for tag in record.tags().unwrap_or_default() {
+                if !tag_anchors.contains_key(tag.as_str()) {
+                    let anchor = unique_slug(format!("tag-{}", slug(tag)), &mut anchor_names);
+                    tag_anchors.insert(tag, anchor);
+                }
+            }
+        }
+    }
+    let by_id: HashMap<&str, &Page<'_>> = pages
+        .iter()
+        .filter_map(|p| p.record.id().map(|id| (id, p)))
+        .collect();
+
+    // Backlinks: records that relate to or supersede each ID
+    let mut backlinks: HashMap<&str, Vec<&Page<'_>>> = HashMap::new();
+    for page in &pages {
+        let targets = page
+            .record
+            .relates_to()
+            .unwrap_or_default()
+            .iter()
+            .chain(page.record.supersedes().unwrap_or_default());
+        for target in targets {
+            backlinks.entry(target.as_str()).or_default().push(page);
+        }
+    }
+
+    let record_values: Vec<Value> = pages
+        .iter()
+        .map(|p| {
+            let refs = p
+                .record
+                .id()
+                .and_then(|id| backlinks.get(id))
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            record_value(p, &by_id, refs, &tag_anchors)
+        })
+        .collect();
+
+    let mut files = Vec::new();
+    let mut render = |template: &str, path: PathBuf, root: &str, ctx: Value| -> Result<()> {
+        let mut ctx = ctx;
+        ctx["site_title"] = json!(title);
+        ctx["root"] = json!(root);
+        let contents = env
+            .get_template(template)
+            .and_then(|t| t.render(&ctx))
+            .map_err(render_error)?;
+        files.push(SiteFile { path, contents });
+        Ok(())
+    };
+
+    // Domain pages
+    let mut domain_summaries = Vec::new();
+    let mut offset = 0;
+    for (domain, records) in domains {
+        let recs = &record_values[offset..offset + records.len()];
+        offset += records.len();
+        let groups: Vec<Value> = SECTIONS
+            .iter()
+            .filter_map(|(record_type, title)| {
+                let records: Vec<&Value> = recs
+                    .iter()
+                    .filter(|r| r["type"] == record_type.as_str())
+                    .collect();
+                (!records.is_empty()).then(|| json!({ "title": title, "records": records }))
+            })
+            .collect();
+        let page = format!("{}.html", slug(domain));
+        render(
+            "domain.html",
+            Path::new("domains").join(&page),
+            "../",
+            json!({ "domain": domain, "count": records.len(), "groups": groups }),
+        )?;
+        domain_summaries.push(json!({ "name": domain, "count": records.len(), "page": page }));
+    }
+
+    // Record pages
+    for value in &record_values {
+        let page = value["page"].as_str().unwrap_or_default().to_string();
+        render(
+            "record.html",
+            Path::new("records").join(page),
+            "../",
+            json!({ "record": value }),
+        )?;
+    }
+
+    // Tag index
+    let mut tags: BTreeMap<&str, Vec<&Value>> = BTreeMap::new();
+    for value in &record_values {
+        for tag in value["tags"].as_array().into_iter().flatten() {
+            tags.entry(tag["name"].as_str().unwrap_or_default())
+                .or_default()
+                .push(value);
+        }
+    }
+    let tag_list: Vec<Value> = tags
+        .iter()
+        .map(|(name, records)| {
+            json!({ "name": name, "anchor": tag_anchors[name], "records": records })
+        })
+        .collect();
+    render(
+        "tags.html",
+        PathBuf::from("tags.html"),
+        "",
+        json!({ "tags": tag_list }),
+    )?;
+
+    render(
+        "index.html",
+        PathBuf::from("index.html"),
+        "",
+        json!({ "domains": domain_summaries, "total": pages.len(), "tag_count": tags.len() }),
+    )?;
+
+    // Search index, also loadable via <script> since fetch() is blocked on file://
+    let index: Vec<Value> = pages
+        .iter()
+        .map(|p| {
+            json!({
+                "id": p.record.id(),
+                "domain": p.domain,
+                "type": p.record.record_type().as_str(),
+                "title": get_record_summary(p.record),
+                "text": search_text(p.record),
+                "tags": p.record.tags().unwrap_or_default(),
+                "url": format!("records/{}.html", p.name),
+            })
+        })
+        .collect();
+    let index = Value::Array(index);
+    files.push(SiteFile {
+        path: PathBuf::from("search-index.json"),
+        contents: serde_json::to_string(&index)?,
+    });
+    files.push(SiteFile {
+        path: PathBuf::from("search-index.js"),
+        contents: js_assign("KB_SEARCH_INDEX", &index),
+    });
+    files.push(SiteFile {
+        path: PathBuf::from("search.js"),
+        contents: SEARCH_JS.to_string(),
+    });
+    files.push(SiteFile {
+        path: PathBuf::from("style.css"),
+        contents: STYLE_CSS.to_string(),
+    });
+
+    Ok(files)
+}
+
+/// Lists the files of the last export, relative to the site directory.
+pub const MANIFEST: &str = ".kb-site.json";
+
+/// Write generated files under `dir`, creating subdirectories as needed.
+/// Pages listed in the previous export's manifest that are not generated
+/// again are removed, so deleted records and domains do not linger; files
+/// the manifest does not list are never touched.
+pub fn write_site(dir: &Path, files: &[SiteFile]) -> Result<()> {
+    let manifest_path = dir.join(MANIFEST);
+    let previous: Vec<String> = match std::fs::read_to_string(&manifest_path) {
+        Ok(content) => serde_json::from_str(&content)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let generated: Vec<String> = files
+        .iter()
+        .map(|f| f.path.to_string_lossy().replace('\\', "/"))
+        .collect();
+
+    for stale in previous.iter().filter(|p| !generated.contains(p)) {
+        // Only plain relative paths, in case the manifest was edited
+        let relative = Path::new(stale);
+        if relative.is_absolute()
+            || relative
+                .components()
+                .any(|c| !matches!(c, std::path::Component::Normal(_)))
+        {
+            continue;
+        }
+        match std::fs::remove_file(dir.join(relative)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    for file in files {
+        let path = dir.join(&file.path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, &file.contents)?;
+    }
+    std::fs::write(
+        &manifest_path,
+        serde_json::to_string_pretty(&generated)? + "\n",
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<ExpertiseRecord> {
+        serde_json::from_value(json!([
+            {"type": "convention", "id": "mx-000001", "content": "Escape <script> tags",
+             "classification": "foundational", "recorded_at": "2024-01-01T00:00:00Z",
+             "tags": ["security", "html"]},
+            {"type": "failure", "id": "mx-000002", "description": "XSS in preview",
+             "resolution": "Escape output", "classification": "tactical",
+             "recorded_at": "2024-01-02T00:00:00Z", "relates_to": ["mx-000001"],
+             "evidence": {"commit": "abc123"},
+             "outcomes": [{"status": "success", "agent": "ci"}], "tags": ["security"]},
+        ]))
+        .unwrap()
+    }
+
+    fn file<'a>(files: &'a [SiteFile], path: &str) -> &'a str {
+        &files
+            .iter()
+            .find(|f| f.path == Path::new(path))
+            .unwrap_or_else(|| panic!("missing {path}"))
+            .contents
+    }
+
+    #[test]
+    fn builds_pages_for_domains_records_and_tags() {
+        let files = build_site(&[("web".to_string(), records())], "KB").unwrap();
+
+        let index = file(&files, "index.html");
+        assert!(index.contains("href=\"domains/web.html\""));
+        assert!(index.contains("search-index.js"));
+
+        let domain = file(&files, "domains/web.html");
+        assert!(domain.contains("href=\"../records/mx-000001.html\""));
+
+        let record = file(&files, "records/mx-000002.html");
+        assert!(record.contains("abc123"));
+        assert!(record.contains("success"));
+        assert!(record.contains("href=\"mx-000001.html\""));
+
+        // Backlink from the related record
+        let related = file(&files, "records/mx-000001.html");
+        assert!(related.contains("Referenced by"));
+        assert!(related.contains("href=\"mx-000002.html\""));
+
+        let tags = file(&files, "tags.html");
+        assert!(tags.contains("id=\"tag-security\""));
+        assert!(tags.contains("id=\"tag-html\""));
+    }
+
+    #[test]
+    fn escapes_record_content() {
+        let files = build_site(&[("web".to_string(), records())], "KB").unwrap();
+        let record = file(&files, "records/mx-000001.html");
+        assert!(record.contains("Escape &lt;script&gt; tags"));
+        assert!(!record.contains("<script> tags"));
+
+        let js = file(&files, "search-index.js");
+        assert!(js.starts_with("window.KB_SEARCH_INDEX = "));
+        let json: Value = serde_json::from_str(file(&files, "search-index.json")).unwrap();
+        assert_eq!(json[1]["url"], "records/mx-000002.html");
+        assert_eq!(json[1]["text"], "XSS in preview Escape output");
+    }
+
+    #[test]
+    fn page_names_and_tag_anchors_are_unique() {
+        let mut other = records();
+        other.truncate(1);
+        if let ExpertiseRecord::Convention { tags, .. } = &mut other[0] {
+            *tags = Some(vec!["c++".to_string(), "c__".to_string()]);
+        }
+        let files = build_site(
+            &[("web".to_string(), records()), ("api".to_string(), other)],
+            "KB",
+        )
+        .unwrap();
+
+        // The same ID in two domains gets two pages
+        assert!(file(&files, "records/mx-000001.html").contains("../domains/web.html"));
+        assert!(file(&files, "records/mx-000001-2.html").contains("../domains/api.html"));
+        let api = file(&files, "domains/api.html");
+        assert!(api.contains("href=\"../records/mx-000001-2.html\""));
+
+        let tags = file(&files, "tags.html");
+        assert!(tags.contains("id=\"tag-c__\""));
+        assert!(tags.contains("id=\"tag-c__-2\""));
+    }
+
+    #[test]
+    fn write_site_removes_stale_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let foreign = dir.path().join("records").join("notes.html");
+        std::fs::create_dir_all(foreign.parent().unwrap()).unwrap();
+        std::fs::write(&foreign, "keep").unwrap();
+
+        let files = build_site(&[("web".to_string(), records())], "KB").unwrap();
+        write_site(dir.path(), &files).unwrap();
+        let stale = dir.path().join("records").join("mx-000002.html");
+        assert!(stale.exists());
+
+        // mx-000002 is gone from the next export; the foreign page survives
+        let files = build_site(&[("web".to_string(), records()[..1].to_vec())], "KB").unwrap();
+        write_site(dir.path(), &files).unwrap();
+        assert!(!stale.exists());
+        assert!(dir.path().join("records").join("mx-000001.html").exists());
+        assert_eq!(std::fs::read_to_string(&foreign).unwrap(), "keep");
+        let manifest = std::fs::read_to_string(dir.path().join(MANIFEST)).unwrap();
+        assert!(manifest.contains("records/mx-000001.html"));
+        assert!(!manifest.contains("mx-000002"));
+    }
+
+    #[test]
+    fn no_external_assets() {
+        let files = build_site(&[("web".to_string(), records())], "KB").unwrap();
+        for f in &files {
+            assert!(!f.contents.contains("http://"), "{:?}", f.path);
+            assert!(!f.contents.contains("https://"), "{:?}", f.path);
+        }
+    }
+}
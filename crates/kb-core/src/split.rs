@@ -0,0 +1,321 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::check::record_text;
+use crate::churn::record_paths;
+use crate::config::validate_domain_name;
+use crate::error::{KbError, Result};
+use crate::gaps::content_tokens;
+use crate::types::ExpertiseRecord;
+
+/// Default cap on the number of proposed sub-domains.
+pub const DEFAULT_MAX_GROUPS: usize = 4;
+
+/// Default minimum number of records in a proposed sub-domain.
+pub const DEFAULT_MIN_SIZE: usize = 3;
+
+/// What the records of a proposed sub-domain have in common, in order of
+/// preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitBasis {
+    Tag,
+    Path,
+    Term,
+}
+
+impl SplitBasis {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tag => "tag",
+            Self::Path => "path",
+            Self::Term => "term",
+        }
+    }
+}
+
+/// Records proposed to move into one sub-domain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitGroup {
+    /// Target domain, which must not exist yet.
+    pub domain: String,
+    pub basis: SplitBasis,
+    /// The shared tag, directory or term.
+    pub key: String,
+    pub ids: Vec<String>,
+}
+
+/// A reviewable split plan, as written by `kb split --plan` and executed by
+/// `kb split --apply`. Records not in any group stay in `domain`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitPlan {
+    pub domain: String,
+    pub groups: Vec<SplitGroup>,
+}
+
+/// The tags, directories and terms a record can be grouped by.
+fn record_keys(record: &ExpertiseRecord) -> HashSet<(SplitBasis, String)> {
+    let mut keys = HashSet::new();
+    for tag in record.tags().into_iter().flatten() {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() {
+            keys.insert((SplitBasis::Tag, tag));
+        }
+    }
+    for path in record_paths(record) {
+        if let Some((dir, _)) = path.rsplit_once('/') {
+            keys.insert((SplitBasis::Path, dir.to_string()));
+        }
+    }
+    for term in content_tokens(&record_text(record)) {
+        if term.len() >= 4 && !term.chars().all(|c| c.is_ascii_digit()) {
+            keys.insert((SplitBasis::Term, term));
+        }
+    }
+    keys
+}
+
+/// A valid domain name for records sharing `key`: `<domain>-<last segment>`.
+fn sub_domain_name(domain: &str, key: &str) -> String {
+    let segment = key.rsplit('/').next().unwrap_or(key);
+    let slug: String = segment
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        format!("{domain}-part")
+    } else {
+        format!("{domain}-{slug}")
+    }
+}
+
+/// Propose sub-domains for `records`. Groups are picked greedily: the key
+/// (tag, then directory, then term) shared by the most records not yet
+/// grouped wins, as long as it covers at least `min_size` of them and no
+/// more than half the domain. A key shared by most of the domain does not
+/// separate anything. Records matching no group stay where they are. Names
+/// already taken by one of the `existing` domains get a numeric suffix.
+pub fn propose_split(
+    domain: &str,
+    records: &[ExpertiseRecord],
+    existing: &[String],
+    max_groups: usize,
+    min_size: usize,
+) -> SplitPlan {
+    let keyed: Vec<(&str, HashSet<(SplitBasis, String)>)> = records
+        .iter()
+        .filter_map(|r| r.id().map(|id| (id, record_keys(r))))
+        .collect();
+
+    let mut members: HashMap<&(SplitBasis, String), Vec<usize>> = HashMap::new();
+    for (i, (_, keys)) in keyed.iter().enumerate() {
+        for key in keys {
+            members.entry(key).or_default().push(i);
+        }
+    }
+    let max_coverage = keyed.len() / 2;
+    members.retain(|_, m| m.len() <= max_coverage);
+
+    let mut grouped = vec![false; keyed.len()];
+    let mut names: HashSet<String> = existing.iter().cloned().collect();
+    let mut groups = Vec::new();
+    while groups.len() < max_groups {
+        let best = members
+            .iter()
+            .map(|(key, m)| (*key, m.iter().filter(|&&i| !grouped[i]).count()))
+            .filter(|(_, count)| *count >= min_size.max(1))
+            .min_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+        let Some(((basis, key), _)) = best else {
+            break;
+        };
+        let (basis, key) = (*basis, key.clone());
+
+        let mut ids = Vec::new();
+        for &i in &members[&(basis, key.clone())] {
+            if !grouped[i] {
+                grouped[i] = true;
+                ids.push(keyed[i].0.to_string());
+            }
+        }
+
+        let base = sub_domain_name(domain, &key);
+        let mut name = base.clone();
+        let mut n = 2;
+        while !names.insert(name.clone()) {
+            name = format!("{base}-{n}");
+            n += 1;
+        }
+        groups.push(SplitGroup {
+            domain: name,
+            basis,
+            key,
+            ids,
+        });
+    }
+
+    SplitPlan {
+        domain: domain.to_string(),
+        groups,
+    }
+}
+
+/// Remove the records named by `plan` from `records`, returning them per
+/// target domain in plan order. Fails without changing `records` if a group
+/// names an invalid, the source or one of the `existing` domains, or an ID
+/// that is unknown or listed twice.
+pub fn take_groups(
+    plan: &SplitPlan,
+    records: &mut Vec<ExpertiseRecord>,
+    existing: &[String],
+) -> Result<Vec<(String, Vec<ExpertiseRecord>)>> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    for group in &plan.groups {
+        validate_domain_name(&group.domain)?;
+        if group.domain == plan.domain {
+            return Err(KbError::ValidationError(format!(
+                "cannot split {} into itself",
+                plan.domain
+            )));
+        }
+        if existing.contains(&group.domain) {
+            return Err(KbError::ValidationError(format!(
+                "domain {} already exists; split into a new domain",
+                group.domain
+            )));
+        }
+        for id in &group.ids {
+            if !seen.insert(id) {
+                return Err(KbError::ValidationError(format!(
+                    "record {id} is listed in more than one group"
+                )));
+            }
+            if !records.iter().any(|r| r.id() == Some(id.as_str())) {
+                return Err(KbError::ValidationError(format!(
+                    "record {id} is not in {}",
+                    plan.domain
+                )));
+            }
+        }
+    }
+
+    let mut moved: Vec<(String, Vec<ExpertiseRecord>)> = Vec::new();
+    for group in &plan.groups {
+        let ids: HashSet<&str> = group.ids.iter().map(String::as_str).collect();
+        let (taken, kept) = std::mem::take(records)
+            .into_iter()
+            .partition(|r| r.id().is_some_and(|id| ids.contains(id)));
+        *records = kept;
+        match moved.iter_mut().find(|(d, _)| *d == group.domain) {
+            Some((_, list)) => list.extend(taken),
+            None => moved.push((group.domain.clone(), taken)),
+        }
+    }
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Classification;
+
+    fn convention(id: &str, content: &str, tags: &[&str]) -> ExpertiseRecord {
+        ExpertiseRecord::Convention {
+            id: Some(id.to_string()),
+            content: content.to_string(),
+            classification: Classification::Tactical,
+            recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: None,
+            tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+            relates_to: None,
+            supersedes: None,
+            outcomes: None,
+        }
+    }
+
+    fn domain_records() -> Vec<ExpertiseRecord> {
+        vec![
+            convention("mx-000001", "Handlers return typed errors", &["api"]),
+            convention("mx-000002", "Version every endpoint", &["api"]),
+            convention("mx-000003", "Paginate list endpoints", &["API"]),
+            convention("mx-000004", "Migrations run from src/db/migrate.rs", &[]),
+            convention("mx-000005", "Never edit applied src/db/schema.sql", &[]),
+            convention("mx-000006", "Queries go through src/db/pool.rs", &[]),
+            convention("mx-000007", "Log with structured fields", &[]),
+            convention("mx-000008", "Keep secrets out of logs", &[]),
+        ]
+    }
+
+    #[test]
+    fn propose_split_groups_by_tag_then_path() {
+        let plan = propose_split("backend", &domain_records(), &[], 4, 3);
+        let groups: Vec<(&str, SplitBasis, &str, usize)> = plan
+            .groups
+            .iter()
+            .map(|g| (g.domain.as_str(), g.basis, g.key.as_str(), g.ids.len()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                ("backend-api", SplitBasis::Tag, "api", 3),
+                ("backend-db", SplitBasis::Path, "src/db", 3),
+            ]
+        );
+
+        // Names of existing domains are not proposed
+        let existing = ["backend-api".to_string()];
+        let plan = propose_split("backend", &domain_records(), &existing, 4, 3);
+        assert_eq!(plan.groups[0].domain, "backend-api-2");
+
+        // Nothing reaches the minimum size
+        assert!(
+            propose_split("backend", &domain_records(), &[], 4, 5)
+                .groups
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn take_groups_moves_records_and_validates() {
+        let mut records = domain_records();
+        let plan = propose_split("backend", &records, &[], 4, 3);
+        let moved = take_groups(&plan, &mut records, &[]).unwrap();
+        assert_eq!(moved.len(), 2);
+        assert_eq!(moved[0].1[0].id(), Some("mx-000001"));
+        assert_eq!(records.len(), 2);
+
+        let mut records = domain_records();
+        let bad = SplitPlan {
+            domain: "backend".to_string(),
+            groups: vec![SplitGroup {
+                domain: "backend-x".to_string(),
+                basis: SplitBasis::Term,
+                key: "x".to_string(),
+                ids: vec!["mx-000001".to_string(), "mx-999999".to_string()],
+            }],
+        };
+        assert!(matches!(
+            take_groups(&bad, &mut records, &[]),
+            Err(KbError::ValidationError(_))
+        ));
+        assert_eq!(records.len(), 8);
+
+        // Targets must be new domains
+        let plan = propose_split("backend", &records, &[], 4, 3);
+        assert!(matches!(
+            take_groups(&plan, &mut records, &["backend-db".to_string()]),
+            Err(KbError::ValidationError(_))
+        ));
+        assert_eq!(records.len(), 8);
+    }
+}
@@ -8,14 +8,17 @@ use crate::types::ExpertiseRecord;
 
 /// Read all records from a JSONL expertise file.
 /// Returns an empty vec if the file doesn't exist.
-/// Handles legacy `outcome` (singular) → `outcomes` (array) migration.
 pub fn read_expertise_file(file_path: &Path) -> Result<Vec<ExpertiseRecord>> {
-    let content = match fs::read_to_string(file_path) {
-        Ok(c) => c,
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
-        Err(e) => return Err(e.into()),
-    };
+    match fs::read_to_string(file_path) {
+        Ok(content) => parse_expertise(&content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
 
+/// Parse JSONL expertise content.
+/// Handles legacy `outcome` (singular) → `outcomes` (array) migration.
+pub fn parse_expertise(content: &str) -> Result<Vec<ExpertiseRecord>> {
     let mut records = Vec::new();
     for line in content.lines() {
         let trimmed = line.trim();
@@ -100,6 +103,8 @@ mod tests {
             content: content.to_string(),
             classification: Classification::Foundational,
             recorded_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: None,
+            branch: None,
             evidence: None,
             tags: None,
             relates_to: None,
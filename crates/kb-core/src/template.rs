@@ -0,0 +1,470 @@
+use std::path::{Path, PathBuf};
+
+use minijinja::{AutoEscape, Environment};
+use serde_json::{Value, json};
+
+use crate::config::get_kb_dir;
+use crate::error::{KbError, Result};
+use crate::format::{
+    format_links, format_outcome, format_record_meta, format_time_ago, get_record_summary,
+    truncate, xml_escape,
+};
+use crate::types::{ExpertiseRecord, RecordType};
+
+/// Built-in templates, used for `kb prime` output unless `--template` names another.
+pub const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("compact", include_str!("../templates/compact.j2")),
+    ("markdown", include_str!("../templates/markdown.j2")),
+    ("plain", include_str!("../templates/plain.j2")),
+    ("xml", include_str!("../templates/xml.j2")),
+];
+
+/// Prefix that always resolves to a built-in, so a user template can extend
+/// the built-in it shadows: `{% extends "builtin/markdown" %}`.
+const BUILTIN_PREFIX: &str = "builtin/";
+
+const TEMPLATE_EXT: &str = "j2";
+
+/// Block rendered when only the domain sections are wanted (`kb query`).
+const DOMAINS_BLOCK: &str = "domains";
+
+/// Section order and headings used by the built-in templates.
+pub const SECTIONS: &[(RecordType, &str)] = &[
+    (RecordType::Convention, "Conventions"),
+    (RecordType::Pattern, "Patterns"),
+    (RecordType::Failure, "Known Failures"),
+    (RecordType::Decision, "Decisions"),
+    (RecordType::Reference, "References"),
+    (RecordType::Guide, "Guides"),
+];
+
+pub fn get_templates_dir(cwd: &Path) -> PathBuf {
+    get_kb_dir(cwd).join("templates")
+}
+
+fn builtin_source(name: &str) -> Option<&'static str> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, src)| *src)
+}
+
+/// One domain passed to a template.
+pub struct TemplateDomain<'a> {
+    pub name: &'a str,
+    pub records: &'a [&'a ExpertiseRecord],
+    pub last_updated: Option<&'a str>,
+}
+
+/// Everything a template can render.
+pub struct TemplateInput<'a> {
+    pub domains: Vec<TemplateDomain<'a>>,
+    /// Show classification, evidence and tags (`--full`).
+    pub full: bool,
+    /// Records dropped by the budget that fit in the overflow index.
+    pub overflow: &'a [(&'a str, &'a ExpertiseRecord)],
+    /// Dropped records left out of the overflow index: (records, domains).
+    pub truncated: Option<(usize, usize)>,
+}
+
+/// Serialize a record with the pre-rendered fragments templates commonly need.
+fn record_value(record: &ExpertiseRecord) -> Value {
+    let mut value = serde_json::to_value(record).unwrap_or(Value::Null);
+    if let Some(obj) = value.as_object_mut() {
+        // Whole-number durations print without a fractional part
+        if let Some(Value::Array(outcomes)) = obj.get_mut("outcomes") {
+            for o in outcomes {
+                if let Some(d) = o.get("duration").and_then(Value::as_f64)
+                    && d.fract() == 0.0
+                {
+                    o["duration"] = json!(d as i64);
+                }
+            }
+        }
+        obj.insert("summary".into(), json!(get_record_summary(record)));
+        obj.insert("links".into(), json!(format_links(record)));
+        obj.insert("outcome".into(), json!(format_outcome(record.outcomes())));
+        obj.insert("meta".into(), json!(format_record_meta(record, true)));
+    }
+    value
+}
+
+fn domain_value(domain: &TemplateDomain<'_>) -> Value {
+    let sections: Vec<Value> = SECTIONS
+        .iter()
+        .filter_map(|(record_type, title)| {
+            let records: Vec<Value> = domain
+                .records
+                .iter()
+                .filter(|r| r.record_type() == *record_type)
+                .map(|r| record_value(r))
+                .collect();
+            (!records.is_empty()).then(|| {
+                json!({
+                    "type": record_type.as_str(),
+                    "title": title,
+                    "records": records,
+                })
+            })
+        })
+        .collect();
+    json!({
+        "name": domain.name,
+        "count": domain.records.len(),
+        "updated": domain.last_updated.map(format_time_ago),
+        "records": domain.records.iter().map(|r| record_value(r)).collect::<Vec<_>>(),
+        "sections": sections,
+    })
+}
+
+impl TemplateInput<'_> {
+    fn to_value(&self) -> Value {
+        let overflow: Vec<Value> = self
+            .overflow
+            .iter()
+            .map(|(domain, r)| {
+                json!({
+                    "domain": domain,
+                    "id": r.id().unwrap_or("-"),
+                    "type": r.record_type().as_str(),
+                    "summary": get_record_summary(r),
+                })
+            })
+            .collect();
+        json!({
+            "domains": self.domains.iter().map(domain_value).collect::<Vec<_>>(),
+            "full": self.full,
+            "overflow": overflow,
+            "truncated": self.truncated.map(|(records, domains)| json!({
+                "records": records,
+                "domains": domains,
+                "message": crate::budget::format_budget_summary(records, domains),
+            })),
+        })
+    }
+}
+
+/// Output templates for `kb prime` and `kb query`: the built-ins plus any
+/// `<name>.j2` files in `.kb/templates/`, which take precedence.
+pub struct Templates {
+    env: Environment<'static>,
+    dir: PathBuf,
+}
+
+impl Templates {
+    pub fn load(cwd: &Path) -> Self {
+        let dir = get_templates_dir(cwd);
+        let mut env = Environment::new();
+        env.set_trim_blocks(true);
+        env.set_lstrip_blocks(true);
+        // Escaping is explicit (`|xml`); the default would HTML-escape a template named "xml"
+        env.set_auto_escape_callback(|_| AutoEscape::None);
+        env.add_filter("shorten", |text: String, len: usize| truncate(&text, len));
+        env.add_filter("xml", |text: String| xml_escape(&text));
+
+        let user_dir = dir.clone();
+        env.set_loader(move |name| {
+            if let Some(builtin) = name.strip_prefix(BUILTIN_PREFIX) {
+                return Ok(builtin_source(builtin).map(str::to_string));
+            }
+            let path = user_dir.join(format!("{name}.{TEMPLATE_EXT}"));
+            match std::fs::read_to_string(&path) {
+                Ok(source) => Ok(Some(source)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    Ok(builtin_source(name).map(str::to_string))
+                }
+                Err(e) => Err(minijinja::Error::new(
+                    minijinja::ErrorKind::InvalidOperation,
+                    format!("failed to read {}", path.display()),
+                )
+                .with_source(e)),
+            }
+        });
+        Self { env, dir }
+    }
+
+    /// Names of all available templates, user templates first.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != TEMPLATE_EXT {
+                    return None;
+                }
+                Some(path.file_stem()?.to_string_lossy().into_owned())
+            })
+            .collect();
+        names.sort();
+        for (name, _) in BUILTIN_TEMPLATES {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+        names
+    }
+
+    fn get(&self, name: &str) -> Result<minijinja::Template<'_, '_>> {
+        self.env.get_template(name).map_err(|e| {
+            if e.kind() == minijinja::ErrorKind::TemplateNotFound {
+                KbError::TemplateNotFound {
+                    name: name.to_string(),
+                    available: self.names().join(", "),
+                }
+            } else {
+                template_error(e)
+            }
+        })
+    }
+
+    /// Render a complete prime document.
+    pub fn render(&self, name: &str, input: &TemplateInput<'_>) -> Result<String> {
+        let out = self
+            .get(name)?
+            .render(input.to_value())
+            .map_err(template_error)?;
+        Ok(out.trim_end().to_string())
+    }
+
+    /// Render only the template's `domains` block.
+    pub fn render_domains(&self, name: &str, input: &TemplateInput<'_>) -> Result<String> {
+        let mut rendered = self
+            .get(name)?
+            .render_captured(input.to_value())
+            .map_err(template_error)?;
+        let out = rendered
+            .with_state_mut(|state| state.render_block(DOMAINS_BLOCK))
+            .map_err(template_error)?;
+        Ok(out.trim_end().to_string())
+    }
+}
+
+fn template_error(err: minijinja::Error) -> KbError {
+    let mut msg = err.to_string();
+    let mut source = std::error::Error::source(&err);
+    while let Some(cause) = source {
+        msg.push_str(&format!(": {cause}"));
+        source = cause.source();
+    }
+    KbError::Template(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::*;
+    use crate::types::{Classification, Evidence, Outcome, OutcomeStatus};
+    use pretty_assertions::assert_eq;
+
+    fn sample_records() -> Vec<ExpertiseRecord> {
+        serde_json::from_value(json!([
+            {"type": "convention", "id": "mx-000001", "content": "Use tabs & spaces <never>.",
+             "classification": "foundational", "recorded_at": "2024-01-01T00:00:00Z",
+             "tags": ["style"]},
+            {"type": "pattern", "id": "mx-000002", "name": "Builder", "description": "Use builders",
+             "files": ["src/a.rs", "src/b.rs"], "classification": "tactical",
+             "recorded_at": "2024-01-01T00:00:00Z", "relates_to": ["mx-000001"],
+             "branch": "feature/builders"},
+            {"type": "failure", "id": "mx-000003", "description": "Crash on start",
+             "resolution": "Set HOME", "classification": "tactical",
+             "recorded_at": "2024-01-01T00:00:00Z"},
+            {"type": "decision", "id": "mx-000004", "title": "SQLite", "rationale": "Simple",
+             "classification": "observational", "recorded_at": "2024-01-01T00:00:00Z",
+             "supersedes": ["mx-000009"]},
+            {"type": "reference", "id": "mx-000005", "name": "Spec", "description": "The spec",
+             "files": ["docs/spec.md"], "classification": "foundational",
+             "recorded_at": "2024-01-01T00:00:00Z"},
+            {"type": "guide", "id": "mx-000006", "name": "Release", "description": "Tag and push",
+             "classification": "tactical", "recorded_at": "2024-01-01T00:00:00Z"},
+        ]))
+        .unwrap()
+    }
+
+    fn with_outcome(mut records: Vec<ExpertiseRecord>) -> Vec<ExpertiseRecord> {
+        if let ExpertiseRecord::Convention {
+            outcomes, evidence, ..
+        } = &mut records[0]
+        {
+            *outcomes = Some(vec![Outcome {
+                status: OutcomeStatus::Success,
+                duration: Some(1500.0),
+                test_results: Some("ok".into()),
+                agent: Some("bot".into()),
+                notes: None,
+                recorded_at: None,
+            }]);
+            *evidence = Some(Evidence {
+                commit: Some("abc123".into()),
+                ..Default::default()
+            });
+        }
+        records
+    }
+
+    fn render(name: &str, domains: &[(&str, &[&ExpertiseRecord])], full: bool) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let input = TemplateInput {
+            domains: domains
+                .iter()
+                .map(|(name, records)| TemplateDomain {
+                    name,
+                    records,
+                    last_updated: None,
+                })
+                .collect(),
+            full,
+            overflow: &[],
+            truncated: None,
+        };
+        Templates::load(dir.path()).render(name, &input).unwrap()
+    }
+
+    #[test]
+    fn builtins_render_each_format() {
+        let records = with_outcome(sample_records());
+        let refs: Vec<&ExpertiseRecord> = records.iter().collect();
+        let other: Vec<&ExpertiseRecord> = refs[2..4].to_vec();
+        let domains: [(&str, &[&ExpertiseRecord]); 2] = [("api", &refs), ("db", &other)];
+
+        let compact = render("compact", &domains, false);
+        assert!(compact.starts_with("# Project Expertise (via KB)\n\n## api (6 records)\n"));
+        assert!(compact.contains(
+            "- [convention] Use tabs & spaces <never>. (mx-000001) [✓ 1500ms @bot]\n\
+             - [pattern] Builder: Use builders (src/a.rs, src/b.rs) (mx-000002) [branch: feature/builders; relates to: mx-000001]\n"
+        ));
+        assert!(compact.contains(
+            "\n\n## db (2 records)\n\
+             - [failure] Crash on start → Set HOME (mx-000003)\n\
+             - [decision] SQLite: Simple (mx-000004) [supersedes: mx-000009]\n\n## Quick Reference\n"
+        ));
+        assert!(compact.ends_with("Unrecorded learnings are lost for the next session."));
+
+        let md = render("markdown", &domains, false);
+        assert!(md.contains("## Rules\n"));
+        assert!(md.contains("### Conventions\n- [mx-000001] Use tabs & spaces <never>.\n"));
+        assert!(md.contains("- [mx-000003] Crash on start\n  → Set HOME\n"));
+        let full = render("markdown", &domains, true);
+        assert!(full.contains(
+            "- [mx-000001] Use tabs & spaces <never>. (foundational) [commit: abc123] [tags: style]\n"
+        ));
+
+        let xml = render("xml", &domains, false);
+        assert!(xml.starts_with("<expertise>\n<domain name=\"api\" entries=\"6\">\n"));
+        assert!(xml.contains(
+            "  <convention id=\"mx-000001\" classification=\"foundational\">\n\
+             \x20   Use tabs &amp; spaces &lt;never&gt;.\n\
+             \x20   <tags>style</tags>\n\
+             \x20   <outcome status=\"success\" duration=\"1500\" agent=\"bot\">ok</outcome>\n\
+             \x20 </convention>\n"
+        ));
+        assert!(xml.contains("</domain>\n</expertise>\n\n<session_close_protocol"));
+
+        let plain = render("plain", &domains, false);
+        assert!(plain.starts_with(
+            "Project Expertise (via KB)\n============================\n\n[api] 6 records\n\n\
+             Conventions:\n  - [mx-000001] Use tabs & spaces <never>.\n"
+        ));
+        assert!(plain.contains("=== SESSION CLOSE PROTOCOL (CRITICAL) ==="));
+    }
+
+    #[test]
+    fn builtins_render_empty_knowledge_base() {
+        assert!(render("compact", &[], false).contains(
+            "# Project Expertise (via KB)\n\nNo expertise recorded yet. Use `kb add <domain>` to create a domain, then `kb record` to add records.\n"
+        ));
+        assert!(render("xml", &[], false).starts_with(
+            "<expertise>\n  <empty>No expertise recorded yet. Use kb add and kb record to get started.</empty>\n</expertise>\n"
+        ));
+    }
+
+    #[test]
+    fn overflow_and_truncation_render() {
+        let records = sample_records();
+        let dir = tempfile::tempdir().unwrap();
+        let overflow = [("api", &records[1])];
+        let input = TemplateInput {
+            domains: Vec::new(),
+            full: false,
+            overflow: &overflow,
+            truncated: Some((2, 1)),
+        };
+        let out = Templates::load(dir.path())
+            .render("compact", &input)
+            .unwrap();
+        assert!(out.contains(&format!(
+            "\n\n## Not Loaded (over budget)\n\
+             Fetch with `kb query <domain>` or `kb search <id>` when relevant:\n\
+             {}\n\n{}\n\n",
+            format_overflow_line("api", &records[1]),
+            crate::budget::format_budget_summary(2, 1)
+        )));
+    }
+
+    #[test]
+    fn user_template_overrides_and_extends_builtin() {
+        let dir = tempfile::tempdir().unwrap();
+        let templates = get_templates_dir(dir.path());
+        std::fs::create_dir_all(&templates).unwrap();
+        std::fs::write(
+            templates.join("markdown.j2"),
+            "{% extends \"builtin/markdown\" %}{% block reminder %}Bye.{% endblock %}",
+        )
+        .unwrap();
+        std::fs::write(
+            templates.join("terse.j2"),
+            "{% block domains %}{% for d in domains %}{{ d.name }}={{ d.count }}\n{% endfor %}{% endblock %}",
+        )
+        .unwrap();
+
+        let records = sample_records();
+        let refs: Vec<&ExpertiseRecord> = records.iter().collect();
+        let input = TemplateInput {
+            domains: vec![TemplateDomain {
+                name: "api",
+                records: &refs,
+                last_updated: None,
+            }],
+            full: false,
+            overflow: &[],
+            truncated: None,
+        };
+        let t = Templates::load(dir.path());
+        let out = t.render("markdown", &input).unwrap();
+        assert!(out.contains("## api (6 records)"));
+        assert!(out.ends_with("Bye."));
+        assert_eq!(t.render_domains("terse", &input).unwrap(), "api=6");
+        assert_eq!(
+            t.names(),
+            vec!["markdown", "terse", "compact", "plain", "xml"]
+        );
+    }
+
+    #[test]
+    fn unknown_template_lists_available() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = TemplateInput {
+            domains: Vec::new(),
+            full: false,
+            overflow: &[],
+            truncated: None,
+        };
+        let err = Templates::load(dir.path())
+            .render("nope", &input)
+            .unwrap_err();
+        assert!(matches!(err, KbError::TemplateNotFound { .. }));
+        assert!(err.to_string().contains("compact, markdown, plain, xml"));
+    }
+
+    #[test]
+    fn classification_and_outcome_fields_exposed() {
+        let records = with_outcome(sample_records());
+        let value = record_value(&records[0]);
+        assert_eq!(
+            value["classification"],
+            Classification::Foundational.as_str()
+        );
+        assert_eq!(value["outcomes"][0]["duration"], json!(1500));
+        assert_eq!(value["summary"], "Use tabs & spaces <never>.");
+    }
+}
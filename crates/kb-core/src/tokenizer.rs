@@ -0,0 +1,139 @@
+use crate::error::Result;
+use crate::types::TokenizerKind;
+
+/// Counts tokens for budget accounting.
+pub trait TokenEstimator {
+    /// Name shown in reports (matches the config value).
+    fn name(&self) -> &'static str;
+
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+/// Character-class heuristic: ~4 ASCII characters per token, one token per
+/// CJK character, ~2 characters per token for other non-ASCII text.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HeuristicEstimator;
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'   // Hiragana, Katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}' // Hangul syllables
+        | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+        | '\u{FF00}'..='\u{FFEF}' // Halfwidth and fullwidth forms
+    )
+}
+
+/// Heuristic token count, see [`HeuristicEstimator`].
+pub fn heuristic_tokens(text: &str) -> usize {
+    let mut ascii = 0usize;
+    let mut cjk = 0usize;
+    let mut other = 0usize;
+    for c in text.chars() {
+        if c.is_ascii() {
+            ascii += 1;
+        } else if is_cjk(c) {
+            cjk += 1;
+        } else {
+            other += 1;
+        }
+    }
+    ascii.div_ceil(4) + cjk + other.div_ceil(2)
+}
+
+impl TokenEstimator for HeuristicEstimator {
+    fn name(&self) -> &'static str {
+        TokenizerKind::Heuristic.as_str()
+    }
+
+    fn count_tokens(&self, text: &str) -> usize {
+        heuristic_tokens(text)
+    }
+}
+
+/// Exact BPE token count using a vocabulary bundled at compile time.
+#[cfg(feature = "bpe")]
+pub struct BpeEstimator {
+    kind: TokenizerKind,
+    bpe: &'static tiktoken_rs::CoreBPE,
+}
+
+#[cfg(feature = "bpe")]
+impl TokenEstimator for BpeEstimator {
+    fn name(&self) -> &'static str {
+        self.kind.as_str()
+    }
+
+    fn count_tokens(&self, text: &str) -> usize {
+        self.bpe.encode_ordinary(text).len()
+    }
+}
+
+/// Build the estimator configured for a knowledge base.
+pub fn estimator_for(kind: TokenizerKind) -> Result<Box<dyn TokenEstimator>> {
+    match kind {
+        TokenizerKind::Heuristic => Ok(Box::new(HeuristicEstimator)),
+        #[cfg(feature = "bpe")]
+        TokenizerKind::Cl100k => Ok(Box::new(BpeEstimator {
+            kind,
+            bpe: tiktoken_rs::cl100k_base_singleton(),
+        })),
+        #[cfg(feature = "bpe")]
+        TokenizerKind::O200k => Ok(Box::new(BpeEstimator {
+            kind,
+            bpe: tiktoken_rs::o200k_base_singleton(),
+        })),
+        #[cfg(not(feature = "bpe"))]
+        TokenizerKind::Cl100k | TokenizerKind::O200k => Err(
+            crate::error::KbError::TokenizerUnavailable(kind.as_str().to_string()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heuristic_ascii_matches_chars_over_four() {
+        assert_eq!(heuristic_tokens(""), 0);
+        assert_eq!(heuristic_tokens("abcd"), 1);
+        assert_eq!(heuristic_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn heuristic_counts_cjk_per_character() {
+        // 4 characters, 12 bytes in UTF-8
+        assert_eq!(heuristic_tokens("日本語文"), 4);
+        assert_eq!(heuristic_tokens("한국어"), 3);
+    }
+
+    #[test]
+    fn heuristic_other_non_ascii() {
+        assert_eq!(heuristic_tokens("ääää"), 2);
+    }
+
+    #[test]
+    fn heuristic_estimator_by_default() {
+        let est = estimator_for(TokenizerKind::default()).unwrap();
+        assert_eq!(est.name(), "heuristic");
+        assert_eq!(est.count_tokens("abcdefgh"), 2);
+    }
+
+    #[cfg(not(feature = "bpe"))]
+    #[test]
+    fn bpe_requires_feature() {
+        assert!(estimator_for(TokenizerKind::Cl100k).is_err());
+    }
+
+    #[cfg(feature = "bpe")]
+    #[test]
+    fn bpe_counts_tokens() {
+        let est = estimator_for(TokenizerKind::Cl100k).unwrap();
+        assert_eq!(est.name(), "cl100k");
+        assert_eq!(est.count_tokens("hello world"), 2);
+        let est = estimator_for(TokenizerKind::O200k).unwrap();
+        assert_eq!(est.count_tokens("hello world"), 2);
+    }
+}
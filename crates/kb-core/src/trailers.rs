@@ -0,0 +1,249 @@
+use crate::git::CommitTrailers;
+use crate::types::{ExpertiseRecord, RecordType};
+
+/// Trailer starting a record, per record type.
+const RECORD_TRAILERS: &[(&str, RecordType)] = &[
+    ("kb-convention", RecordType::Convention),
+    ("kb-pattern", RecordType::Pattern),
+    ("kb-failure", RecordType::Failure),
+    ("kb-decision", RecordType::Decision),
+    ("kb-reference", RecordType::Reference),
+    ("kb-guide", RecordType::Guide),
+];
+
+/// A record proposed by the `Kb-*` trailers of a commit.
+#[derive(Debug, Clone)]
+pub struct TrailerRecord {
+    pub commit: String,
+    /// From `Kb-Domain`; `None` when the commit names no domain.
+    pub domain: Option<String>,
+    pub record: ExpertiseRecord,
+}
+
+/// A record being assembled from its starting trailer and the modifiers
+/// that follow it.
+struct Pending {
+    record_type: RecordType,
+    text: String,
+    detail: Option<String>,
+    classification: Option<String>,
+    tags: Vec<String>,
+    files: Vec<String>,
+    domain: Option<String>,
+}
+
+/// Trailer carrying the second required field of a record type.
+fn detail_trailer(record_type: RecordType) -> Option<&'static str> {
+    match record_type {
+        RecordType::Convention => None,
+        RecordType::Failure => Some("Kb-Resolution"),
+        RecordType::Decision => Some("Kb-Rationale"),
+        RecordType::Pattern | RecordType::Reference | RecordType::Guide => Some("Kb-Description"),
+    }
+}
+
+/// Commit date as a UTC timestamp in the format `kb record` writes.
+fn recorded_at(date: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(date)
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+        .unwrap_or_else(|_| chrono::Utc::now())
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+impl Pending {
+    fn into_record(self, commit: &CommitTrailers) -> std::result::Result<TrailerRecord, String> {
+        let detail = match (detail_trailer(self.record_type), self.detail) {
+            (Some(trailer), None) => {
+                return Err(format!(
+                    "Kb-{} \"{}\" has no {trailer} trailer",
+                    capitalize(self.record_type.as_str()),
+                    self.text
+                ));
+            }
+            (_, detail) => detail.unwrap_or_default(),
+        };
+        let mut value = match self.record_type {
+            RecordType::Convention => serde_json::json!({"content": self.text}),
+            RecordType::Failure => {
+                serde_json::json!({"description": self.text, "resolution": detail})
+            }
+            RecordType::Decision => serde_json::json!({"title": self.text, "rationale": detail}),
+            RecordType::Pattern | RecordType::Reference | RecordType::Guide => {
+                serde_json::json!({"name": self.text, "description": detail})
+            }
+        };
+        value["type"] = self.record_type.as_str().into();
+        value["classification"] = self
+            .classification
+            .unwrap_or_else(|| "tactical".to_string())
+            .to_lowercase()
+            .into();
+        value["recorded_at"] = recorded_at(&commit.date).into();
+        value["evidence"] = serde_json::json!({"commit": commit.hash});
+        if !self.tags.is_empty() {
+            value["tags"] = self.tags.into();
+        }
+        // Only patterns and references have a `files` field
+        if !self.files.is_empty()
+            && matches!(
+                self.record_type,
+                RecordType::Pattern | RecordType::Reference
+            )
+        {
+            value["files"] = self.files.into();
+        }
+
+        let record = serde_json::from_value::<ExpertiseRecord>(value).map_err(|e| e.to_string())?;
+        Ok(TrailerRecord {
+            commit: commit.hash.clone(),
+            domain: self.domain,
+            record,
+        })
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Records described by a commit's `Kb-*` trailers, plus a message for each
+/// trailer group that could not be turned into a record.
+///
+/// `Kb-Convention`, `Kb-Pattern`, `Kb-Failure`, `Kb-Decision`, `Kb-Reference`
+/// and `Kb-Guide` each start a record. `Kb-Resolution`, `Kb-Rationale`,
+/// `Kb-Description`, `Kb-Classification`, `Kb-Tags` and `Kb-Files` complete
+/// the record started before them. `Kb-Domain` sets the domain of the records
+/// after it; records before the first `Kb-Domain` use it too.
+pub fn parse_commit(commit: &CommitTrailers) -> (Vec<TrailerRecord>, Vec<String>) {
+    let mut pending: Vec<Pending> = Vec::new();
+    let mut warnings = Vec::new();
+    let mut domain: Option<String> = None;
+
+    for (name, value) in &commit.trailers {
+        let key = name.to_lowercase();
+        if !key.starts_with("kb-") || value.is_empty() {
+            continue;
+        }
+        if let Some((_, record_type)) = RECORD_TRAILERS.iter().find(|(k, _)| *k == key) {
+            pending.push(Pending {
+                record_type: *record_type,
+                text: value.clone(),
+                detail: None,
+                classification: None,
+                tags: Vec::new(),
+                files: Vec::new(),
+                domain: domain.clone(),
+            });
+            continue;
+        }
+        if key == "kb-domain" {
+            if domain.is_none() {
+                for p in pending.iter_mut() {
+                    p.domain = Some(value.clone());
+                }
+            }
+            domain = Some(value.clone());
+            continue;
+        }
+
+        let Some(current) = pending.last_mut() else {
+            warnings.push(format!("{name} before any record trailer"));
+            continue;
+        };
+        match key.as_str() {
+            "kb-resolution" | "kb-rationale" | "kb-description" => {
+                current.detail = Some(value.clone())
+            }
+            "kb-classification" => current.classification = Some(value.clone()),
+            "kb-tags" => current.tags.extend(split_list(value)),
+            "kb-files" => current.files.extend(split_list(value)),
+            _ => warnings.push(format!("Unknown trailer {name}")),
+        }
+    }
+
+    let mut records = Vec::new();
+    for p in pending {
+        match p.into_record(commit) {
+            Ok(record) => records.push(record),
+            Err(e) => warnings.push(e),
+        }
+    }
+    (records, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::get_record_summary;
+
+    fn commit(trailers: &[(&str, &str)]) -> CommitTrailers {
+        CommitTrailers {
+            hash: "abc123".to_string(),
+            date: "2024-05-01T12:00:00+02:00".to_string(),
+            trailers: trailers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn parse_commit_builds_records_with_commit_evidence() {
+        let (records, warnings) = parse_commit(&commit(&[
+            ("Kb-Convention", "Use anyhow in the CLI crate"),
+            ("Kb-Tags", "errors, cli"),
+            ("Kb-Domain", "backend"),
+            ("Signed-off-by", "Dev <dev@example.com>"),
+            ("Kb-Failure", "Lock file left behind after panic"),
+            ("kb-resolution", "Remove locks older than 30s"),
+            ("Kb-Classification", "Foundational"),
+        ]));
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert_eq!(records.len(), 2);
+
+        let convention = &records[0];
+        assert_eq!(convention.domain.as_deref(), Some("backend"));
+        assert_eq!(
+            get_record_summary(&convention.record),
+            "Use anyhow in the CLI crate"
+        );
+        assert_eq!(convention.record.tags().unwrap().len(), 2);
+        assert_eq!(
+            convention.record.evidence().unwrap().commit.as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(convention.record.recorded_at(), "2024-05-01T10:00:00.000Z");
+
+        let failure = &records[1];
+        assert_eq!(failure.record.record_type(), RecordType::Failure);
+        assert_eq!(
+            failure.record.classification(),
+            crate::types::Classification::Foundational
+        );
+    }
+
+    #[test]
+    fn parse_commit_reports_incomplete_records() {
+        let (records, warnings) = parse_commit(&commit(&[
+            ("Kb-Tags", "orphan"),
+            ("Kb-Decision", "Use JSONL"),
+            ("Kb-Guide", "Release"),
+            ("Kb-Description", "Tag, then publish"),
+        ]));
+        assert_eq!(records.len(), 1);
+        assert!(records[0].domain.is_none());
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[1].contains("Kb-Rationale"));
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 // ── Enums ──────────────────────────────────────────────────────────────────
@@ -123,6 +125,10 @@ pub enum ExpertiseRecord {
         content: String,
         classification: Classification,
         recorded_at: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        updated_at: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         evidence: Option<Evidence>,
         #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -143,6 +149,10 @@ pub enum ExpertiseRecord {
         files: Option<Vec<String>>,
         classification: Classification,
         recorded_at: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        updated_at: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         evidence: Option<Evidence>,
         #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -161,6 +171,10 @@ pub enum ExpertiseRecord {
         resolution: String,
         classification: Classification,
         recorded_at: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        updated_at: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         evidence: Option<Evidence>,
         #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -181,6 +195,10 @@ pub enum ExpertiseRecord {
         date: Option<String>,
         classification: Classification,
         recorded_at: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        updated_at: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         evidence: Option<Evidence>,
         #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -201,6 +219,10 @@ pub enum ExpertiseRecord {
         files: Option<Vec<String>>,
         classification: Classification,
         recorded_at: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        updated_at: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         evidence: Option<Evidence>,
         #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -219,6 +241,10 @@ pub enum ExpertiseRecord {
         description: String,
         classification: Classification,
         recorded_at: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        updated_at: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         evidence: Option<Evidence>,
         #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -300,6 +326,76 @@ impl ExpertiseRecord {
         }
     }
 
+    pub fn set_recorded_at(&mut self, new_val: String) {
+        match self {
+            Self::Convention { recorded_at, .. }
+            | Self::Pattern { recorded_at, .. }
+            | Self::Failure { recorded_at, .. }
+            | Self::Decision { recorded_at, .. }
+            | Self::Reference { recorded_at, .. }
+            | Self::Guide { recorded_at, .. } => *recorded_at = new_val,
+        }
+    }
+
+    pub fn updated_at(&self) -> Option<&str> {
+        match self {
+            Self::Convention { updated_at, .. }
+            | Self::Pattern { updated_at, .. }
+            | Self::Failure { updated_at, .. }
+            | Self::Decision { updated_at, .. }
+            | Self::Reference { updated_at, .. }
+            | Self::Guide { updated_at, .. } => updated_at.as_deref(),
+        }
+    }
+
+    pub fn set_updated_at(&mut self, new_val: Option<String>) {
+        match self {
+            Self::Convention { updated_at, .. }
+            | Self::Pattern { updated_at, .. }
+            | Self::Failure { updated_at, .. }
+            | Self::Decision { updated_at, .. }
+            | Self::Reference { updated_at, .. }
+            | Self::Guide { updated_at, .. } => *updated_at = new_val,
+        }
+    }
+
+    /// Stamp `updated_at` with the current time. Every path that changes a
+    /// stored record calls this, so `last_modified` orders edits for the
+    /// merge driver, compaction plans and shelf life.
+    pub fn touch(&mut self) {
+        self.set_updated_at(Some(
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        ));
+    }
+
+    /// Timestamp of the last change: `updated_at`, or `recorded_at` if never edited.
+    pub fn last_modified(&self) -> &str {
+        self.updated_at().unwrap_or(self.recorded_at())
+    }
+
+    /// Branch the record was recorded on, if it is not yet mainline knowledge.
+    pub fn branch(&self) -> Option<&str> {
+        match self {
+            Self::Convention { branch, .. }
+            | Self::Pattern { branch, .. }
+            | Self::Failure { branch, .. }
+            | Self::Decision { branch, .. }
+            | Self::Reference { branch, .. }
+            | Self::Guide { branch, .. } => branch.as_deref(),
+        }
+    }
+
+    pub fn set_branch(&mut self, new_val: Option<String>) {
+        match self {
+            Self::Convention { branch, .. }
+            | Self::Pattern { branch, .. }
+            | Self::Failure { branch, .. }
+            | Self::Decision { branch, .. }
+            | Self::Reference { branch, .. }
+            | Self::Guide { branch, .. } => *branch = new_val,
+        }
+    }
+
     pub fn evidence(&self) -> Option<&Evidence> {
         match self {
             Self::Convention { evidence, .. }
@@ -311,6 +407,17 @@ impl ExpertiseRecord {
         }
     }
 
+    pub fn set_evidence(&mut self, new_val: Option<Evidence>) {
+        match self {
+            Self::Convention { evidence, .. }
+            | Self::Pattern { evidence, .. }
+            | Self::Failure { evidence, .. }
+            | Self::Decision { evidence, .. }
+            | Self::Reference { evidence, .. }
+            | Self::Guide { evidence, .. } => *evidence = new_val,
+        }
+    }
+
     pub fn tags(&self) -> Option<&[String]> {
         match self {
             Self::Convention { tags, .. }
@@ -406,6 +513,13 @@ impl ExpertiseRecord {
         }
     }
 
+    /// Set `files` on patterns and references; other types have no such field.
+    pub fn set_files(&mut self, new_val: Option<Vec<String>>) {
+        if let Self::Pattern { files, .. } | Self::Reference { files, .. } = self {
+            *files = new_val;
+        }
+    }
+
     /// Returns true if this is a "named" type that supports upsert on duplicate.
     pub fn is_named_type(&self) -> bool {
         matches!(
@@ -431,6 +545,81 @@ pub struct ClassificationDefaults {
     pub shelf_life: ShelfLife,
 }
 
+/// Shelf-life override for the records matching every criterion it names.
+/// A rule without `tactical` or `observational` leaves that classification
+/// to the next matching rule or the default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShelfLifeRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// Matches records carrying this tag (case-insensitive).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub record_type: Option<RecordType>,
+    /// Days before a tactical record goes stale.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tactical: Option<u32>,
+    /// Days before an observational record goes stale.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observational: Option<u32>,
+}
+
+impl ShelfLifeRule {
+    /// Whether the rule's criteria all match `record` in `domain`.
+    pub fn matches(&self, domain: &str, record: &ExpertiseRecord) -> bool {
+        self.domain.as_deref().is_none_or(|d| d == domain)
+            && self.record_type.is_none_or(|t| t == record.record_type())
+            && self.tag.as_deref().is_none_or(|tag| {
+                record
+                    .tags()
+                    .is_some_and(|tags| tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+            })
+    }
+
+    /// Days for `classification`, if this rule sets them.
+    pub fn days(&self, classification: Classification) -> Option<u32> {
+        match classification {
+            Classification::Tactical => self.tactical,
+            Classification::Observational => self.observational,
+            Classification::Foundational => None,
+        }
+    }
+
+    /// Precedence among matching rules: more criteria first, then tag over
+    /// domain over type.
+    pub fn specificity(&self) -> (usize, bool, bool, bool) {
+        let tag = self.tag.is_some();
+        let domain = self.domain.is_some();
+        let record_type = self.record_type.is_some();
+        (
+            [tag, domain, record_type].iter().filter(|b| **b).count(),
+            tag,
+            domain,
+            record_type,
+        )
+    }
+
+    /// The rule's criteria, e.g. `domain=frontend, tag=security`.
+    pub fn criteria(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(ref domain) = self.domain {
+            parts.push(format!("domain={domain}"));
+        }
+        if let Some(ref tag) = self.tag {
+            parts.push(format!("tag={tag}"));
+        }
+        if let Some(record_type) = self.record_type {
+            parts.push(format!("type={record_type}"));
+        }
+        if parts.is_empty() {
+            "all records".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Governance {
     pub max_entries: u32,
@@ -438,12 +627,205 @@ pub struct Governance {
     pub hard_limit: u32,
 }
 
+/// Share of the prime budget a single domain may use.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DomainQuota {
+    /// Fraction of the budget reserved for the domain (0.0-1.0).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_share: Option<f64>,
+    /// Fraction of the budget the domain may not exceed (0.0-1.0).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_share: Option<f64>,
+}
+
+/// Quota policy applied by `kb prime --budget`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetPolicy {
+    /// Default shares for every domain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_share: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_share: Option<f64>,
+    /// Per-domain share overrides.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub domains: HashMap<String, DomainQuota>,
+    /// Maximum records of a type kept per domain.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub type_caps: HashMap<RecordType, usize>,
+    /// Foundational records per domain kept ahead of every other rule.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub foundational_slots: usize,
+    /// Share of the budget reserved for an index of dropped records (disabled when unset).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overflow_share: Option<f64>,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+impl BudgetPolicy {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Resolved (min, max) share for a domain, clamped so that min <= max.
+    pub fn shares_for(&self, domain: &str) -> (f64, f64) {
+        let quota = self.domains.get(domain);
+        let max = quota
+            .and_then(|q| q.max_share)
+            .or(self.max_share)
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0);
+        let min = quota
+            .and_then(|q| q.min_share)
+            .or(self.min_share)
+            .unwrap_or(0.0)
+            .clamp(0.0, max);
+        (min, max)
+    }
+}
+
+/// Thresholds above which changes to a record's referenced files make it
+/// likely stale.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChurnPolicy {
+    /// Commits touching the files since the record was last written.
+    pub min_commits: usize,
+    /// Lines added plus removed in those commits.
+    pub min_lines: u64,
+}
+
+impl Default for ChurnPolicy {
+    fn default() -> Self {
+        Self {
+            min_commits: 3,
+            min_lines: 100,
+        }
+    }
+}
+
+impl ChurnPolicy {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Evidence thresholds for promoting a record to a more durable
+/// classification. A rule fires when either threshold that is set is met
+/// and the record has at most `max_failures` failure outcomes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromotionRule {
+    pub from: Classification,
+    pub to: Classification,
+    /// Success outcomes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_successes: Option<u32>,
+    /// `helpful` signals from `kb_feedback`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_helpful: Option<u32>,
+    #[serde(default)]
+    pub max_failures: u32,
+}
+
+/// Evidence thresholds for demoting a record to a less durable
+/// classification. A rule fires when either threshold that is set is met.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DemotionRule {
+    pub from: Classification,
+    pub to: Classification,
+    /// Failure outcomes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_failures: Option<u32>,
+    /// `not-helpful` signals from `kb_feedback`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_not_helpful: Option<u32>,
+}
+
+/// Rules applied by `kb promote --auto`, first match wins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromotionPolicy {
+    pub promote: Vec<PromotionRule>,
+    pub demote: Vec<DemotionRule>,
+}
+
+impl Default for PromotionPolicy {
+    fn default() -> Self {
+        Self {
+            promote: vec![PromotionRule {
+                from: Classification::Tactical,
+                to: Classification::Foundational,
+                min_successes: Some(3),
+                min_helpful: None,
+                max_failures: 0,
+            }],
+            demote: vec![DemotionRule {
+                from: Classification::Foundational,
+                to: Classification::Tactical,
+                min_failures: Some(2),
+                min_not_helpful: None,
+            }],
+        }
+    }
+}
+
+impl PromotionPolicy {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Token counter used for budget accounting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerKind {
+    /// Character-class estimate, always available.
+    #[default]
+    Heuristic,
+    /// BPE with the cl100k_base vocabulary (requires the `bpe` feature).
+    Cl100k,
+    /// BPE with the o200k_base vocabulary (requires the `bpe` feature).
+    O200k,
+}
+
+impl TokenizerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Heuristic => "heuristic",
+            Self::Cl100k => "cl100k",
+            Self::O200k => "o200k",
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KbConfig {
     pub version: String,
     pub domains: Vec<String>,
     pub governance: Governance,
     pub classification_defaults: ClassificationDefaults,
+    #[serde(default, skip_serializing_if = "TokenizerKind::is_default")]
+    pub tokenizer: TokenizerKind,
+    #[serde(default, skip_serializing_if = "BudgetPolicy::is_default")]
+    pub budget: BudgetPolicy,
+    /// Mainline branch; records made on other branches are branch-scoped.
+    /// Detected from git when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub main_branch: Option<String>,
+    #[serde(default, skip_serializing_if = "ChurnPolicy::is_default")]
+    pub churn: ChurnPolicy,
+    /// Shelf-life overrides by domain, tag and record type, on top of
+    /// `classification_defaults.shelf_life`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shelf_life_rules: Vec<ShelfLifeRule>,
+    #[serde(default, skip_serializing_if = "PromotionPolicy::is_default")]
+    pub promotion: PromotionPolicy,
 }
 
 impl Default for KbConfig {
@@ -462,6 +844,12 @@ impl Default for KbConfig {
                     observational: 30,
                 },
             },
+            tokenizer: TokenizerKind::default(),
+            budget: BudgetPolicy::default(),
+            main_branch: None,
+            churn: ChurnPolicy::default(),
+            shelf_life_rules: Vec::new(),
+            promotion: PromotionPolicy::default(),
         }
     }
 }
@@ -510,4 +898,36 @@ mod tests {
         assert_eq!(parsed.version, config.version);
         assert_eq!(parsed.governance.max_entries, config.governance.max_entries);
     }
+
+    #[test]
+    fn config_tokenizer_defaults_to_heuristic() {
+        let yaml = "version: '1'\ndomains: []\ngovernance:\n  max_entries: 100\n  warn_entries: 150\n  hard_limit: 200\nclassification_defaults:\n  shelf_life:\n    tactical: 14\n    observational: 30\n";
+        let parsed: KbConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(parsed.tokenizer, TokenizerKind::Heuristic);
+
+        let with_bpe: KbConfig =
+            serde_yaml::from_str(&format!("{yaml}tokenizer: o200k\n")).unwrap();
+        assert_eq!(with_bpe.tokenizer, TokenizerKind::O200k);
+    }
+
+    #[test]
+    fn budget_policy_from_yaml() {
+        let yaml = "min_share: 0.1\nmax_share: 0.6\ndomains:\n  api:\n    max_share: 0.3\ntype_caps:\n  convention: 5\nfoundational_slots: 2\n";
+        let policy: BudgetPolicy = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(policy.type_caps.get(&RecordType::Convention), Some(&5));
+        assert_eq!(policy.foundational_slots, 2);
+        assert_eq!(policy.shares_for("db"), (0.1, 0.6));
+        assert_eq!(policy.shares_for("api"), (0.1, 0.3));
+    }
+
+    #[test]
+    fn budget_policy_clamps_min_to_max() {
+        let policy = BudgetPolicy {
+            min_share: Some(0.8),
+            max_share: Some(0.5),
+            ..Default::default()
+        };
+        assert_eq!(policy.shares_for("any"), (0.5, 0.5));
+        assert_eq!(BudgetPolicy::default().shares_for("any"), (0.0, 1.0));
+    }
 }
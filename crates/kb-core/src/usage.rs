@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::access_log::AccessLogEntry;
+use crate::format::get_record_summary;
+use crate::types::ExpertiseRecord;
+
+#[derive(Debug, Clone)]
+pub struct UsageOptions {
+    /// Maximum number of rows in the most/least-retrieved and zero-result lists.
+    pub limit: usize,
+    /// Window for the "not accessed" report.
+    pub unused_days: i64,
+    pub now: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordUsage {
+    pub id: String,
+    pub domain: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub summary: String,
+    pub retrievals: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_accessed: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ZeroResultQuery {
+    pub tool: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    pub query: String,
+    pub count: usize,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CallCount {
+    pub name: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionActivity {
+    pub session_id: String,
+    pub calls: usize,
+    pub records_retrieved: usize,
+    pub zero_result_queries: usize,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    pub total_calls: usize,
+    pub total_records: usize,
+    pub unused_days: i64,
+    pub most_retrieved: Vec<RecordUsage>,
+    pub least_retrieved: Vec<RecordUsage>,
+    pub unused: Vec<RecordUsage>,
+    pub zero_result_queries: Vec<ZeroResultQuery>,
+    pub by_domain: Vec<CallCount>,
+    pub by_tool: Vec<CallCount>,
+    pub by_session: Vec<SessionActivity>,
+}
+
+/// Sort counts descending, ties broken alphabetically.
+fn sorted_counts(map: HashMap<String, usize>) -> Vec<CallCount> {
+    let mut counts: Vec<CallCount> = map
+        .into_iter()
+        .map(|(name, count)| CallCount { name, count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    counts
+}
+
+/// Build a usage report from access log entries and the current records.
+///
+/// A record counts as retrieved when its ID appears in an entry's
+/// `entry_ids`. An `entry_id` (feedback and other single-record tools) only
+/// marks it as accessed.
+/// Records created inside the `unused_days` window are never reported as
+/// unused, since they have not had the chance to be retrieved yet.
+pub fn compute_usage(
+    entries: &[AccessLogEntry],
+    records: &[(String, ExpertiseRecord)],
+    opts: &UsageOptions,
+) -> UsageReport {
+    let mut retrievals: HashMap<&str, usize> = HashMap::new();
+    let mut last_accessed: HashMap<&str, DateTime<Utc>> = HashMap::new();
+    let mut by_domain: HashMap<String, usize> = HashMap::new();
+    let mut by_tool: HashMap<String, usize> = HashMap::new();
+    let mut sessions: HashMap<&str, SessionActivity> = HashMap::new();
+    let mut zero: HashMap<(String, Option<String>, String), ZeroResultQuery> = HashMap::new();
+
+    for entry in entries {
+        let domain = entry.domain.as_deref().unwrap_or("(all)");
+        *by_domain.entry(domain.to_string()).or_default() += 1;
+        *by_tool.entry(entry.tool.clone()).or_default() += 1;
+
+        let retrieved = entry.entry_ids.iter().flatten().map(|s| s.as_str());
+        for id in retrieved.clone() {
+            *retrievals.entry(id).or_default() += 1;
+        }
+        for id in retrieved.chain(entry.entry_id.as_deref()) {
+            let last = last_accessed.entry(id).or_insert(entry.timestamp);
+            if entry.timestamp > *last {
+                *last = entry.timestamp;
+            }
+        }
+
+        let is_zero = entry.result_count == Some(0) && entry.query.is_some();
+        if let Some(query) = entry.query.as_ref().filter(|_| is_zero) {
+            let key = (
+                entry.tool.clone(),
+                entry.domain.clone(),
+                query.trim().to_lowercase(),
+            );
+            let z = zero.entry(key).or_insert_with(|| ZeroResultQuery {
+                tool: entry.tool.clone(),
+                domain: entry.domain.clone(),
+                query: query.clone(),
+                count: 0,
+                last_seen: entry.timestamp,
+            });
+            z.count += 1;
+            if entry.timestamp > z.last_seen {
+                z.last_seen = entry.timestamp;
+            }
+        }
+
+        let s = sessions
+            .entry(entry.session_id.as_str())
+            .or_insert_with(|| SessionActivity {
+                session_id: entry.session_id.clone(),
+                calls: 0,
+                records_retrieved: 0,
+                zero_result_queries: 0,
+                first_seen: entry.timestamp,
+                last_seen: entry.timestamp,
+            });
+        s.calls += 1;
+        s.records_retrieved += entry.entry_ids.as_ref().map_or(0, |ids| ids.len());
+        if is_zero {
+            s.zero_result_queries += 1;
+        }
+        s.first_seen = s.first_seen.min(entry.timestamp);
+        s.last_seen = s.last_seen.max(entry.timestamp);
+    }
+
+    let cutoff = opts.now - chrono::Duration::days(opts.unused_days);
+    let mut usage: Vec<RecordUsage> = Vec::new();
+    let mut unused: Vec<RecordUsage> = Vec::new();
+    for (domain, record) in records {
+        let Some(id) = record.id() else { continue };
+        let u = RecordUsage {
+            id: id.to_string(),
+            domain: domain.clone(),
+            record_type: record.record_type().as_str().to_string(),
+            summary: get_record_summary(record),
+            retrievals: retrievals.get(id).copied().unwrap_or(0),
+            last_accessed: last_accessed.get(id).copied(),
+        };
+        let recorded_before_cutoff = chrono::DateTime::parse_from_rfc3339(record.recorded_at())
+            .map(|dt| dt.with_timezone(&Utc) < cutoff)
+            .unwrap_or(true);
+        if recorded_before_cutoff && u.last_accessed.is_none_or(|t| t < cutoff) {
+            unused.push(u.clone());
+        }
+        usage.push(u);
+    }
+    unused.sort_by(|a, b| a.domain.cmp(&b.domain).then_with(|| a.id.cmp(&b.id)));
+
+    let mut most: Vec<RecordUsage> = usage.iter().filter(|u| u.retrievals > 0).cloned().collect();
+    most.sort_by(|a, b| {
+        b.retrievals
+            .cmp(&a.retrievals)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    most.truncate(opts.limit);
+
+    let mut least = usage;
+    least.sort_by(|a, b| {
+        a.retrievals
+            .cmp(&b.retrievals)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    least.truncate(opts.limit);
+
+    let mut zero_result_queries: Vec<ZeroResultQuery> = zero.into_values().collect();
+    zero_result_queries.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| b.last_seen.cmp(&a.last_seen))
+    });
+    zero_result_queries.truncate(opts.limit);
+
+    let mut by_session: Vec<SessionActivity> = sessions.into_values().collect();
+    by_session.sort_by_key(|s| std::cmp::Reverse(s.last_seen));
+
+    UsageReport {
+        total_calls: entries.len(),
+        total_records: records.len(),
+        unused_days: opts.unused_days,
+        most_retrieved: most,
+        least_retrieved: least,
+        unused,
+        zero_result_queries,
+        by_domain: sorted_counts(by_domain),
+        by_tool: sorted_counts(by_tool),
+        by_session,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Classification;
+
+    fn record(id: &str, recorded_at: &str) -> ExpertiseRecord {
+        ExpertiseRecord::Convention {
+            id: Some(id.to_string()),
+            content: format!("convention {id}"),
+            classification: Classification::Foundational,
+            recorded_at: recorded_at.to_string(),
+            updated_at: None,
+            branch: None,
+            evidence: None,
+            tags: None,
+            relates_to: None,
+            supersedes: None,
+            outcomes: None,
+        }
+    }
+
+    fn entry(
+        session: &str,
+        tool: &str,
+        ts: &str,
+        query: Option<&str>,
+        ids: Option<&[&str]>,
+    ) -> AccessLogEntry {
+        AccessLogEntry {
+            session_id: session.to_string(),
+            timestamp: ts.parse().unwrap(),
+            tool: tool.to_string(),
+            domain: Some("rust".to_string()),
+            query: query.map(|s| s.to_string()),
+            entry_id: None,
+            entry_ids: ids.map(|ids| ids.iter().map(|s| s.to_string()).collect()),
+            result_count: ids.map(|ids| ids.len()),
+            signal: None,
+        }
+    }
+
+    fn opts() -> UsageOptions {
+        UsageOptions {
+            limit: 10,
+            unused_days: 30,
+            now: "2024-06-30T00:00:00Z".parse().unwrap(),
+        }
+    }
+
+    fn records() -> Vec<(String, ExpertiseRecord)> {
+        vec![
+            (
+                "rust".to_string(),
+                record("mx-aaaaaa", "2024-01-01T00:00:00Z"),
+            ),
+            (
+                "rust".to_string(),
+                record("mx-bbbbbb", "2024-01-01T00:00:00Z"),
+            ),
+            (
+                "rust".to_string(),
+                record("mx-cccccc", "2024-06-29T00:00:00Z"),
+            ),
+        ]
+    }
+
+    #[test]
+    fn counts_retrievals_per_record() {
+        let entries = vec![
+            entry(
+                "s1",
+                "query",
+                "2024-06-20T00:00:00Z",
+                None,
+                Some(&["mx-aaaaaa"]),
+            ),
+            entry(
+                "s1",
+                "search",
+                "2024-06-21T00:00:00Z",
+                Some("errors"),
+                Some(&["mx-aaaaaa", "mx-bbbbbb"]),
+            ),
+            // Feedback is not a retrieval
+            AccessLogEntry {
+                entry_id: Some("mx-bbbbbb".to_string()),
+                ..entry("s1", "feedback", "2024-06-22T00:00:00Z", None, None)
+            },
+        ];
+        let report = compute_usage(&entries, &records(), &opts());
+
+        assert_eq!(report.most_retrieved[0].id, "mx-aaaaaa");
+        assert_eq!(report.most_retrieved[0].retrievals, 2);
+        assert_eq!(report.most_retrieved[1].retrievals, 1);
+        assert_eq!(report.most_retrieved.len(), 2);
+        assert_eq!(report.least_retrieved[0].id, "mx-cccccc");
+        assert_eq!(report.least_retrieved[0].retrievals, 0);
+    }
+
+    #[test]
+    fn unused_skips_recent_records() {
+        let entries = vec![entry(
+            "s1",
+            "query",
+            "2024-01-15T00:00:00Z",
+            None,
+            Some(&["mx-aaaaaa"]),
+        )];
+        let report = compute_usage(&entries, &records(), &opts());
+        let ids: Vec<&str> = report.unused.iter().map(|u| u.id.as_str()).collect();
+        // mx-aaaaaa was last accessed outside the window, mx-bbbbbb never;
+        // mx-cccccc is too new to judge.
+        assert_eq!(ids, vec!["mx-aaaaaa", "mx-bbbbbb"]);
+    }
+
+    #[test]
+    fn groups_zero_result_queries() {
+        let entries = vec![
+            entry(
+                "s1",
+                "search",
+                "2024-06-20T00:00:00Z",
+                Some("Tokio"),
+                Some(&[]),
+            ),
+            entry(
+                "s2",
+                "search",
+                "2024-06-21T00:00:00Z",
+                Some("tokio "),
+                Some(&[]),
+            ),
+            entry(
+                "s2",
+                "search",
+                "2024-06-21T00:00:00Z",
+                Some("serde"),
+                Some(&[]),
+            ),
+        ];
+        let report = compute_usage(&entries, &records(), &opts());
+        assert_eq!(report.zero_result_queries.len(), 2);
+        assert_eq!(report.zero_result_queries[0].count, 2);
+    }
+
+    #[test]
+    fn counts_by_domain_tool_and_session() {
+        let entries = vec![
+            entry(
+                "s1",
+                "query",
+                "2024-06-20T00:00:00Z",
+                None,
+                Some(&["mx-aaaaaa"]),
+            ),
+            entry("s1", "search", "2024-06-21T00:00:00Z", Some("x"), Some(&[])),
+            entry("s2", "search", "2024-06-22T00:00:00Z", Some("y"), Some(&[])),
+        ];
+        let report = compute_usage(&entries, &records(), &opts());
+
+        assert_eq!(report.total_calls, 3);
+        assert_eq!(report.by_domain[0].name, "rust");
+        assert_eq!(report.by_domain[0].count, 3);
+        assert_eq!(report.by_tool[0].name, "search");
+        assert_eq!(report.by_tool[0].count, 2);
+
+        assert_eq!(report.by_session[0].session_id, "s2");
+        let s1 = &report.by_session[1];
+        assert_eq!(s1.calls, 2);
+        assert_eq!(s1.records_retrieved, 1);
+        assert_eq!(s1.zero_result_queries, 1);
+    }
+}
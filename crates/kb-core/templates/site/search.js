@@ -0,0 +1,51 @@
+// Client-side search over the prebuilt index in search-index.js.
+(function () {
+  var input = document.getElementById("search");
+  var results = document.getElementById("results");
+  var index = window.KB_SEARCH_INDEX || [];
+  var MAX_RESULTS = 50;
+
+  var docs = index.map(function (entry) {
+    return {
+      entry: entry,
+      haystack: [entry.id || "", entry.domain, entry.type, entry.title, entry.text]
+        .concat(entry.tags)
+        .join(" ")
+        .toLowerCase(),
+    };
+  });
+
+  function render(matches) {
+    results.textContent = "";
+    matches.slice(0, MAX_RESULTS).forEach(function (entry) {
+      var li = document.createElement("li");
+      var a = document.createElement("a");
+      a.href = entry.url;
+      a.textContent = entry.title;
+      var meta = document.createElement("span");
+      meta.className = "meta";
+      meta.textContent = " " + entry.domain + " / " + entry.type;
+      li.appendChild(a);
+      li.appendChild(meta);
+      results.appendChild(li);
+    });
+  }
+
+  input.addEventListener("input", function () {
+    var terms = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+    if (terms.length === 0) {
+      render([]);
+      return;
+    }
+    var matches = docs
+      .filter(function (doc) {
+        return terms.every(function (term) {
+          return doc.haystack.indexOf(term) !== -1;
+        });
+      })
+      .map(function (doc) {
+        return doc.entry;
+      });
+    render(matches);
+  });
+})();
@@ -66,12 +66,21 @@ pub enum Commands {
     /// Show recently added/updated records
     Ready(ReadyArgs),
 
+    /// List records that likely need re-verification
+    Review(ReviewArgs),
+
     /// Show changed files and suggest domains
     Learn(LearnArgs),
 
     /// Merge/consolidate record groups
     Compact(CompactArgs),
 
+    /// Propose sub-domains for a large domain and move records into them
+    Split(SplitArgs),
+
+    /// Promote branch-scoped records, or reclassify records by their evidence
+    Promote(PromoteArgs),
+
     /// Configure IDE provider recipes and git hooks
     Setup(SetupArgs),
 
@@ -85,17 +94,50 @@ pub enum Commands {
     /// Check for updates
     Update(UpdateArgs),
 
-    /// Show expertise changes since a git ref
+    /// Show field-level expertise changes between git refs
     Diff(DiffArgs),
 
+    /// Show the git history of a record: every commit that added, changed or removed it
+    Log(LogArgs),
+
+    /// Annotate each record in a domain with the commit that introduced it
+    Blame(BlameArgs),
+
+    /// Create records from `Kb-*` trailers in commit messages
+    #[command(name = "ingest-commits")]
+    IngestCommits(IngestCommitsArgs),
+
+    /// Git merge driver for expertise JSONL (installed by `kb setup --merge-driver`)
+    #[command(name = "merge-driver")]
+    MergeDriver(MergeDriverArgs),
+
     /// Query the access log
     #[command(name = "access-log")]
     AccessLog(AccessLogArgs),
 
+    /// List, inspect and restore records removed by delete, prune and compact
+    #[command(subcommand)]
+    Archive(ArchiveCommands),
+
     /// Manage sessions
     #[command(subcommand)]
     Session(SessionCommands),
 
+    /// Usage analytics
+    #[command(subcommand)]
+    Stats(StatsCommands),
+
+    /// Report knowledge gaps from oracle calls and zero-result searches
+    Gaps(GapsArgs),
+
+    /// Export records as Markdown, CSV, YAML or NDJSON, or as a static site
+    #[command(args_conflicts_with_subcommands = true)]
+    Export(ExportArgs),
+
+    /// Import records from a Markdown, CSV, YAML or NDJSON export, or from ADRs
+    #[command(args_conflicts_with_subcommands = true)]
+    Import(ImportArgs),
+
     /// Check file references in records
     Check(CheckArgs),
 
@@ -208,7 +250,7 @@ pub struct RecordArgs {
     #[arg(long = "outcome-agent")]
     pub outcome_agent: Option<String>,
 
-    /// Force recording even if duplicate exists
+    /// Force recording even if a duplicate exists or the domain is at its hard limit
     #[arg(long)]
     pub force: bool,
 
@@ -223,6 +265,10 @@ pub struct RecordArgs {
     /// Preview what would be recorded without writing
     #[arg(long)]
     pub dry_run: bool,
+
+    /// Record as mainline knowledge even when on a feature branch
+    #[arg(long)]
+    pub no_branch: bool,
 }
 
 #[derive(Args, Debug)]
@@ -318,6 +364,10 @@ pub struct QueryArgs {
     /// Query all domains
     #[arg(long)]
     pub all: bool,
+
+    /// Render with a named template from .kb/templates/ or a built-in
+    #[arg(long)]
+    pub template: Option<String>,
 }
 
 #[derive(Args, Debug)]
@@ -352,6 +402,10 @@ pub struct SearchArgs {
     /// Sort by confirmation score
     #[arg(long)]
     pub sort_by_score: bool,
+
+    /// Also search records removed to .kb/archive/
+    #[arg(long)]
+    pub include_archived: bool,
 }
 
 #[derive(Args, Debug)]
@@ -361,6 +415,18 @@ pub struct DeleteArgs {
 
     /// Record ID to delete
     pub id: String,
+
+    /// Why the record is removed, kept in the archive
+    #[arg(long, default_value = "deleted")]
+    pub reason: String,
+
+    /// Remove permanently instead of moving to .kb/archive/
+    #[arg(long)]
+    pub purge: bool,
+
+    /// Session ID recorded with the archived records
+    #[arg(long)]
+    pub session: Option<String>,
 }
 
 #[derive(Args, Debug)]
@@ -384,6 +450,10 @@ pub struct PrimeArgs {
     #[arg(long, default_value = "markdown", value_parser = ["markdown", "xml", "plain"])]
     pub format: String,
 
+    /// Render with a named template from .kb/templates/ or a built-in (compact, markdown, xml, plain)
+    #[arg(long, conflicts_with = "format")]
+    pub template: Option<String>,
+
     /// Filter by git-changed files
     #[arg(long)]
     pub context: bool,
@@ -396,11 +466,11 @@ pub struct PrimeArgs {
     #[arg(long)]
     pub export: Option<String>,
 
-    /// Token budget
+    /// Token budget (default 4000); also caps --json and --mcp output
     #[arg(long)]
     pub budget: Option<usize>,
 
-    /// Remove token budget limit
+    /// Remove token budget limit, including for --json and --mcp output
     #[arg(long)]
     pub no_limit: bool,
 
@@ -411,6 +481,30 @@ pub struct PrimeArgs {
     /// Exclude specific domain
     #[arg(long = "exclude-domain")]
     pub exclude_domain: Option<String>,
+
+    /// Print a token report (per-domain and output totals) instead of the output
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Minimum share of the budget reserved per domain (0.0-1.0)
+    #[arg(long)]
+    pub min_share: Option<f64>,
+
+    /// Maximum share of the budget a domain may use (0.0-1.0)
+    #[arg(long)]
+    pub max_share: Option<f64>,
+
+    /// Cap records of a type per domain (e.g., convention=10); repeatable
+    #[arg(long, value_name = "TYPE=N")]
+    pub type_cap: Vec<String>,
+
+    /// Foundational records per domain kept regardless of quotas
+    #[arg(long)]
+    pub foundational_slots: Option<usize>,
+
+    /// Reserve a share of the budget (default 0.1) for an index of dropped records
+    #[arg(long, value_name = "SHARE", num_args = 0..=1, require_equals = true, default_missing_value = "0.1")]
+    pub overflow_index: Option<f64>,
 }
 
 #[derive(Args, Debug)]
@@ -418,6 +512,34 @@ pub struct PruneArgs {
     /// Preview what would be pruned without deleting
     #[arg(long)]
     pub dry_run: bool,
+
+    /// Show how long each stale record has gone unchanged, its shelf life and the rule that set it
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Remove permanently instead of moving to .kb/archive/
+    #[arg(long)]
+    pub purge: bool,
+
+    /// Session ID recorded with the archived records
+    #[arg(long)]
+    pub session: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ReviewArgs {
+    /// Records whose referenced files changed heavily since they were written
+    /// (the default queue)
+    #[arg(long)]
+    pub stale: bool,
+
+    /// Only review this domain
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Maximum number of records to show
+    #[arg(long)]
+    pub limit: Option<usize>,
 }
 
 #[derive(Args, Debug)]
@@ -452,6 +574,16 @@ pub struct LearnArgs {
     #[arg(long)]
     pub skip: bool,
 
+    /// Also list existing records related to the changed files, to confirm,
+    /// revise or supersede
+    #[arg(long)]
+    pub relevant: bool,
+
+    /// Print record drafts inferred from the diffs and commit messages as a
+    /// JSON array for `kb record --batch`
+    #[arg(long, conflicts_with = "relevant")]
+    pub draft: bool,
+
     /// Session ID (for access log tracking)
     #[arg(long)]
     pub session: Option<String>,
@@ -459,13 +591,94 @@ pub struct LearnArgs {
 
 #[derive(Args, Debug)]
 pub struct CompactArgs {
-    /// Auto-merge without prompting
-    #[arg(long)]
+    /// Domain to compact (default: all domains)
+    pub domain: Option<String>,
+
+    /// Merge without prompting; only exact duplicates unless --similarity is given
+    #[arg(long, conflicts_with = "interactive")]
     pub auto: bool,
 
     /// Preview what would be compacted
     #[arg(long)]
     pub dry_run: bool,
+
+    /// Review each proposed merge: accept, skip or edit
+    #[arg(long, short)]
+    pub interactive: bool,
+
+    /// Write the proposed merges to a JSON plan file instead of applying them
+    #[arg(long, value_name = "FILE", conflicts_with = "auto")]
+    pub plan: Option<String>,
+
+    /// Execute a plan file written by --plan
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["domain", "auto", "interactive", "plan"])]
+    pub apply: Option<String>,
+
+    /// Token-set similarity (0-1) above which records are near-duplicates (default 0.6; --auto without it merges exact duplicates only)
+    #[arg(long)]
+    pub similarity: Option<f64>,
+
+    /// Remove permanently instead of moving to .kb/archive/
+    #[arg(long)]
+    pub purge: bool,
+
+    /// Session ID recorded with the archived records
+    #[arg(long)]
+    pub session: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct SplitArgs {
+    /// Domain to split
+    #[arg(required_unless_present = "apply")]
+    pub domain: Option<String>,
+
+    /// Move the records into the proposed sub-domains without prompting
+    #[arg(long)]
+    pub auto: bool,
+
+    /// Preview what would be moved
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Write the proposed split to a JSON plan file instead of applying it
+    #[arg(long, value_name = "FILE", conflicts_with = "auto")]
+    pub plan: Option<String>,
+
+    /// Execute a plan file written by --plan
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["domain", "auto", "plan"])]
+    pub apply: Option<String>,
+
+    /// Maximum number of sub-domains to propose
+    #[arg(long, default_value_t = kb_core::split::DEFAULT_MAX_GROUPS)]
+    pub max_groups: usize,
+
+    /// Minimum number of records in a proposed sub-domain
+    #[arg(long, default_value_t = kb_core::split::DEFAULT_MIN_SIZE)]
+    pub min_size: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct PromoteArgs {
+    /// Branch whose records to promote
+    #[arg(long, required_unless_present = "auto", conflicts_with = "auto")]
+    pub branch: Option<String>,
+
+    /// Promote and demote classifications by outcomes and feedback, per the `promotion` policy
+    #[arg(long)]
+    pub auto: bool,
+
+    /// Only promote records in this domain
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Show what would be promoted without writing
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Session ID recorded with classification changes in the changelog
+    #[arg(long, requires = "auto")]
+    pub session: Option<String>,
 }
 
 #[derive(Args, Debug)]
@@ -474,6 +687,10 @@ pub struct SetupArgs {
     #[arg(long)]
     pub git_hook: bool,
 
+    /// Install the record-aware git merge driver for .kb/expertise/*.jsonl
+    #[arg(long)]
+    pub merge_driver: bool,
+
     /// Provider to configure
     #[arg(long, value_parser = ["claude", "cursor", "codex", "gemini", "windsurf", "aider"])]
     pub provider: Option<String>,
@@ -538,11 +755,65 @@ pub struct UpdateArgs {
 
 #[derive(Args, Debug)]
 pub struct DiffArgs {
-    /// Git ref to compare against
+    /// Git ref or range to compare (`<ref>`, `<from>..<to>`, `<from>..`, `..<to>`)
+    #[arg(conflicts_with = "since")]
+    pub range: Option<String>,
+
+    /// Git ref to compare the working tree against
     #[arg(long, default_value = "HEAD~1")]
     pub since: String,
 }
 
+#[derive(Args, Debug)]
+pub struct LogArgs {
+    /// Record ID (full, bare hash, or prefix); deleted records are found too
+    pub id: String,
+
+    /// Only search this domain
+    #[arg(long)]
+    pub domain: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct BlameArgs {
+    /// Expertise domain
+    pub domain: String,
+}
+
+#[derive(Args, Debug)]
+pub struct IngestCommitsArgs {
+    /// Commit or range to read (`HEAD`, `<from>..<to>`, `origin/main..`)
+    #[arg(default_value = "HEAD")]
+    pub range: String,
+
+    /// Domain for records whose commit has no `Kb-Domain` trailer
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Preview what would be recorded without writing
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Record as mainline knowledge even when on a feature branch
+    #[arg(long)]
+    pub no_branch: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct MergeDriverArgs {
+    /// Common ancestor version (%O)
+    pub base: String,
+
+    /// Current branch version, overwritten with the result (%A)
+    pub ours: String,
+
+    /// Other branch version (%B)
+    pub theirs: String,
+
+    /// Path of the file being merged, for messages (%P)
+    pub path: Option<String>,
+}
+
 #[derive(Args, Debug)]
 pub struct AccessLogArgs {
     /// Filter by session ID
@@ -558,6 +829,34 @@ pub struct AccessLogArgs {
     pub tool_type: Option<String>,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum ArchiveCommands {
+    /// List archived records
+    List(ArchiveListArgs),
+
+    /// Show an archived record with its removal metadata
+    Show(ArchiveRecordArgs),
+
+    /// Move an archived record back into its domain
+    Restore(ArchiveRecordArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ArchiveListArgs {
+    /// Limit to a specific domain
+    pub domain: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ArchiveRecordArgs {
+    /// Archived record ID (full ID, hash or prefix)
+    pub id: String,
+
+    /// Domain to look in (default: search every domain's archive)
+    #[arg(long)]
+    pub domain: Option<String>,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum SessionCommands {
     /// List all sessions
@@ -573,6 +872,179 @@ pub struct SessionShowArgs {
     pub id: String,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum StatsCommands {
+    /// Report record retrievals, unused records and zero-result queries
+    Usage(StatsUsageArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct StatsUsageArgs {
+    /// Report records not accessed in this many days
+    #[arg(long, default_value = "30")]
+    pub days: i64,
+
+    /// Maximum rows in ranked lists
+    #[arg(long, default_value = "10")]
+    pub limit: usize,
+
+    /// Limit report to a specific domain
+    #[arg(long)]
+    pub domain: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    #[command(subcommand)]
+    pub command: Option<ExportCommands>,
+
+    /// Export format (inferred from --output when omitted)
+    #[arg(long, value_parser = ["markdown", "csv", "yaml", "ndjson"])]
+    pub format: Option<String>,
+
+    /// Export specific domains only (comma-separated)
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Write to a file, or to one file per domain when the path is a directory
+    #[arg(long, short)]
+    pub output: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExportCommands {
+    /// Generate a self-contained static HTML site
+    Html(ExportHtmlArgs),
+
+    /// Write decision records as numbered MADR files
+    Adr(ExportAdrArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ExportAdrArgs {
+    /// ADR directory (e.g. docs/adr)
+    pub dir: String,
+
+    /// Export specific domains only (comma-separated)
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Show which files would be written without writing
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportHtmlArgs {
+    /// Output directory
+    pub dir: String,
+
+    /// Site title
+    #[arg(long, default_value = "Knowledge Base")]
+    pub title: String,
+
+    /// Export specific domains only (comma-separated)
+    #[arg(long)]
+    pub domain: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    #[command(subcommand)]
+    pub command: Option<ImportCommands>,
+
+    /// File to import (`-` for stdin)
+    pub file: Option<String>,
+
+    /// Input format (inferred from the file extension when omitted)
+    #[arg(long, value_parser = ["markdown", "csv", "yaml", "ndjson"])]
+    pub format: Option<String>,
+
+    /// Domain for records that don't name one
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Show what would change without writing
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Add records even if they duplicate existing content (ID matches still update)
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ImportCommands {
+    /// Import MADR or Nygard-style ADRs as decision records
+    Adr(ImportAdrArgs),
+
+    /// Propose records from agent instruction files (CLAUDE.md, AGENTS.md, .cursor/rules)
+    Instructions(ImportInstructionsArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ImportInstructionsArgs {
+    /// Instruction file, or a directory of rule files (e.g. .cursor/rules)
+    pub file: String,
+
+    /// Put every candidate in this domain instead of the suggested one
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Write candidates to <dir>/<domain>.json for `kb record --batch`
+    #[arg(long, conflicts_with = "interactive")]
+    pub drafts: Option<String>,
+
+    /// Review candidates one by one and record the accepted ones
+    #[arg(long, short)]
+    pub interactive: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ImportAdrArgs {
+    /// Directory containing numbered ADR files (e.g. docs/adr)
+    pub dir: String,
+
+    /// Domain to store decision records in
+    #[arg(long)]
+    pub domain: String,
+
+    /// Show what would change without writing
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Also write ADRs for decision records that lack one
+    #[arg(long)]
+    pub sync: bool,
+
+    /// Side that wins when a record and its ADR disagree (with --sync)
+    #[arg(long, default_value = "adr", value_parser = ["adr", "kb"], requires = "sync")]
+    pub prefer: String,
+}
+
+#[derive(Args, Debug)]
+pub struct GapsArgs {
+    /// Filter by domain
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Only include gaps logged since duration (e.g., 24h, 7d, 2w)
+    #[arg(long, default_value = "7d")]
+    pub since: String,
+
+    /// Include the whole access log (ignores --since)
+    #[arg(long)]
+    pub all: bool,
+
+    /// Maximum clusters shown per domain
+    #[arg(long, default_value = "10")]
+    pub limit: usize,
+
+    /// Write draft record stubs to <DIR>/<domain>.json for `kb record --batch`
+    #[arg(long, value_name = "DIR")]
+    pub drafts: Option<String>,
+}
+
 #[derive(Args, Debug)]
 pub struct CheckArgs {
     /// Limit check to a specific domain
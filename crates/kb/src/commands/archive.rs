@@ -0,0 +1,184 @@
+use anyhow::{Result, bail};
+
+use crate::cli::{ArchiveCommands, ArchiveListArgs, ArchiveRecordArgs};
+use crate::context::RuntimeContext;
+use crate::output::*;
+use kb_core::archive::{self, ArchivedRecord};
+use kb_core::error::KbError;
+use kb_core::types::KbConfig;
+use kb_core::{config, format};
+
+pub fn run(ctx: &RuntimeContext, cmd: &ArchiveCommands) -> Result<()> {
+    config::ensure_kb_dir(&ctx.cwd)?;
+    let cfg = config::read_config(&ctx.cwd)?;
+
+    match cmd {
+        ArchiveCommands::List(args) => run_list(ctx, &cfg, args),
+        ArchiveCommands::Show(args) => run_show(ctx, &cfg, args),
+        ArchiveCommands::Restore(args) => run_restore(ctx, &cfg, args),
+    }
+}
+
+fn domains<'a>(cfg: &'a KbConfig, domain: Option<&'a String>) -> Result<Vec<&'a String>> {
+    match domain {
+        Some(domain) => {
+            config::ensure_domain_exists(cfg, domain)?;
+            Ok(vec![domain])
+        }
+        None => Ok(cfg.domains.iter().collect()),
+    }
+}
+
+fn entry_json(domain: &str, entry: &ArchivedRecord) -> serde_json::Value {
+    serde_json::json!({
+        "domain": domain,
+        "record": entry.record,
+        "removal": entry.removal,
+    })
+}
+
+fn run_list(ctx: &RuntimeContext, cfg: &KbConfig, args: &ArchiveListArgs) -> Result<()> {
+    let mut entries: Vec<(&String, ArchivedRecord)> = Vec::new();
+    for domain in domains(cfg, args.domain.as_ref())? {
+        let path = config::get_archive_path(domain, &ctx.cwd)?;
+        entries.extend(
+            archive::read_archive(&path)?
+                .into_iter()
+                .map(|entry| (domain, entry)),
+        );
+    }
+
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "archive list",
+            "count": entries.len(),
+            "records": entries
+                .iter()
+                .map(|(domain, entry)| entry_json(domain, entry))
+                .collect::<Vec<_>>(),
+        }));
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        println!("No archived records.");
+        return Ok(());
+    }
+    for (domain, entry) in &entries {
+        println!(
+            "  [{domain}] {} {}: {}",
+            entry.record.id().unwrap_or("?"),
+            entry.record.record_type(),
+            format::get_record_summary(&entry.record)
+        );
+        println!(
+            "      {} (kb {}, {})",
+            entry.removal.reason,
+            entry.removal.command,
+            format::format_time_ago(&entry.removal.removed_at)
+        );
+    }
+    println!("\n{} archived record(s).", entries.len());
+    Ok(())
+}
+
+/// The domain and latest archive entry for `args.id`, searching every
+/// domain's archive unless `--domain` is given.
+fn find_archived(
+    ctx: &RuntimeContext,
+    cfg: &KbConfig,
+    args: &ArchiveRecordArgs,
+) -> Result<(String, ArchivedRecord)> {
+    let mut found: Vec<(String, ArchivedRecord)> = Vec::new();
+    for domain in domains(cfg, args.domain.as_ref())? {
+        let path = config::get_archive_path(domain, &ctx.cwd)?;
+        let mut entries = archive::read_archive(&path)?;
+        match archive::resolve_archived(&entries, &args.id) {
+            Ok(i) => found.push((domain.clone(), entries.swap_remove(i))),
+            Err(KbError::RecordNotFound(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    match found.len() {
+        0 => bail!("No archived record matches \"{}\".", args.id),
+        1 => Ok(found.remove(0)),
+        _ => {
+            let names: Vec<&str> = found.iter().map(|(d, _)| d.as_str()).collect();
+            bail!(
+                "\"{}\" is archived in several domains ({}). Pass --domain.",
+                args.id,
+                names.join(", ")
+            )
+        }
+    }
+}
+
+fn run_show(ctx: &RuntimeContext, cfg: &KbConfig, args: &ArchiveRecordArgs) -> Result<()> {
+    let (domain, entry) = match find_archived(ctx, cfg, args) {
+        Ok(found) => found,
+        Err(e) if ctx.json => {
+            output_json_error("archive show", &format!("{e:#}"));
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+
+    if ctx.json {
+        let mut json = entry_json(&domain, &entry);
+        json["success"] = true.into();
+        json["command"] = "archive show".into();
+        output_json(&json);
+        return Ok(());
+    }
+
+    println!(
+        "{} ({}) archived from {domain}",
+        entry.record.id().unwrap_or("?"),
+        entry.record.record_type()
+    );
+    println!("Reason:  {}", entry.removal.reason);
+    println!(
+        "Removed: {} by `kb {}`",
+        entry.removal.removed_at, entry.removal.command
+    );
+    if let Some(ref session) = entry.removal.session_id {
+        println!("Session: {session}");
+    }
+    println!();
+    println!("{}", serde_json::to_string_pretty(&entry.record)?);
+    Ok(())
+}
+
+fn run_restore(ctx: &RuntimeContext, cfg: &KbConfig, args: &ArchiveRecordArgs) -> Result<()> {
+    let restored = find_archived(ctx, cfg, args).and_then(|(domain, entry)| {
+        let id = entry.record.id().unwrap_or_default();
+        let restored = archive::restore_record(&ctx.cwd, &domain, id)?;
+        Ok((domain, restored))
+    });
+    let (domain, restored) = match restored {
+        Ok(r) => r,
+        Err(e) if ctx.json => {
+            output_json_error("archive restore", &format!("{e:#}"));
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+
+    let id = restored.record.id().unwrap_or("?");
+    let summary = format::get_record_summary(&restored.record);
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "archive restore",
+            "domain": domain,
+            "id": id,
+            "summary": summary,
+        }));
+    } else {
+        print_success(&format!(
+            "Restored record {id} (\"{summary}\") to \"{domain}\"."
+        ));
+    }
+    Ok(())
+}
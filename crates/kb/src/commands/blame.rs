@@ -0,0 +1,63 @@
+use anyhow::{Result, bail};
+
+use crate::cli::BlameArgs;
+use crate::commands::log::commit_label;
+use crate::context::RuntimeContext;
+use crate::output::*;
+use kb_core::{config, git, history, storage};
+
+pub fn run(ctx: &RuntimeContext, args: &BlameArgs) -> Result<()> {
+    config::ensure_kb_dir(&ctx.cwd)?;
+
+    if !git::is_git_repo(&ctx.cwd) {
+        if ctx.json {
+            output_json_error("blame", "Not in a git repository.");
+            return Ok(());
+        }
+        bail!("Not in a git repository. `kb blame` requires git.");
+    }
+
+    let cfg = config::read_config(&ctx.cwd)?;
+    config::ensure_domain_exists(&cfg, &args.domain)?;
+
+    let path = config::get_expertise_path(&args.domain, &ctx.cwd)?;
+    let records = storage::read_expertise_file(&path)?;
+    let entries = history::domain_history(&ctx.cwd, &args.domain)?;
+    let blamed = history::blame(&entries, &records);
+
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "blame",
+            "domain": args.domain,
+            "records": blamed,
+        }));
+        return Ok(());
+    }
+
+    if blamed.is_empty() {
+        println!("No records in {}.", args.domain);
+        return Ok(());
+    }
+
+    for entry in &blamed {
+        println!(
+            "{}  {} {}: {}",
+            commit_label(entry.introduced.as_ref()),
+            entry.id,
+            entry.record_type,
+            entry.summary
+        );
+        let introduced = entry.introduced.as_ref().map(|c| &c.hash);
+        let last_changed = entry.last_changed.as_ref().map(|c| &c.hash);
+        // A committed record with a later commit or uncommitted edit
+        if introduced.is_some() && last_changed != introduced {
+            println!(
+                "    last changed: {}",
+                commit_label(entry.last_changed.as_ref())
+            );
+        }
+    }
+
+    Ok(())
+}
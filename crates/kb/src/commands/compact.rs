@@ -1,148 +1,326 @@
-use anyhow::Result;
-use std::collections::HashMap;
+use std::io::{BufRead as _, Write as _};
+
+use anyhow::{Context, Result, bail};
 
 use crate::cli::CompactArgs;
 use crate::context::RuntimeContext;
 use crate::output::*;
-use kb_core::types::*;
-use kb_core::{config, lock, storage};
-
-/// Group records by type for potential merging.
-fn find_compact_groups(records: &[ExpertiseRecord]) -> HashMap<RecordType, Vec<usize>> {
-    let mut groups: HashMap<RecordType, Vec<usize>> = HashMap::new();
-    for (i, r) in records.iter().enumerate() {
-        groups.entry(r.record_type()).or_default().push(i);
-    }
-    // Only keep groups with more than 1 record (candidates for compaction)
-    groups.retain(|_, indices| indices.len() > 1);
-    groups
+use kb_core::archive::{self, ArchivedRecord, Removal};
+use kb_core::compact::{self, CompactCluster, CompactPlan};
+use kb_core::types::ExpertiseRecord;
+use kb_core::{config, format, lock, storage};
+
+/// A proposed merge plus the records it would replace, for display.
+struct Proposal {
+    cluster: CompactCluster,
+    members: Vec<ExpertiseRecord>,
 }
 
 pub fn run(ctx: &RuntimeContext, args: &CompactArgs) -> Result<()> {
     config::ensure_kb_dir(&ctx.cwd)?;
     let cfg = config::read_config(&ctx.cwd)?;
 
-    let mut total_merged = 0usize;
-    let mut json_domains: Vec<serde_json::Value> = Vec::new();
+    if let Some(ref plan) = args.apply {
+        return run_apply(ctx, args, &cfg.domains, plan);
+    }
+    if let Some(similarity) = args.similarity
+        && !(0.0..=1.0).contains(&similarity)
+    {
+        let msg = format!("--similarity must be between 0 and 1, got {similarity}");
+        if ctx.json {
+            output_json_error("compact", &msg);
+            return Ok(());
+        }
+        bail!(msg);
+    }
+    // Unattended merges stick to exact duplicates unless a threshold is given
+    let threshold = match args.similarity {
+        None if args.auto => None,
+        similarity => Some(similarity.unwrap_or(compact::DEFAULT_SIMILARITY)),
+    };
+
+    let domains: Vec<&String> = match args.domain {
+        Some(ref domain) => {
+            config::ensure_domain_exists(&cfg, domain)?;
+            vec![domain]
+        }
+        None => cfg.domains.iter().collect(),
+    };
 
-    for domain in &cfg.domains {
+    let mut proposals: Vec<Proposal> = Vec::new();
+    for domain in domains {
         let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
         let records = storage::read_expertise_file(&file_path)?;
-        let groups = find_compact_groups(&records);
+        for cluster in compact::find_clusters(domain, &records, threshold) {
+            let members = cluster
+                .ids
+                .iter()
+                .filter_map(|id| records.iter().find(|r| r.id() == Some(id.as_str())))
+                .cloned()
+                .collect();
+            proposals.push(Proposal { cluster, members });
+        }
+    }
+
+    let accepted: Vec<CompactCluster> = if args.interactive {
+        review_proposals(proposals)?
+    } else {
+        if !ctx.json {
+            for proposal in &proposals {
+                print_proposal(proposal);
+            }
+        }
+        proposals.into_iter().map(|p| p.cluster).collect()
+    };
+
+    if let Some(ref plan) = args.plan {
+        let path = ctx.cwd.join(plan);
+        let plan_json = serde_json::to_string_pretty(&CompactPlan {
+            clusters: accepted.clone(),
+        })?;
+        std::fs::write(&path, plan_json + "\n")
+            .with_context(|| format!("Failed to write plan file: {plan}"))?;
+        if ctx.json {
+            output_json(&serde_json::json!({
+                "success": true,
+                "command": "compact",
+                "plan": plan,
+                "clusters": accepted,
+            }));
+        } else {
+            print_success(&format!(
+                "Wrote {} proposed merge(s) to {plan}. Apply with `kb compact --apply {plan}`.",
+                accepted.len()
+            ));
+        }
+        return Ok(());
+    }
+
+    let apply = (args.auto || args.interactive) && !args.dry_run;
+    let total_merged = if apply {
+        apply_clusters(ctx, args, &accepted)?
+    } else {
+        0
+    };
+
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "compact",
+            "dry_run": !apply,
+            "total_merged": total_merged,
+            "clusters": accepted,
+        }));
+    } else if accepted.is_empty() && args.interactive {
+        println!("No merges accepted.");
+    } else if accepted.is_empty() {
+        print_success("No near-duplicate records found to compact.");
+    } else if !apply {
+        println!();
+        print_warning(&format!(
+            "{} proposed merge(s). Apply with --auto, review with --interactive, or write a plan with --plan <file>.",
+            accepted.len()
+        ));
+    } else {
+        print_success(&format!(
+            "Compacted {total_merged} record(s) into {} merged record(s).",
+            accepted.len()
+        ));
+    }
+
+    Ok(())
+}
 
-        if groups.is_empty() {
-            continue;
+fn print_proposal(proposal: &Proposal) {
+    let cluster = &proposal.cluster;
+    println!(
+        "  [{}] {} {} records -> {} (similarity {:.2})",
+        cluster.domain,
+        cluster.ids.len(),
+        cluster.merged.record_type(),
+        cluster.ids[0],
+        cluster.similarity
+    );
+    for record in &proposal.members {
+        let id = record.id().unwrap_or("?");
+        let marker = if id == cluster.ids[0] { "=" } else { "-" };
+        println!("    {marker} {id} {}", format::get_record_summary(record));
+    }
+    let merged = &cluster.merged;
+    println!("    merged: {}", compact::headline(merged));
+    for (label, values) in [
+        ("tags", merged.tags()),
+        ("files", merged.files()),
+        ("relates to", merged.relates_to()),
+        ("supersedes", merged.supersedes()),
+    ] {
+        if let Some(values) = values {
+            println!("      {label}: {}", values.join(", "));
         }
+    }
+    if let Some(outcomes) = merged.outcomes() {
+        println!("      outcomes: {}", outcomes.len());
+    }
+}
+
+/// Prompt for each proposal on stdin and return the accepted (possibly
+/// edited) merges.
+fn review_proposals(proposals: Vec<Proposal>) -> Result<Vec<CompactCluster>> {
+    let total = proposals.len();
+    let mut accepted = Vec::new();
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
 
-        let mut domain_merged = 0usize;
-
-        if args.dry_run {
-            for (record_type, indices) in &groups {
-                if ctx.json {
-                    json_domains.push(serde_json::json!({
-                        "domain": domain,
-                        "type": record_type.as_str(),
-                        "count": indices.len(),
-                        "action": "would_compact",
-                    }));
-                } else {
-                    println!(
-                        "  {}: {} {} records could be compacted",
-                        domain,
-                        indices.len(),
-                        record_type
-                    );
+    'proposals: for (i, mut proposal) in proposals.into_iter().enumerate() {
+        println!();
+        println!("[{}/{total}]", i + 1);
+        print_proposal(&proposal);
+        loop {
+            print!("  Merge? [y]es, [n]o, [e]dit <text>, [q]uit: ");
+            std::io::stdout().flush()?;
+            let Some(answer) = lines.next().transpose()? else {
+                break 'proposals;
+            };
+            let answer = answer.trim();
+            match answer.split_once(' ').unwrap_or((answer, "")) {
+                ("y" | "yes", _) => {
+                    accepted.push(proposal.cluster);
+                    continue 'proposals;
                 }
-            }
-        } else if args.auto {
-            // Auto-compact: for same-type records, keep the newest of each "name" group
-            // For conventions, deduplicate exact content matches
-            lock::with_file_lock(&file_path, || {
-                let mut records = storage::read_expertise_file(&file_path)?;
-                let original_len = records.len();
-
-                // Deduplicate by content/name identity, keeping the newest
-                let mut seen: HashMap<String, usize> = HashMap::new();
-                let mut to_remove: Vec<usize> = Vec::new();
-
-                for (i, r) in records.iter().enumerate() {
-                    let key = match r {
-                        ExpertiseRecord::Convention { content, .. } => {
-                            format!("convention:{content}")
-                        }
-                        ExpertiseRecord::Pattern { name, .. } => format!("pattern:{name}"),
-                        ExpertiseRecord::Failure { description, .. } => {
-                            format!("failure:{description}")
-                        }
-                        ExpertiseRecord::Decision { title, .. } => format!("decision:{title}"),
-                        ExpertiseRecord::Reference { name, .. } => format!("reference:{name}"),
-                        ExpertiseRecord::Guide { name, .. } => format!("guide:{name}"),
-                    };
-
-                    if let Some(&prev_idx) = seen.get(&key) {
-                        // Keep the one with the newer recorded_at
-                        let prev_ts = records[prev_idx].recorded_at();
-                        let curr_ts = r.recorded_at();
-                        if curr_ts >= prev_ts {
-                            to_remove.push(prev_idx);
-                            seen.insert(key, i);
-                        } else {
-                            to_remove.push(i);
-                        }
-                    } else {
-                        seen.insert(key, i);
-                    }
+                ("n" | "no" | "", _) => continue 'proposals,
+                ("e" | "edit", text) if !text.trim().is_empty() => {
+                    compact::set_headline(&mut proposal.cluster.merged, text.trim().to_string());
+                    println!("    merged: {}", text.trim());
                 }
+                ("e" | "edit", _) => print_warning("  Give the merged text: e <text>"),
+                ("q" | "quit", _) => break 'proposals,
+                _ => print_warning("  Unrecognised answer."),
+            }
+        }
+    }
+    Ok(accepted)
+}
 
-                to_remove.sort_unstable_by(|a, b| b.cmp(a));
-                to_remove.dedup();
-                for i in &to_remove {
-                    records.remove(*i);
-                }
+/// Apply merges domain by domain, archiving the absorbed records unless
+/// `--purge` is set, and return the number of records removed.
+fn apply_clusters(
+    ctx: &RuntimeContext,
+    args: &CompactArgs,
+    clusters: &[CompactCluster],
+) -> Result<usize> {
+    let mut by_domain: Vec<(&str, Vec<&CompactCluster>)> = Vec::new();
+    for cluster in clusters {
+        match by_domain.iter_mut().find(|(d, _)| *d == cluster.domain) {
+            Some((_, list)) => list.push(cluster),
+            None => by_domain.push((&cluster.domain, vec![cluster])),
+        }
+    }
 
-                domain_merged = original_len - records.len();
-                if domain_merged > 0 {
-                    storage::write_expertise_file(&file_path, &mut records)?;
-                }
-                Ok(())
-            })?;
-
-            if domain_merged > 0 {
-                total_merged += domain_merged;
-                if ctx.json {
-                    json_domains.push(serde_json::json!({
-                        "domain": domain,
-                        "merged": domain_merged,
-                    }));
-                } else {
-                    println!(
-                        "  {}: compacted {} duplicate record(s)",
-                        domain, domain_merged
-                    );
+    let mut total = 0;
+    for (domain, clusters) in by_domain {
+        let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
+        lock::with_file_lock(&file_path, || {
+            let mut records = storage::read_expertise_file(&file_path)?;
+            let mut archived = Vec::new();
+            for cluster in &clusters {
+                let mut cluster = (*cluster).clone();
+                cluster.merged.touch();
+                let reason = format!("merged into {}", cluster.ids[0]);
+                for record in compact::apply_cluster(&mut records, &cluster)? {
+                    archived.push(ArchivedRecord {
+                        record,
+                        removal: Removal::new("compact", reason.as_str(), args.session.as_deref()),
+                    });
                 }
             }
+            // Archive before rewriting the domain file, as in `kb delete`
+            if !args.purge {
+                archive::archive_records(&ctx.cwd, domain, &archived)?;
+            }
+            storage::write_expertise_file(&file_path, &mut records)
+        })
+        .with_context(|| format!("Failed to compact {domain}"))?;
+        let merged: usize = clusters.iter().map(|c| c.absorbed().len()).sum();
+        if !ctx.json {
+            println!(
+                "  {domain}: merged {merged} record(s) into {}",
+                clusters.len()
+            );
+        }
+        total += merged;
+    }
+    Ok(total)
+}
+
+fn run_apply(
+    ctx: &RuntimeContext,
+    args: &CompactArgs,
+    domains: &[String],
+    plan: &str,
+) -> Result<()> {
+    let dry_run = args.dry_run;
+    let path = ctx.cwd.join(plan);
+    let parsed = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read plan file: {plan}"))
+        .and_then(|content| {
+            serde_json::from_str::<CompactPlan>(&content)
+                .with_context(|| format!("Invalid plan file: {plan}"))
+        });
+    let plan_data = match parsed {
+        Ok(p) => p,
+        Err(e) => {
+            if ctx.json {
+                output_json_error("compact", &format!("{e:#}"));
+                return Ok(());
+            }
+            return Err(e);
+        }
+    };
+
+    if let Some(cluster) = plan_data
+        .clusters
+        .iter()
+        .find(|c| !domains.contains(&c.domain))
+    {
+        let msg = format!("Plan names unknown domain \"{}\"", cluster.domain);
+        if ctx.json {
+            output_json_error("compact", &msg);
+            return Ok(());
         }
+        bail!(msg);
     }
 
+    let total_merged = if dry_run {
+        0
+    } else {
+        match apply_clusters(ctx, args, &plan_data.clusters) {
+            Ok(total) => total,
+            Err(e) if ctx.json => {
+                output_json_error("compact", &format!("{e:#}"));
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
     if ctx.json {
         output_json(&serde_json::json!({
             "success": true,
             "command": "compact",
-            "dry_run": args.dry_run,
+            "dry_run": dry_run,
             "total_merged": total_merged,
-            "domains": json_domains,
+            "clusters": plan_data.clusters,
         }));
-    } else if args.dry_run {
-        if total_merged == 0 && json_domains.is_empty() {
-            print_success("No records to compact.");
-        } else {
-            print_warning("Dry run complete. Run without --dry-run to apply.");
-        }
-    } else if total_merged == 0 {
-        print_success("No duplicate records found to compact.");
+    } else if dry_run {
+        print_warning(&format!(
+            "{} merge(s) in {plan}. Run without --dry-run to apply.",
+            plan_data.clusters.len()
+        ));
     } else {
-        print_success(&format!("Compacted {} record(s) total.", total_merged));
+        print_success(&format!(
+            "Applied {plan}: compacted {total_merged} record(s) into {} merged record(s).",
+            plan_data.clusters.len()
+        ));
     }
-
     Ok(())
 }
@@ -3,6 +3,7 @@ use anyhow::Result;
 use crate::cli::DeleteArgs;
 use crate::context::RuntimeContext;
 use crate::output::*;
+use kb_core::archive::{self, ArchivedRecord, Removal};
 use kb_core::{config, lock, resolve, storage};
 
 pub fn run(ctx: &RuntimeContext, args: &DeleteArgs) -> Result<()> {
@@ -12,15 +13,27 @@ pub fn run(ctx: &RuntimeContext, args: &DeleteArgs) -> Result<()> {
 
     let file_path = config::get_expertise_path(&args.domain, &ctx.cwd)?;
     let records = storage::read_expertise_file(&file_path)?;
-    let (idx, matched) = resolve::resolve_record_id(&records, &args.id)?;
+    let (_, matched) = resolve::resolve_record_id(&records, &args.id)?;
     let record_id = matched.id().unwrap_or("unknown").to_string();
     let summary = kb_core::format::get_record_summary(matched);
 
+    // Archive before rewriting the domain file, so a failed archive write
+    // leaves the record in place rather than losing it
     lock::with_file_lock(&file_path, || {
         let mut records = storage::read_expertise_file(&file_path)?;
-        records.remove(idx);
-        storage::write_expertise_file(&file_path, &mut records)?;
-        Ok(())
+        let idx = resolve::resolve_record_id(&records, &record_id)?.0;
+        let removed = records.remove(idx);
+        if !args.purge {
+            archive::archive_records(
+                &ctx.cwd,
+                &args.domain,
+                &[ArchivedRecord {
+                    record: removed,
+                    removal: Removal::new("delete", args.reason.as_str(), args.session.as_deref()),
+                }],
+            )?;
+        }
+        storage::write_expertise_file(&file_path, &mut records)
     })?;
 
     if ctx.json {
@@ -29,12 +42,16 @@ pub fn run(ctx: &RuntimeContext, args: &DeleteArgs) -> Result<()> {
             "command": "delete",
             "domain": args.domain,
             "id": record_id,
+            "archived": !args.purge,
         }));
     } else {
         print_success(&format!(
             "Deleted record {} (\"{}\") from \"{}\".",
             record_id, summary, args.domain
         ));
+        if !args.purge {
+            println!("Archived. Undo with `kb archive restore {record_id}`.");
+        }
     }
 
     Ok(())
@@ -1,41 +1,79 @@
 use anyhow::{Result, bail};
-use std::collections::HashMap;
-use std::process::Command;
 
 use crate::cli::DiffArgs;
 use crate::context::RuntimeContext;
 use crate::output::*;
+use kb_core::diff::{self, ChangeKind, DiffRange, FieldChange, RecordDiff};
+use kb_core::template::SECTIONS;
 use kb_core::types::ExpertiseRecord;
-use kb_core::{config, format, storage};
-
-/// Read a JSONL file content from a git ref.
-fn read_file_at_ref(cwd: &std::path::Path, git_ref: &str, rel_path: &str) -> Option<String> {
-    let output = Command::new("git")
-        .args(["show", &format!("{git_ref}:{rel_path}")])
-        .current_dir(cwd)
-        .output()
-        .ok()?;
-
-    if output.status.success() {
-        Some(String::from_utf8_lossy(&output.stdout).to_string())
-    } else {
-        None
+use kb_core::{config, git, storage};
+
+const EXPERTISE_REL_DIR: &str = ".kb/expertise";
+
+/// Read a domain's records at a git ref, or from the working tree when `git_ref` is `None`.
+fn read_side(
+    ctx: &RuntimeContext,
+    git_ref: Option<&str>,
+    domain: &str,
+) -> Result<Vec<ExpertiseRecord>> {
+    match git_ref {
+        Some(r) => {
+            let rel_path = format!("{EXPERTISE_REL_DIR}/{domain}.jsonl");
+            Ok(git::read_file_at_ref(&ctx.cwd, r, &rel_path)
+                .map(|content| diff::parse_jsonl(&content))
+                .unwrap_or_default())
+        }
+        None => {
+            let path = config::get_expertise_path(domain, &ctx.cwd)?;
+            Ok(storage::read_expertise_file(&path)?)
+        }
+    }
+}
+
+/// Configured domains, plus any domain file present at either ref.
+fn domains_to_diff(ctx: &RuntimeContext, range: &DiffRange) -> Result<Vec<String>> {
+    let cfg = config::read_config(&ctx.cwd)?;
+    let mut domains = cfg.domains.clone();
+    let mut extra: Vec<String> = std::iter::once(range.from.as_str())
+        .chain(range.to.as_deref())
+        .flat_map(|r| git::list_files_at_ref(&ctx.cwd, r, EXPERTISE_REL_DIR))
+        .filter_map(|f| f.strip_suffix(".jsonl").map(str::to_string))
+        .filter(|d| config::validate_domain_name(d).is_ok() && !domains.contains(d))
+        .collect();
+    extra.sort();
+    extra.dedup();
+    domains.extend(extra);
+    Ok(domains)
+}
+
+/// Print `field: old -> new` lines for a modified record.
+pub(crate) fn print_field_changes(fields: &[FieldChange], indent: &str) {
+    let show = |v: &Option<serde_json::Value>| match v {
+        Some(v) => v.to_string(),
+        None => "(none)".to_string(),
+    };
+    for field in fields {
+        println!(
+            "{indent}{}: {} -> {}",
+            field.field,
+            show(&field.old),
+            show(&field.new)
+        );
     }
 }
 
-/// Parse JSONL content into records, skipping bad lines.
-fn parse_jsonl(content: &str) -> Vec<ExpertiseRecord> {
-    content
-        .lines()
-        .filter(|l| !l.trim().is_empty())
-        .filter_map(|l| serde_json::from_str(l).ok())
-        .collect()
+fn marker(kind: ChangeKind) -> &'static str {
+    match kind {
+        ChangeKind::Added => "+",
+        ChangeKind::Modified => "~",
+        ChangeKind::Removed => "-",
+    }
 }
 
 pub fn run(ctx: &RuntimeContext, args: &DiffArgs) -> Result<()> {
     config::ensure_kb_dir(&ctx.cwd)?;
 
-    if !kb_core::git::is_git_repo(&ctx.cwd) {
+    if !git::is_git_repo(&ctx.cwd) {
         if ctx.json {
             output_json_error("diff", "Not in a git repository.");
             return Ok(());
@@ -43,93 +81,109 @@ pub fn run(ctx: &RuntimeContext, args: &DiffArgs) -> Result<()> {
         bail!("Not in a git repository. `kb diff` requires git.");
     }
 
-    let cfg = config::read_config(&ctx.cwd)?;
-
-    let mut added: Vec<(String, ExpertiseRecord)> = Vec::new();
-    let mut removed: Vec<(String, ExpertiseRecord)> = Vec::new();
-
-    for domain in &cfg.domains {
-        let rel_path = format!(".kb/expertise/{domain}.jsonl");
-
-        // Current state
-        let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
-        let current_records = storage::read_expertise_file(&file_path)?;
-
-        // Old state from git ref
-        let old_records = read_file_at_ref(&ctx.cwd, &args.since, &rel_path)
-            .map(|content| parse_jsonl(&content))
-            .unwrap_or_default();
-
-        // Build ID maps
-        let current_ids: HashMap<String, &ExpertiseRecord> = current_records
-            .iter()
-            .filter_map(|r| r.id().map(|id| (id.to_string(), r)))
-            .collect();
-
-        let old_ids: HashMap<String, &ExpertiseRecord> = old_records
-            .iter()
-            .filter_map(|r| r.id().map(|id| (id.to_string(), r)))
-            .collect();
-
-        // Added: in current but not in old
-        for (id, record) in &current_ids {
-            if !old_ids.contains_key(id) {
-                added.push((domain.clone(), (*record).clone()));
+    let spec = args.range.as_deref().unwrap_or(&args.since);
+    let range = match DiffRange::parse(spec) {
+        Ok(range) => range,
+        Err(e) => {
+            if ctx.json {
+                output_json_error("diff", &e.to_string());
+                return Ok(());
             }
+            return Err(e.into());
         }
-
-        // Removed: in old but not in current
-        for (id, record) in &old_ids {
-            if !current_ids.contains_key(id) {
-                removed.push((domain.clone(), (*record).clone()));
+    };
+    for r in std::iter::once(range.from.as_str()).chain(range.to.as_deref()) {
+        if !git::ref_exists(&ctx.cwd, r) {
+            if ctx.json {
+                output_json_error("diff", &format!("Unknown git ref: {r}"));
+                return Ok(());
             }
+            bail!("Unknown git ref: {r}");
         }
     }
 
+    // (domain, changes ordered by type then kind)
+    let mut groups: Vec<(String, Vec<RecordDiff>)> = Vec::new();
+    for domain in domains_to_diff(ctx, &range)? {
+        let old = read_side(ctx, Some(&range.from), &domain)?;
+        let new = read_side(ctx, range.to.as_deref(), &domain)?;
+        let changes = diff::diff_records(&domain, &old, &new)?;
+        if !changes.is_empty() {
+            groups.push((domain, changes));
+        }
+    }
+
+    let all = || groups.iter().flat_map(|(_, c)| c.iter());
+    let count = |kind: ChangeKind| all().filter(|c| c.kind == kind).count();
+    let (added, modified, removed) = (
+        count(ChangeKind::Added),
+        count(ChangeKind::Modified),
+        count(ChangeKind::Removed),
+    );
+
     if ctx.json {
-        let added_json: Vec<serde_json::Value> = added
-            .iter()
-            .map(|(d, r)| serde_json::json!({"domain": d, "record": r}))
-            .collect();
-        let removed_json: Vec<serde_json::Value> = removed
+        let of_kind =
+            |kind: ChangeKind| -> Vec<&RecordDiff> { all().filter(|c| c.kind == kind).collect() };
+        let domains: Vec<serde_json::Value> = groups
             .iter()
-            .map(|(d, r)| serde_json::json!({"domain": d, "record": r}))
+            .map(|(domain, changes)| {
+                let types: Vec<serde_json::Value> = SECTIONS
+                    .iter()
+                    .filter_map(|(rt, _)| {
+                        let of_type: Vec<&RecordDiff> =
+                            changes.iter().filter(|c| c.record_type == *rt).collect();
+                        (!of_type.is_empty())
+                            .then(|| serde_json::json!({"type": rt, "changes": of_type}))
+                    })
+                    .collect();
+                serde_json::json!({"domain": domain, "types": types})
+            })
             .collect();
         output_json(&serde_json::json!({
             "success": true,
             "command": "diff",
-            "since": args.since,
-            "added": added_json,
-            "removed": removed_json,
+            "from": range.from,
+            "to": range.to,
+            "since": range.from,
+            "summary": {"added": added, "modified": modified, "removed": removed},
+            "added": of_kind(ChangeKind::Added),
+            "modified": of_kind(ChangeKind::Modified),
+            "removed": of_kind(ChangeKind::Removed),
+            "domains": domains,
         }));
-    } else {
-        println!("Expertise diff since {}:", args.since);
-        println!();
+        return Ok(());
+    }
 
-        if added.is_empty() && removed.is_empty() {
-            println!("No changes.");
-            return Ok(());
-        }
+    println!("Expertise diff {}..{}:", range.from, range.to_label());
+    println!();
 
-        if !added.is_empty() {
-            println!("Added ({}):", added.len());
-            for (domain, r) in &added {
-                let id = r.id().unwrap_or("?");
-                let summary = format::get_record_summary(r);
-                println!("  + [{domain}] {id} {}: {summary}", r.record_type());
-            }
-            println!();
-        }
+    if groups.is_empty() {
+        println!("No changes.");
+        return Ok(());
+    }
 
-        if !removed.is_empty() {
-            println!("Removed ({}):", removed.len());
-            for (domain, r) in &removed {
-                let id = r.id().unwrap_or("?");
-                let summary = format::get_record_summary(r);
-                println!("  - [{domain}] {id} {}: {summary}", r.record_type());
+    for (domain, changes) in &groups {
+        println!("[{domain}]");
+        for (rt, heading) in SECTIONS {
+            let of_type: Vec<&RecordDiff> =
+                changes.iter().filter(|c| c.record_type == *rt).collect();
+            if of_type.is_empty() {
+                continue;
+            }
+            println!("  {heading}:");
+            for change in of_type {
+                println!(
+                    "    {} {} {}",
+                    marker(change.kind),
+                    change.id,
+                    change.summary
+                );
+                print_field_changes(&change.fields, "        ");
             }
         }
+        println!();
     }
 
+    println!("{added} added, {modified} modified, {removed} removed.");
     Ok(())
 }
@@ -4,13 +4,15 @@ use crate::cli::DoctorArgs;
 use crate::context::RuntimeContext;
 use crate::output::*;
 use kb_core::types::ExpertiseRecord;
-use kb_core::{config, storage};
+use kb_core::{churn, config, storage};
 
 pub fn run(ctx: &RuntimeContext, args: &DoctorArgs) -> Result<()> {
     config::ensure_kb_dir(&ctx.cwd)?;
 
     let mut issues: Vec<serde_json::Value> = Vec::new();
     let mut fixed: Vec<String> = Vec::new();
+    // Advisory findings that do not fail the check
+    let mut warnings: Vec<serde_json::Value> = Vec::new();
 
     // Check 1: config readable
     let cfg = match config::read_config(&ctx.cwd) {
@@ -153,6 +155,27 @@ pub fn run(ctx: &RuntimeContext, args: &DoctorArgs) -> Result<()> {
                 }
             }
         }
+
+        // Check 4: records whose referenced files churned since they were written
+        if let Ok(stale) = churn::stale_records(&ctx.cwd, cfg, None) {
+            for record in &stale {
+                warnings.push(serde_json::json!({
+                    "check": "churn",
+                    "domain": record.domain,
+                    "id": record.id,
+                    "commits": record.commits,
+                    "lines": record.lines,
+                }));
+            }
+            if !stale.is_empty() && !ctx.json {
+                print_warning(&format!(
+                    "  Churn: {} record(s) reference files changed in {}+ commits and {}+ lines since they were written. Run `kb review --stale`.",
+                    stale.len(),
+                    cfg.churn.min_commits,
+                    cfg.churn.min_lines
+                ));
+            }
+        }
     }
 
     if ctx.json {
@@ -160,6 +183,7 @@ pub fn run(ctx: &RuntimeContext, args: &DoctorArgs) -> Result<()> {
             "success": issues.is_empty(),
             "command": "doctor",
             "issues": issues,
+            "warnings": warnings,
             "fixed": fixed,
         }));
     } else if issues.is_empty() {
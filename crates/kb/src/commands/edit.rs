@@ -1,11 +1,14 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
 
 use crate::cli::EditArgs;
 use crate::context::RuntimeContext;
 use crate::output::*;
 
+use kb_core::changelog::{self, ChangelogEntry};
 use kb_core::types::*;
-use kb_core::{config, lock, resolve, storage};
+use kb_core::{config, format, lock, resolve, storage};
 
 // ── Helpers ─────────────────────────────────────────────────────────────────
 
@@ -89,6 +92,7 @@ pub fn run(ctx: &RuntimeContext, args: &EditArgs) -> Result<()> {
         };
 
         let record = &mut records[target_index];
+        let old_classification = record.classification();
 
         // ── Apply common field updates ──────────────────────────────────
 
@@ -115,7 +119,9 @@ pub fn run(ctx: &RuntimeContext, args: &EditArgs) -> Result<()> {
                 test_results: args.outcome_test_results.clone(),
                 agent: args.outcome_agent.clone(),
                 notes: None,
-                recorded_at: None,
+                recorded_at: Some(
+                    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                ),
             };
             let mut existing_outcomes = record.outcomes().map(|o| o.to_vec()).unwrap_or_default();
             existing_outcomes.push(new_outcome);
@@ -196,16 +202,41 @@ pub fn run(ctx: &RuntimeContext, args: &EditArgs) -> Result<()> {
             }
         }
 
+        records[target_index].touch();
+
         // ── Write back ──────────────────────────────────────────────────
 
         storage::write_expertise_file(&file_path, &mut records)?;
 
-        // ── Output ──────────────────────────────────────────────────────
-
         let record = &records[target_index];
         let record_id = record.id().unwrap_or_default();
         let record_type = record.record_type();
 
+        // A manual reclassification restarts `kb promote --auto` evidence,
+        // which reads it from the changelog
+        if record.classification() != old_classification {
+            changelog::append(
+                &ctx.cwd,
+                &ChangelogEntry {
+                    session_id: None,
+                    timestamp: chrono::Utc::now(),
+                    action: "edit".to_string(),
+                    domain: args.domain.clone(),
+                    entry_id: record_id.to_string(),
+                    summary: Some(format::get_record_summary(record)),
+                    diff: Some(HashMap::from([(
+                        "classification".to_string(),
+                        (
+                            old_classification.to_string(),
+                            record.classification().to_string(),
+                        ),
+                    )])),
+                },
+            )?;
+        }
+
+        // ── Output ──────────────────────────────────────────────────────
+
         if ctx.json {
             output_json(&serde_json::json!({
                 "success": true,
@@ -0,0 +1,266 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use crate::cli::{ExportAdrArgs, ExportArgs, ExportCommands, ExportHtmlArgs};
+use crate::context::RuntimeContext;
+use crate::output::*;
+use kb_core::adr::{self, AdrWrite};
+use kb_core::interchange::{self, InterchangeFormat};
+use kb_core::types::ExpertiseRecord;
+use kb_core::{config, site, storage};
+
+pub fn run(ctx: &RuntimeContext, args: &ExportArgs) -> Result<()> {
+    config::ensure_kb_dir(&ctx.cwd)?;
+
+    match args.command {
+        Some(ExportCommands::Html(ref html)) => run_html(ctx, html),
+        Some(ExportCommands::Adr(ref adr)) => run_adr(ctx, adr),
+        None => run_format(ctx, args),
+    }
+}
+
+/// Resolve an output path relative to the working directory.
+pub(crate) fn resolve_path(ctx: &RuntimeContext, path: &str) -> PathBuf {
+    let path = PathBuf::from(path);
+    if path.is_absolute() {
+        path
+    } else {
+        ctx.cwd.join(path)
+    }
+}
+
+/// Read the requested domains (comma-separated), or every configured domain.
+fn read_domains(
+    ctx: &RuntimeContext,
+    domain: Option<&str>,
+) -> Result<Vec<(String, Vec<ExpertiseRecord>)>> {
+    let cfg = config::read_config(&ctx.cwd)?;
+
+    let domains: Vec<String> = match domain {
+        Some(raw) => {
+            let requested: Vec<String> = raw
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+            for d in &requested {
+                config::ensure_domain_exists(&cfg, d)?;
+            }
+            requested
+        }
+        None => cfg.domains.clone(),
+    };
+
+    let mut data = Vec::new();
+    for domain in domains {
+        let file_path = config::get_expertise_path(&domain, &ctx.cwd)?;
+        let records = storage::read_expertise_file(&file_path)?;
+        data.push((domain, records));
+    }
+    Ok(data)
+}
+
+// ── --format ────────────────────────────────────────────────────────────────
+
+fn run_format(ctx: &RuntimeContext, args: &ExportArgs) -> Result<()> {
+    let output = args.output.as_deref().map(|o| resolve_path(ctx, o));
+    let format = args
+        .format
+        .as_deref()
+        .and_then(InterchangeFormat::parse)
+        .or_else(|| output.as_deref().and_then(InterchangeFormat::from_path));
+    let Some(format) = format else {
+        let msg = "--format is required (markdown, csv, yaml, ndjson) unless --output has a known extension";
+        if ctx.json {
+            output_json_error("export", msg);
+            return Ok(());
+        }
+        bail!("{msg}");
+    };
+
+    let data = read_domains(ctx, args.domain.as_deref())?;
+    let record_count: usize = data.iter().map(|(_, r)| r.len()).sum();
+
+    let Some(output) = output else {
+        let content = interchange::export_records(format, &data)?;
+        if ctx.json {
+            output_json(&serde_json::json!({
+                "success": true,
+                "command": "export",
+                "format": format.as_str(),
+                "records": record_count,
+                "content": content,
+            }));
+        } else {
+            print!("{content}");
+        }
+        return Ok(());
+    };
+
+    let files = if is_dir_target(&output, args.output.as_deref().unwrap_or_default()) {
+        std::fs::create_dir_all(&output)
+            .with_context(|| format!("Failed to create {}", output.display()))?;
+        let mut files = Vec::new();
+        for (domain, records) in data {
+            let path = output.join(format!("{domain}.{}", format.extension()));
+            let content = interchange::export_records(format, &[(domain, records)])?;
+            write_file(&path, &content)?;
+            files.push(path);
+        }
+        files
+    } else {
+        write_file(&output, &interchange::export_records(format, &data)?)?;
+        vec![output]
+    };
+
+    if ctx.json {
+        let files: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "export",
+            "format": format.as_str(),
+            "records": record_count,
+            "files": files,
+        }));
+    } else {
+        for file in &files {
+            print_success(&format!("Exported to {}", file.display()));
+        }
+        println!("  {record_count} record(s) as {format}");
+    }
+
+    Ok(())
+}
+
+/// A directory target gets one file per domain.
+fn is_dir_target(path: &Path, raw: &str) -> bool {
+    path.is_dir() || raw.ends_with('/') || raw.ends_with(std::path::MAIN_SEPARATOR)
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write to {}", path.display()))
+}
+
+// ── html ────────────────────────────────────────────────────────────────────
+
+fn run_html(ctx: &RuntimeContext, args: &ExportHtmlArgs) -> Result<()> {
+    let data = read_domains(ctx, args.domain.as_deref())?;
+    let record_count: usize = data.iter().map(|(_, r)| r.len()).sum();
+
+    let files = site::build_site(&data, &args.title)?;
+    let dir = resolve_path(ctx, &args.dir);
+    site::write_site(&dir, &files)
+        .with_context(|| format!("Failed to write site to {}", dir.display()))?;
+
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "export html",
+            "dir": dir.display().to_string(),
+            "domains": data.len(),
+            "records": record_count,
+            "files": files.len(),
+        }));
+    } else {
+        print_success(&format!(
+            "Exported {record_count} records from {} domains to {} ({} files)",
+            data.len(),
+            dir.display(),
+            files.len()
+        ));
+    }
+
+    Ok(())
+}
+
+// ── adr ─────────────────────────────────────────────────────────────────────
+
+fn run_adr(ctx: &RuntimeContext, args: &ExportAdrArgs) -> Result<()> {
+    let data = read_domains(ctx, args.domain.as_deref())?;
+    let dir = resolve_path(ctx, &args.dir);
+    let existing = adr::read_adr_dir(&dir)?;
+
+    let decisions: Vec<&ExpertiseRecord> = data
+        .iter()
+        .flat_map(|(_, records)| records)
+        .filter(|r| matches!(r, ExpertiseRecord::Decision { .. }))
+        .collect();
+    let writes = adr::plan_adr_export(&decisions, &existing);
+
+    if !args.dry_run {
+        write_adrs(&dir, &writes)?;
+    }
+
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "export adr",
+            "dir": dir.display().to_string(),
+            "dry_run": args.dry_run,
+            "decisions": decisions.len(),
+            "files": adr_writes_json(&writes),
+        }));
+    } else {
+        print_adr_writes(&writes);
+        let verb = if args.dry_run { "Would write" } else { "Wrote" };
+        print_success(&format!(
+            "{verb} {} ADR file(s) for {} decision record(s) in {}",
+            writes.iter().filter(|w| w.conflict.is_none()).count(),
+            decisions.len(),
+            dir.display()
+        ));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn write_adrs(dir: &Path, writes: &[AdrWrite]) -> Result<()> {
+    if writes.is_empty() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    for write in writes.iter().filter(|w| w.conflict.is_none()) {
+        write_file(&dir.join(&write.file_name), &write.contents)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn adr_writes_json(writes: &[AdrWrite]) -> Vec<serde_json::Value> {
+    writes
+        .iter()
+        .map(|w| {
+            let action = match (w.created, &w.conflict) {
+                (true, _) => "create",
+                (false, None) => "update",
+                (false, Some(_)) => "conflict",
+            };
+            serde_json::json!({
+                "file": w.file_name,
+                "id": w.id,
+                "title": w.title,
+                "action": action,
+                "reason": w.conflict,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn print_adr_writes(writes: &[AdrWrite]) {
+    for write in writes {
+        let marker = if write.created { "+" } else { "~" };
+        match write.conflict {
+            Some(ref reason) => print_warning(&format!(
+                "{} ({}) left unchanged: {reason}",
+                write.file_name, write.id
+            )),
+            None => println!(
+                "  {marker} {} ({}) {}",
+                write.file_name, write.id, write.title
+            ),
+        }
+    }
+}
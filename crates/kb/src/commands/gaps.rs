@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::cli::GapsArgs;
+use crate::commands::ready::parse_duration;
+use crate::context::RuntimeContext;
+use crate::output::*;
+use kb_core::gaps::{self, GapOptions};
+use kb_core::{access_log, config};
+
+pub fn run(ctx: &RuntimeContext, args: &GapsArgs) -> Result<()> {
+    config::ensure_kb_dir(&ctx.cwd)?;
+    let cfg = config::read_config(&ctx.cwd)?;
+
+    if let Some(ref domain) = args.domain {
+        config::ensure_domain_exists(&cfg, domain)?;
+    }
+
+    let since = if args.all {
+        None
+    } else {
+        Some(chrono::Utc::now() - parse_duration(&args.since)?)
+    };
+
+    let entries = access_log::query_log(
+        &ctx.cwd,
+        &access_log::AccessLogFilter {
+            domain: args.domain.clone(),
+            ..Default::default()
+        },
+    )?;
+
+    let mut report = gaps::collect_gaps(
+        &entries,
+        &GapOptions {
+            since,
+            ..Default::default()
+        },
+    );
+
+    // Write drafts before truncating so every cluster gets a stub.
+    let mut draft_files: Vec<(String, usize)> = Vec::new();
+    if let Some(ref dir) = args.drafts {
+        let dir = PathBuf::from(dir);
+        let dir = if dir.is_absolute() {
+            dir
+        } else {
+            ctx.cwd.join(dir)
+        };
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        for domain_gaps in &report {
+            // Gaps without a (known) domain cannot be batch-recorded
+            let Some(domain) = domain_gaps
+                .domain
+                .as_ref()
+                .filter(|d| cfg.domains.contains(d))
+            else {
+                continue;
+            };
+            let stubs: Vec<serde_json::Value> =
+                domain_gaps.clusters.iter().map(gaps::draft_stub).collect();
+            let path = dir.join(format!("{domain}.json"));
+            std::fs::write(&path, serde_json::to_string_pretty(&stubs)? + "\n")
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            draft_files.push((path.display().to_string(), stubs.len()));
+        }
+    }
+
+    for domain_gaps in &mut report {
+        domain_gaps.clusters.truncate(args.limit);
+    }
+
+    if ctx.json {
+        let drafts: Vec<serde_json::Value> = draft_files
+            .iter()
+            .map(|(path, count)| serde_json::json!({ "path": path, "count": count }))
+            .collect();
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "gaps",
+            "since": since,
+            "total": report.iter().map(|d| d.count).sum::<usize>(),
+            "domains": report,
+            "drafts": drafts,
+        }));
+        return Ok(());
+    }
+
+    if report.is_empty() {
+        println!("No knowledge gaps found.");
+    }
+
+    for domain_gaps in &report {
+        println!(
+            "\n{} ({} gap queries)",
+            domain_gaps.domain.as_deref().unwrap_or("(no domain)"),
+            domain_gaps.count
+        );
+        for cluster in &domain_gaps.clusters {
+            println!(
+                "  {:>4}x  {}  [oracle={} search={} sessions={} last={}]",
+                cluster.count,
+                cluster.representative,
+                cluster.oracle_count,
+                cluster.search_count,
+                cluster.sessions,
+                cluster.last_seen.format("%Y-%m-%d"),
+            );
+            for q in cluster.queries.iter().skip(1) {
+                println!("          ~ {} ({}x)", q.query, q.count);
+            }
+        }
+    }
+
+    for (path, count) in &draft_files {
+        print_success(&format!("Wrote {count} draft stub(s) to {path}"));
+    }
+    if !draft_files.is_empty() {
+        println!("Fill in the stubs, then run: kb record <domain> --batch <file>");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,587 @@
+use std::io::{BufRead as _, Read as _, Write as _};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use crate::cli::{ImportAdrArgs, ImportArgs, ImportCommands, ImportInstructionsArgs};
+use crate::commands::{diff, export};
+use crate::context::RuntimeContext;
+use crate::output::*;
+use kb_core::instructions::{self, Candidate};
+use kb_core::interchange::{self, ImportAction, ImportChange, InterchangeFormat};
+use kb_core::types::ExpertiseRecord;
+use kb_core::{adr, config, lock, storage};
+
+pub fn run(ctx: &RuntimeContext, args: &ImportArgs) -> Result<()> {
+    config::ensure_kb_dir(&ctx.cwd)?;
+
+    match args.command {
+        Some(ImportCommands::Adr(ref adr)) => run_adr(ctx, adr),
+        Some(ImportCommands::Instructions(ref instr)) => run_instructions(ctx, instr),
+        None => run_file(ctx, args),
+    }
+}
+
+fn run_file(ctx: &RuntimeContext, args: &ImportArgs) -> Result<()> {
+    let cfg = config::read_config(&ctx.cwd)?;
+    let Some(ref file) = args.file else {
+        if ctx.json {
+            output_json_error("import", "A file to import is required.");
+            return Ok(());
+        }
+        bail!("A file to import is required (or `kb import adr <dir>`).");
+    };
+
+    let format = args
+        .format
+        .as_deref()
+        .and_then(InterchangeFormat::parse)
+        .or_else(|| InterchangeFormat::from_path(Path::new(file)));
+    let Some(format) = format else {
+        let msg = format!(
+            "Cannot infer the format of {file}. Pass --format (markdown, csv, yaml, ndjson)."
+        );
+        if ctx.json {
+            output_json_error("import", &msg);
+            return Ok(());
+        }
+        bail!("{msg}");
+    };
+
+    let input = if file == "-" {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .context("Failed to read from stdin")?;
+        input
+    } else {
+        let path = ctx.cwd.join(file);
+        std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read import file: {file}"))?
+    };
+
+    let imported = match interchange::parse_records(format, &input) {
+        Ok(records) => records,
+        Err(e) => {
+            if ctx.json {
+                output_json_error("import", &e.to_string());
+                return Ok(());
+            }
+            return Err(e.into());
+        }
+    };
+
+    // Group by domain, keeping the order domains first appear in
+    let mut by_domain: Vec<(String, Vec<ExpertiseRecord>)> = Vec::new();
+    for (i, item) in imported.into_iter().enumerate() {
+        let Some(domain) = item.domain.or_else(|| args.domain.clone()) else {
+            let msg = format!("Record {} has no domain. Pass --domain.", i + 1);
+            if ctx.json {
+                output_json_error("import", &msg);
+                return Ok(());
+            }
+            bail!("{msg}");
+        };
+        match by_domain.iter_mut().find(|(d, _)| *d == domain) {
+            Some((_, records)) => records.push(item.record),
+            None => by_domain.push((domain, vec![item.record])),
+        }
+    }
+    for (domain, _) in &by_domain {
+        config::ensure_domain_exists(&cfg, domain)?;
+    }
+
+    let mut changes: Vec<(String, ImportChange)> = Vec::new();
+    for (domain, records) in by_domain {
+        let file_path = config::get_expertise_path(&domain, &ctx.cwd)?;
+        let domain_changes = if args.dry_run {
+            let mut existing = storage::read_expertise_file(&file_path)?;
+            interchange::merge_records(&mut existing, records, args.force)?
+        } else {
+            lock::with_file_lock(&file_path, || {
+                let mut existing = storage::read_expertise_file(&file_path)?;
+                let changes = interchange::merge_records(&mut existing, records, args.force)?;
+                if changes
+                    .iter()
+                    .any(|c| matches!(c.action, ImportAction::Create | ImportAction::Update))
+                {
+                    storage::write_expertise_file(&file_path, &mut existing)?;
+                }
+                Ok(changes)
+            })?
+        };
+        changes.extend(domain_changes.into_iter().map(|c| (domain.clone(), c)));
+    }
+
+    let count = |action: ImportAction| changes.iter().filter(|(_, c)| c.action == action).count();
+    let created = count(ImportAction::Create);
+    let updated = count(ImportAction::Update);
+    let unchanged = count(ImportAction::Unchanged);
+    let skipped = count(ImportAction::Skip);
+
+    if ctx.json {
+        let changes_json: Vec<serde_json::Value> = changes
+            .iter()
+            .map(|(domain, change)| {
+                let mut value = serde_json::to_value(change).unwrap_or_default();
+                value["domain"] = serde_json::json!(domain);
+                value
+            })
+            .collect();
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "import",
+            "format": format.as_str(),
+            "dry_run": args.dry_run,
+            "created": created,
+            "updated": updated,
+            "unchanged": unchanged,
+            "skipped": skipped,
+            "changes": changes_json,
+        }));
+        return Ok(());
+    }
+
+    if args.dry_run {
+        println!("Dry-run: importing {file} ({format})");
+        println!();
+        print_changes(&changes);
+        println!();
+        println!("Create: {created}, Update: {updated}, Unchanged: {unchanged}, Skip: {skipped}");
+        println!("  Run without --dry-run to apply changes.");
+        return Ok(());
+    }
+
+    if created > 0 {
+        print_success(&format!("Created {created} record(s)"));
+    }
+    if updated > 0 {
+        print_success(&format!("Updated {updated} record(s)"));
+    }
+    if skipped > 0 {
+        print_warning(&format!(
+            "Skipped {skipped} duplicate(s). Use --force to add anyway."
+        ));
+    }
+    if created + updated == 0 {
+        println!("Nothing to import: {unchanged} record(s) unchanged.");
+    }
+
+    Ok(())
+}
+
+fn print_changes(changes: &[(String, ImportChange)]) {
+    for (domain, change) in changes {
+        let marker = match change.action {
+            ImportAction::Create => "+",
+            ImportAction::Update => "~",
+            ImportAction::Unchanged => continue,
+            ImportAction::Skip => "!",
+        };
+        let note = if change.action == ImportAction::Skip {
+            " (duplicate, skipped)"
+        } else {
+            ""
+        };
+        println!(
+            "  {marker} [{domain}] {} {}: {}{note}",
+            change.id, change.record_type, change.summary
+        );
+        diff::print_field_changes(&change.fields, "      ");
+    }
+}
+
+// ── adr ─────────────────────────────────────────────────────────────────────
+
+fn run_adr(ctx: &RuntimeContext, args: &ImportAdrArgs) -> Result<()> {
+    let cfg = config::read_config(&ctx.cwd)?;
+    config::ensure_domain_exists(&cfg, &args.domain)?;
+
+    let dir = export::resolve_path(ctx, &args.dir);
+    if !dir.is_dir() && !args.sync {
+        if ctx.json {
+            output_json_error("import", &format!("ADR directory not found: {}", args.dir));
+            return Ok(());
+        }
+        bail!("ADR directory not found: {}", args.dir);
+    }
+    let adrs = adr::read_adr_dir(&dir)?;
+
+    // Evidence points at the ADR relative to the project root
+    let rel_dir = dir.strip_prefix(&ctx.cwd).unwrap_or(&dir).to_path_buf();
+    let evidence_path = |name: &str| rel_dir.join(name).to_string_lossy().replace('\\', "/");
+    let update_existing = !args.sync || args.prefer == "adr";
+
+    let file_path = config::get_expertise_path(&args.domain, &ctx.cwd)?;
+    let sync = |records: &mut Vec<ExpertiseRecord>| -> kb_core::error::Result<_> {
+        let changes = adr::merge_adrs(records, &adrs, evidence_path, update_existing)?;
+        let writes = if args.sync {
+            let decisions: Vec<&ExpertiseRecord> = records
+                .iter()
+                .filter(|r| matches!(r, ExpertiseRecord::Decision { .. }))
+                .collect();
+            let writes = adr::plan_adr_export(&decisions, &adrs);
+            for write in writes.iter().filter(|w| w.created) {
+                if let Some(record) = records.iter_mut().find(|r| r.id() == Some(&write.id)) {
+                    adr::link_adr(record, evidence_path(&write.file_name));
+                }
+            }
+            writes
+        } else {
+            Vec::new()
+        };
+        Ok((changes, writes))
+    };
+
+    let (changes, writes) = if args.dry_run {
+        let mut records = storage::read_expertise_file(&file_path)?;
+        sync(&mut records)?
+    } else {
+        lock::with_file_lock(&file_path, || {
+            let mut records = storage::read_expertise_file(&file_path)?;
+            let result = sync(&mut records)?;
+            let (changes, writes) = &result;
+            if !writes.is_empty()
+                || changes
+                    .iter()
+                    .any(|c| matches!(c.action, ImportAction::Create | ImportAction::Update))
+            {
+                storage::write_expertise_file(&file_path, &mut records)?;
+            }
+            Ok(result)
+        })?
+    };
+    if !args.dry_run {
+        export::write_adrs(&dir, &writes)?;
+    }
+
+    let count = |action: ImportAction| changes.iter().filter(|c| c.action == action).count();
+    let created = count(ImportAction::Create);
+    let updated = count(ImportAction::Update);
+    let unchanged = count(ImportAction::Unchanged);
+    let written = writes.iter().filter(|w| w.conflict.is_none()).count();
+
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "import adr",
+            "dir": dir.display().to_string(),
+            "domain": args.domain,
+            "dry_run": args.dry_run,
+            "created": created,
+            "updated": updated,
+            "unchanged": unchanged,
+            "changes": changes,
+            "adr_files": export::adr_writes_json(&writes),
+        }));
+        return Ok(());
+    }
+
+    let labelled: Vec<(String, ImportChange)> = changes
+        .into_iter()
+        .map(|c| (args.domain.clone(), c))
+        .collect();
+    if args.dry_run {
+        println!("Dry-run: importing ADRs from {}", args.dir);
+        println!();
+        print_changes(&labelled);
+        if !writes.is_empty() {
+            println!();
+            println!("ADR files:");
+            export::print_adr_writes(&writes);
+        }
+        println!();
+        println!(
+            "Create: {created}, Update: {updated}, Unchanged: {unchanged}, ADR files: {written}"
+        );
+        println!("  Run without --dry-run to apply changes.");
+        return Ok(());
+    }
+
+    print_success(&format!(
+        "Imported {} ADR(s) into {}: {created} created, {updated} updated, {unchanged} unchanged",
+        adrs.len(),
+        args.domain
+    ));
+    if !writes.is_empty() {
+        export::print_adr_writes(&writes);
+        print_success(&format!("Wrote {written} ADR file(s) to {}", args.dir));
+    }
+
+    Ok(())
+}
+
+// ── instructions ────────────────────────────────────────────────────────────
+
+/// Rule files read from a directory such as `.cursor/rules`.
+const RULE_EXTENSIONS: &[&str] = &["md", "mdc"];
+
+fn run_instructions(ctx: &RuntimeContext, args: &ImportInstructionsArgs) -> Result<()> {
+    let cfg = config::read_config(&ctx.cwd)?;
+    if let Some(ref domain) = args.domain {
+        config::ensure_domain_exists(&cfg, domain)?;
+    }
+    if args.interactive && ctx.json {
+        output_json_error("import", "--interactive cannot be combined with --json.");
+        return Ok(());
+    }
+
+    let path = export::resolve_path(ctx, &args.file);
+    let files: Vec<PathBuf> = if path.is_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&path)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| RULE_EXTENSIONS.contains(&e))
+            })
+            .collect();
+        files.sort();
+        files
+    } else if path.exists() {
+        vec![path.clone()]
+    } else {
+        if ctx.json {
+            output_json_error("import", &format!("File not found: {}", args.file));
+            return Ok(());
+        }
+        bail!("File not found: {}", args.file);
+    };
+
+    // (source path, candidate)
+    let mut candidates: Vec<(String, Candidate)> = Vec::new();
+    for file in &files {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let source = file
+            .strip_prefix(&ctx.cwd)
+            .unwrap_or(file)
+            .to_string_lossy()
+            .replace('\\', "/");
+        for mut candidate in instructions::extract_candidates(&content, &cfg.domains) {
+            if let Some(ref domain) = args.domain {
+                candidate.domain = Some(domain.clone());
+                candidate.new_domain = false;
+            }
+            candidates.push((source.clone(), candidate));
+        }
+    }
+
+    if let Some(ref drafts) = args.drafts {
+        return write_instruction_drafts(ctx, drafts, &cfg.domains, &candidates);
+    }
+    if args.interactive {
+        return review_instructions(ctx, &cfg.domains, candidates);
+    }
+
+    if ctx.json {
+        let list: Vec<serde_json::Value> = candidates
+            .iter()
+            .map(|(source, c)| {
+                serde_json::json!({
+                    "source": source,
+                    "line": c.line,
+                    "domain": c.domain,
+                    "new_domain": c.new_domain,
+                    "headings": c.headings,
+                    "record": c.to_record_json(source),
+                })
+            })
+            .collect();
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "import instructions",
+            "files": files.len(),
+            "candidates": list,
+        }));
+        return Ok(());
+    }
+
+    if candidates.is_empty() {
+        println!("No candidate records found in {}.", args.file);
+        return Ok(());
+    }
+    println!("Candidate records from {}:", args.file);
+    for (source, c) in &candidates {
+        print_candidate(source, c);
+    }
+    println!();
+    println!(
+        "{} candidate(s). Review them with --interactive, or write batch files with --drafts <dir>.",
+        candidates.len()
+    );
+    Ok(())
+}
+
+fn domain_label(c: &Candidate) -> String {
+    match (&c.domain, c.new_domain) {
+        (Some(d), false) => d.clone(),
+        (Some(d), true) => format!("{d} (new)"),
+        (None, _) => "(no domain)".to_string(),
+    }
+}
+
+fn print_candidate(source: &str, c: &Candidate) {
+    println!();
+    println!(
+        "  {} -> {}  [{source}:{}]",
+        c.record_type,
+        domain_label(c),
+        c.line
+    );
+    println!("    {}", c.text);
+    if let Some(ref detail) = c.detail {
+        if *detail != c.text {
+            for line in detail.lines() {
+                println!("      {line}");
+            }
+        }
+    }
+}
+
+fn write_instruction_drafts(
+    ctx: &RuntimeContext,
+    dir: &str,
+    domains: &[String],
+    candidates: &[(String, Candidate)],
+) -> Result<()> {
+    let dir = export::resolve_path(ctx, dir);
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let mut by_domain: Vec<(String, Vec<serde_json::Value>)> = Vec::new();
+    for (source, c) in candidates {
+        let domain = c.domain.clone().unwrap_or_else(|| "unsorted".to_string());
+        let record = c.to_record_json(source);
+        match by_domain.iter_mut().find(|(d, _)| *d == domain) {
+            Some((_, records)) => records.push(record),
+            None => by_domain.push((domain, vec![record])),
+        }
+    }
+
+    let mut written = Vec::new();
+    for (domain, records) in &by_domain {
+        let path = dir.join(format!("{domain}.json"));
+        std::fs::write(&path, serde_json::to_string_pretty(records)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        written.push((domain.clone(), path.display().to_string(), records.len()));
+    }
+
+    if ctx.json {
+        let drafts: Vec<serde_json::Value> = written
+            .iter()
+            .map(|(domain, path, count)| {
+                serde_json::json!({ "domain": domain, "path": path, "count": count })
+            })
+            .collect();
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "import instructions",
+            "candidates": candidates.len(),
+            "drafts": drafts,
+        }));
+    } else {
+        for (domain, path, count) in &written {
+            print_success(&format!("Wrote {count} draft(s) to {path}"));
+            if domains.contains(domain) {
+                println!("  Review, then run: kb record {domain} --batch {path}");
+            } else {
+                println!(
+                    "  Review, then run: kb add {domain} && kb record {domain} --batch {path}"
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prompt for each candidate on stdin; accepted records are merged into their
+/// domains with the usual dedupe once the review ends.
+fn review_instructions(
+    ctx: &RuntimeContext,
+    domains: &[String],
+    candidates: Vec<(String, Candidate)>,
+) -> Result<()> {
+    let total = candidates.len();
+    let mut accepted: Vec<(String, ExpertiseRecord)> = Vec::new();
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    'candidates: for (i, (source, mut c)) in candidates.into_iter().enumerate() {
+        println!();
+        println!("[{}/{total}]", i + 1);
+        print_candidate(&source, &c);
+        loop {
+            print!("  Record? [y]es, [n]o, [d]omain <name>, [q]uit: ");
+            std::io::stdout().flush()?;
+            let Some(answer) = lines.next().transpose()? else {
+                break 'candidates;
+            };
+            let answer = answer.trim();
+            match answer.split_once(' ').unwrap_or((answer, "")) {
+                ("y" | "yes", _) => match c
+                    .domain
+                    .as_deref()
+                    .filter(|d| domains.iter().any(|x| x == d))
+                {
+                    Some(domain) => {
+                        let mut value = c.to_record_json(&source);
+                        value["recorded_at"] = serde_json::json!(
+                            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+                        );
+                        accepted.push((domain.to_string(), serde_json::from_value(value)?));
+                        continue 'candidates;
+                    }
+                    None => print_warning(&format!(
+                        "  {} is not a domain. Pick one with `d <name>` (available: {}).",
+                        domain_label(&c),
+                        domains.join(", ")
+                    )),
+                },
+                ("n" | "no" | "", _) => continue 'candidates,
+                ("d" | "domain", name) if !name.trim().is_empty() => {
+                    c.domain = Some(name.trim().to_string());
+                    c.new_domain = !domains.iter().any(|d| d == name.trim());
+                    println!("    -> {}", domain_label(&c));
+                }
+                ("q" | "quit", _) => break 'candidates,
+                _ => print_warning("  Unrecognised answer."),
+            }
+        }
+    }
+
+    let mut by_domain: Vec<(String, Vec<ExpertiseRecord>)> = Vec::new();
+    for (domain, record) in accepted {
+        match by_domain.iter_mut().find(|(d, _)| *d == domain) {
+            Some((_, records)) => records.push(record),
+            None => by_domain.push((domain, vec![record])),
+        }
+    }
+
+    println!();
+    let mut recorded = 0;
+    for (domain, records) in by_domain {
+        let file_path = config::get_expertise_path(&domain, &ctx.cwd)?;
+        let changes = lock::with_file_lock(&file_path, || {
+            let mut existing = storage::read_expertise_file(&file_path)?;
+            let changes = interchange::merge_records(&mut existing, records, false)?;
+            storage::write_expertise_file(&file_path, &mut existing)?;
+            Ok(changes)
+        })?;
+        let created = changes
+            .iter()
+            .filter(|c| c.action == ImportAction::Create)
+            .count();
+        recorded += created;
+        print_success(&format!("Recorded {created} record(s) in {domain}"));
+        let duplicates = changes.len() - created;
+        if duplicates > 0 {
+            print_warning(&format!(
+                "Skipped {duplicates} already recorded in {domain}"
+            ));
+        }
+    }
+    if recorded == 0 {
+        println!("Nothing recorded.");
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use anyhow::{Result, bail};
+
+use crate::cli::IngestCommitsArgs;
+use crate::context::RuntimeContext;
+use crate::output::*;
+use kb_core::id::generate_record_id;
+use kb_core::trailers::{self, TrailerRecord};
+use kb_core::{config, filter, format, git, lock, storage};
+
+/// Outcome for one trailer record: created, or skipped as a duplicate.
+struct Ingested {
+    domain: String,
+    id: String,
+    commit: String,
+    record_type: String,
+    summary: String,
+    duplicate: bool,
+}
+
+impl Ingested {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "domain": self.domain,
+            "id": self.id,
+            "commit": self.commit,
+            "type": self.record_type,
+            "summary": self.summary,
+        })
+    }
+}
+
+pub fn run(ctx: &RuntimeContext, args: &IngestCommitsArgs) -> Result<()> {
+    config::ensure_kb_dir(&ctx.cwd)?;
+    let cfg = config::read_config(&ctx.cwd)?;
+    if let Some(ref domain) = args.domain {
+        config::ensure_domain_exists(&cfg, domain)?;
+    }
+
+    if !git::is_git_repo(&ctx.cwd) {
+        if ctx.json {
+            output_json_error("ingest-commits", "Not in a git repository.");
+            return Ok(());
+        }
+        bail!("Not in a git repository. `kb ingest-commits` requires git.");
+    }
+    let Some(commits) = git::commit_trailers(&ctx.cwd, &args.range) else {
+        let msg = format!("Unknown git ref or range: {}", args.range);
+        if ctx.json {
+            output_json_error("ingest-commits", &msg);
+            return Ok(());
+        }
+        bail!(msg);
+    };
+
+    let branch = if args.no_branch {
+        None
+    } else {
+        git::scoped_branch(&ctx.cwd, cfg.main_branch.as_deref())
+    };
+
+    let mut warnings: Vec<String> = Vec::new();
+    // (domain, records) in first-seen order
+    let mut by_domain: Vec<(String, Vec<TrailerRecord>)> = Vec::new();
+    for commit in &commits {
+        let short = &commit.hash[..commit.hash.len().min(7)];
+        let (records, commit_warnings) = trailers::parse_commit(commit);
+        warnings.extend(commit_warnings.into_iter().map(|w| format!("{short}: {w}")));
+        for record in records {
+            let Some(domain) = record.domain.clone().or_else(|| args.domain.clone()) else {
+                warnings.push(format!(
+                    "{short}: no Kb-Domain trailer for \"{}\" (pass --domain)",
+                    format::get_record_summary(&record.record)
+                ));
+                continue;
+            };
+            if !cfg.domains.contains(&domain) {
+                warnings.push(format!("{short}: unknown domain \"{domain}\""));
+                continue;
+            }
+            match by_domain.iter_mut().find(|(d, _)| *d == domain) {
+                Some((_, records)) => records.push(record),
+                None => by_domain.push((domain, vec![record])),
+            }
+        }
+    }
+
+    let mut results: Vec<Ingested> = Vec::new();
+    for (domain, incoming) in by_domain {
+        let file_path = config::get_expertise_path(&domain, &ctx.cwd)?;
+        lock::with_file_lock(&file_path, || {
+            let mut records = storage::read_expertise_file(&file_path)?;
+            let mut created = false;
+            for TrailerRecord {
+                commit, mut record, ..
+            } in incoming
+            {
+                let duplicate = filter::find_duplicate(&records, &record)
+                    .map(|(_, existing)| existing.id().unwrap_or("?").to_string());
+                let id = match duplicate {
+                    Some(ref id) => id.clone(),
+                    None => generate_record_id(&record),
+                };
+                results.push(Ingested {
+                    domain: domain.clone(),
+                    id: id.clone(),
+                    commit,
+                    record_type: record.record_type().to_string(),
+                    summary: format::get_record_summary(&record),
+                    duplicate: duplicate.is_some(),
+                });
+                if duplicate.is_none() {
+                    record.set_id(id);
+                    record.set_branch(branch.clone());
+                    records.push(record);
+                    created = true;
+                }
+            }
+            if created && !args.dry_run {
+                storage::write_expertise_file(&file_path, &mut records)?;
+            }
+            Ok(())
+        })?;
+    }
+
+    let (skipped, created): (Vec<&Ingested>, Vec<&Ingested>) =
+        results.iter().partition(|r| r.duplicate);
+
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "ingest-commits",
+            "range": args.range,
+            "dry_run": args.dry_run,
+            "commits": commits.len(),
+            "created": created.iter().map(|r| r.to_json()).collect::<Vec<_>>(),
+            "skipped": skipped.iter().map(|r| r.to_json()).collect::<Vec<_>>(),
+            "warnings": warnings,
+        }));
+        return Ok(());
+    }
+
+    for warning in &warnings {
+        print_warning(warning);
+    }
+    if results.is_empty() {
+        println!("No Kb-* trailers found in {}.", args.range);
+        return Ok(());
+    }
+    for r in &results {
+        let marker = if r.duplicate { "=" } else { "+" };
+        println!(
+            "  {marker} [{}] {} {}: {} ({})",
+            r.domain,
+            r.id,
+            r.record_type,
+            r.summary,
+            &r.commit[..r.commit.len().min(7)]
+        );
+    }
+    let verb = if args.dry_run {
+        "Would ingest"
+    } else {
+        "Ingested"
+    };
+    print_success(&format!(
+        "{verb} {} record(s) from {} commit(s), skipped {} already recorded.",
+        created.len(),
+        commits.len(),
+        skipped.len()
+    ));
+
+    Ok(())
+}
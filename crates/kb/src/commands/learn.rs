@@ -5,7 +5,189 @@ use std::collections::HashMap;
 use crate::cli::LearnArgs;
 use crate::context::RuntimeContext;
 use crate::output::*;
-use kb_core::{access_log, config, git, storage};
+use kb_core::draft::{self, ChangeSet, FilePatch, FileStatus};
+use kb_core::relevance::{self, Relevance};
+use kb_core::types::{ExpertiseRecord, KbConfig, RecordType};
+use kb_core::{access_log, config, format, git, storage};
+
+/// An existing record touched by the changes, with what to do about it.
+struct RelevantRecord {
+    domain: String,
+    record: ExpertiseRecord,
+    relevance: Relevance,
+}
+
+impl RelevantRecord {
+    fn id(&self) -> &str {
+        self.record.id().unwrap_or("?")
+    }
+
+    /// `kb` invocations for each way of handling the record.
+    fn actions(&self) -> [(&'static str, String); 3] {
+        let (domain, id) = (&self.domain, self.id());
+        let record_type = self.record.record_type();
+        let field = match record_type {
+            RecordType::Convention => "--content",
+            RecordType::Failure => "--resolution",
+            RecordType::Decision => "--rationale",
+            RecordType::Pattern | RecordType::Reference | RecordType::Guide => "--description",
+        };
+        [
+            (
+                "confirm",
+                format!("kb edit {domain} {id} --outcome-status success"),
+            ),
+            ("revise", format!("kb edit {domain} {id} {field} \"...\"")),
+            (
+                "supersede",
+                format!("kb record {domain} --type {record_type} --supersedes {id} ..."),
+            ),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let actions: serde_json::Map<String, serde_json::Value> = self
+            .actions()
+            .into_iter()
+            .map(|(name, cmd)| (name.to_string(), cmd.into()))
+            .collect();
+        serde_json::json!({
+            "domain": self.domain,
+            "id": self.id(),
+            "type": self.record.record_type(),
+            "summary": format::get_record_summary(&self.record),
+            "files": self.relevance.files,
+            "tags": self.relevance.tags,
+            "actions": actions,
+        })
+    }
+}
+
+/// Record files per domain, from the `files` of its existing records.
+fn domain_files(ctx: &RuntimeContext, cfg: &KbConfig) -> Result<HashMap<String, Vec<String>>> {
+    let mut domain_files: HashMap<String, Vec<String>> = HashMap::new();
+    for domain in &cfg.domains {
+        let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
+        let records = storage::read_expertise_file(&file_path)?;
+        let files = records
+            .iter()
+            .filter_map(|r| r.files())
+            .flatten()
+            .cloned()
+            .collect();
+        domain_files.insert(domain.clone(), files);
+    }
+    Ok(domain_files)
+}
+
+/// Domain a changed file most likely belongs to: one whose records list the
+/// file, else one whose name appears in its path.
+fn best_domain(
+    changed: &str,
+    domains: &[String],
+    domain_files: &HashMap<String, Vec<String>>,
+) -> Option<String> {
+    for (domain, files) in domain_files {
+        if git::file_matches_any(changed, files) {
+            return Some(domain.clone());
+        }
+    }
+
+    // Heuristic: match by directory prefix / domain name substring
+    let changed_lower = changed.to_lowercase();
+    domains
+        .iter()
+        .find(|d| changed_lower.contains(&d.to_lowercase()))
+        .cloned()
+}
+
+/// Log that learn was called so `kb guard` knows
+fn log_learn(ctx: &RuntimeContext, args: &LearnArgs, result_count: usize) -> Result<()> {
+    let session_id = args
+        .session
+        .clone()
+        .unwrap_or_else(|| "unknown".to_string());
+    access_log::append(
+        &ctx.cwd,
+        &access_log::AccessLogEntry {
+            session_id,
+            timestamp: Utc::now(),
+            tool: "learn".into(),
+            domain: None,
+            query: None,
+            entry_id: None,
+            entry_ids: None,
+            result_count: Some(result_count),
+            signal: None,
+        },
+    )?;
+    Ok(())
+}
+
+/// Commits since `since` and uncommitted changes, including untracked files.
+fn change_sets(ctx: &RuntimeContext, since: &str) -> Vec<ChangeSet> {
+    let mut changes: Vec<ChangeSet> = git::commit_patches(&ctx.cwd, &format!("{since}..HEAD"))
+        .iter()
+        .map(ChangeSet::from_commit)
+        .collect();
+
+    let mut files = draft::parse_patch(&git::working_tree_patch(&ctx.cwd));
+    for path in git::untracked_files(&ctx.cwd) {
+        let added = std::fs::read_to_string(ctx.cwd.join(&path))
+            .map(|content| content.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        files.push(FilePatch {
+            path,
+            status: FileStatus::Added,
+            added,
+        });
+    }
+    if !files.is_empty() {
+        changes.push(ChangeSet {
+            files,
+            ..Default::default()
+        });
+    }
+    changes
+}
+
+/// `kb learn --draft`: record drafts as a JSON array for `kb record --batch`.
+fn run_draft(ctx: &RuntimeContext, args: &LearnArgs, cfg: &KbConfig) -> Result<()> {
+    let changes = change_sets(ctx, &args.since);
+    let drafts = draft::draft_records(&changes);
+    let domain_files = domain_files(ctx, cfg)?;
+
+    if ctx.json {
+        let drafts: Vec<serde_json::Value> = drafts
+            .iter()
+            .map(|d| {
+                let domain = d
+                    .files
+                    .iter()
+                    .find_map(|f| best_domain(f, &cfg.domains, &domain_files));
+                serde_json::json!({
+                    "type": d.record_type,
+                    "reason": d.reason,
+                    "files": d.files,
+                    "domain": domain,
+                    "record": d.record,
+                })
+            })
+            .collect();
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "learn",
+            "since": args.since,
+            "changes": changes.len(),
+            "drafts": drafts,
+        }));
+    } else {
+        let records: Vec<&serde_json::Value> = drafts.iter().map(|d| &d.record).collect();
+        println!("{}", serde_json::to_string_pretty(&records)?);
+    }
+
+    log_learn(ctx, args, drafts.len())
+}
 
 pub fn run(ctx: &RuntimeContext, args: &LearnArgs) -> Result<()> {
     config::ensure_kb_dir(&ctx.cwd)?;
@@ -24,6 +206,7 @@ pub fn run(ctx: &RuntimeContext, args: &LearnArgs) -> Result<()> {
                 domain: None,
                 query: None,
                 entry_id: None,
+                entry_ids: None,
                 result_count: None,
                 signal: Some("skipped".into()),
             },
@@ -49,6 +232,9 @@ pub fn run(ctx: &RuntimeContext, args: &LearnArgs) -> Result<()> {
     }
 
     let cfg = config::read_config(&ctx.cwd)?;
+    if args.draft {
+        return run_draft(ctx, args, &cfg);
+    }
     let changed_files = git::get_changed_files(&ctx.cwd, &args.since);
 
     if changed_files.is_empty() {
@@ -58,6 +244,7 @@ pub fn run(ctx: &RuntimeContext, args: &LearnArgs) -> Result<()> {
                 "command": "learn",
                 "changed_files": 0,
                 "suggestions": [],
+                "relevant": [],
             }));
         } else {
             println!("No changed files found since {}.", args.since);
@@ -66,17 +253,22 @@ pub fn run(ctx: &RuntimeContext, args: &LearnArgs) -> Result<()> {
     }
 
     // Build a map of domain -> file patterns from existing records
-    let mut domain_files: HashMap<String, Vec<String>> = HashMap::new();
-    for domain in &cfg.domains {
-        let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
-        let records = storage::read_expertise_file(&file_path)?;
-        let mut files: Vec<String> = Vec::new();
-        for r in &records {
-            if let Some(record_files) = r.files() {
-                files.extend(record_files.iter().cloned());
+    let domain_files = domain_files(ctx, &cfg)?;
+    let mut relevant: Vec<RelevantRecord> = Vec::new();
+    if args.relevant {
+        for domain in &cfg.domains {
+            let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
+            for record in storage::read_expertise_file(&file_path)? {
+                let relevance = relevance::relevance(&record, &changed_files);
+                if !relevance.is_empty() {
+                    relevant.push(RelevantRecord {
+                        domain: domain.clone(),
+                        record,
+                        relevance,
+                    });
+                }
             }
         }
-        domain_files.insert(domain.clone(), files);
     }
 
     // Group changed files by best-matching domain
@@ -89,28 +281,7 @@ pub fn run(ctx: &RuntimeContext, args: &LearnArgs) -> Result<()> {
             continue;
         }
 
-        let mut best_domain: Option<String> = None;
-
-        for (domain, files) in &domain_files {
-            if git::file_matches_any(changed, files) {
-                best_domain = Some(domain.clone());
-                break;
-            }
-        }
-
-        // Heuristic: match by directory prefix / domain name substring
-        if best_domain.is_none() {
-            let changed_lower = changed.to_lowercase();
-            for domain in &cfg.domains {
-                let domain_lower = domain.to_lowercase();
-                if changed_lower.contains(&domain_lower) {
-                    best_domain = Some(domain.clone());
-                    break;
-                }
-            }
-        }
-
-        match best_domain {
+        match best_domain(changed, &cfg.domains, &domain_files) {
             Some(d) => suggestions.entry(d).or_default().push(changed.clone()),
             None => unmatched.push(changed.clone()),
         }
@@ -126,13 +297,17 @@ pub fn run(ctx: &RuntimeContext, args: &LearnArgs) -> Result<()> {
                 })
             })
             .collect();
-        output_json(&serde_json::json!({
+        let mut output = serde_json::json!({
             "success": true,
             "command": "learn",
             "changed_files": changed_files.len(),
             "suggestions": domain_suggestions,
             "unmatched": unmatched,
-        }));
+        });
+        if args.relevant {
+            output["relevant"] = relevant.iter().map(|r| r.to_json()).collect();
+        }
+        output_json(&output);
     } else {
         println!(
             "Changed files since {}: {}",
@@ -169,28 +344,40 @@ pub fn run(ctx: &RuntimeContext, args: &LearnArgs) -> Result<()> {
             println!();
         }
 
+        if !relevant.is_empty() {
+            println!(
+                "Existing records related to these changes ({}): confirm, revise or supersede each one.",
+                relevant.len()
+            );
+            for r in &relevant {
+                println!(
+                    "  [{}] {} {}: {}",
+                    r.domain,
+                    r.id(),
+                    r.record.record_type(),
+                    format::get_record_summary(&r.record)
+                );
+                let matched: Vec<&str> = r
+                    .relevance
+                    .files
+                    .iter()
+                    .chain(&r.relevance.tags)
+                    .map(String::as_str)
+                    .collect();
+                println!("    matches: {}", matched.join(", "));
+                for (name, cmd) in r.actions() {
+                    println!("    {name:<9} {cmd}");
+                }
+            }
+            println!();
+        } else if args.relevant {
+            println!("No existing records relate to these changes.");
+            println!();
+        }
+
         println!("Record learnings with:");
         println!("  kb record <domain> --type <type> --description \"...\"");
     }
 
-    // Log that learn was called so `kb guard` knows
-    let session_id = args
-        .session
-        .clone()
-        .unwrap_or_else(|| "unknown".to_string());
-    access_log::append(
-        &ctx.cwd,
-        &access_log::AccessLogEntry {
-            session_id,
-            timestamp: Utc::now(),
-            tool: "learn".into(),
-            domain: None,
-            query: None,
-            entry_id: None,
-            result_count: Some(changed_files.len()),
-            signal: None,
-        },
-    )?;
-
-    Ok(())
+    log_learn(ctx, args, changed_files.len())
 }
@@ -0,0 +1,110 @@
+use anyhow::{Result, bail};
+
+use crate::cli::LogArgs;
+use crate::commands::diff::print_field_changes;
+use crate::context::RuntimeContext;
+use crate::output::*;
+use kb_core::diff::ChangeKind;
+use kb_core::git::CommitInfo;
+use kb_core::{config, git, history};
+
+fn kind_label(kind: ChangeKind) -> &'static str {
+    match kind {
+        ChangeKind::Added => "added",
+        ChangeKind::Modified => "modified",
+        ChangeKind::Removed => "removed",
+    }
+}
+
+/// `<hash> <day> <author>` for a commit, or "working tree" when uncommitted.
+pub(crate) fn commit_label(commit: Option<&CommitInfo>) -> String {
+    match commit {
+        Some(c) => format!("{} {} {}", c.short_hash(), c.day(), c.author),
+        None => "working tree".to_string(),
+    }
+}
+
+pub fn run(ctx: &RuntimeContext, args: &LogArgs) -> Result<()> {
+    config::ensure_kb_dir(&ctx.cwd)?;
+
+    if !git::is_git_repo(&ctx.cwd) {
+        if ctx.json {
+            output_json_error("log", "Not in a git repository.");
+            return Ok(());
+        }
+        bail!("Not in a git repository. `kb log` requires git.");
+    }
+
+    let cfg = config::read_config(&ctx.cwd)?;
+    let domains: Vec<String> = match args.domain {
+        Some(ref domain) => {
+            config::ensure_domain_exists(&cfg, domain)?;
+            vec![domain.clone()]
+        }
+        None => cfg.domains.clone(),
+    };
+
+    let mut histories = Vec::new();
+    for domain in domains {
+        let entries = history::domain_history(&ctx.cwd, &domain)?;
+        histories.push((domain, entries));
+    }
+
+    let (domain, id) = match history::resolve_history_id(&histories, &args.id) {
+        Ok(found) => found,
+        Err(e) => {
+            if ctx.json {
+                output_json_error("log", &e.to_string());
+                return Ok(());
+            }
+            return Err(e.into());
+        }
+    };
+    let entries = histories
+        .iter()
+        .find(|(d, _)| *d == domain)
+        .map(|(_, e)| history::record_log(e, &id))
+        .unwrap_or_default();
+
+    if ctx.json {
+        let entries: Vec<serde_json::Value> = entries
+            .iter()
+            .map(|(commit, change)| {
+                serde_json::json!({
+                    "commit": commit,
+                    "kind": change.kind,
+                    "fields": change.fields,
+                    "record": change.record,
+                })
+            })
+            .collect();
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "log",
+            "domain": domain,
+            "id": id,
+            "entries": entries,
+        }));
+        return Ok(());
+    }
+
+    println!("History of {id} [{domain}]:");
+    println!();
+    for (commit, change) in &entries {
+        println!(
+            "{} {}",
+            commit_label(commit.as_ref()),
+            kind_label(change.kind)
+        );
+        if let Some(c) = commit {
+            println!("    {}", c.subject);
+        }
+        match change.kind {
+            ChangeKind::Modified => print_field_changes(&change.fields, "    "),
+            _ => println!("    {}: {}", change.record_type, change.summary),
+        }
+        println!();
+    }
+
+    Ok(())
+}
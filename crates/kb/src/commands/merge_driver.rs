@@ -0,0 +1,63 @@
+use anyhow::{Context, Result};
+use std::fs;
+
+use crate::cli::MergeDriverArgs;
+use crate::commands::export::resolve_path;
+use crate::context::RuntimeContext;
+use crate::output::*;
+use kb_core::merge;
+
+fn read_version(ctx: &RuntimeContext, path: &str) -> Result<String> {
+    let full = resolve_path(ctx, path);
+    match fs::read_to_string(&full) {
+        Ok(content) => Ok(content),
+        // No common ancestor (both sides added the file)
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", full.display())),
+    }
+}
+
+/// Entry point for git: `kb merge-driver %O %A %B %P`. Writes the result to
+/// the `%A` file and exits non-zero when conflicts remain.
+pub fn run(ctx: &RuntimeContext, args: &MergeDriverArgs) -> Result<()> {
+    let base = read_version(ctx, &args.base)?;
+    let ours = read_version(ctx, &args.ours)?;
+    let theirs = read_version(ctx, &args.theirs)?;
+
+    let result = merge::merge_expertise(&base, &ours, &theirs)?;
+
+    let ours_path = resolve_path(ctx, &args.ours);
+    fs::write(&ours_path, &result.content)
+        .with_context(|| format!("Failed to write {}", ours_path.display()))?;
+
+    let path = args.path.as_deref().unwrap_or(&args.ours);
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": result.conflicts.is_empty(),
+            "command": "merge-driver",
+            "path": path,
+            "conflicts": result.conflicts,
+            "resolved": result.resolved,
+        }));
+    } else {
+        if !result.resolved.is_empty() {
+            print_warning(&format!(
+                "{path}: kept the latest edit of {} concurrently edited record(s): {}",
+                result.resolved.len(),
+                result.resolved.join(", ")
+            ));
+        }
+        if !result.conflicts.is_empty() {
+            print_error(&format!(
+                "{path}: {} conflicting record(s): {}",
+                result.conflicts.len(),
+                result.conflicts.join(", ")
+            ));
+        }
+    }
+
+    if !result.conflicts.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
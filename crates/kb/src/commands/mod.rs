@@ -1,24 +1,36 @@
 pub mod access_log;
 pub mod add;
+pub mod archive;
+pub mod blame;
 pub mod check;
 pub mod compact;
 pub mod delete;
 pub mod diff;
 pub mod doctor;
 pub mod edit;
+pub mod export;
+pub mod gaps;
 pub mod guard;
+pub mod import;
+pub mod ingest_commits;
 pub mod init;
 pub mod learn;
+pub mod log;
+pub mod merge_driver;
 pub mod onboard;
 pub mod prime;
+pub mod promote;
 pub mod prune;
 pub mod query;
 pub mod ready;
 pub mod record;
 pub mod remove;
+pub mod review;
 pub mod search;
 pub mod session;
 pub mod setup;
+pub mod split;
+pub mod stats;
 pub mod status;
 pub mod sync_cmd;
 pub mod update;
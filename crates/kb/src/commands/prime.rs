@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 
 use anyhow::{Context, Result, bail};
@@ -7,59 +7,56 @@ use crate::cli::PrimeArgs;
 use crate::context::RuntimeContext;
 use crate::output::*;
 use kb_core::format::PrimeFormat;
+use kb_core::template::{TemplateDomain, TemplateInput, Templates};
+use kb_core::tokenizer;
 use kb_core::{budget, config, format, git, storage, types::*};
 
-/// Produce a rough text representation of a record for token estimation.
-/// Uses a simple format similar to compact lines.
-fn estimate_record_text(record: &ExpertiseRecord) -> String {
-    match record {
-        ExpertiseRecord::Convention { content, .. } => {
-            format!("[convention] {content}")
-        }
-        ExpertiseRecord::Pattern {
-            name,
-            description,
-            files,
-            ..
-        } => {
-            let f = files
-                .as_ref()
-                .filter(|f| !f.is_empty())
-                .map(|f| format!(" ({})", f.join(", ")))
-                .unwrap_or_default();
-            format!("[pattern] {name}: {description}{f}")
-        }
-        ExpertiseRecord::Failure {
-            description,
-            resolution,
-            ..
-        } => {
-            format!("[failure] {description} -> {resolution}")
-        }
-        ExpertiseRecord::Decision {
-            title, rationale, ..
-        } => {
-            format!("[decision] {title}: {rationale}")
-        }
-        ExpertiseRecord::Reference {
-            name,
-            description,
-            files,
-            ..
-        } => {
-            let detail = files
-                .as_ref()
-                .filter(|f| !f.is_empty())
-                .map(|f| format!(": {}", f.join(", ")))
-                .unwrap_or_else(|| format!(": {description}"));
-            format!("[reference] {name}{detail}")
-        }
-        ExpertiseRecord::Guide {
-            name, description, ..
-        } => {
-            format!("[guide] {name}: {description}")
+/// Apply `kb prime` quota flags on top of the configured budget policy.
+/// Share flags apply to every domain, replacing per-domain config values.
+fn apply_policy_overrides(policy: &mut BudgetPolicy, args: &PrimeArgs) -> Result<(), String> {
+    for (flag, share) in [
+        ("--min-share", args.min_share),
+        ("--max-share", args.max_share),
+        ("--overflow-index", args.overflow_index),
+    ] {
+        if share.is_some_and(|s| !(0.0..=1.0).contains(&s)) {
+            return Err(format!("{flag} must be between 0.0 and 1.0"));
         }
     }
+    if let Some(min) = args.min_share {
+        policy.min_share = Some(min);
+        policy.domains.values_mut().for_each(|q| q.min_share = None);
+    }
+    if let Some(max) = args.max_share {
+        policy.max_share = Some(max);
+        policy.domains.values_mut().for_each(|q| q.max_share = None);
+    }
+    for cap in &args.type_cap {
+        let (name, n) = cap
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid --type-cap \"{cap}\". Expected <type>=<count>."))?;
+        let record_type = match name.trim() {
+            "convention" => RecordType::Convention,
+            "pattern" => RecordType::Pattern,
+            "failure" => RecordType::Failure,
+            "decision" => RecordType::Decision,
+            "reference" => RecordType::Reference,
+            "guide" => RecordType::Guide,
+            other => return Err(format!("Unknown record type: {other}")),
+        };
+        let count: usize = n
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid --type-cap count: \"{n}\""))?;
+        policy.type_caps.insert(record_type, count);
+    }
+    if let Some(slots) = args.foundational_slots {
+        policy.foundational_slots = slots;
+    }
+    if let Some(share) = args.overflow_index {
+        policy.overflow_share = Some(share);
+    }
+    Ok(())
 }
 
 /// Get the last-modified time of a file as an RFC 3339 string, or None.
@@ -87,6 +84,16 @@ fn parse_domain_flag(raw: &str) -> Vec<String> {
         .collect()
 }
 
+/// The built-in template for `--format` and verbosity when no `--template` is given.
+fn builtin_template(fmt: PrimeFormat, args: &PrimeArgs) -> &'static str {
+    match fmt {
+        PrimeFormat::Xml => "xml",
+        PrimeFormat::Plain => "plain",
+        PrimeFormat::Markdown if args.verbose || args.full => "markdown",
+        PrimeFormat::Markdown => "compact",
+    }
+}
+
 pub fn run(ctx: &RuntimeContext, args: &PrimeArgs) -> Result<()> {
     config::ensure_kb_dir(&ctx.cwd)?;
     let cfg = config::read_config(&ctx.cwd)?;
@@ -185,164 +192,224 @@ pub fn run(ctx: &RuntimeContext, args: &PrimeArgs) -> Result<()> {
 
     // ── Budget settings ─────────────────────────────────────────────────
 
-    let is_machine_output = args.mcp || ctx.json;
-    let budget_enabled = !is_machine_output && !args.no_limit;
+    // With --stats, --json only changes the report format; stats describe the rendered output
+    let is_machine_output = args.mcp || (ctx.json && !args.stats);
+    let budget_enabled = !args.no_limit;
     let token_budget = args.budget.unwrap_or(budget::DEFAULT_BUDGET);
-
-    // ── Generate output ─────────────────────────────────────────────────
-
-    let output = if is_machine_output {
-        // --json and --mcp produce structured JSON output — no budget
-        let mut domains: Vec<(String, usize, Vec<ExpertiseRecord>)> = Vec::new();
-
-        for domain in &target_domains {
-            let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
-            let records = storage::read_expertise_file(&file_path)?;
-
-            let filtered: Vec<ExpertiseRecord> = if let Some(ref filter_files) = files_to_filter {
-                let refs = git::filter_by_context(&records, filter_files);
-                refs.into_iter().cloned().collect()
-            } else {
-                records
-            };
-
-            // Include domain if no file filter is active, or if filtering produced results
-            if files_to_filter.is_none() || !filtered.is_empty() {
-                let count = filtered.len();
-                domains.push((domain.clone(), count, filtered));
+    let estimator = match tokenizer::estimator_for(cfg.tokenizer) {
+        Ok(e) => e,
+        Err(e) => {
+            if ctx.json {
+                output_json_error("prime", &e.to_string());
+                return Ok(());
             }
+            bail!("{e}");
         }
+    };
+    let mut overflow_indexed = 0;
 
-        // format_mcp_output takes &[(String, usize, &[ExpertiseRecord])]
-        let mcp_input: Vec<(String, usize, &[ExpertiseRecord])> = domains
-            .iter()
-            .map(|(d, c, recs)| (d.clone(), *c, recs.as_slice()))
-            .collect();
-        format::format_mcp_output(&mcp_input)
-    } else {
-        // Human-readable output
-
-        // Load all records per domain
-        struct DomainData {
-            domain: String,
-            records: Vec<ExpertiseRecord>,
-            last_updated: Option<String>,
+    let mut policy = cfg.budget.clone();
+    if let Err(msg) = apply_policy_overrides(&mut policy, args) {
+        if ctx.json {
+            output_json_error("prime", &msg);
+            return Ok(());
         }
+        bail!("{msg}");
+    }
 
-        let mut all_domains: Vec<DomainData> = Vec::new();
+    // ── Load records ────────────────────────────────────────────────────
 
-        for domain in &target_domains {
-            let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
-            let records = storage::read_expertise_file(&file_path)?;
+    struct DomainData {
+        domain: String,
+        records: Vec<ExpertiseRecord>,
+        last_updated: Option<String>,
+    }
 
-            let filtered: Vec<ExpertiseRecord> = if let Some(ref filter_files) = files_to_filter {
-                let refs = git::filter_by_context(&records, filter_files);
-                if refs.is_empty() {
-                    continue;
-                }
-                refs.into_iter().cloned().collect()
-            } else {
-                records
-            };
+    let mut loaded: Vec<DomainData> = Vec::new();
+    for domain in &target_domains {
+        let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
+        loaded.push(DomainData {
+            domain: domain.clone(),
+            records: storage::read_expertise_file(&file_path)?,
+            last_updated: get_file_mod_time(&file_path),
+        });
+    }
 
-            let last_updated = get_file_mod_time(&file_path);
+    // Borrowed views of the records; with a file filter, domains without matches are skipped
+    let filtered: Vec<budget::DomainRecords<'_>> = loaded
+        .iter()
+        .filter_map(|dd| {
+            let records = match files_to_filter {
+                Some(ref filter_files) => git::filter_by_context(&dd.records, filter_files),
+                None => dd.records.iter().collect(),
+            };
+            if files_to_filter.is_some() && records.is_empty() {
+                return None;
+            }
+            Some(budget::DomainRecords {
+                domain: dd.domain.clone(),
+                records,
+            })
+        })
+        .collect();
 
-            all_domains.push(DomainData {
-                domain: domain.clone(),
-                records: filtered,
-                last_updated,
-            });
-        }
+    // ── Apply budget ────────────────────────────────────────────────────
 
-        // Build DomainRecords for budget calculation (references into our owned data)
-        let domain_records: Vec<budget::DomainRecords<'_>> = all_domains
-            .iter()
-            .map(|dd| budget::DomainRecords {
-                domain: dd.domain.clone(),
-                records: dd.records.iter().collect(),
+    // Human output holds back the overflow index reservation
+    let overflow_reserve = match policy.overflow_share {
+        Some(share) if !is_machine_output => (share * token_budget as f64).round() as usize,
+        _ => 0,
+    };
+    let result = budget_enabled.then(|| {
+        budget::apply_budget(
+            &filtered,
+            token_budget.saturating_sub(overflow_reserve),
+            &policy,
+            estimator.as_ref(),
+        )
+    });
+    let records_to_format: &[budget::DomainRecords<'_>] =
+        result.as_ref().map_or(&filtered, |r| &r.kept);
+    let token_stats = budget::domain_stats(&filtered, records_to_format, estimator.as_ref());
+    let budget_value = budget_enabled.then_some(token_budget);
+
+    let dropped_json: Vec<serde_json::Value> = result
+        .iter()
+        .flat_map(|r| &r.dropped)
+        .map(|d| {
+            serde_json::json!({
+                "id": d.record.id(),
+                "domain": d.domain,
+                "reason": d.reason,
             })
-            .collect();
+        })
+        .collect();
 
-        // Apply budget filtering
-        let (records_to_format, dropped_count, dropped_domain_count) = if budget_enabled {
-            let result = budget::apply_budget(&domain_records, token_budget, |record, _domain| {
-                estimate_record_text(record)
-            });
-            (
-                result.kept,
-                result.dropped_count,
-                result.dropped_domain_count,
-            )
-        } else {
-            (domain_records, 0, 0)
-        };
+    // ── Generate output ─────────────────────────────────────────────────
+
+    let output = if is_machine_output {
+        // --json and --mcp produce structured JSON output
+        format::format_mcp_output(records_to_format, &token_stats, budget_value)
+    } else {
+        // Human-readable output
 
         // Build a lookup for last_updated by domain name
-        let mod_times: std::collections::HashMap<&str, Option<&str>> = all_domains
+        let mod_times: HashMap<&str, Option<&str>> = loaded
             .iter()
             .map(|dd| (dd.domain.as_str(), dd.last_updated.as_deref()))
             .collect();
 
-        // Determine whether to use full/verbose or compact formatting
-        let use_full = args.verbose || args.full || fmt != PrimeFormat::Markdown;
+        // Split dropped records into the overflow index and the truncation summary
+        let mut overflow_entries: Vec<(&str, &ExpertiseRecord)> = Vec::new();
+        let mut truncated: Option<(usize, usize)> = None;
+        if let Some(ref result) = result
+            && result.dropped_count > 0
+        {
+            if policy.overflow_share.is_some() {
+                overflow_indexed = budget::fit_overflow_index(
+                    &result.dropped,
+                    token_budget.saturating_sub(result.used_tokens),
+                    estimator.as_ref(),
+                );
+            }
+            overflow_entries = result.dropped[..overflow_indexed]
+                .iter()
+                .map(|d| (d.domain.as_str(), d.record))
+                .collect();
+
+            let unlisted = &result.dropped[overflow_indexed..];
+            if !unlisted.is_empty() {
+                let unlisted_domains: HashSet<&str> =
+                    unlisted.iter().map(|d| d.domain.as_str()).collect();
+                truncated = Some((unlisted.len(), unlisted_domains.len()));
+            }
+        }
 
-        // Format each domain section
-        let domain_sections: Vec<String> = records_to_format
-            .iter()
-            .map(|dr| {
-                let last_updated = mod_times.get(dr.domain.as_str()).copied().flatten();
-
-                // Collect owned records for formatting functions that take &[ExpertiseRecord]
-                let owned: Vec<ExpertiseRecord> = dr.records.iter().map(|r| (*r).clone()).collect();
-
-                if use_full {
-                    match fmt {
-                        PrimeFormat::Xml => {
-                            format::format_domain_expertise_xml(&dr.domain, &owned, last_updated)
-                        }
-                        PrimeFormat::Plain => {
-                            format::format_domain_expertise_plain(&dr.domain, &owned, last_updated)
-                        }
-                        PrimeFormat::Markdown => format::format_domain_expertise(
-                            &dr.domain,
-                            &owned,
-                            last_updated,
-                            args.full,
-                        ),
-                    }
-                } else {
-                    format::format_domain_expertise_compact(&dr.domain, &owned, last_updated)
+        // Built-ins are addressed through the `builtin/` prefix so a user
+        // template of the same name only applies when asked for by name
+        let name = match args.template {
+            Some(ref name) => name.clone(),
+            None => format!("builtin/{}", builtin_template(fmt, args)),
+        };
+        let input = TemplateInput {
+            domains: records_to_format
+                .iter()
+                .map(|dr| TemplateDomain {
+                    name: &dr.domain,
+                    records: &dr.records,
+                    last_updated: mod_times.get(dr.domain.as_str()).copied().flatten(),
+                })
+                .collect(),
+            full: args.full,
+            overflow: &overflow_entries,
+            truncated,
+        };
+        match Templates::load(&ctx.cwd).render(&name, &input) {
+            Ok(out) => out,
+            Err(e) => {
+                if ctx.json {
+                    output_json_error("prime", &e.to_string());
+                    return Ok(());
                 }
-            })
-            .collect();
-
-        // Wrap in prime output format
-        let mut out = if use_full {
-            match fmt {
-                PrimeFormat::Xml => format::format_prime_output_xml(&domain_sections),
-                PrimeFormat::Plain => format::format_prime_output_plain(&domain_sections),
-                PrimeFormat::Markdown => format::format_prime_output(&domain_sections),
+                bail!("{e}");
             }
-        } else {
-            format::format_prime_output_compact(&domain_sections)
-        };
-
-        // Append truncation summary if records were dropped
-        if dropped_count > 0 {
-            out.push_str("\n\n");
-            out.push_str(&budget::format_budget_summary(
-                dropped_count,
-                dropped_domain_count,
-            ));
         }
-
-        // Append session-end reminder
-        out.push_str("\n\n");
-        out.push_str(&format::get_session_end_reminder(fmt));
-
-        out
     };
 
+    // ── Token report ─────────────────────────────────────────────────────
+
+    if args.stats {
+        let output_tokens = estimator.count_tokens(&output);
+        if ctx.json {
+            output_json(&serde_json::json!({
+                "success": true,
+                "command": "prime",
+                "stats": {
+                    "tokenizer": estimator.name(),
+                    "budget": budget_value,
+                    "output_tokens": output_tokens,
+                    "domains": token_stats,
+                    "dropped": dropped_json,
+                    "overflow_indexed": overflow_indexed,
+                },
+            }));
+        } else {
+            println!("Tokenizer: {}", estimator.name());
+            match budget_value {
+                Some(b) => println!("Budget:    {b}"),
+                None => println!("Budget:    none"),
+            }
+            println!("Output:    {output_tokens} tokens\n");
+            println!(
+                "  {:<20} {:>8} {:>8} {:>10} {:>10}",
+                "domain", "records", "kept", "tokens", "kept"
+            );
+            for s in &token_stats {
+                println!(
+                    "  {:<20} {:>8} {:>8} {:>10} {:>10}",
+                    s.domain, s.records, s.kept, s.tokens, s.kept_tokens
+                );
+            }
+            if !dropped_json.is_empty() {
+                let mut by_reason: std::collections::BTreeMap<&str, usize> =
+                    std::collections::BTreeMap::new();
+                for d in &dropped_json {
+                    *by_reason
+                        .entry(d["reason"].as_str().unwrap_or_default())
+                        .or_default() += 1;
+                }
+                if overflow_indexed > 0 {
+                    println!("\nOverflow index: {overflow_indexed} records");
+                }
+                println!("\nDropped:");
+                for (reason, count) in by_reason {
+                    println!("  {reason:<20} {count:>8}");
+                }
+            }
+        }
+        return Ok(());
+    }
+
     // ── Export or print ──────────────────────────────────────────────────
 
     if let Some(ref export_path) = args.export {
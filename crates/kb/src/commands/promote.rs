@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::cli::PromoteArgs;
+use crate::context::RuntimeContext;
+use crate::output::*;
+use kb_core::access_log::{self, AccessLogFilter};
+use kb_core::changelog::{self, ChangelogEntry, ChangelogFilter};
+use kb_core::promotion::{self, ClassificationChange, Direction};
+use kb_core::types::PromotionPolicy;
+use kb_core::{config, format, git, lock, storage};
+
+pub fn run(ctx: &RuntimeContext, args: &PromoteArgs) -> Result<()> {
+    config::ensure_kb_dir(&ctx.cwd)?;
+    let cfg = config::read_config(&ctx.cwd)?;
+
+    let domains: Vec<String> = match args.domain {
+        Some(ref domain) => {
+            config::ensure_domain_exists(&cfg, domain)?;
+            vec![domain.clone()]
+        }
+        None => cfg.domains.clone(),
+    };
+
+    match args.branch {
+        Some(ref branch) => run_branch(ctx, args, branch, &domains),
+        None => run_auto(ctx, args, &cfg.promotion, &domains),
+    }
+}
+
+fn run_branch(
+    ctx: &RuntimeContext,
+    args: &PromoteArgs,
+    branch: &str,
+    domains: &[String],
+) -> Result<()> {
+    // (domain, id, type, summary)
+    let mut promoted: Vec<(String, String, String, String)> = Vec::new();
+
+    for domain in domains {
+        let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
+        lock::with_file_lock(&file_path, || {
+            let mut records = storage::read_expertise_file(&file_path)?;
+            let mut changed = false;
+            for record in records.iter_mut() {
+                if record.branch() != Some(branch) {
+                    continue;
+                }
+                promoted.push((
+                    domain.clone(),
+                    record.id().unwrap_or("?").to_string(),
+                    record.record_type().to_string(),
+                    format::get_record_summary(record),
+                ));
+                record.set_branch(None);
+                record.touch();
+                changed = true;
+            }
+            if changed && !args.dry_run {
+                storage::write_expertise_file(&file_path, &mut records)?;
+            }
+            Ok(())
+        })?;
+    }
+
+    // Promoting before the merge is allowed, but usually a mistake
+    let unmerged = git::is_git_repo(&ctx.cwd)
+        && git::ref_exists(&ctx.cwd, branch)
+        && !git::is_merged(&ctx.cwd, branch);
+
+    if ctx.json {
+        let records: Vec<serde_json::Value> = promoted
+            .iter()
+            .map(|(domain, id, record_type, summary)| {
+                serde_json::json!({
+                    "domain": domain,
+                    "id": id,
+                    "type": record_type,
+                    "summary": summary,
+                })
+            })
+            .collect();
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "promote",
+            "branch": branch,
+            "dry_run": args.dry_run,
+            "merged": !unmerged,
+            "promoted": records,
+        }));
+        return Ok(());
+    }
+
+    if unmerged {
+        print_warning(&format!("Branch \"{branch}\" is not merged into HEAD yet."));
+    }
+
+    if promoted.is_empty() {
+        println!("No records scoped to branch \"{branch}\".");
+        return Ok(());
+    }
+
+    for (domain, id, record_type, summary) in &promoted {
+        println!("  [{domain}] {id} {record_type}: {summary}");
+    }
+    let verb = if args.dry_run {
+        "Would promote"
+    } else {
+        "Promoted"
+    };
+    print_success(&format!(
+        "{verb} {} record(s) from branch \"{branch}\".",
+        promoted.len()
+    ));
+
+    Ok(())
+}
+
+/// A classification change applied (or proposed) to a record.
+struct Reclassified {
+    domain: String,
+    id: String,
+    record_type: String,
+    summary: String,
+    change: ClassificationChange,
+}
+
+fn run_auto(
+    ctx: &RuntimeContext,
+    args: &PromoteArgs,
+    policy: &PromotionPolicy,
+    domains: &[String],
+) -> Result<()> {
+    // Evidence counts only from each record's last reclassification on
+    let reclassified = promotion::reclassified_at(&changelog::query_changelog(
+        &ctx.cwd,
+        &ChangelogFilter::default(),
+    )?);
+    let feedback = promotion::feedback_counts(
+        &access_log::query_log(&ctx.cwd, &AccessLogFilter::default())?,
+        &reclassified,
+    );
+    let mut changes: Vec<Reclassified> = Vec::new();
+
+    for domain in domains {
+        let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
+        let domain_changes = lock::with_file_lock(&file_path, || {
+            let mut records = storage::read_expertise_file(&file_path)?;
+            let mut domain_changes = Vec::new();
+            for record in records.iter_mut() {
+                let since = record.id().and_then(|id| reclassified.get(id)).copied();
+                let evidence = promotion::evidence_for(record, &feedback, since);
+                let Some(change) = promotion::evaluate(record.classification(), evidence, policy)
+                else {
+                    continue;
+                };
+                record.set_classification(change.to);
+                record.touch();
+                domain_changes.push(Reclassified {
+                    domain: domain.clone(),
+                    id: record.id().unwrap_or("?").to_string(),
+                    record_type: record.record_type().to_string(),
+                    summary: format::get_record_summary(record),
+                    change,
+                });
+            }
+            if !domain_changes.is_empty() && !args.dry_run {
+                storage::write_expertise_file(&file_path, &mut records)?;
+            }
+            Ok(domain_changes)
+        })?;
+        changes.extend(domain_changes);
+    }
+
+    if !args.dry_run {
+        for r in &changes {
+            changelog::append(
+                &ctx.cwd,
+                &ChangelogEntry {
+                    session_id: args.session.clone(),
+                    timestamp: chrono::Utc::now(),
+                    action: r.change.direction.as_str().to_string(),
+                    domain: r.domain.clone(),
+                    entry_id: r.id.clone(),
+                    summary: Some(r.summary.clone()),
+                    diff: Some(HashMap::from([(
+                        "classification".to_string(),
+                        (r.change.from.to_string(), r.change.to.to_string()),
+                    )])),
+                },
+            )?;
+        }
+    }
+
+    if ctx.json {
+        let records: Vec<serde_json::Value> = changes
+            .iter()
+            .map(|r| {
+                serde_json::json!({
+                    "domain": r.domain,
+                    "id": r.id,
+                    "type": r.record_type,
+                    "summary": r.summary,
+                    "direction": r.change.direction,
+                    "from": r.change.from,
+                    "to": r.change.to,
+                    "rule": r.change.rule,
+                    "evidence": r.change.evidence,
+                    "reason": r.change.reason(),
+                })
+            })
+            .collect();
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "promote",
+            "auto": true,
+            "dry_run": args.dry_run,
+            "changes": records,
+        }));
+        return Ok(());
+    }
+
+    if changes.is_empty() {
+        println!("No records meet the promotion policy.");
+        return Ok(());
+    }
+
+    for r in &changes {
+        println!("  [{}] {} {}: {}", r.domain, r.id, r.record_type, r.summary);
+        println!(
+            "      {} {} -> {}: {}",
+            r.change.direction.as_str(),
+            r.change.from,
+            r.change.to,
+            r.change.reason()
+        );
+    }
+    let count = |direction| {
+        changes
+            .iter()
+            .filter(|r| r.change.direction == direction)
+            .count()
+    };
+    let (promote_verb, demote_verb) = if args.dry_run {
+        ("Would promote", "would demote")
+    } else {
+        ("Promoted", "demoted")
+    };
+    print_success(&format!(
+        "{promote_verb} {} and {demote_verb} {} record(s).",
+        count(Direction::Promote),
+        count(Direction::Demote)
+    ));
+
+    Ok(())
+}
@@ -3,14 +3,45 @@ use anyhow::Result;
 use crate::cli::PruneArgs;
 use crate::context::RuntimeContext;
 use crate::output::*;
-use kb_core::{config, health, lock, storage};
+use kb_core::archive::{self, ArchivedRecord, Removal};
+use kb_core::health::{self, Staleness};
+use kb_core::types::{ExpertiseRecord, KbConfig};
+use kb_core::{config, format, lock, storage};
+
+/// Why a stale record is pruned: how long it has gone unchanged against the
+/// shelf life and the rule that set it.
+fn explanation(record: &ExpertiseRecord, stale: &Staleness, cfg: &KbConfig) -> String {
+    format!(
+        "{} record unchanged for {} days, past its {}-day shelf life from {}",
+        record.classification(),
+        stale.age_days,
+        stale.shelf_life.days,
+        stale.source(cfg)
+    )
+}
+
+fn explanation_json(
+    record: &ExpertiseRecord,
+    stale: &Staleness,
+    cfg: &KbConfig,
+) -> serde_json::Value {
+    serde_json::json!({
+        "id": record.id(),
+        "type": record.record_type().as_str(),
+        "summary": format::get_record_summary(record),
+        "classification": record.classification().as_str(),
+        "age_days": stale.age_days,
+        "shelf_life_days": stale.shelf_life.days,
+        "rule": stale.shelf_life.rule.map(|i| i + 1),
+        "source": stale.source(cfg),
+    })
+}
 
 pub fn run(ctx: &RuntimeContext, args: &PruneArgs) -> Result<()> {
     config::ensure_kb_dir(&ctx.cwd)?;
     let cfg = config::read_config(&ctx.cwd)?;
 
     let now = chrono::Utc::now();
-    let shelf_life = &cfg.classification_defaults.shelf_life;
     let mut total_pruned = 0usize;
     let mut json_domains: Vec<serde_json::Value> = Vec::new();
 
@@ -18,67 +49,101 @@ pub fn run(ctx: &RuntimeContext, args: &PruneArgs) -> Result<()> {
         let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
         let records = storage::read_expertise_file(&file_path)?;
 
-        let stale_indices: Vec<usize> = records
+        let stale: Vec<(&ExpertiseRecord, Staleness)> = records
             .iter()
-            .enumerate()
-            .filter(|(_, r)| health::is_record_stale(r, now, shelf_life))
-            .map(|(i, _)| i)
+            .filter_map(|r| health::staleness(r, domain, now, &cfg).map(|s| (r, s)))
             .collect();
 
-        if stale_indices.is_empty() {
+        if stale.is_empty() {
             continue;
         }
 
-        let stale_count = stale_indices.len();
+        let stale_count = stale.len();
 
         if args.dry_run {
             if ctx.json {
-                let stale_ids: Vec<&str> = stale_indices
-                    .iter()
-                    .filter_map(|&i| records[i].id())
-                    .collect();
-                json_domains.push(serde_json::json!({
+                let stale_ids: Vec<&str> = stale.iter().filter_map(|(r, _)| r.id()).collect();
+                let mut entry = serde_json::json!({
                     "domain": domain,
                     "stale_count": stale_count,
                     "stale_ids": stale_ids,
-                }));
+                });
+                if args.explain {
+                    entry["records"] = stale
+                        .iter()
+                        .map(|(r, s)| explanation_json(r, s, &cfg))
+                        .collect();
+                }
+                json_domains.push(entry);
             } else {
                 println!(
                     "  {}: {} stale record(s) would be pruned",
                     domain, stale_count
                 );
-                for &i in &stale_indices {
-                    let r = &records[i];
+                for (r, s) in &stale {
                     let id = r.id().unwrap_or("?");
-                    let summary = kb_core::format::get_record_summary(r);
+                    let summary = format::get_record_summary(r);
                     println!("    {} {} ({})", id, r.record_type(), summary);
+                    if args.explain {
+                        println!("      {}", explanation(r, s, &cfg));
+                    }
                 }
             }
         } else {
-            lock::with_file_lock(&file_path, || {
-                let mut records = storage::read_expertise_file(&file_path)?;
-                // Remove in reverse order to preserve indices
-                let mut indices: Vec<usize> = records
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, r)| health::is_record_stale(r, now, shelf_life))
-                    .map(|(i, _)| i)
-                    .collect();
-                indices.sort_unstable_by(|a, b| b.cmp(a));
-                for i in indices {
-                    records.remove(i);
+            // Archive before rewriting the domain file, as in `kb delete`
+            let removed = lock::with_file_lock(&file_path, || {
+                let records = storage::read_expertise_file(&file_path)?;
+                let mut removed = Vec::new();
+                let mut kept = Vec::new();
+                for record in records {
+                    match health::staleness(&record, domain, now, &cfg) {
+                        Some(stale) => removed.push((record, stale)),
+                        None => kept.push(record),
+                    }
+                }
+                if !args.purge {
+                    let archived: Vec<ArchivedRecord> = removed
+                        .iter()
+                        .map(|(record, stale)| ArchivedRecord {
+                            removal: Removal::new(
+                                "prune",
+                                format!("stale: {}", explanation(record, stale, &cfg)),
+                                args.session.as_deref(),
+                            ),
+                            record: record.clone(),
+                        })
+                        .collect();
+                    archive::archive_records(&ctx.cwd, domain, &archived)?;
                 }
-                storage::write_expertise_file(&file_path, &mut records)?;
-                Ok(())
+                storage::write_expertise_file(&file_path, &mut kept)?;
+                Ok(removed)
             })?;
 
             if ctx.json {
-                json_domains.push(serde_json::json!({
+                let mut entry = serde_json::json!({
                     "domain": domain,
                     "pruned": stale_count,
-                }));
+                    "archived": !args.purge,
+                });
+                if args.explain {
+                    entry["records"] = removed
+                        .iter()
+                        .map(|(r, s)| explanation_json(r, s, &cfg))
+                        .collect();
+                }
+                json_domains.push(entry);
             } else {
                 println!("  {}: pruned {} stale record(s)", domain, stale_count);
+                if args.explain {
+                    for (r, s) in &removed {
+                        println!(
+                            "    {} {}: {}",
+                            r.id().unwrap_or("?"),
+                            r.record_type(),
+                            explanation(r, s, &cfg)
+                        );
+                    }
+                }
             }
         }
 
@@ -100,8 +165,13 @@ pub fn run(ctx: &RuntimeContext, args: &PruneArgs) -> Result<()> {
             "{} stale record(s) would be pruned. Run without --dry-run to remove.",
             total_pruned
         ));
-    } else {
+    } else if args.purge {
         print_success(&format!("Pruned {} stale record(s).", total_pruned));
+    } else {
+        print_success(&format!(
+            "Pruned {} stale record(s) to .kb/archive/. Restore with `kb archive restore <id>`.",
+            total_pruned
+        ));
     }
 
     Ok(())
@@ -3,8 +3,9 @@ use anyhow::{Result, bail};
 use crate::cli::QueryArgs;
 use crate::context::RuntimeContext;
 use crate::output::*;
+use kb_core::template::{TemplateDomain, TemplateInput, Templates};
 use kb_core::types::*;
-use kb_core::{config, filter, format, scoring, storage};
+use kb_core::{config, filter, scoring, storage};
 
 pub fn run(ctx: &RuntimeContext, args: &QueryArgs) -> Result<()> {
     config::ensure_kb_dir(&ctx.cwd)?;
@@ -21,6 +22,8 @@ pub fn run(ctx: &RuntimeContext, args: &QueryArgs) -> Result<()> {
 
     let mut json_domains: Vec<serde_json::Value> = Vec::new();
     let mut sections: Vec<String> = Vec::new();
+    let templates = Templates::load(&ctx.cwd);
+    let template = args.template.as_deref().unwrap_or("builtin/markdown");
 
     for domain in &domains {
         let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
@@ -85,10 +88,17 @@ pub fn run(ctx: &RuntimeContext, args: &QueryArgs) -> Result<()> {
             }));
         } else {
             let last_updated = records.iter().map(|r| r.recorded_at().to_string()).max();
-            let owned: Vec<ExpertiseRecord> = filtered.into_iter().cloned().collect();
-            let output =
-                format::format_domain_expertise(domain, &owned, last_updated.as_deref(), false);
-            sections.push(output);
+            let input = TemplateInput {
+                domains: vec![TemplateDomain {
+                    name: domain,
+                    records: &filtered,
+                    last_updated: last_updated.as_deref(),
+                }],
+                full: false,
+                overflow: &[],
+                truncated: None,
+            };
+            sections.push(templates.render_domains(template, &input)?);
         }
     }
 
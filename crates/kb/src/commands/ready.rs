@@ -6,7 +6,7 @@ use crate::output::*;
 use kb_core::{config, format, storage};
 
 /// Parse a human-friendly duration string like "24h", "7d", "2w" into a chrono Duration.
-fn parse_duration(s: &str) -> Result<chrono::Duration> {
+pub(crate) fn parse_duration(s: &str) -> Result<chrono::Duration> {
     let s = s.trim();
     if s.is_empty() {
         bail!("Empty duration string");
@@ -7,7 +7,7 @@ use crate::context::RuntimeContext;
 use crate::output::*;
 
 use kb_core::types::*;
-use kb_core::{config, filter, lock, storage};
+use kb_core::{config, filter, git, health, lock, storage};
 
 // ── Helpers ─────────────────────────────────────────────────────────────────
 
@@ -61,14 +61,27 @@ fn build_outcomes(args: &RecordArgs) -> Option<Vec<Outcome>> {
         test_results: args.outcome_test_results.clone(),
         agent: args.outcome_agent.clone(),
         notes: None,
-        recorded_at: None,
+        recorded_at: Some(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
     }])
 }
 
+/// Branch to scope new records to, unless `--no-branch` was given.
+fn record_branch(ctx: &RuntimeContext, cfg: &KbConfig, args: &RecordArgs) -> Option<String> {
+    if args.no_branch {
+        return None;
+    }
+    git::scoped_branch(&ctx.cwd, cfg.main_branch.as_deref())
+}
+
 // ── Fill defaults on raw JSON before deserialization ─────────────────────────
 
-fn fill_defaults(value: &mut serde_json::Value) {
+fn fill_defaults(value: &mut serde_json::Value, branch: Option<&str>) {
     if let Some(obj) = value.as_object_mut() {
+        if let Some(branch) = branch {
+            if !obj.contains_key("branch") {
+                obj.insert("branch".to_string(), serde_json::Value::from(branch));
+            }
+        }
         if !obj.contains_key("recorded_at") {
             obj.insert(
                 "recorded_at".to_string(),
@@ -97,9 +110,14 @@ struct BatchResult {
 
 /// Parses JSON input (single object or array), validates each record via serde,
 /// fills in defaults, deduplicates, and writes atomically with file locking.
+/// Nothing is written if the new records would take the domain past its hard
+/// limit, unless `force` is set.
 fn process_bulk_records(
     file_path: &Path,
     input_data: &str,
+    domain: &str,
+    governance: &Governance,
+    branch: Option<&str>,
     force: bool,
     dry_run: bool,
 ) -> Result<BatchResult> {
@@ -116,7 +134,7 @@ fn process_bulk_records(
     let mut valid_records: Vec<ExpertiseRecord> = Vec::new();
 
     for (i, mut raw) in raw_records.into_iter().enumerate() {
-        fill_defaults(&mut raw);
+        fill_defaults(&mut raw, branch);
 
         match serde_json::from_value::<ExpertiseRecord>(raw) {
             Ok(record) => valid_records.push(record),
@@ -142,6 +160,7 @@ fn process_bulk_records(
     if dry_run {
         // Dry-run: check for duplicates without writing
         let existing = storage::read_expertise_file(file_path)?;
+        let existing_count = existing.len();
         let mut current_records = existing;
 
         for record in &valid_records {
@@ -159,10 +178,14 @@ fn process_bulk_records(
             // Track what we would add for accurate intra-batch dedup
             current_records.push(record.clone());
         }
+        if !force {
+            health::check_hard_limit(governance, domain, existing_count, created)?;
+        }
     } else {
         // Normal mode: write with file locking
         lock::with_file_lock(file_path, || {
             let existing = storage::read_expertise_file(file_path)?;
+            let existing_count = existing.len();
             let mut current_records = existing;
 
             for record in valid_records {
@@ -172,6 +195,8 @@ fn process_bulk_records(
                     if !force {
                         if record.is_named_type() {
                             // Upsert: replace in place
+                            let mut record = record;
+                            record.touch();
                             current_records[idx] = record;
                             updated += 1;
                         } else {
@@ -188,6 +213,10 @@ fn process_bulk_records(
                 }
             }
 
+            if !force {
+                health::check_hard_limit(governance, domain, existing_count, created)?;
+            }
+
             // Write all changes at once
             if created > 0 || updated > 0 {
                 storage::write_expertise_file(file_path, &mut current_records)?;
@@ -309,7 +338,23 @@ fn run_batch(ctx: &RuntimeContext, args: &RecordArgs, batch_file: &str) -> Resul
     let file_content = std::fs::read_to_string(batch_file)
         .with_context(|| format!("Failed to read batch file: {batch_file}"))?;
 
-    let result = process_bulk_records(&file_path, &file_content, args.force, args.dry_run)?;
+    let branch = record_branch(ctx, &cfg, args);
+    let result = match process_bulk_records(
+        &file_path,
+        &file_content,
+        &args.domain,
+        &cfg.governance,
+        branch.as_deref(),
+        args.force,
+        args.dry_run,
+    ) {
+        Ok(result) => result,
+        Err(e) if ctx.json => {
+            output_json_error("record", &format!("{e:#}"));
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
 
     output_bulk_errors(ctx, &result);
 
@@ -343,7 +388,23 @@ fn run_stdin(ctx: &RuntimeContext, args: &RecordArgs) -> Result<()> {
         .read_to_string(&mut input)
         .context("Failed to read from stdin")?;
 
-    let result = process_bulk_records(&file_path, &input, args.force, args.dry_run)?;
+    let branch = record_branch(ctx, &cfg, args);
+    let result = match process_bulk_records(
+        &file_path,
+        &input,
+        &args.domain,
+        &cfg.governance,
+        branch.as_deref(),
+        args.force,
+        args.dry_run,
+    ) {
+        Ok(result) => result,
+        Err(e) if ctx.json => {
+            output_json_error("record", &format!("{e:#}"));
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
 
     output_bulk_errors(ctx, &result);
 
@@ -387,6 +448,7 @@ fn run_cli(ctx: &RuntimeContext, args: &RecordArgs) -> Result<()> {
 
     let classification = parse_classification(&args.classification);
     let recorded_at = now_iso();
+    let branch = record_branch(ctx, &cfg, args);
     let evidence = build_evidence(args);
     let tags = args.tags.as_deref().map(parse_csv);
     let relates_to = args.relates_to.as_deref().map(parse_csv);
@@ -418,6 +480,8 @@ fn run_cli(ctx: &RuntimeContext, args: &RecordArgs) -> Result<()> {
                 content,
                 classification,
                 recorded_at,
+                updated_at: None,
+                branch: branch.clone(),
                 evidence,
                 tags,
                 relates_to,
@@ -437,6 +501,8 @@ fn run_cli(ctx: &RuntimeContext, args: &RecordArgs) -> Result<()> {
                     files,
                     classification,
                     recorded_at,
+                    updated_at: None,
+                    branch: branch.clone(),
                     evidence,
                     tags,
                     relates_to,
@@ -468,6 +534,8 @@ fn run_cli(ctx: &RuntimeContext, args: &RecordArgs) -> Result<()> {
                     resolution: r.to_string(),
                     classification,
                     recorded_at,
+                    updated_at: None,
+                    branch: branch.clone(),
                     evidence,
                     tags,
                     relates_to,
@@ -498,6 +566,8 @@ fn run_cli(ctx: &RuntimeContext, args: &RecordArgs) -> Result<()> {
                     date: None,
                     classification,
                     recorded_at,
+                    updated_at: None,
+                    branch: branch.clone(),
                     evidence,
                     tags,
                     relates_to,
@@ -528,6 +598,8 @@ fn run_cli(ctx: &RuntimeContext, args: &RecordArgs) -> Result<()> {
                     files,
                     classification,
                     recorded_at,
+                    updated_at: None,
+                    branch: branch.clone(),
                     evidence,
                     tags,
                     relates_to,
@@ -559,6 +631,8 @@ fn run_cli(ctx: &RuntimeContext, args: &RecordArgs) -> Result<()> {
                     description: d.to_string(),
                     classification,
                     recorded_at,
+                    updated_at: None,
+                    branch: branch.clone(),
                     evidence,
                     tags,
                     relates_to,
@@ -586,10 +660,11 @@ fn run_cli(ctx: &RuntimeContext, args: &RecordArgs) -> Result<()> {
     let file_path = config::get_expertise_path(&args.domain, &ctx.cwd)?;
     let record_type = record.record_type();
 
-    if args.dry_run {
+    let result = if args.dry_run {
         run_cli_dry_run(
             ctx,
             &args.domain,
+            &cfg.governance,
             &file_path,
             &record,
             record_type,
@@ -599,11 +674,19 @@ fn run_cli(ctx: &RuntimeContext, args: &RecordArgs) -> Result<()> {
         run_cli_write(
             ctx,
             &args.domain,
+            &cfg.governance,
             &file_path,
             record,
             record_type,
             args.force,
         )
+    };
+    match result {
+        Err(e) if ctx.json => {
+            output_json_error("record", &format!("{e:#}"));
+            Ok(())
+        }
+        other => other,
     }
 }
 
@@ -612,6 +695,7 @@ fn run_cli(ctx: &RuntimeContext, args: &RecordArgs) -> Result<()> {
 fn run_cli_dry_run(
     ctx: &RuntimeContext,
     domain: &str,
+    governance: &Governance,
     file_path: &Path,
     record: &ExpertiseRecord,
     record_type: RecordType,
@@ -629,6 +713,9 @@ fn run_cli_dry_run(
     } else {
         "created"
     };
+    if action == "created" && !force {
+        health::check_hard_limit(governance, domain, existing.len(), 1)?;
+    }
 
     if ctx.json {
         output_json(&serde_json::json!({
@@ -667,6 +754,7 @@ fn run_cli_dry_run(
 fn run_cli_write(
     ctx: &RuntimeContext,
     domain: &str,
+    governance: &Governance,
     file_path: &Path,
     record: ExpertiseRecord,
     record_type: RecordType,
@@ -681,6 +769,7 @@ fn run_cli_write(
                 if record.is_named_type() {
                     // Upsert: replace in place
                     existing[idx] = record.clone();
+                    existing[idx].touch();
                     storage::write_expertise_file(file_path, &mut existing)?;
 
                     if ctx.json {
@@ -721,7 +810,10 @@ fn run_cli_write(
             }
         }
 
-        // New record (or --force): append
+        // New record (or --force): append, within the hard limit unless forced
+        if !force {
+            health::check_hard_limit(governance, domain, existing.len(), 1)?;
+        }
         let mut record = record;
         storage::append_record(file_path, &mut record)?;
 
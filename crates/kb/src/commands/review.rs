@@ -0,0 +1,77 @@
+use anyhow::{Result, bail};
+
+use crate::cli::ReviewArgs;
+use crate::context::RuntimeContext;
+use crate::output::*;
+use kb_core::{churn, config, git};
+
+pub fn run(ctx: &RuntimeContext, args: &ReviewArgs) -> Result<()> {
+    config::ensure_kb_dir(&ctx.cwd)?;
+    let cfg = config::read_config(&ctx.cwd)?;
+    if let Some(ref domain) = args.domain {
+        config::ensure_domain_exists(&cfg, domain)?;
+    }
+
+    if !git::is_git_repo(&ctx.cwd) {
+        if ctx.json {
+            output_json_error("review", "Not in a git repository.");
+            return Ok(());
+        }
+        bail!("Not in a git repository. `kb review --stale` requires git.");
+    }
+
+    let mut stale = churn::stale_records(&ctx.cwd, &cfg, args.domain.as_deref())?;
+    let total = stale.len();
+    if let Some(limit) = args.limit {
+        stale.truncate(limit);
+    }
+
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "review",
+            "queue": "stale",
+            "thresholds": cfg.churn,
+            "total": total,
+            "records": stale,
+        }));
+        return Ok(());
+    }
+
+    if stale.is_empty() {
+        println!("No stale records.");
+        return Ok(());
+    }
+
+    println!(
+        "Stale records ({total}): referenced files changed in at least {} commits and {} lines since the record was written.",
+        cfg.churn.min_commits, cfg.churn.min_lines
+    );
+    println!();
+    for record in &stale {
+        println!(
+            "  [{}] {} {}: {}",
+            record.domain, record.id, record.record_type, record.summary
+        );
+        println!(
+            "    {} commits, {} lines since {}",
+            record.commits,
+            record.lines,
+            record.since.split('T').next().unwrap_or(&record.since)
+        );
+        for file in &record.files {
+            println!(
+                "      {} ({} commits, {} lines)",
+                file.path, file.commits, file.lines
+            );
+        }
+    }
+    if total > stale.len() {
+        println!();
+        println!("... and {} more.", total - stale.len());
+    }
+    println!();
+    println!("Re-verify each record against the code and update it with `kb edit`.");
+
+    Ok(())
+}
@@ -3,9 +3,113 @@ use anyhow::{Result, bail};
 use crate::cli::SearchArgs;
 use crate::context::RuntimeContext;
 use crate::output::*;
+use kb_core::archive::{self, ArchivedRecord};
+use kb_core::template::{TemplateDomain, TemplateInput, Templates};
 use kb_core::types::*;
 use kb_core::{config, format, scoring, search, storage};
 
+/// Pre-search filters from the command line.
+struct Filters {
+    record_type: Option<RecordType>,
+    classification: Option<Classification>,
+    tag: Option<String>,
+    file: Option<String>,
+    outcome_status: Option<OutcomeStatus>,
+}
+
+impl Filters {
+    fn from_args(args: &SearchArgs) -> Result<Self> {
+        let record_type = match args.record_type.as_deref() {
+            None => None,
+            Some("convention") => Some(RecordType::Convention),
+            Some("pattern") => Some(RecordType::Pattern),
+            Some("failure") => Some(RecordType::Failure),
+            Some("decision") => Some(RecordType::Decision),
+            Some("reference") => Some(RecordType::Reference),
+            Some("guide") => Some(RecordType::Guide),
+            Some(other) => bail!("Unknown record type: {other}"),
+        };
+        let classification = match args.classification.as_deref() {
+            None => None,
+            Some("foundational") => Some(Classification::Foundational),
+            Some("tactical") => Some(Classification::Tactical),
+            Some("observational") => Some(Classification::Observational),
+            Some(other) => bail!("Unknown classification: {other}"),
+        };
+        let outcome_status = match args.outcome_status.as_deref() {
+            None => None,
+            Some("success") => Some(OutcomeStatus::Success),
+            Some("failure") => Some(OutcomeStatus::Failure),
+            Some("partial") => Some(OutcomeStatus::Partial),
+            Some(other) => bail!("Unknown outcome status: {other}"),
+        };
+        Ok(Self {
+            record_type,
+            classification,
+            tag: args.tag.as_ref().map(|t| t.to_lowercase()),
+            file: args.file.as_ref().map(|f| f.to_lowercase()),
+            outcome_status,
+        })
+    }
+
+    fn keep(&self, r: &ExpertiseRecord) -> bool {
+        if self.record_type.is_some_and(|t| r.record_type() != t) {
+            return false;
+        }
+        if self.classification.is_some_and(|c| r.classification() != c) {
+            return false;
+        }
+        if let Some(ref tag) = self.tag {
+            let has_tag = r
+                .tags()
+                .map(|tags| tags.iter().any(|t| t.to_lowercase() == *tag))
+                .unwrap_or(false);
+            if !has_tag {
+                return false;
+            }
+        }
+        if let Some(ref file) = self.file {
+            let has_file = r
+                .files()
+                .map(|files| files.iter().any(|f| f.to_lowercase().contains(file)))
+                .unwrap_or(false);
+            if !has_file {
+                return false;
+            }
+        }
+        if let Some(status) = self.outcome_status {
+            let has_status = r
+                .outcomes()
+                .map(|outcomes| outcomes.iter().any(|o| o.status == status))
+                .unwrap_or(false);
+            if !has_status {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Archived records of `domain` matching the filters and query.
+fn search_archive(
+    ctx: &RuntimeContext,
+    domain: &str,
+    filters: &Filters,
+    query: &str,
+) -> Result<Vec<ArchivedRecord>> {
+    let path = config::get_archive_path(domain, &ctx.cwd)?;
+    let entries: Vec<ArchivedRecord> = archive::read_archive(&path)?
+        .into_iter()
+        .filter(|e| filters.keep(&e.record))
+        .collect();
+    let records: Vec<ExpertiseRecord> = entries.iter().map(|e| e.record.clone()).collect();
+    Ok(search::search_records(&records, query)
+        .into_iter()
+        .filter_map(|m| records.iter().position(|r| std::ptr::eq(r, m)))
+        .map(|i| entries[i].clone())
+        .collect())
+}
+
 pub fn run(ctx: &RuntimeContext, args: &SearchArgs) -> Result<()> {
     config::ensure_kb_dir(&ctx.cwd)?;
     let cfg = config::read_config(&ctx.cwd)?;
@@ -22,72 +126,20 @@ pub fn run(ctx: &RuntimeContext, args: &SearchArgs) -> Result<()> {
         cfg.domains.clone()
     };
 
+    let filters = Filters::from_args(args)?;
+    let templates = Templates::load(&ctx.cwd);
     let mut json_domains: Vec<serde_json::Value> = Vec::new();
     let mut sections: Vec<String> = Vec::new();
     let mut total_matches = 0usize;
+    let mut total_archived = 0usize;
 
     for domain in &domains {
         let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
         let records = storage::read_expertise_file(&file_path)?;
         let last_updated = records.iter().map(|r| r.recorded_at().to_string()).max();
 
-        // Apply pre-search filters
-        let mut filtered: Vec<ExpertiseRecord> = records;
-
-        if let Some(ref rt) = args.record_type {
-            let record_type = match rt.as_str() {
-                "convention" => RecordType::Convention,
-                "pattern" => RecordType::Pattern,
-                "failure" => RecordType::Failure,
-                "decision" => RecordType::Decision,
-                "reference" => RecordType::Reference,
-                "guide" => RecordType::Guide,
-                other => bail!("Unknown record type: {other}"),
-            };
-            filtered.retain(|r| r.record_type() == record_type);
-        }
-
-        if let Some(ref cls_str) = args.classification {
-            let cls = match cls_str.as_str() {
-                "foundational" => Classification::Foundational,
-                "tactical" => Classification::Tactical,
-                "observational" => Classification::Observational,
-                other => bail!("Unknown classification: {other}"),
-            };
-            filtered.retain(|r| r.classification() == cls);
-        }
-
-        if let Some(ref tag) = args.tag {
-            let tag_lower = tag.to_lowercase();
-            filtered.retain(|r| {
-                r.tags()
-                    .map(|tags| tags.iter().any(|t| t.to_lowercase() == tag_lower))
-                    .unwrap_or(false)
-            });
-        }
-
-        if let Some(ref file) = args.file {
-            let file_lower = file.to_lowercase();
-            filtered.retain(|r| {
-                r.files()
-                    .map(|files| files.iter().any(|f| f.to_lowercase().contains(&file_lower)))
-                    .unwrap_or(false)
-            });
-        }
-
-        if let Some(ref os) = args.outcome_status {
-            let target_status = match os.as_str() {
-                "success" => OutcomeStatus::Success,
-                "failure" => OutcomeStatus::Failure,
-                "partial" => OutcomeStatus::Partial,
-                other => bail!("Unknown outcome status: {other}"),
-            };
-            filtered.retain(|r| {
-                r.outcomes()
-                    .map(|outcomes| outcomes.iter().any(|o| o.status == target_status))
-                    .unwrap_or(false)
-            });
-        }
+        let filtered: Vec<ExpertiseRecord> =
+            records.into_iter().filter(|r| filters.keep(r)).collect();
 
         // BM25 search
         let matches: Vec<ExpertiseRecord> = search::search_records(&filtered, &query)
@@ -100,20 +152,58 @@ pub fn run(ctx: &RuntimeContext, args: &SearchArgs) -> Result<()> {
             scoring::sort_by_confirmation_score(&mut refs);
         }
 
-        if matches.is_empty() {
+        let archived = if args.include_archived {
+            search_archive(ctx, domain, &filters, &query)?
+        } else {
+            Vec::new()
+        };
+
+        if matches.is_empty() && archived.is_empty() {
             continue;
         }
 
         total_matches += matches.len();
+        total_archived += archived.len();
 
         if ctx.json {
-            json_domains.push(serde_json::json!({
+            let mut entry = serde_json::json!({
                 "domain": domain,
                 "matches": matches,
-            }));
+            });
+            if args.include_archived {
+                entry["archived"] = serde_json::json!(archived);
+            }
+            json_domains.push(entry);
         } else {
-            let output =
-                format::format_domain_expertise(domain, &matches, last_updated.as_deref(), false);
+            let mut output = if matches.is_empty() {
+                format!("## {domain}")
+            } else {
+                let refs: Vec<&ExpertiseRecord> = matches.iter().collect();
+                let input = TemplateInput {
+                    domains: vec![TemplateDomain {
+                        name: domain,
+                        records: &refs,
+                        last_updated: last_updated.as_deref(),
+                    }],
+                    full: false,
+                    overflow: &[],
+                    truncated: None,
+                };
+                templates.render_domains("builtin/markdown", &input)?
+            };
+            if !archived.is_empty() {
+                output.push_str("\n\n### Archived\n");
+                for entry in &archived {
+                    output.push_str(&format!(
+                        "\n- [{}] {} {}: {} ({})",
+                        entry.record.id().unwrap_or("?"),
+                        entry.record.record_type(),
+                        format::get_record_summary(&entry.record),
+                        entry.removal.reason,
+                        format::format_time_ago(&entry.removal.removed_at)
+                    ));
+                }
+            }
             sections.push(output);
         }
     }
@@ -124,6 +214,7 @@ pub fn run(ctx: &RuntimeContext, args: &SearchArgs) -> Result<()> {
             "command": "search",
             "query": query,
             "total": total_matches,
+            "archived_total": total_archived,
             "domains": json_domains,
         }));
     } else if sections.is_empty() {
@@ -131,7 +222,11 @@ pub fn run(ctx: &RuntimeContext, args: &SearchArgs) -> Result<()> {
     } else {
         println!("{}", sections.join("\n\n"));
         let suffix = if total_matches == 1 { "" } else { "es" };
-        println!("\n{total_matches} match{suffix} found.");
+        if args.include_archived {
+            println!("\n{total_matches} match{suffix} found, {total_archived} archived.");
+        } else {
+            println!("\n{total_matches} match{suffix} found.");
+        }
     }
 
     Ok(())
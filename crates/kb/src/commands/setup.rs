@@ -1,13 +1,15 @@
 use anyhow::{Context, Result, bail};
 use std::fs;
 use std::path::PathBuf;
+use std::process::Command;
 
 use crate::cli::SetupArgs;
 use crate::context::RuntimeContext;
 use crate::output::*;
 
 const POST_COMMIT_HOOK: &str = r#"#!/bin/sh
-# kb: suggest recording learnings after each commit
+# kb: record Kb-* trailers, then suggest recording learnings after each commit
+kb ingest-commits HEAD 2>/dev/null || true
 kb learn --since HEAD~1 2>/dev/null || true
 "#;
 
@@ -21,11 +23,18 @@ fn install_git_hook(cwd: &std::path::Path) -> Result<()> {
 
     if hook_path.exists() {
         let existing = fs::read_to_string(&hook_path)?;
-        if existing.contains("kb learn") {
+        // Append whichever kb commands are missing, so older hooks get upgraded
+        let missing: Vec<&str> = POST_COMMIT_HOOK
+            .lines()
+            .filter(|l| {
+                l.starts_with("kb ")
+                    && !existing.contains(l.trim_end_matches(" 2>/dev/null || true"))
+            })
+            .collect();
+        if missing.is_empty() {
             return Ok(()); // Already installed
         }
-        // Append to existing hook
-        let updated = format!("{}\n{}", existing.trim_end(), POST_COMMIT_HOOK.trim());
+        let updated = format!("{}\n{}\n", existing.trim_end(), missing.join("\n"));
         fs::write(&hook_path, updated)?;
     } else {
         fs::write(&hook_path, POST_COMMIT_HOOK)?;
@@ -42,6 +51,50 @@ fn install_git_hook(cwd: &std::path::Path) -> Result<()> {
     Ok(())
 }
 
+/// Attribute routing expertise files through the kb driver. Written to
+/// `.git/info/attributes` so clones without the driver keep `merge=union`
+/// from the committed `.gitattributes`.
+const MERGE_DRIVER_ATTRIBUTES_LINE: &str = ".kb/expertise/*.jsonl merge=kb";
+
+fn install_merge_driver(cwd: &std::path::Path) -> Result<()> {
+    let git_dir = cwd.join(".git");
+    if !git_dir.is_dir() {
+        bail!("No .git directory found. Is this a git repository?");
+    }
+
+    for (key, value) in [
+        ("merge.kb.name", "kb expertise JSONL merge"),
+        ("merge.kb.driver", "kb merge-driver %O %A %B %P"),
+    ] {
+        let status = Command::new("git")
+            .args(["config", key, value])
+            .current_dir(cwd)
+            .status()
+            .context("Failed to run git config")?;
+        if !status.success() {
+            bail!("git config {key} failed");
+        }
+    }
+
+    let info_dir = git_dir.join("info");
+    fs::create_dir_all(&info_dir)?;
+    let attributes_path = info_dir.join("attributes");
+    let existing = fs::read_to_string(&attributes_path).unwrap_or_default();
+    if !existing.contains(MERGE_DRIVER_ATTRIBUTES_LINE) {
+        let separator = if !existing.is_empty() && !existing.ends_with('\n') {
+            "\n"
+        } else {
+            ""
+        };
+        fs::write(
+            &attributes_path,
+            format!("{existing}{separator}{MERGE_DRIVER_ATTRIBUTES_LINE}\n"),
+        )?;
+    }
+
+    Ok(())
+}
+
 fn get_provider_config(provider: &str) -> Result<(PathBuf, String)> {
     let (path, content) = match provider {
         "claude" => (
@@ -88,6 +141,11 @@ pub fn run(ctx: &RuntimeContext, args: &SetupArgs) -> Result<()> {
         actions.push("Installed post-commit git hook.".to_string());
     }
 
+    if args.merge_driver {
+        install_merge_driver(&ctx.cwd)?;
+        actions.push("Installed kb merge driver for .kb/expertise/*.jsonl.".to_string());
+    }
+
     if let Some(ref provider) = args.provider {
         let (rel_path, content) = get_provider_config(provider)?;
         let full_path = ctx.cwd.join(&rel_path);
@@ -111,11 +169,11 @@ pub fn run(ctx: &RuntimeContext, args: &SetupArgs) -> Result<()> {
         if ctx.json {
             output_json_error(
                 "setup",
-                "No action specified. Use --git-hook or --provider.",
+                "No action specified. Use --git-hook, --merge-driver or --provider.",
             );
             return Ok(());
         }
-        bail!("No action specified. Use --git-hook or --provider.");
+        bail!("No action specified. Use --git-hook, --merge-driver or --provider.");
     }
 
     if ctx.json {
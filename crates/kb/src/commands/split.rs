@@ -0,0 +1,246 @@
+use anyhow::{Context, Result, bail};
+
+use crate::cli::SplitArgs;
+use crate::context::RuntimeContext;
+use crate::output::*;
+use kb_core::error::KbError;
+use kb_core::split::{self, SplitPlan};
+use kb_core::types::{ExpertiseRecord, KbConfig};
+use kb_core::{config, format, health, lock, storage};
+
+pub fn run(ctx: &RuntimeContext, args: &SplitArgs) -> Result<()> {
+    config::ensure_kb_dir(&ctx.cwd)?;
+    let cfg = config::read_config(&ctx.cwd)?;
+
+    if let Some(ref plan) = args.apply {
+        return run_apply(ctx, args, &cfg, plan);
+    }
+    let Some(ref domain) = args.domain else {
+        bail!("Specify a domain to split.");
+    };
+    config::ensure_domain_exists(&cfg, domain)?;
+
+    let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
+    let records = storage::read_expertise_file(&file_path)?;
+    let plan = split::propose_split(
+        domain,
+        &records,
+        &cfg.domains,
+        args.max_groups,
+        args.min_size,
+    );
+    let grouped: usize = plan.groups.iter().map(|g| g.ids.len()).sum();
+    let remaining = records.len() - grouped;
+
+    if let Some(ref plan_file) = args.plan {
+        let path = ctx.cwd.join(plan_file);
+        std::fs::write(&path, serde_json::to_string_pretty(&plan)? + "\n")
+            .with_context(|| format!("Failed to write plan file: {plan_file}"))?;
+        if ctx.json {
+            output_json(&serde_json::json!({
+                "success": true,
+                "command": "split",
+                "plan": plan_file,
+                "domain": domain,
+                "groups": plan.groups,
+            }));
+        } else {
+            print_success(&format!(
+                "Wrote {} proposed sub-domain(s) to {plan_file}. Apply with `kb split --apply {plan_file}`.",
+                plan.groups.len()
+            ));
+        }
+        return Ok(());
+    }
+
+    if !ctx.json {
+        print_plan(&plan, &records, remaining);
+    }
+
+    let apply = args.auto && !args.dry_run;
+    let moved = if apply && !plan.groups.is_empty() {
+        match apply_plan(ctx, &plan) {
+            Ok(moved) => moved,
+            Err(e) if ctx.json => {
+                output_json_error("split", &format!("{e:#}"));
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        }
+    } else {
+        0
+    };
+
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "split",
+            "domain": domain,
+            "dry_run": !apply,
+            "moved": moved,
+            "remaining": remaining,
+            "groups": plan.groups,
+        }));
+    } else if plan.groups.is_empty() {
+        print_warning(&format!(
+            "No sub-domains of at least {} records found in \"{domain}\". Try a lower --min-size.",
+            args.min_size
+        ));
+    } else if !apply {
+        println!();
+        print_warning(&format!(
+            "{} proposed sub-domain(s). Apply with --auto, or write a plan with --plan <file>.",
+            plan.groups.len()
+        ));
+    } else {
+        print_success(&format!(
+            "Moved {moved} record(s) from \"{domain}\" into {} sub-domain(s).",
+            plan.groups.len()
+        ));
+    }
+
+    Ok(())
+}
+
+fn print_plan(plan: &SplitPlan, records: &[ExpertiseRecord], remaining: usize) {
+    println!(
+        "Proposed split of \"{}\" ({} records):",
+        plan.domain,
+        records.len()
+    );
+    for group in &plan.groups {
+        println!(
+            "  {}: {} records sharing {} \"{}\"",
+            group.domain,
+            group.ids.len(),
+            group.basis.as_str(),
+            group.key
+        );
+        for id in &group.ids {
+            if let Some(record) = records.iter().find(|r| r.id() == Some(id.as_str())) {
+                println!(
+                    "    {id} {}: {}",
+                    record.record_type(),
+                    format::get_record_summary(record)
+                );
+            }
+        }
+    }
+    println!("  {}: {remaining} records stay", plan.domain);
+}
+
+/// Move the plan's records into new target domains and return the number of
+/// records moved. Records keep their IDs, so links between them stay valid.
+/// Everything is checked before anything is written, and the source domain is
+/// rewritten last; if a write fails, the new domains are removed again.
+fn apply_plan(ctx: &RuntimeContext, plan: &SplitPlan) -> Result<usize> {
+    let source_path = config::get_expertise_path(&plan.domain, &ctx.cwd)?;
+    let moved = lock::with_file_lock(&source_path, || {
+        let cfg = config::read_config(&ctx.cwd)?;
+        let mut records = storage::read_expertise_file(&source_path)?;
+        let mut moved = split::take_groups(plan, &mut records, &cfg.domains)?;
+
+        let mut targets = Vec::new();
+        for (domain, group) in &moved {
+            health::check_hard_limit(&cfg.governance, domain, 0, group.len())?;
+            let target_path = config::get_expertise_path(domain, &ctx.cwd)?;
+            if target_path.exists() {
+                return Err(KbError::ValidationError(format!(
+                    "{} already exists; remove it or pick another name for {domain}",
+                    target_path.display()
+                )));
+            }
+            targets.push(target_path);
+        }
+
+        let mut written = Vec::new();
+        let result = (|| {
+            for ((_, group), target_path) in moved.iter_mut().zip(&targets) {
+                written.push(target_path);
+                storage::write_expertise_file(target_path, group)?;
+            }
+            let mut updated = cfg.clone();
+            updated
+                .domains
+                .extend(moved.iter().map(|(domain, _)| domain.clone()));
+            config::write_config(&updated, &ctx.cwd)?;
+            storage::write_expertise_file(&source_path, &mut records)
+        })();
+        if let Err(e) = result {
+            // The source is untouched; drop the half-created targets
+            for path in written {
+                let _ = std::fs::remove_file(path);
+            }
+            let _ = config::write_config(&cfg, &ctx.cwd);
+            return Err(e);
+        }
+        Ok(moved.iter().map(|(_, group)| group.len()).sum())
+    })
+    .with_context(|| format!("Failed to split {}", plan.domain))?;
+    Ok(moved)
+}
+
+fn run_apply(
+    ctx: &RuntimeContext,
+    args: &SplitArgs,
+    cfg: &KbConfig,
+    plan_file: &str,
+) -> Result<()> {
+    let path = ctx.cwd.join(plan_file);
+    let parsed = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read plan file: {plan_file}"))
+        .and_then(|content| {
+            serde_json::from_str::<SplitPlan>(&content)
+                .with_context(|| format!("Invalid plan file: {plan_file}"))
+        })
+        .and_then(|plan| {
+            config::ensure_domain_exists(cfg, &plan.domain)?;
+            Ok(plan)
+        });
+    let plan = match parsed {
+        Ok(p) => p,
+        Err(e) => {
+            if ctx.json {
+                output_json_error("split", &format!("{e:#}"));
+                return Ok(());
+            }
+            return Err(e);
+        }
+    };
+
+    let moved = if args.dry_run {
+        0
+    } else {
+        match apply_plan(ctx, &plan) {
+            Ok(moved) => moved,
+            Err(e) if ctx.json => {
+                output_json_error("split", &format!("{e:#}"));
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "split",
+            "domain": plan.domain,
+            "dry_run": args.dry_run,
+            "moved": moved,
+            "groups": plan.groups,
+        }));
+    } else if args.dry_run {
+        print_warning(&format!(
+            "{} sub-domain(s) in {plan_file}. Run without --dry-run to apply.",
+            plan.groups.len()
+        ));
+    } else {
+        print_success(&format!(
+            "Applied {plan_file}: moved {moved} record(s) from \"{}\" into {} sub-domain(s).",
+            plan.domain,
+            plan.groups.len()
+        ));
+    }
+    Ok(())
+}
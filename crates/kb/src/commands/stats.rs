@@ -0,0 +1,132 @@
+use anyhow::Result;
+
+use crate::cli::{StatsCommands, StatsUsageArgs};
+use crate::context::RuntimeContext;
+use crate::output::*;
+use kb_core::usage::{self, RecordUsage, UsageOptions};
+use kb_core::{access_log, config, storage};
+
+pub fn run(ctx: &RuntimeContext, cmd: &StatsCommands) -> Result<()> {
+    config::ensure_kb_dir(&ctx.cwd)?;
+
+    match cmd {
+        StatsCommands::Usage(args) => run_usage(ctx, args),
+    }
+}
+
+fn run_usage(ctx: &RuntimeContext, args: &StatsUsageArgs) -> Result<()> {
+    let cfg = config::read_config(&ctx.cwd)?;
+
+    let domains: Vec<String> = if let Some(ref domain) = args.domain {
+        config::ensure_domain_exists(&cfg, domain)?;
+        vec![domain.clone()]
+    } else {
+        cfg.domains.clone()
+    };
+
+    let mut records = Vec::new();
+    for domain in &domains {
+        let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
+        for record in storage::read_expertise_file(&file_path)? {
+            records.push((domain.clone(), record));
+        }
+    }
+
+    let entries = access_log::query_log(
+        &ctx.cwd,
+        &access_log::AccessLogFilter {
+            domain: args.domain.clone(),
+            ..Default::default()
+        },
+    )?;
+
+    let report = usage::compute_usage(
+        &entries,
+        &records,
+        &UsageOptions {
+            limit: args.limit,
+            unused_days: args.days,
+            now: chrono::Utc::now(),
+        },
+    );
+
+    if ctx.json {
+        output_json(&serde_json::json!({
+            "success": true,
+            "command": "stats usage",
+            "report": report,
+        }));
+        return Ok(());
+    }
+
+    println!(
+        "Usage: {} access log entries, {} records",
+        report.total_calls, report.total_records
+    );
+
+    print_records("Most retrieved", &report.most_retrieved);
+    print_records("Least retrieved", &report.least_retrieved);
+    print_records(
+        &format!("Not accessed in {} days", report.unused_days),
+        &report.unused,
+    );
+
+    println!("\nZero-result queries:");
+    if report.zero_result_queries.is_empty() {
+        println!("  (none)");
+    }
+    for q in &report.zero_result_queries {
+        println!(
+            "  {:>5}  {:<8} {:<12} {}",
+            q.count,
+            q.tool,
+            q.domain.as_deref().unwrap_or("-"),
+            q.query
+        );
+    }
+
+    println!("\nCalls by domain:");
+    for c in &report.by_domain {
+        println!("  {:>5}  {}", c.count, c.name);
+    }
+
+    println!("\nCalls by tool:");
+    for c in &report.by_tool {
+        println!("  {:>5}  {}", c.count, c.name);
+    }
+
+    println!("\nSessions:");
+    if report.by_session.is_empty() {
+        println!("  (none)");
+    }
+    for s in &report.by_session {
+        println!(
+            "  {}  calls={} retrieved={} zero-result={} last={}",
+            s.session_id,
+            s.calls,
+            s.records_retrieved,
+            s.zero_result_queries,
+            s.last_seen.format("%Y-%m-%d %H:%M:%S"),
+        );
+    }
+
+    Ok(())
+}
+
+fn print_records(title: &str, rows: &[RecordUsage]) {
+    println!("\n{title}:");
+    if rows.is_empty() {
+        println!("  (none)");
+        return;
+    }
+    for r in rows {
+        let last = r
+            .last_accessed
+            .map(|t| t.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "never".to_string());
+        println!(
+            "  {:>5}  {} [{}/{}] {} (last: {last})",
+            r.retrievals, r.id, r.domain, r.record_type, r.summary
+        );
+    }
+}
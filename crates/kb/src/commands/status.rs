@@ -3,33 +3,119 @@ use anyhow::Result;
 use crate::context::RuntimeContext;
 use crate::output::*;
 use kb_core::format::{self, DomainStat};
-use kb_core::{config, storage};
+use kb_core::types::ExpertiseRecord;
+use kb_core::{churn, config, git, health, storage};
+
+/// Records still scoped to one branch, awaiting `kb promote`.
+struct PendingBranch {
+    branch: String,
+    merged: bool,
+    records: Vec<(String, ExpertiseRecord)>,
+}
+
+fn pending_branches(
+    ctx: &RuntimeContext,
+    records: Vec<(String, ExpertiseRecord)>,
+) -> Vec<PendingBranch> {
+    let mut pending: Vec<PendingBranch> = Vec::new();
+    for (domain, record) in records {
+        let Some(branch) = record.branch().map(str::to_string) else {
+            continue;
+        };
+        match pending.iter_mut().find(|p| p.branch == branch) {
+            Some(p) => p.records.push((domain, record)),
+            None => pending.push(PendingBranch {
+                branch,
+                merged: false,
+                records: vec![(domain, record)],
+            }),
+        }
+    }
+
+    let current = git::current_branch(&ctx.cwd);
+    for p in pending.iter_mut() {
+        p.merged = current.as_deref() != Some(p.branch.as_str())
+            && git::ref_exists(&ctx.cwd, &p.branch)
+            && git::is_merged(&ctx.cwd, &p.branch);
+    }
+    pending.sort_by(|a, b| a.branch.cmp(&b.branch));
+    pending
+}
 
 pub fn run(ctx: &RuntimeContext) -> Result<()> {
     config::ensure_kb_dir(&ctx.cwd)?;
     let cfg = config::read_config(&ctx.cwd)?;
 
     let mut stats: Vec<DomainStat> = Vec::new();
+    let mut scoped: Vec<(String, ExpertiseRecord)> = Vec::new();
+    let mut all: Vec<(String, Vec<ExpertiseRecord>)> = Vec::new();
+    // Records past their shelf life, per domain
+    let mut shelf_stale: Vec<usize> = Vec::new();
+    let now = chrono::Utc::now();
 
     for domain in &cfg.domains {
         let file_path = config::get_expertise_path(domain, &ctx.cwd)?;
         let records = storage::read_expertise_file(&file_path)?;
         let last_updated = records.iter().map(|r| r.recorded_at().to_string()).max();
+        shelf_stale.push(
+            records
+                .iter()
+                .filter(|r| health::is_record_stale(r, domain, now, &cfg))
+                .count(),
+        );
         stats.push(DomainStat {
             domain: domain.clone(),
             count: records.len(),
             last_updated,
         });
+        scoped.extend(
+            records
+                .iter()
+                .filter(|r| r.branch().is_some())
+                .map(|r| (domain.clone(), r.clone())),
+        );
+        all.push((domain.clone(), records));
     }
+    let pending = pending_branches(ctx, scoped);
+    let churn_stale: Vec<churn::RecordChurn> = churn::compute_churn(&ctx.cwd, &all)
+        .into_iter()
+        .filter(|c| c.is_stale(&cfg.churn))
+        .collect();
+    let stale_in = |domain: &str| churn_stale.iter().filter(|c| c.domain == domain).count();
 
     if ctx.json {
         let domain_data: Vec<serde_json::Value> = stats
             .iter()
-            .map(|s| {
+            .zip(&shelf_stale)
+            .map(|(s, stale)| {
                 serde_json::json!({
                     "domain": s.domain,
                     "count": s.count,
                     "last_updated": s.last_updated,
+                    "stale": stale,
+                    "churn_stale": stale_in(&s.domain),
+                })
+            })
+            .collect();
+        let pending_data: Vec<serde_json::Value> = pending
+            .iter()
+            .map(|p| {
+                let records: Vec<serde_json::Value> = p
+                    .records
+                    .iter()
+                    .map(|(domain, r)| {
+                        serde_json::json!({
+                            "domain": domain,
+                            "id": r.id(),
+                            "type": r.record_type().as_str(),
+                            "summary": format::get_record_summary(r),
+                        })
+                    })
+                    .collect();
+                serde_json::json!({
+                    "branch": p.branch,
+                    "merged": p.merged,
+                    "records": records,
                 })
             })
             .collect();
@@ -37,9 +123,44 @@ pub fn run(ctx: &RuntimeContext) -> Result<()> {
             "success": true,
             "command": "status",
             "domains": domain_data,
+            "pending_branches": pending_data,
         }));
     } else {
         println!("{}", format::format_status_output(&stats, &cfg.governance));
+        let total_stale: usize = shelf_stale.iter().sum();
+        if total_stale > 0 {
+            println!();
+            print_warning(&format!(
+                "{total_stale} record(s) are past their shelf life. Run `kb prune --dry-run --explain`."
+            ));
+        }
+        if !churn_stale.is_empty() {
+            println!();
+            print_warning(&format!(
+                "{} record(s) reference files that changed heavily since they were written. Run `kb review --stale`.",
+                churn_stale.len()
+            ));
+        }
+        if !pending.is_empty() {
+            println!();
+            println!("Branch-local records (pending promotion):");
+            for p in &pending {
+                let hint = if p.merged {
+                    format!(" \u{2014} merged, run `kb promote --branch {}`", p.branch)
+                } else {
+                    String::new()
+                };
+                println!("  {} ({} records){hint}", p.branch, p.records.len());
+                for (domain, r) in &p.records {
+                    println!(
+                        "    [{domain}] {} {}: {}",
+                        r.id().unwrap_or("?"),
+                        r.record_type(),
+                        format::get_record_summary(r)
+                    );
+                }
+            }
+        }
     }
 
     Ok(())
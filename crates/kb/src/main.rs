@@ -38,15 +38,27 @@ fn main() {
         Commands::Prune(args) => commands::prune::run(&ctx, args),
         Commands::Doctor(args) => commands::doctor::run(&ctx, args),
         Commands::Ready(args) => commands::ready::run(&ctx, args),
+        Commands::Review(args) => commands::review::run(&ctx, args),
         Commands::Learn(args) => commands::learn::run(&ctx, args),
         Commands::Compact(args) => commands::compact::run(&ctx, args),
+        Commands::Split(args) => commands::split::run(&ctx, args),
+        Commands::Promote(args) => commands::promote::run(&ctx, args),
         Commands::Setup(args) => commands::setup::run(&ctx, args),
         Commands::Onboard(args) => commands::onboard::run(&ctx, args),
         Commands::Sync(args) => commands::sync_cmd::run(&ctx, args),
         Commands::Update(args) => commands::update::run(&ctx, args),
         Commands::Diff(args) => commands::diff::run(&ctx, args),
+        Commands::Log(args) => commands::log::run(&ctx, args),
+        Commands::Blame(args) => commands::blame::run(&ctx, args),
+        Commands::IngestCommits(args) => commands::ingest_commits::run(&ctx, args),
+        Commands::MergeDriver(args) => commands::merge_driver::run(&ctx, args),
         Commands::AccessLog(args) => commands::access_log::run(&ctx, args),
+        Commands::Archive(cmd) => commands::archive::run(&ctx, cmd),
         Commands::Session(cmd) => commands::session::run(&ctx, cmd),
+        Commands::Stats(cmd) => commands::stats::run(&ctx, cmd),
+        Commands::Gaps(args) => commands::gaps::run(&ctx, args),
+        Commands::Export(args) => commands::export::run(&ctx, args),
+        Commands::Import(args) => commands::import::run(&ctx, args),
         Commands::Check(args) => commands::check::run(&ctx, args),
         Commands::Guard => commands::guard::run(&ctx),
         Commands::Mcp => unreachable!(),
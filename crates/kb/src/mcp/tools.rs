@@ -5,9 +5,11 @@ use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
 use rust_mcp_sdk::schema::{CallToolResult, TextContent, schema_utils::CallToolError};
 use rust_mcp_sdk::tool_box;
 
+use kb_core::template::{TemplateDomain, TemplateInput, Templates};
 use kb_core::types::*;
 use kb_core::{
-    access_log, changelog, check, config, filter, format, lock, resolve, search, session, storage,
+    access_log, archive, budget, changelog, check, config, filter, format, git, health, lock,
+    resolve, search, session, storage, tokenizer,
 };
 
 // ── Helper ───────────────────────────────────────────────────────────────────
@@ -69,12 +71,47 @@ fn log_access(
             domain: domain.map(|s| s.to_string()),
             query: query.map(|s| s.to_string()),
             entry_id: entry_id.map(|s| s.to_string()),
+            entry_ids: None,
             result_count,
             signal: None,
         },
     );
 }
 
+/// Collect the IDs of retrieved records
+fn record_ids(records: &[&ExpertiseRecord]) -> Vec<String> {
+    records
+        .iter()
+        .filter_map(|r| r.id().map(|id| id.to_string()))
+        .collect()
+}
+
+/// Log a retrieval along with the IDs of the records it returned
+fn log_retrieval(
+    cwd: &Path,
+    session_id: Option<&str>,
+    tool: &str,
+    domain: Option<&str>,
+    query: Option<&str>,
+    ids: Vec<String>,
+) {
+    let Some(sid) = session_id else { return };
+    let _ = access_log::append(
+        cwd,
+        &access_log::AccessLogEntry {
+            session_id: sid.to_string(),
+            timestamp: chrono::Utc::now(),
+            tool: tool.to_string(),
+            domain: domain.map(|s| s.to_string()),
+            query: query.map(|s| s.to_string()),
+            entry_id: None,
+            result_count: Some(ids.len()),
+            entry_ids: Some(ids),
+            signal: None,
+        },
+    );
+}
+
 /// Log a mutation to the changelog
 fn log_change(
     cwd: &Path,
@@ -111,6 +148,14 @@ pub struct KbPrimeTool {
     pub label: Option<String>,
     /// Domains to prime (omit for all)
     pub domains: Option<Vec<String>>,
+    /// Token budget (default 4000); records that do not fit are dropped by priority
+    pub budget: Option<u64>,
+    /// Include every record regardless of the budget
+    pub no_limit: Option<bool>,
+    /// Only include records relevant to these file paths
+    pub files: Option<Vec<String>>,
+    /// Render with this output template (e.g. "compact", "markdown") instead of JSON
+    pub template: Option<String>,
 }
 
 impl KbPrimeTool {
@@ -128,19 +173,69 @@ impl KbPrimeTool {
             cfg.domains.clone()
         };
 
-        let mut domain_data: Vec<(String, usize, Vec<ExpertiseRecord>)> = Vec::new();
+        let mut domain_data: Vec<(String, Vec<ExpertiseRecord>)> = Vec::new();
         for domain in &target_domains {
             let file_path = config::get_expertise_path(domain, &cwd_buf).map_err(map_err)?;
             let records = storage::read_expertise_file(&file_path).map_err(map_err)?;
-            let count = records.len();
-            domain_data.push((domain.clone(), count, records));
+            domain_data.push((domain.clone(), records));
         }
 
-        let mcp_input: Vec<(String, usize, &[ExpertiseRecord])> = domain_data
+        // As in `kb prime --files`, domains without matches are skipped
+        let filtered: Vec<budget::DomainRecords<'_>> = domain_data
             .iter()
-            .map(|(d, c, recs)| (d.clone(), *c, recs.as_slice()))
+            .filter_map(|(domain, records)| {
+                let records = match self.files {
+                    Some(ref files) => git::filter_by_context(records, files),
+                    None => records.iter().collect(),
+                };
+                if self.files.is_some() && records.is_empty() {
+                    return None;
+                }
+                Some(budget::DomainRecords {
+                    domain: domain.clone(),
+                    records,
+                })
+            })
             .collect();
-        let primed = format::format_mcp_output(&mcp_input);
+
+        let estimator = tokenizer::estimator_for(cfg.tokenizer).map_err(map_err)?;
+        // Same default as `kb prime --json`
+        let limit = (!self.no_limit.unwrap_or(false))
+            .then(|| self.budget.map_or(budget::DEFAULT_BUDGET, |b| b as usize));
+        let result = limit
+            .map(|limit| budget::apply_budget(&filtered, limit, &cfg.budget, estimator.as_ref()));
+        let kept = result.as_ref().map_or(&filtered, |r| &r.kept);
+        let stats = budget::domain_stats(&filtered, kept, estimator.as_ref());
+        let primed = match self.template {
+            Some(ref name) => {
+                let last_updated: HashMap<&str, Option<String>> = domain_data
+                    .iter()
+                    .map(|(domain, records)| {
+                        let newest = records.iter().map(|r| r.recorded_at().to_string()).max();
+                        (domain.as_str(), newest)
+                    })
+                    .collect();
+                let input = TemplateInput {
+                    domains: kept
+                        .iter()
+                        .map(|dr| TemplateDomain {
+                            name: &dr.domain,
+                            records: &dr.records,
+                            last_updated: last_updated[dr.domain.as_str()].as_deref(),
+                        })
+                        .collect(),
+                    full: false,
+                    overflow: &[],
+                    truncated: result.as_ref().filter(|r| r.dropped_count > 0).map(|r| {
+                        let domains: std::collections::HashSet<&str> =
+                            r.dropped.iter().map(|d| d.domain.as_str()).collect();
+                        (r.dropped_count, domains.len())
+                    }),
+                };
+                Templates::load(cwd).render(name, &input).map_err(map_err)?
+            }
+            None => format::format_mcp_output(kept, &stats, limit),
+        };
 
         let sess = session::start_session(cwd, self.label.as_deref()).map_err(map_err)?;
         let session_id = sess.id.clone();
@@ -155,7 +250,8 @@ impl KbPrimeTool {
                 domain: None,
                 query: None,
                 entry_id: None,
-                result_count: Some(domain_data.iter().map(|(_, c, _)| c).sum()),
+                entry_ids: None,
+                result_count: Some(stats.iter().map(|s| s.kept).sum()),
                 signal: None,
             },
         )
@@ -195,6 +291,7 @@ impl KbSessionResumeTool {
                 domain: None,
                 query: None,
                 entry_id: None,
+                entry_ids: None,
                 result_count: None,
                 signal: None,
             },
@@ -228,6 +325,7 @@ impl KbSessionEndTool {
                 domain: None,
                 query: None,
                 entry_id: None,
+                entry_ids: None,
                 result_count: None,
                 signal: None,
             },
@@ -273,14 +371,13 @@ impl KbQueryTool {
             records.iter().collect()
         };
 
-        log_access(
+        log_retrieval(
             cwd,
             session_id,
             "query",
             Some(&self.domain),
-            self.record_type.as_deref(),
             None,
-            Some(filtered.len()),
+            record_ids(&filtered),
         );
 
         let result = serde_json::json!({
@@ -307,6 +404,7 @@ impl KbQueryAllTool {
         let cfg = config::read_config(&cwd_buf).map_err(map_err)?;
 
         let mut domains_json: Vec<serde_json::Value> = Vec::new();
+        let mut ids: Vec<String> = Vec::new();
         for domain in &cfg.domains {
             let file_path = config::get_expertise_path(domain, &cwd_buf).map_err(map_err)?;
             let records = storage::read_expertise_file(&file_path).map_err(map_err)?;
@@ -315,21 +413,14 @@ impl KbQueryAllTool {
                 "count": records.len(),
                 "records": records,
             }));
+            ids.extend(
+                records
+                    .iter()
+                    .filter_map(|r| r.id().map(|id| id.to_string())),
+            );
         }
 
-        let total_count: usize = domains_json
-            .iter()
-            .filter_map(|d| d.get("count").and_then(|c| c.as_u64()))
-            .sum::<u64>() as usize;
-        log_access(
-            cwd,
-            session_id,
-            "query_all",
-            None,
-            None,
-            None,
-            Some(total_count),
-        );
+        log_retrieval(cwd, session_id, "query_all", None, None, ids);
 
         json_result(&serde_json::json!({ "domains": domains_json }))
     }
@@ -368,6 +459,7 @@ impl KbSearchTool {
 
         let mut results_json: Vec<serde_json::Value> = Vec::new();
         let mut total: usize = 0;
+        let mut matched_ids: Vec<String> = Vec::new();
 
         for domain in &domains {
             let file_path = config::get_expertise_path(domain, &cwd_buf).map_err(map_err)?;
@@ -383,6 +475,11 @@ impl KbSearchTool {
             let matches: Vec<&ExpertiseRecord> = search::search_records(&records, &self.query);
             if !matches.is_empty() {
                 total += matches.len();
+                matched_ids.extend(
+                    matches
+                        .iter()
+                        .filter_map(|r| r.id().map(|id| id.to_string())),
+                );
                 results_json.push(serde_json::json!({
                     "domain": domain,
                     "matches": matches,
@@ -390,14 +487,13 @@ impl KbSearchTool {
             }
         }
 
-        log_access(
+        log_retrieval(
             cwd,
             session_id,
             "search",
             self.domain.as_deref(),
             Some(&self.query),
-            None,
-            Some(total),
+            matched_ids,
         );
 
         json_result(&serde_json::json!({
@@ -428,6 +524,8 @@ pub struct KbRecordTool {
     pub rationale: Option<String>,
     /// Tags
     pub tags: Option<Vec<String>>,
+    /// Record even if the domain is at its hard limit
+    pub force: Option<bool>,
 }
 
 impl KbRecordTool {
@@ -442,6 +540,7 @@ impl KbRecordTool {
         config::ensure_domain_exists(&cfg, &self.domain).map_err(map_err)?;
 
         let recorded_at = now_iso();
+        let branch = git::scoped_branch(&cwd_buf, cfg.main_branch.as_deref());
         let classification = Classification::Tactical;
         let tags = self.tags.clone();
 
@@ -451,6 +550,8 @@ impl KbRecordTool {
                 content: self.description.clone(),
                 classification,
                 recorded_at,
+                updated_at: None,
+                branch: branch.clone(),
                 evidence: None,
                 tags,
                 relates_to: None,
@@ -469,6 +570,8 @@ impl KbRecordTool {
                     files: None,
                     classification,
                     recorded_at,
+                    updated_at: None,
+                    branch: branch.clone(),
                     evidence: None,
                     tags,
                     relates_to: None,
@@ -485,6 +588,8 @@ impl KbRecordTool {
                     .unwrap_or_else(|| "unresolved".to_string()),
                 classification,
                 recorded_at,
+                updated_at: None,
+                branch: branch.clone(),
                 evidence: None,
                 tags,
                 relates_to: None,
@@ -507,6 +612,8 @@ impl KbRecordTool {
                     date: None,
                     classification,
                     recorded_at,
+                    updated_at: None,
+                    branch: branch.clone(),
                     evidence: None,
                     tags,
                     relates_to: None,
@@ -526,6 +633,8 @@ impl KbRecordTool {
                     files: None,
                     classification,
                     recorded_at,
+                    updated_at: None,
+                    branch: branch.clone(),
                     evidence: None,
                     tags,
                     relates_to: None,
@@ -544,6 +653,8 @@ impl KbRecordTool {
                     description: self.description.clone(),
                     classification,
                     recorded_at,
+                    updated_at: None,
+                    branch: branch.clone(),
                     evidence: None,
                     tags,
                     relates_to: None,
@@ -564,11 +675,15 @@ impl KbRecordTool {
                 if record.is_named_type() {
                     let mut records = existing;
                     records[idx] = record.clone();
+                    records[idx].touch();
                     storage::write_expertise_file(&file_path, &mut records)?;
                     return Ok(());
                 }
                 return Ok(());
             }
+            if !self.force.unwrap_or(false) {
+                health::check_hard_limit(&cfg.governance, &self.domain, existing.len(), 1)?;
+            }
             storage::append_record(&file_path, &mut record)?;
             Ok(())
         })
@@ -676,6 +791,7 @@ impl KbEditTool {
                 }
             }
 
+            records[idx].touch();
             let updated = records[idx].clone();
             storage::write_expertise_file(&file_path, &mut records)?;
             Ok(updated)
@@ -715,7 +831,10 @@ impl KbEditTool {
     }
 }
 
-#[mcp_tool(name = "kb_delete", description = "Delete a knowledge entry.")]
+#[mcp_tool(
+    name = "kb_delete",
+    description = "Delete a knowledge entry. The entry is moved to the domain archive and can be restored with `kb archive restore`."
+)]
 #[derive(Debug, serde::Deserialize, serde::Serialize, JsonSchema)]
 pub struct KbDeleteTool {
     /// Domain containing the entry
@@ -738,16 +857,24 @@ impl KbDeleteTool {
         let file_path = config::get_expertise_path(&self.domain, &cwd_buf).map_err(map_err)?;
 
         let records = storage::read_expertise_file(&file_path).map_err(map_err)?;
-        let (idx, matched) =
-            resolve::resolve_record_id(&records, &self.entry_id).map_err(map_err)?;
+        let (_, matched) = resolve::resolve_record_id(&records, &self.entry_id).map_err(map_err)?;
         let record_id = matched.id().unwrap_or("unknown").to_string();
         let summary = format::get_record_summary(matched);
 
+        // Archive first, as in `kb delete`
         lock::with_file_lock(&file_path, || {
             let mut records = storage::read_expertise_file(&file_path)?;
-            records.remove(idx);
-            storage::write_expertise_file(&file_path, &mut records)?;
-            Ok(())
+            let idx = resolve::resolve_record_id(&records, &record_id)?.0;
+            let removed = records.remove(idx);
+            archive::archive_records(
+                cwd,
+                &self.domain,
+                &[archive::ArchivedRecord {
+                    record: removed,
+                    removal: archive::Removal::new("delete", "deleted", session_id),
+                }],
+            )?;
+            storage::write_expertise_file(&file_path, &mut records)
         })
         .map_err(map_err)?;
 
@@ -869,6 +996,7 @@ impl KbFeedbackTool {
                     domain: self.domain.clone(),
                     query: None,
                     entry_id: Some(self.entry_id.clone()),
+                    entry_ids: None,
                     result_count: None,
                     signal: Some(self.signal.clone()),
                 },
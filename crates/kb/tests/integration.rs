@@ -6,7 +6,7 @@ use tempfile::TempDir;
 // ── Helpers ──────────────────────────────────────────────────────────────────
 
 fn kb() -> Command {
-    Command::cargo_bin("kb").unwrap()
+    assert_cmd::cargo::cargo_bin_cmd!("kb")
 }
 
 fn init_project() -> TempDir {
@@ -495,6 +495,8 @@ fn record_with_outcome() {
     assert_eq!(outcome["status"], "success");
     assert_eq!(outcome["duration"], serde_json::json!(150.0));
     assert_eq!(outcome["agent"], "claude");
+    // Timestamped, so it counts as evidence after a reclassification
+    assert!(outcome["recorded_at"].is_string());
 }
 
 #[test]
@@ -2297,3 +2299,2325 @@ fn onboard_is_idempotent() {
         .success()
         .stdout(predicate::str::contains("already exists"));
 }
+
+// ── Stats ───────────────────────────────────────────────────────────────────
+
+#[test]
+fn stats_usage_reports_retrievals_and_zero_results() {
+    let dir = init_project_with_domain("test");
+    record_convention(&dir, "test", "Used convention");
+    record_convention(&dir, "test", "Unused convention");
+    let id = get_record_id(&dir, "test", 0);
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let log = format!(
+        "{{\"session_id\":\"kb-aaaaaa\",\"timestamp\":\"{now}\",\"tool\":\"query\",\"domain\":\"test\",\"entry_ids\":[\"{id}\"],\"result_count\":1}}\n\
+         {{\"session_id\":\"kb-aaaaaa\",\"timestamp\":\"{now}\",\"tool\":\"search\",\"domain\":\"test\",\"query\":\"missing\",\"entry_ids\":[],\"result_count\":0}}\n"
+    );
+    fs::write(dir.path().join(".kb/access.jsonl"), log).unwrap();
+
+    let output = kb()
+        .args(["--json", "stats", "usage"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let report = &json["report"];
+    assert_eq!(report["total_calls"], 2);
+    assert_eq!(report["most_retrieved"][0]["id"], id.as_str());
+    assert_eq!(report["most_retrieved"][0]["retrievals"], 1);
+    assert_eq!(report["zero_result_queries"][0]["query"], "missing");
+    assert_eq!(report["by_session"][0]["calls"], 2);
+
+    kb().args(["stats", "usage"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Most retrieved"))
+        .stdout(predicate::str::contains("missing"));
+}
+
+// ── Gaps ────────────────────────────────────────────────────────────────────
+
+#[test]
+fn gaps_clusters_oracle_and_empty_searches() {
+    let dir = init_project_with_domain("db");
+    let now = chrono::Utc::now().to_rfc3339();
+    let log = format!(
+        "{{\"session_id\":\"kb-aaaaaa\",\"timestamp\":\"{now}\",\"tool\":\"oracle\",\"domain\":\"db\",\"query\":\"How do we run migrations?\",\"result_count\":0}}\n\
+         {{\"session_id\":\"kb-bbbbbb\",\"timestamp\":\"{now}\",\"tool\":\"search\",\"domain\":\"db\",\"query\":\"run migrations\",\"entry_ids\":[],\"result_count\":0}}\n\
+         {{\"session_id\":\"kb-bbbbbb\",\"timestamp\":\"{now}\",\"tool\":\"search\",\"domain\":\"db\",\"query\":\"pool size\",\"entry_ids\":[\"mx-000000\"],\"result_count\":1}}\n"
+    );
+    fs::write(dir.path().join(".kb/access.jsonl"), log).unwrap();
+
+    let output = kb()
+        .args(["--json", "gaps", "--drafts", "drafts"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["total"], 2);
+    assert_eq!(json["domains"][0]["domain"], "db");
+    assert_eq!(json["domains"][0]["clusters"][0]["count"], 2);
+    assert_eq!(json["domains"][0]["clusters"][0]["sessions"], 2);
+
+    // Draft stubs can be fed straight into batch record
+    let drafts = dir.path().join("drafts/db.json");
+    assert!(drafts.exists());
+    kb().args(["record", "db", "--batch", drafts.to_str().unwrap()])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let records = query_json(&dir, "db");
+    assert_eq!(
+        records["domains"][0]["records"].as_array().unwrap().len(),
+        1
+    );
+}
+
+// ── Prime token stats ───────────────────────────────────────────────────────
+
+#[test]
+fn prime_stats_reports_tokens_per_domain() {
+    let dir = init_project_with_domain("test");
+    record_convention(&dir, "test", "Use snake_case for modules");
+    record_convention(&dir, "test", "ログは必ず構造化する");
+
+    let output = kb()
+        .args(["--json", "prime", "--stats"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let stats = &json["stats"];
+    assert_eq!(stats["tokenizer"], "heuristic");
+    assert_eq!(stats["domains"][0]["domain"], "test");
+    assert_eq!(stats["domains"][0]["records"], 2);
+    assert!(stats["domains"][0]["tokens"].as_u64().unwrap() > 0);
+    assert!(stats["output_tokens"].as_u64().unwrap() > 0);
+
+    kb().args(["prime", "--stats", "--budget", "500"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tokenizer: heuristic"))
+        .stdout(predicate::str::contains("Budget:    500"));
+}
+
+#[test]
+fn prime_max_share_keeps_small_domains() {
+    let dir = init_project_with_domain("big");
+    kb().args(["add", "small"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    for i in 0..6 {
+        record_convention(
+            &dir,
+            "big",
+            &format!("Convention number {i} with enough text to cost a handful of tokens"),
+        );
+    }
+    kb().args([
+        "record",
+        "small",
+        "--type",
+        "decision",
+        "--title",
+        "Use Postgres",
+        "--rationale",
+        "Mature and well understood",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success();
+
+    let stats = |extra: &[&str]| -> serde_json::Value {
+        let mut args = vec!["--json", "prime", "--stats", "--budget", "80"];
+        args.extend_from_slice(extra);
+        let output = kb().args(&args).current_dir(dir.path()).output().unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+
+    let greedy = stats(&[]);
+    assert_eq!(greedy["stats"]["domains"][1]["kept"], 0);
+
+    let fair = stats(&["--max-share", "0.6"]);
+    assert_eq!(fair["stats"]["domains"][1]["kept"], 1);
+    let reasons: Vec<&str> = fair["stats"]["dropped"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["reason"].as_str().unwrap())
+        .collect();
+    assert!(reasons.contains(&"domain_max_share"));
+
+    let capped = stats(&["--type-cap", "convention=1"]);
+    assert_eq!(capped["stats"]["domains"][0]["kept"], 1);
+}
+
+#[test]
+fn prime_overflow_index_lists_dropped_records() {
+    let dir = init_project_with_domain("test");
+    for i in 0..8 {
+        record_convention(
+            &dir,
+            "test",
+            &format!("Convention number {i} with enough text to cost a handful of tokens"),
+        );
+    }
+    let ids: Vec<String> = (0..8).map(|i| get_record_id(&dir, "test", i)).collect();
+
+    let output = kb()
+        .args(["prime", "--budget", "200", "--overflow-index=0.5"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("## Not Loaded (over budget)"));
+    // Every record is either rendered or indexed by ID
+    for id in &ids {
+        assert!(stdout.contains(id.as_str()), "missing {id}");
+    }
+
+    // Without the flag only the summary line is printed
+    kb().args(["prime", "--budget", "100"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Not Loaded").not())
+        .stdout(predicate::str::contains("more records"));
+}
+
+// ── Templates ───────────────────────────────────────────────────────────────
+
+#[test]
+fn prime_builtin_templates_match_formats() {
+    let dir = init_project_with_domain("test");
+    record_convention(&dir, "test", "Use tabs");
+    record_pattern(&dir, "test", "Builder", "Use builders for config");
+
+    let prime = |args: &[&str]| -> String {
+        let output = kb().args(args).current_dir(dir.path()).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert_eq!(
+        prime(&["prime"]),
+        prime(&["prime", "--template", "compact"])
+    );
+    assert_eq!(
+        prime(&["prime", "--full"]),
+        prime(&["prime", "--full", "--template", "markdown"])
+    );
+    assert_eq!(
+        prime(&["prime", "--format", "xml"]),
+        prime(&["prime", "--template", "xml"])
+    );
+    assert_eq!(
+        prime(&["prime", "--format", "plain"]),
+        prime(&["prime", "--template", "plain"])
+    );
+}
+
+#[test]
+fn prime_and_query_use_user_templates() {
+    let dir = init_project_with_domain("test");
+    record_convention(&dir, "test", "Use tabs");
+    let templates = dir.path().join(".kb").join("templates");
+    std::fs::create_dir_all(&templates).unwrap();
+    std::fs::write(
+        templates.join("terse.j2"),
+        "{% block domains %}{% for d in domains %}{% for r in d.records %}* {{ r.summary }}\n\
+         {% endfor %}{% endfor %}{% endblock %}\n{% block reminder %}Record before you stop.{% endblock %}",
+    )
+    .unwrap();
+
+    kb().args(["prime", "--template", "terse"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout("* Use tabs\nRecord before you stop.\n");
+
+    kb().args(["query", "test", "--template", "terse"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout("* Use tabs\n");
+
+    kb().args(["prime", "--template", "missing"])
+        .current_dir(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Available templates: terse, compact",
+        ));
+}
+
+// ── Budgeted JSON prime ─────────────────────────────────────────────────────
+
+#[test]
+fn prime_json_honours_budget_and_filters() {
+    let dir = init_project_with_domain("test");
+    for i in 0..5 {
+        record_convention(
+            &dir,
+            "test",
+            &format!("Convention {i} with enough words to cost several tokens"),
+        );
+    }
+    kb().args([
+        "record",
+        "test",
+        "--type",
+        "pattern",
+        "--name",
+        "Loader",
+        "--description",
+        "Loads config",
+        "--files",
+        "src/config.rs",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success();
+
+    let prime = |args: &[&str]| -> serde_json::Value {
+        let output = kb().args(args).current_dir(dir.path()).output().unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+
+    let budgeted = prime(&["--json", "prime", "--budget", "40"]);
+    assert_eq!(budgeted["budget"], 40);
+    let domain = &budgeted["domains"][0];
+    assert_eq!(domain["entry_count"], 6);
+    assert_eq!(domain["kept"], 2);
+    assert_eq!(domain["dropped"], 4);
+    assert_eq!(budgeted["dropped_count"], 4);
+    assert_eq!(domain["records"].as_array().unwrap().len(), 2);
+    assert_eq!(budgeted["used_tokens"], domain["tokens"]);
+
+    let unlimited = prime(&["prime", "--mcp", "--no-limit"]);
+    assert_eq!(unlimited["budget"], serde_json::Value::Null);
+    assert_eq!(unlimited["domains"][0]["kept"], 6);
+
+    // Records tied to other files are filtered out before budgeting
+    let filtered = prime(&["--json", "prime", "--no-limit", "--files", "src/main.rs"]);
+    assert_eq!(filtered["domains"][0]["entry_count"], 5);
+    let names: Vec<&serde_json::Value> = filtered["domains"][0]["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| &r["name"])
+        .collect();
+    assert!(!names.contains(&&serde_json::json!("Loader")));
+}
+
+// ── Static site export ──────────────────────────────────────────────────────
+
+#[test]
+fn export_html_writes_static_site() {
+    let dir = init_project_with_domain("test");
+    record_convention(&dir, "test", "Use <anyhow> for errors");
+    kb().args([
+        "record",
+        "test",
+        "--type",
+        "pattern",
+        "--name",
+        "Loader",
+        "--description",
+        "Loads config",
+        "--tags",
+        "config,io",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success();
+    let id = get_record_id(&dir, "test", 1);
+
+    let output = kb()
+        .args(["--json", "export", "html", "site", "--title", "Team KB"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["success"], true);
+    assert_eq!(json["records"], 2);
+
+    let site = dir.path().join("site");
+    let index = fs::read_to_string(site.join("index.html")).unwrap();
+    assert!(index.contains("Team KB"));
+    assert!(index.contains("domains/test.html"));
+
+    let domain = fs::read_to_string(site.join("domains/test.html")).unwrap();
+    assert!(domain.contains("Use &lt;anyhow&gt; for errors"));
+    assert!(domain.contains(&format!("../records/{id}.html")));
+
+    let record = fs::read_to_string(site.join(format!("records/{id}.html"))).unwrap();
+    assert!(record.contains("Loader"));
+    assert!(record.contains("Loads config"));
+
+    let tags = fs::read_to_string(site.join("tags.html")).unwrap();
+    assert!(tags.contains("config"));
+    assert!(tags.contains("io"));
+
+    let search = fs::read_to_string(site.join("search-index.js")).unwrap();
+    assert!(search.starts_with("window.KB_SEARCH_INDEX"));
+    assert!(search.contains("Loader"));
+    assert!(site.join("search-index.json").exists());
+    assert!(site.join("style.css").exists());
+}
+
+// ── Import / export ─────────────────────────────────────────────────────────
+
+#[test]
+fn export_formats_round_trip_through_import() {
+    let dir = init_project_with_domain("test");
+    record_convention(&dir, "test", "Use anyhow, not unwrap");
+    record_pattern(&dir, "test", "Loader", "Loads config");
+
+    for format in ["markdown", "csv", "yaml", "ndjson"] {
+        let file = format!("export.{format}");
+        kb().args(["export", "--format", format, "--output", &file])
+            .current_dir(dir.path())
+            .assert()
+            .success();
+
+        let output = kb()
+            .args(["--json", "import", &file, "--format", format, "--dry-run"])
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(json["unchanged"], 2, "{format}: {json}");
+        assert_eq!(json["created"], 0, "{format}");
+        assert_eq!(json["updated"], 0, "{format}");
+    }
+}
+
+#[test]
+fn import_updates_by_id_and_reports_field_diff() {
+    let dir = init_project_with_domain("test");
+    record_pattern(&dir, "test", "Loader", "Loads config");
+    let id = get_record_id(&dir, "test", 0);
+
+    let output = kb()
+        .args(["export", "--format", "ndjson"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let exported = String::from_utf8(output.stdout).unwrap();
+    // Rename the pattern: the ID still ties it to the stored record
+    let edited = exported.replace("\"Loader\"", "\"Config loader\"")
+        + "{\"type\":\"convention\",\"content\":\"Log at info\"}\n";
+    fs::write(dir.path().join("edit.ndjson"), edited).unwrap();
+
+    let output = kb()
+        .args([
+            "--json",
+            "import",
+            "edit.ndjson",
+            "--domain",
+            "test",
+            "--dry-run",
+        ])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["updated"], 1);
+    assert_eq!(json["created"], 1);
+    let update = &json["changes"][0];
+    assert_eq!(update["id"], id.as_str());
+    assert_eq!(update["fields"][0]["field"], "name");
+    assert_eq!(update["fields"][0]["old"], "Loader");
+    assert_eq!(update["fields"][0]["new"], "Config loader");
+    assert_eq!(
+        query_json(&dir, "test")["domains"][0]["records"][0]["name"],
+        "Loader"
+    );
+
+    kb().args(["import", "edit.ndjson", "--domain", "test"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Created 1 record(s)"))
+        .stdout(predicate::str::contains("Updated 1 record(s)"));
+
+    let records = query_json(&dir, "test")["domains"][0]["records"].clone();
+    assert_eq!(records.as_array().unwrap().len(), 2);
+    assert_eq!(records[0]["id"], id.as_str());
+    assert_eq!(records[0]["name"], "Config loader");
+}
+
+#[test]
+fn import_csv_skips_duplicates_and_requires_domain() {
+    let dir = init_project_with_domain("test");
+    record_convention(&dir, "test", "Use anyhow");
+
+    fs::write(
+        dir.path().join("sheet.csv"),
+        "type,content,tags\nconvention,Use anyhow,\nconvention,\"Prefer \"\"?\"\" over match\",errors; style\n",
+    )
+    .unwrap();
+
+    kb().args(["import", "sheet.csv"])
+        .current_dir(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("has no domain"));
+
+    let output = kb()
+        .args(["--json", "import", "sheet.csv", "--domain", "test"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["created"], 1);
+    assert_eq!(json["skipped"], 1);
+
+    let records = query_json(&dir, "test")["domains"][0]["records"].clone();
+    assert_eq!(records[1]["content"], "Prefer \"?\" over match");
+    assert_eq!(records[1]["tags"], serde_json::json!(["errors", "style"]));
+}
+
+// ── ADR interop ─────────────────────────────────────────────────────────────
+
+#[test]
+fn import_adr_creates_decisions_with_evidence() {
+    let dir = init_project_with_domain("arch");
+    let adr_dir = dir.path().join("docs/adr");
+    fs::create_dir_all(&adr_dir).unwrap();
+    fs::write(
+        adr_dir.join("0001-record-architecture-decisions.md"),
+        "# 1. Record architecture decisions\n\nDate: 2024-01-01\n\n## Status\n\nAccepted\n\n## Context\n\nWe need a log.\n\n## Decision\n\nWe will use ADRs.\n",
+    )
+    .unwrap();
+    fs::write(adr_dir.join("template.md"), "# Title\n").unwrap();
+
+    kb().args(["import", "adr", "docs/adr", "--domain", "arch"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 created"));
+
+    let record = query_json(&dir, "arch")["domains"][0]["records"][0].clone();
+    assert_eq!(record["type"], "decision");
+    assert_eq!(record["title"], "Record architecture decisions");
+    assert_eq!(record["rationale"], "We will use ADRs.");
+    assert_eq!(record["date"], "2024-01-01");
+    assert_eq!(
+        record["evidence"]["file"],
+        "docs/adr/0001-record-architecture-decisions.md"
+    );
+
+    // Re-importing matches on the evidence file
+    let output = kb()
+        .args(["--json", "import", "adr", "docs/adr", "--domain", "arch"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["unchanged"], 1);
+    assert_eq!(json["created"], 0);
+}
+
+#[test]
+fn export_adr_writes_numbered_files_and_sync_is_stable() {
+    let dir = init_project_with_domain("arch");
+    for (title, rationale) in [("Use JSONL", "Line diffs"), ("Use clap", "Derive API")] {
+        kb().args([
+            "record",
+            "arch",
+            "--type",
+            "decision",
+            "--title",
+            title,
+            "--rationale",
+            rationale,
+        ])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    }
+    let id = get_record_id(&dir, "arch", 0);
+
+    kb().args(["export", "adr", "docs/adr"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let adr = fs::read_to_string(dir.path().join("docs/adr/0001-use-jsonl.md")).unwrap();
+    assert!(adr.contains(&format!("kb-id: {id}")));
+    assert!(adr.contains("# Use JSONL"));
+    assert!(dir.path().join("docs/adr/0002-use-clap.md").exists());
+
+    // An edited ADR wins by default and is matched by ID despite the new title
+    fs::write(
+        dir.path().join("docs/adr/0001-use-jsonl.md"),
+        adr.replace("# Use JSONL", "# Store records as JSONL"),
+    )
+    .unwrap();
+    let sync = |args: &[&str]| -> serde_json::Value {
+        let output = kb()
+            .args([
+                "--json", "import", "adr", "docs/adr", "--domain", "arch", "--sync",
+            ])
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+    // Both records are also linked to their ADR files through evidence
+    let json = sync(&[]);
+    assert_eq!(json["updated"], 2);
+    assert_eq!(json["adr_files"].as_array().unwrap().len(), 0);
+    assert_eq!(
+        query_json(&dir, "arch")["domains"][0]["records"][0]["title"],
+        "Store records as JSONL"
+    );
+
+    let json = sync(&[]);
+    assert_eq!(json["updated"], 0);
+    assert_eq!(json["unchanged"], 2);
+
+    // With --prefer kb the record is written back to the ADR instead
+    kb().args(["edit", "arch", &id, "--title", "Use JSONL storage"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let json = sync(&["--prefer", "kb"]);
+    assert_eq!(json["updated"], 0);
+    assert_eq!(json["adr_files"][0]["file"], "0001-use-jsonl.md");
+    assert_eq!(json["adr_files"][0]["action"], "update");
+    let adr = fs::read_to_string(dir.path().join("docs/adr/0001-use-jsonl.md")).unwrap();
+    assert!(adr.contains("# Use JSONL storage"));
+}
+
+// ── Instruction file import ─────────────────────────────────────────────────
+
+const INSTRUCTIONS_MD: &str = "# Project\n\n## Testing\n\n- Use assert_cmd for CLI tests\n- Keep unit tests next to the code\n\n## Release\n\n1. Bump the version\n2. Tag and push\n\n<!-- kb:start -->\n- Generated by kb onboard, never imported\n<!-- kb:end -->\n";
+
+#[test]
+fn import_instructions_lists_candidates_outside_markers() {
+    let dir = init_project_with_domain("testing");
+    fs::write(dir.path().join("CLAUDE.md"), INSTRUCTIONS_MD).unwrap();
+
+    let output = kb()
+        .args(["--json", "import", "instructions", "CLAUDE.md"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let candidates = json["candidates"].as_array().unwrap();
+    assert_eq!(candidates.len(), 3);
+    assert_eq!(candidates[0]["domain"], "testing");
+    assert_eq!(candidates[0]["new_domain"], false);
+    assert_eq!(
+        candidates[0]["record"]["content"],
+        "Use assert_cmd for CLI tests"
+    );
+    assert_eq!(candidates[0]["record"]["evidence"]["file"], "CLAUDE.md");
+    assert_eq!(candidates[2]["record"]["type"], "guide");
+    assert_eq!(candidates[2]["domain"], "release");
+    assert_eq!(candidates[2]["new_domain"], true);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("never imported"));
+
+    // Drafts are batch files for `kb record --batch`
+    kb().args(["import", "instructions", "CLAUDE.md", "--drafts", "drafts"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    assert!(dir.path().join("drafts/release.json").exists());
+    kb().args(["record", "testing", "--batch", "drafts/testing.json"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let records = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    assert_eq!(records.as_array().unwrap().len(), 2);
+    assert_eq!(records[0]["classification"], "foundational");
+}
+
+#[test]
+fn import_instructions_interactive_records_accepted() {
+    let dir = init_project_with_domain("testing");
+    fs::write(dir.path().join("CLAUDE.md"), INSTRUCTIONS_MD).unwrap();
+
+    kb().args(["import", "instructions", "CLAUDE.md", "--interactive"])
+        .current_dir(dir.path())
+        .write_stdin("y\nn\nd testing\ny\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Recorded 2 record(s) in testing"));
+
+    let records = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    assert_eq!(records[0]["content"], "Use assert_cmd for CLI tests");
+    assert_eq!(records[1]["type"], "guide");
+    assert_eq!(records[1]["name"], "Release");
+}
+
+// ── Diff ─────────────────────────────────────────────────────────────────────
+
+fn git(dir: &TempDir, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .args(["-c", "user.name=kb", "-c", "user.email=kb@example.com"])
+        .args(args)
+        .current_dir(dir.path())
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {args:?} failed");
+}
+
+fn commit_all(dir: &TempDir, message: &str) {
+    git(dir, &["add", "-A"]);
+    git(dir, &["commit", "-q", "-m", message]);
+}
+
+/// A repo with two commits: the second edits one convention, removes another
+/// and adds a pattern.
+fn diff_project() -> (TempDir, String) {
+    let dir = init_project_with_domain("testing");
+    git(&dir, &["init", "-q"]);
+    record_convention(&dir, "testing", "Use tabs");
+    record_convention(&dir, "testing", "Old rule");
+    commit_all(&dir, "initial");
+
+    let id = get_record_id(&dir, "testing", 0);
+    let removed = get_record_id(&dir, "testing", 1);
+    kb().args(["edit", "testing", &id, "--content", "Use spaces"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    kb().args(["delete", "testing", &removed])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    record_pattern(&dir, "testing", "fixture", "Shared fixtures");
+    commit_all(&dir, "edit");
+    (dir, id)
+}
+
+#[test]
+fn diff_range_reports_modified_fields() {
+    let (dir, id) = diff_project();
+
+    let output = kb()
+        .args(["diff", "HEAD~1..HEAD", "--json"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["from"], "HEAD~1");
+    assert_eq!(json["to"], "HEAD");
+    assert_eq!(json["summary"]["added"], 1);
+    assert_eq!(json["summary"]["modified"], 1);
+    assert_eq!(json["summary"]["removed"], 1);
+
+    let modified = &json["modified"][0];
+    assert_eq!(modified["id"], id.as_str());
+    assert_eq!(modified["fields"][0]["field"], "content");
+    assert_eq!(modified["fields"][0]["old"], "Use tabs");
+    assert_eq!(modified["fields"][0]["new"], "Use spaces");
+
+    // Grouped by domain, then type in section order
+    let types = &json["domains"][0]["types"];
+    assert_eq!(json["domains"][0]["domain"], "testing");
+    assert_eq!(types[0]["type"], "convention");
+    assert_eq!(types[0]["changes"].as_array().unwrap().len(), 2);
+    assert_eq!(types[1]["type"], "pattern");
+}
+
+#[test]
+fn diff_human_output_and_working_tree() {
+    let (dir, _) = diff_project();
+
+    kb().args(["diff", "HEAD~1.."])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[testing]"))
+        .stdout(predicate::str::contains("Conventions:"))
+        .stdout(predicate::str::contains(
+            "content: \"Use tabs\" -> \"Use spaces\"",
+        ))
+        .stdout(predicate::str::contains("1 added, 1 modified, 1 removed."));
+
+    // A single ref is compared against the working tree
+    record_convention(&dir, "testing", "Uncommitted");
+    let output = kb()
+        .args(["diff", "HEAD", "--json"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["to"], serde_json::Value::Null);
+    assert_eq!(json["added"][0]["record"]["content"], "Uncommitted");
+    kb().args(["diff", "HEAD..HEAD"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No changes."));
+}
+
+#[test]
+fn diff_rejects_unknown_ref() {
+    let (dir, _) = diff_project();
+    kb().args(["diff", "nope..HEAD"])
+        .current_dir(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown git ref: nope"));
+}
+
+// ── Merge driver ─────────────────────────────────────────────────────────────
+
+fn convention_line(id: &str, content: &str, extra: &str) -> String {
+    format!(
+        r#"{{"type":"convention","id":"{id}","content":"{content}","classification":"tactical","recorded_at":"2024-01-01T00:00:00.000Z"{extra}}}"#
+    )
+}
+
+#[test]
+fn merge_driver_merges_by_record_id() {
+    let dir = TempDir::new().unwrap();
+    let base = convention_line("mx-1", "Base", "");
+    fs::write(dir.path().join("base"), format!("{base}\n")).unwrap();
+    fs::write(
+        dir.path().join("ours"),
+        format!(
+            "{}\n{}\n",
+            convention_line("mx-1", "Base", r#","outcomes":[{"status":"success"}]"#),
+            convention_line("mx-2", "Ours", "")
+        ),
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("theirs"),
+        format!(
+            "{}\n{}\n",
+            convention_line("mx-1", "Edited", r#","outcomes":[{"status":"failure"}]"#),
+            convention_line("mx-3", "Theirs", "")
+        ),
+    )
+    .unwrap();
+
+    kb().args(["merge-driver", "base", "ours", "theirs"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+
+    let merged = fs::read_to_string(dir.path().join("ours")).unwrap();
+    let lines: Vec<serde_json::Value> = merged
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["content"], "Edited");
+    assert_eq!(lines[0]["outcomes"].as_array().unwrap().len(), 2);
+    assert_eq!(lines[1]["id"], "mx-2");
+    assert_eq!(lines[2]["id"], "mx-3");
+}
+
+#[test]
+fn merge_driver_conflict_writes_markers_and_fails() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("base"), convention_line("mx-1", "Base", "")).unwrap();
+    fs::write(dir.path().join("ours"), convention_line("mx-1", "Ours", "")).unwrap();
+    fs::write(
+        dir.path().join("theirs"),
+        convention_line("mx-1", "Theirs", ""),
+    )
+    .unwrap();
+
+    kb().args([
+        "merge-driver",
+        "base",
+        "ours",
+        "theirs",
+        ".kb/expertise/x.jsonl",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains(
+        ".kb/expertise/x.jsonl: 1 conflicting record(s): mx-1",
+    ));
+
+    let merged = fs::read_to_string(dir.path().join("ours")).unwrap();
+    assert!(merged.starts_with("<<<<<<< ours\n"));
+    assert!(merged.contains("\"content\":\"Theirs\""));
+}
+
+#[test]
+fn setup_merge_driver_merges_concurrent_field_edits() {
+    let dir = init_project_with_domain("testing");
+    git(&dir, &["init", "-q", "-b", "main"]);
+
+    kb().args(["setup", "--merge-driver"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let attributes = fs::read_to_string(dir.path().join(".git/info/attributes")).unwrap();
+    assert!(attributes.contains(".kb/expertise/*.jsonl merge=kb"));
+
+    // Point git at the test binary rather than a `kb` on PATH
+    let driver = format!("{} merge-driver %O %A %B %P", env!("CARGO_BIN_EXE_kb"));
+    git(&dir, &["config", "merge.kb.driver", &driver]);
+
+    record_convention(&dir, "testing", "Use tabs");
+    commit_all(&dir, "initial");
+    let id = get_record_id(&dir, "testing", 0);
+
+    git(&dir, &["checkout", "-q", "-b", "feature"]);
+    kb().args(["edit", "testing", &id, "--content", "Use spaces"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    commit_all(&dir, "content");
+
+    git(&dir, &["checkout", "-q", "main"]);
+    kb().args(["edit", "testing", &id, "--classification", "foundational"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    commit_all(&dir, "classification");
+
+    git(&dir, &["merge", "-q", "--no-edit", "feature"]);
+
+    let records = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    assert_eq!(records.as_array().unwrap().len(), 1);
+    assert_eq!(records[0]["content"], "Use spaces");
+    assert_eq!(records[0]["classification"], "foundational");
+}
+
+// ── Branch scoping ───────────────────────────────────────────────────────────
+
+#[test]
+fn branch_scoped_records_are_labeled_and_promoted() {
+    let dir = init_project_with_domain("testing");
+    git(&dir, &["init", "-q", "-b", "main"]);
+    record_convention(&dir, "testing", "Mainline rule");
+    commit_all(&dir, "initial");
+
+    git(&dir, &["checkout", "-q", "-b", "feature/cache"]);
+    record_convention(&dir, "testing", "Tentative rule");
+    kb().args([
+        "record",
+        "testing",
+        "Not scoped",
+        "--type",
+        "convention",
+        "--no-branch",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success();
+
+    let records = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    assert!(records[0].get("branch").is_none());
+    assert_eq!(records[1]["branch"], "feature/cache");
+    assert!(records[2].get("branch").is_none());
+
+    kb().args(["prime"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[branch: feature/cache]"));
+    kb().args(["status"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("feature/cache (1 records)"))
+        .stdout(predicate::str::contains("run `kb promote").not());
+    commit_all(&dir, "feature");
+
+    git(&dir, &["checkout", "-q", "main"]);
+    git(&dir, &["merge", "-q", "feature/cache"]);
+    kb().args(["status"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "merged, run `kb promote --branch feature/cache`",
+        ));
+
+    kb().args(["promote", "--branch", "feature/cache"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Promoted 1 record(s)"));
+
+    let records = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    assert!(records[1].get("branch").is_none());
+    let output = kb()
+        .args(["status", "--json"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["pending_branches"], serde_json::json!([]));
+}
+
+// ── Log and blame ────────────────────────────────────────────────────────────
+
+#[test]
+fn log_lists_every_commit_touching_a_record() {
+    let (dir, id) = diff_project();
+    let output = kb()
+        .args(["--json", "log", &id])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["id"], id.as_str());
+    assert_eq!(json["domain"], "testing");
+    let entries = json["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["kind"], "added");
+    assert_eq!(entries[0]["commit"]["subject"], "initial");
+    assert_eq!(entries[0]["commit"]["author"], "kb");
+    assert_eq!(entries[1]["kind"], "modified");
+    assert_eq!(entries[1]["fields"][0]["field"], "content");
+    assert_eq!(entries[1]["fields"][0]["new"], "Use spaces");
+
+    kb().args(["log", &id[3..]])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("added"))
+        .stdout(predicate::str::contains(
+            r#"content: "Use tabs" -> "Use spaces""#,
+        ));
+}
+
+#[test]
+fn log_finds_deleted_and_uncommitted_changes() {
+    let (dir, id) = diff_project();
+    let output = kb()
+        .args(["--json", "diff", "HEAD~1..HEAD"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let removed = json["removed"][0]["id"].as_str().unwrap().to_string();
+
+    kb().args(["log", &removed])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("removed"))
+        .stdout(predicate::str::contains("Old rule"));
+
+    kb().args(["delete", "testing", &id])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let output = kb()
+        .args(["--json", "log", &id])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let last = json["entries"].as_array().unwrap().last().unwrap().clone();
+    assert_eq!(last["kind"], "removed");
+    assert!(last["commit"].is_null());
+
+    kb().args(["log", "mx-ffffff"])
+        .current_dir(dir.path())
+        .assert()
+        .failure();
+}
+
+#[test]
+fn blame_annotates_records_with_introducing_commit() {
+    let (dir, id) = diff_project();
+    record_convention(&dir, "testing", "Not committed");
+
+    let output = kb()
+        .args(["--json", "blame", "testing"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let records = json["records"].as_array().unwrap();
+    assert_eq!(records.len(), 3);
+    let edited = records.iter().find(|r| r["id"] == id.as_str()).unwrap();
+    assert_eq!(edited["introduced"]["subject"], "initial");
+    assert_eq!(edited["last_changed"]["subject"], "edit");
+    let pattern = records.iter().find(|r| r["type"] == "pattern").unwrap();
+    assert_eq!(pattern["introduced"]["subject"], "edit");
+    let fresh = records
+        .iter()
+        .find(|r| r["summary"] == "Not committed")
+        .unwrap();
+    assert!(fresh["introduced"].is_null());
+
+    kb().args(["blame", "testing"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("working tree"))
+        .stdout(predicate::str::contains("last changed:"));
+}
+
+// ── Churn staleness ──────────────────────────────────────────────────────────
+
+#[test]
+fn review_stale_flags_records_whose_files_churned() {
+    let dir = init_project_with_domain("testing");
+    git(&dir, &["init", "-q"]);
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join(".kb/expertise/testing.jsonl"),
+        format!(
+            "{}\n{}\n",
+            convention_line("mx-000001", "Keep src/storage.rs free of locking", ""),
+            convention_line("mx-000002", "Document src/quiet.rs", ""),
+        ),
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("src/quiet.rs"), "// quiet\n").unwrap();
+    for round in 0..3 {
+        let body: String = (0..50).map(|i| format!("// {round} {i}\n")).collect();
+        std::fs::write(dir.path().join("src/storage.rs"), body).unwrap();
+        commit_all(&dir, &format!("rewrite {round}"));
+    }
+
+    let output = kb()
+        .args(["--json", "review", "--stale"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["total"], 1);
+    let record = &json["records"][0];
+    assert_eq!(record["id"], "mx-000001");
+    assert_eq!(record["commits"], 3);
+    assert_eq!(record["files"][0]["path"], "src/storage.rs");
+
+    kb().args(["status"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 record(s) reference files"));
+    let output = kb()
+        .args(["--json", "doctor"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["success"], true);
+    assert_eq!(json["warnings"][0]["check"], "churn");
+
+    // Editing the record counts as re-verification
+    kb().args([
+        "edit",
+        "testing",
+        "mx-000001",
+        "--content",
+        "Keep src/storage.rs lock-free",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success();
+    kb().args(["review", "--stale"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No stale records."));
+}
+
+// ── Commit trailers ──────────────────────────────────────────────────────────
+
+fn commit_with_trailers(dir: &TempDir, message: &str) {
+    std::fs::write(dir.path().join("notes.txt"), message).unwrap();
+    git(dir, &["add", "notes.txt"]);
+    git(dir, &["commit", "-q", "-m", message]);
+}
+
+#[test]
+fn ingest_commits_records_trailers_with_commit_evidence() {
+    let dir = init_project_with_domain("testing");
+    git(&dir, &["init", "-q"]);
+    commit_all(&dir, "initial");
+    commit_with_trailers(
+        &dir,
+        "Fix flaky lock test\n\nKb-Domain: testing\nKb-Failure: Lock test races on CI\nKb-Resolution: Use a temp dir per test\nKb-Tags: ci, locking",
+    );
+    commit_with_trailers(
+        &dir,
+        "Tidy errors\n\nKb-Convention: Use anyhow in the CLI crate\nKb-Convention: Missing domain here",
+    );
+
+    let output = kb()
+        .args([
+            "--json",
+            "ingest-commits",
+            "HEAD~2..HEAD",
+            "--dry-run",
+            "--no-branch",
+        ])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["commits"], 2);
+    assert_eq!(json["created"].as_array().unwrap().len(), 1);
+    assert_eq!(json["warnings"].as_array().unwrap().len(), 2);
+    assert!(
+        query_json(&dir, "testing")["domains"][0]["records"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+
+    kb().args([
+        "ingest-commits",
+        "HEAD~2..HEAD",
+        "--domain",
+        "testing",
+        "--no-branch",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(
+        "Ingested 3 record(s) from 2 commit(s)",
+    ));
+
+    let records = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    assert_eq!(records.as_array().unwrap().len(), 3);
+    assert_eq!(records[0]["type"], "failure");
+    assert_eq!(records[0]["resolution"], "Use a temp dir per test");
+    assert_eq!(records[0]["tags"], serde_json::json!(["ci", "locking"]));
+    let head = std::process::Command::new("git")
+        .args(["rev-parse", "HEAD~1"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let hash = String::from_utf8_lossy(&head.stdout).trim().to_string();
+    assert_eq!(records[0]["evidence"]["commit"], hash.as_str());
+
+    // Re-ingesting skips what is already recorded
+    kb().args(["ingest-commits", "HEAD", "--domain", "testing"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Ingested 0 record(s) from 1 commit(s), skipped 2 already recorded.",
+        ));
+
+    kb().args(["ingest-commits", "nope..HEAD"])
+        .current_dir(dir.path())
+        .assert()
+        .failure();
+}
+
+#[test]
+fn git_hook_runs_ingest_commits() {
+    let dir = init_project_with_domain("testing");
+    git(&dir, &["init", "-q"]);
+    let hook_path = dir.path().join(".git/hooks/post-commit");
+    std::fs::create_dir_all(hook_path.parent().unwrap()).unwrap();
+    std::fs::write(
+        &hook_path,
+        "#!/bin/sh\nkb learn --since HEAD~1 2>/dev/null || true\n",
+    )
+    .unwrap();
+
+    kb().args(["setup", "--git-hook"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let hook = std::fs::read_to_string(&hook_path).unwrap();
+    assert_eq!(hook.matches("kb learn").count(), 1);
+    assert!(hook.contains("kb ingest-commits HEAD"));
+}
+
+// ── Learn relevant records ───────────────────────────────────────────────────
+
+#[test]
+fn learn_relevant_lists_records_for_changed_files() {
+    let dir = init_project_with_domain("testing");
+    git(&dir, &["init", "-q"]);
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/storage.rs"), "// v1\n").unwrap();
+    record_convention(&dir, "testing", "Keep src/storage.rs free of locking");
+    record_convention(&dir, "testing", "Use tabs");
+    kb().args([
+        "record",
+        "testing",
+        "Write through temp files",
+        "--type",
+        "convention",
+        "--tags",
+        "storage",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success();
+    commit_all(&dir, "initial");
+
+    std::fs::write(dir.path().join("src/storage.rs"), "// v2\n").unwrap();
+    let output = kb()
+        .args(["--json", "learn", "--relevant"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let relevant = json["relevant"].as_array().unwrap();
+    assert_eq!(relevant.len(), 2);
+    assert_eq!(relevant[0]["files"], serde_json::json!(["src/storage.rs"]));
+    assert_eq!(relevant[1]["tags"], serde_json::json!(["storage"]));
+    let id = relevant[0]["id"].as_str().unwrap();
+    assert_eq!(
+        relevant[0]["actions"]["confirm"],
+        format!("kb edit testing {id} --outcome-status success")
+    );
+    assert!(
+        relevant[0]["actions"]["supersede"]
+            .as_str()
+            .unwrap()
+            .contains(&format!("--supersedes {id}"))
+    );
+
+    kb().args(["learn", "--relevant"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("confirm, revise or supersede"))
+        .stdout(predicate::str::contains("Use tabs").not());
+
+    let output = kb()
+        .args(["--json", "learn"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json.get("relevant").is_none());
+}
+
+// ── Learn drafts ─────────────────────────────────────────────────────────────
+
+#[test]
+fn learn_draft_emits_batch_ready_records() {
+    let dir = init_project_with_domain("testing");
+    git(&dir, &["init", "-q"]);
+    std::fs::create_dir_all(dir.path().join("tests")).unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("tests/lock.rs"), "// v1\n").unwrap();
+    commit_all(&dir, "initial");
+    std::fs::write(dir.path().join("tests/lock.rs"), "// v2\n").unwrap();
+    commit_all(&dir, "Fix flaky lock test");
+    std::fs::write(
+        dir.path().join("src/cache.rs"),
+        "//! Cache of parsed expertise files.\npub struct Cache;\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("rustfmt.toml"), "max_width = 100\n").unwrap();
+
+    let output = kb()
+        .args(["learn", "--draft", "--since", "HEAD~1"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let drafts: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let drafts = drafts.as_array().unwrap();
+    let types: Vec<&str> = drafts.iter().map(|d| d["type"].as_str().unwrap()).collect();
+    assert_eq!(types, vec!["failure", "convention", "reference"]);
+    assert_eq!(drafts[0]["description"], "Fix flaky lock test");
+    assert!(drafts[0]["evidence"]["commit"].is_string());
+    assert_eq!(drafts[1]["evidence"]["file"], "rustfmt.toml");
+    assert_eq!(drafts[2]["description"], "Cache of parsed expertise files.");
+
+    let batch = dir.path().join("drafts.json");
+    std::fs::write(&batch, &output.stdout).unwrap();
+    kb().args(["record", "testing", "--batch", batch.to_str().unwrap()])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let records = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    assert_eq!(records.as_array().unwrap().len(), 3);
+
+    let output = kb()
+        .args(["--json", "learn", "--draft", "--since", "HEAD~1"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["changes"], 2);
+    assert_eq!(json["drafts"][2]["reason"], "new module");
+}
+
+// ── Compaction ───────────────────────────────────────────────────────────────
+
+fn record_near_duplicates(dir: &TempDir) {
+    kb().args([
+        "record",
+        "testing",
+        "--type",
+        "convention",
+        "--tags",
+        "errors",
+        "Use anyhow for errors in the CLI crate",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success();
+    record_convention(dir, "testing", "Run cargo fmt before committing");
+    kb().args([
+        "record",
+        "testing",
+        "--type",
+        "convention",
+        "--tags",
+        "cli",
+        "In the CLI crate, use anyhow for errors",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success();
+}
+
+#[test]
+fn compact_auto_merges_near_duplicates() {
+    let dir = init_project_with_domain("testing");
+    record_near_duplicates(&dir);
+    let first = get_record_id(&dir, "testing", 0);
+    let third = get_record_id(&dir, "testing", 2);
+
+    kb().args(["compact", "--dry-run"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "[testing] 2 convention records -> {third}"
+        )))
+        .stdout(predicate::str::contains(format!("- {first}")));
+    assert_eq!(
+        query_json(&dir, "testing")["domains"][0]["records"]
+            .as_array()
+            .unwrap()
+            .len(),
+        3
+    );
+
+    // Without --similarity, --auto only merges exact duplicates
+    let output = kb()
+        .args(["--json", "compact", "testing", "--auto"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["total_merged"], 0);
+
+    let output = kb()
+        .args([
+            "--json",
+            "compact",
+            "testing",
+            "--auto",
+            "--similarity",
+            "0.6",
+        ])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["total_merged"], 1);
+    assert_eq!(json["clusters"][0]["ids"][1], first.as_str());
+
+    let records = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    let records = records.as_array().unwrap();
+    assert_eq!(records.len(), 2);
+    let merged = &records[0];
+    assert_eq!(merged["id"], third.as_str());
+    assert_eq!(merged["tags"], serde_json::json!(["cli", "errors"]));
+    assert_eq!(merged["supersedes"], serde_json::json!([first]));
+    assert!(merged["updated_at"].is_string());
+}
+
+#[test]
+fn compact_plan_file_is_applied() {
+    let dir = init_project_with_domain("testing");
+    record_near_duplicates(&dir);
+
+    kb().args(["compact", "--plan", "plan.json"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Wrote 1 proposed merge(s)"));
+    let plan: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("plan.json")).unwrap())
+            .unwrap();
+    assert_eq!(plan["clusters"][0]["domain"], "testing");
+    assert_eq!(
+        query_json(&dir, "testing")["domains"][0]["records"]
+            .as_array()
+            .unwrap()
+            .len(),
+        3
+    );
+
+    kb().args(["compact", "--apply", "plan.json"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("compacted 1 record(s)"));
+    assert_eq!(
+        query_json(&dir, "testing")["domains"][0]["records"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    // The absorbed record is gone, so the plan no longer applies
+    kb().args(["compact", "--apply", "plan.json"])
+        .current_dir(dir.path())
+        .assert()
+        .failure();
+}
+
+#[test]
+fn compact_plan_is_rejected_after_member_changes() {
+    let dir = init_project_with_domain("testing");
+    record_near_duplicates(&dir);
+    let first = get_record_id(&dir, "testing", 0);
+
+    kb().args(["compact", "--plan", "plan.json"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    kb().args([
+        "edit",
+        "testing",
+        &first,
+        "--classification",
+        "foundational",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success();
+    let before = query_json(&dir, "testing")["domains"][0]["records"].clone();
+
+    kb().args(["compact", "--apply", "plan.json"])
+        .current_dir(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("changed after the merge"));
+    assert_eq!(query_json(&dir, "testing")["domains"][0]["records"], before);
+}
+
+#[test]
+fn compact_plan_is_rejected_after_import_updates_member() {
+    let dir = init_project_with_domain("testing");
+    record_near_duplicates(&dir);
+
+    kb().args(["compact", "--plan", "plan.json"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    // The imported copy carries no updated_at of its own
+    let mut record = query_json(&dir, "testing")["domains"][0]["records"][0].clone();
+    record["content"] = "Use anyhow for CLI errors, thiserror for libraries".into();
+    record.as_object_mut().unwrap().remove("updated_at");
+    fs::write(dir.path().join("edit.ndjson"), format!("{record}\n")).unwrap();
+    kb().args(["import", "edit.ndjson", "--domain", "testing"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Updated 1 record(s)"));
+    let before = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    assert!(before[0]["updated_at"].is_string());
+
+    kb().args(["compact", "--apply", "plan.json"])
+        .current_dir(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("changed after the merge"));
+    assert_eq!(query_json(&dir, "testing")["domains"][0]["records"], before);
+}
+
+#[test]
+fn compact_interactive_edits_merged_record() {
+    let dir = init_project_with_domain("testing");
+    record_near_duplicates(&dir);
+
+    kb().args(["compact", "--interactive"])
+        .current_dir(dir.path())
+        .write_stdin("e Use anyhow for all CLI errors\ny\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compacted 1 record(s)"));
+
+    let records = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    assert_eq!(records.as_array().unwrap().len(), 2);
+    assert_eq!(records[0]["content"], "Use anyhow for all CLI errors");
+
+    // Skipping leaves the records alone
+    record_near_duplicates(&dir);
+    let before = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    kb().args(["compact", "-i"])
+        .current_dir(dir.path())
+        .write_stdin("n\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No merges accepted."));
+    assert_eq!(query_json(&dir, "testing")["domains"][0]["records"], before);
+}
+
+// ── Archive ──────────────────────────────────────────────────────────────────
+
+fn archive_list_json(dir: &TempDir) -> serde_json::Value {
+    let output = kb()
+        .args(["--json", "archive", "list"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn delete_archives_record_and_restore_brings_it_back() {
+    let dir = init_project_with_domain("testing");
+    record_convention(&dir, "testing", "Use anyhow in the CLI crate");
+    let id = get_record_id(&dir, "testing", 0);
+
+    kb().args([
+        "delete",
+        "testing",
+        &id,
+        "--reason",
+        "superseded by ADR 7",
+        "--session",
+        "kb-abc123",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success()
+    .stdout(predicate::str::contains("kb archive restore"));
+    assert!(
+        query_json(&dir, "testing")["domains"][0]["records"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+
+    let list = archive_list_json(&dir);
+    assert_eq!(list["count"], 1);
+    let entry = &list["records"][0];
+    assert_eq!(entry["domain"], "testing");
+    assert_eq!(entry["record"]["id"], id.as_str());
+    assert_eq!(entry["removal"]["reason"], "superseded by ADR 7");
+    assert_eq!(entry["removal"]["command"], "delete");
+    assert_eq!(entry["removal"]["session_id"], "kb-abc123");
+    assert!(entry["removal"]["removed_at"].is_string());
+
+    kb().args(["archive", "show", &id[3..7]])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Reason:  superseded by ADR 7"));
+
+    kb().args(["archive", "restore", &id])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Restored record"));
+    let records = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    assert_eq!(records[0]["id"], id.as_str());
+    assert_eq!(archive_list_json(&dir)["count"], 0);
+
+    kb().args(["archive", "restore", &id])
+        .current_dir(dir.path())
+        .assert()
+        .failure();
+}
+
+#[test]
+fn delete_purge_skips_archive() {
+    let dir = init_project_with_domain("testing");
+    record_convention(&dir, "testing", "Temporary");
+    let id = get_record_id(&dir, "testing", 0);
+
+    kb().args(["delete", "testing", &id, "--purge"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    assert_eq!(archive_list_json(&dir)["count"], 0);
+    assert!(!dir.path().join(".kb/archive/testing.jsonl").exists());
+}
+
+#[test]
+fn prune_and_compact_archive_removed_records() {
+    let dir = init_project_with_domain("testing");
+    let old_date = (chrono::Utc::now() - chrono::Duration::days(15))
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    fs::write(
+        dir.path().join(".kb/expertise/testing.jsonl"),
+        format!(
+            r#"{{"type":"convention","content":"Old stale content","classification":"tactical","recorded_at":"{old_date}","id":"mx-stale1"}}"#
+        ) + "\n",
+    )
+    .unwrap();
+    kb().args(["prune"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+
+    record_near_duplicates(&dir);
+    let first = get_record_id(&dir, "testing", 0);
+    let third = get_record_id(&dir, "testing", 2);
+    kb().args(["compact", "--auto", "--similarity", "0.6"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+
+    let list = archive_list_json(&dir);
+    assert_eq!(list["count"], 2);
+    assert_eq!(list["records"][0]["record"]["id"], "mx-stale1");
+    assert_eq!(list["records"][0]["removal"]["command"], "prune");
+    assert!(
+        list["records"][0]["removal"]["reason"]
+            .as_str()
+            .unwrap()
+            .contains("unchanged for 15 days")
+    );
+    assert_eq!(list["records"][1]["record"]["id"], first.as_str());
+    assert_eq!(
+        list["records"][1]["removal"]["reason"],
+        format!("merged into {third}")
+    );
+}
+
+#[test]
+fn search_include_archived() {
+    let dir = init_project_with_domain("testing");
+    record_convention(&dir, "testing", "Retry flaky network calls twice");
+    record_convention(&dir, "testing", "Network timeouts are 30 seconds");
+    let id = get_record_id(&dir, "testing", 0);
+    kb().args(["delete", "testing", &id])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+
+    let output = kb()
+        .args(["--json", "search", "network"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["total"], 1);
+    assert!(json["domains"][0].get("archived").is_none());
+
+    let output = kb()
+        .args(["--json", "search", "network", "--include-archived"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["total"], 1);
+    assert_eq!(json["archived_total"], 1);
+    assert_eq!(
+        json["domains"][0]["archived"][0]["record"]["id"],
+        id.as_str()
+    );
+
+    kb().args(["search", "flaky", "--include-archived"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("### Archived"))
+        .stdout(predicate::str::contains("0 matches found, 1 archived."));
+}
+
+// ── Shelf-life rules ─────────────────────────────────────────────────────────
+
+#[test]
+fn prune_honours_shelf_life_rules_and_explains() {
+    let dir = init_project_with_domain("frontend");
+    kb().args(["add", "billing"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let config_path = dir.path().join(".kb/kb.config.yaml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        format!(
+            "{config}shelf_life_rules:\n- domain: frontend\n  tactical: 7\n- domain: billing\n  observational: 365\n"
+        ),
+    )
+    .unwrap();
+
+    let days_ago = |days: i64| {
+        (chrono::Utc::now() - chrono::Duration::days(days))
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+    };
+    fs::write(
+        dir.path().join(".kb/expertise/frontend.jsonl"),
+        format!(
+            r#"{{"type":"convention","content":"Use the new router","classification":"tactical","recorded_at":"{}","id":"mx-front1"}}"#,
+            days_ago(10)
+        ) + "\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join(".kb/expertise/billing.jsonl"),
+        format!(
+            r#"{{"type":"convention","content":"Invoices are immutable","classification":"observational","recorded_at":"{}","id":"mx-bill1"}}"#,
+            days_ago(200)
+        ) + "\n",
+    )
+    .unwrap();
+
+    let output = kb()
+        .args(["--json", "status"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["domains"][0]["stale"], 1);
+    assert_eq!(json["domains"][1]["stale"], 0);
+
+    kb().args(["prune", "--dry-run", "--explain"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "tactical record unchanged for 10 days, past its 7-day shelf life from rule 1 (domain=frontend)",
+        ))
+        .stdout(predicate::str::contains("mx-bill1").not());
+
+    let output = kb()
+        .args(["--json", "prune", "--explain"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["total_pruned"], 1);
+    let explained = &json["domains"][0]["records"][0];
+    assert_eq!(explained["id"], "mx-front1");
+    assert_eq!(explained["shelf_life_days"], 7);
+    assert_eq!(explained["rule"], 1);
+    assert_eq!(
+        query_json(&dir, "billing")["domains"][0]["records"][0]["id"],
+        "mx-bill1"
+    );
+}
+
+#[test]
+fn editing_a_record_restarts_its_shelf_life() {
+    let dir = init_project_with_domain("test");
+    let old_date = (chrono::Utc::now() - chrono::Duration::days(20))
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    fs::write(
+        dir.path().join(".kb/expertise/test.jsonl"),
+        format!(
+            r#"{{"type":"convention","content":"Use teh new router","classification":"tactical","recorded_at":"{old_date}","id":"mx-aaa111"}}"#
+        ) + "\n",
+    )
+    .unwrap();
+
+    // Even a typo fix counts as a change
+    kb().args([
+        "edit",
+        "test",
+        "mx-aaa111",
+        "--content",
+        "Use the new router",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success();
+    kb().args(["prune"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let records = query_json(&dir, "test")["domains"][0]["records"].clone();
+    assert_eq!(records[0]["id"], "mx-aaa111");
+}
+
+// ── Evidence-driven promotion ────────────────────────────────────────────────
+
+#[test]
+fn promote_auto_reclassifies_by_outcomes_and_feedback() {
+    let dir = init_project_with_domain("testing");
+    let config_path = dir.path().join(".kb/kb.config.yaml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        format!(
+            "{config}promotion:\n  promote:\n  - from: tactical\n    to: foundational\n    min_successes: 3\n  - from: observational\n    to: tactical\n    min_helpful: 2\n  demote:\n  - from: foundational\n    to: tactical\n    min_failures: 2\n"
+        ),
+    )
+    .unwrap();
+
+    let outcomes = |statuses: &[&str]| {
+        statuses
+            .iter()
+            .map(|s| format!(r#"{{"status":"{s}"}}"#))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let line = |id: &str, cls: &str, statuses: &[&str]| {
+        format!(
+            r#"{{"type":"convention","content":"Rule {id}","classification":"{cls}","recorded_at":"2024-01-01T00:00:00.000Z","id":"{id}","outcomes":[{}]}}"#,
+            outcomes(statuses)
+        ) + "\n"
+    };
+    fs::write(
+        dir.path().join(".kb/expertise/testing.jsonl"),
+        line("mx-aaa111", "tactical", &["success", "success", "success"])
+            + &line(
+                "mx-bbb222",
+                "tactical",
+                &["success", "success", "success", "failure"],
+            )
+            + &line(
+                "mx-ccc333",
+                "foundational",
+                &["success", "failure", "failure"],
+            )
+            + &line("mx-ddd444", "observational", &[]),
+    )
+    .unwrap();
+    let now = chrono::Utc::now().to_rfc3339();
+    fs::write(
+        dir.path().join(".kb/access.jsonl"),
+        format!(
+            "{{\"session_id\":\"kb-aaaaaa\",\"timestamp\":\"{now}\",\"tool\":\"feedback\",\"entry_id\":\"mx-ddd444\",\"signal\":\"helpful\"}}\n\
+             {{\"session_id\":\"kb-bbbbbb\",\"timestamp\":\"{now}\",\"tool\":\"feedback\",\"entry_id\":\"ddd444\",\"signal\":\"helpful\"}}\n"
+        ),
+    )
+    .unwrap();
+
+    kb().args(["promote", "--auto", "--dry-run"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "promote tactical -> foundational: 3 successes, 0 failures, 0 helpful, 0 not-helpful (promote rule 1)",
+        ))
+        .stdout(predicate::str::contains("Would promote 2 and would demote 1 record(s)."));
+    assert!(!dir.path().join(".kb/changelog.jsonl").exists());
+
+    let output = kb()
+        .args(["--json", "promote", "--auto", "--session", "kb-cccccc"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let changes = json["changes"].as_array().unwrap();
+    let summary: Vec<(&str, &str, &str)> = changes
+        .iter()
+        .map(|c| {
+            (
+                c["id"].as_str().unwrap(),
+                c["direction"].as_str().unwrap(),
+                c["to"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("mx-aaa111", "promote", "foundational"),
+            ("mx-ccc333", "demote", "tactical"),
+            ("mx-ddd444", "promote", "tactical"),
+        ]
+    );
+    assert_eq!(changes[2]["rule"], 2);
+    assert_eq!(changes[2]["evidence"]["helpful"], 2);
+
+    let records = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    assert_eq!(records[0]["classification"], "foundational");
+    assert_eq!(records[1]["classification"], "tactical");
+    assert_eq!(records[2]["classification"], "tactical");
+    assert!(records[0]["updated_at"].is_string());
+
+    let changelog = fs::read_to_string(dir.path().join(".kb/changelog.jsonl")).unwrap();
+    let entries: Vec<serde_json::Value> = changelog
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[1]["action"], "demote");
+    assert_eq!(entries[1]["session_id"], "kb-cccccc");
+    assert_eq!(
+        entries[1]["diff"]["classification"],
+        serde_json::json!(["foundational", "tactical"])
+    );
+
+    // A second run finds nothing left to change
+    kb().args(["promote", "--auto"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "No records meet the promotion policy.",
+        ));
+    kb().args(["promote"])
+        .current_dir(dir.path())
+        .assert()
+        .failure();
+}
+
+#[test]
+fn demoted_record_is_not_pruned_or_reclassified_on_old_evidence() {
+    let dir = init_project_with_domain("testing");
+    let config_path = dir.path().join(".kb/kb.config.yaml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        format!(
+            "{config}promotion:\n  promote:\n  - from: tactical\n    to: foundational\n    min_successes: 3\n    max_failures: 5\n  demote:\n  - from: foundational\n    to: tactical\n    min_failures: 2\n"
+        ),
+    )
+    .unwrap();
+    let old_date = (chrono::Utc::now() - chrono::Duration::days(60))
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    fs::write(
+        dir.path().join(".kb/expertise/testing.jsonl"),
+        format!(
+            r#"{{"type":"convention","content":"Long-standing rule","classification":"foundational","recorded_at":"{old_date}","id":"mx-aaa111","outcomes":[{{"status":"success"}},{{"status":"success"}},{{"status":"success"}},{{"status":"failure"}},{{"status":"failure"}}]}}"#
+        ) + "\n",
+    )
+    .unwrap();
+
+    kb().args(["promote", "--auto"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("demote foundational -> tactical"));
+
+    // The outcomes behind the demotion do not promote it straight back
+    kb().args(["promote", "--auto"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "No records meet the promotion policy.",
+        ));
+
+    // Recorded 60 days ago, but the demotion restarts its shelf life
+    kb().args(["prune"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let records = query_json(&dir, "testing")["domains"][0]["records"].clone();
+    assert_eq!(records.as_array().unwrap().len(), 1);
+    assert_eq!(records[0]["classification"], "tactical");
+
+    // New outcomes count again
+    for _ in 0..3 {
+        kb().args([
+            "edit",
+            "testing",
+            "mx-aaa111",
+            "--outcome-status",
+            "success",
+        ])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    }
+    kb().args(["promote", "--auto"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "promote tactical -> foundational: 3 successes, 0 failures",
+        ));
+}
+
+#[test]
+fn manual_reclassification_resets_promotion_evidence() {
+    let dir = init_project_with_domain("testing");
+    fs::write(
+        dir.path().join(".kb/expertise/testing.jsonl"),
+        r#"{"type":"convention","content":"Rule","classification":"foundational","recorded_at":"2024-01-01T00:00:00.000Z","id":"mx-aaa111","outcomes":[{"status":"success"},{"status":"success"},{"status":"success"}]}"#
+            .to_string()
+            + "\n",
+    )
+    .unwrap();
+
+    kb().args([
+        "edit",
+        "testing",
+        "mx-aaa111",
+        "--classification",
+        "tactical",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success();
+    let changelog = fs::read_to_string(dir.path().join(".kb/changelog.jsonl")).unwrap();
+    let entry: serde_json::Value = serde_json::from_str(changelog.trim()).unwrap();
+    assert_eq!(
+        entry["diff"]["classification"],
+        serde_json::json!(["foundational", "tactical"])
+    );
+
+    // The three successes predate the demotion, so they do not undo it
+    kb().args(["promote", "--auto"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "No records meet the promotion policy.",
+        ));
+}
+
+// ── Governance ───────────────────────────────────────────────────────────────
+
+fn set_hard_limit(dir: &TempDir, limit: u32) {
+    let config_path = dir.path().join(".kb/kb.config.yaml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(
+        &config_path,
+        config.replace("hard_limit: 200", &format!("hard_limit: {limit}")),
+    )
+    .unwrap();
+}
+
+#[test]
+fn record_refuses_to_exceed_hard_limit_without_force() {
+    let dir = init_project_with_domain("testing");
+    set_hard_limit(&dir, 2);
+    record_convention(&dir, "testing", "First rule");
+    record_convention(&dir, "testing", "Second rule");
+
+    kb().args(["record", "testing", "--type", "convention", "Third rule"])
+        .current_dir(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("hard limit of 2"))
+        .stderr(predicate::str::contains("kb split testing"));
+
+    let output = kb()
+        .args([
+            "--json",
+            "record",
+            "testing",
+            "--type",
+            "convention",
+            "Third rule",
+        ])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["success"], false);
+    assert_eq!(json["command"], "record");
+
+    // A duplicate is skipped rather than refused
+    record_convention(&dir, "testing", "First rule");
+
+    // Batches are all-or-nothing
+    let batch = dir.path().join("batch.json");
+    fs::write(
+        &batch,
+        r#"[{"type":"convention","content":"Batch one"},{"type":"convention","content":"Batch two"}]"#,
+    )
+    .unwrap();
+    kb().args(["record", "testing", "--batch", batch.to_str().unwrap()])
+        .current_dir(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("adding 2 would exceed"));
+    assert_eq!(
+        query_json(&dir, "testing")["domains"][0]["records"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    kb().args([
+        "record",
+        "testing",
+        "--type",
+        "convention",
+        "Third rule",
+        "--force",
+    ])
+    .current_dir(dir.path())
+    .assert()
+    .success();
+    kb().args(["status"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("run `kb split testing`"));
+}
+
+#[test]
+fn split_moves_records_into_sub_domains_keeping_ids_and_links() {
+    let dir = init_project_with_domain("backend");
+    for (content, tags) in [
+        ("Handlers return typed errors", "api"),
+        ("Version every endpoint", "api"),
+        ("Paginate list endpoints", "api"),
+        ("Migrations are forward-only", "db"),
+        ("Never edit applied migrations", "db"),
+        ("Queries go through the pool", "db"),
+        ("Log with structured fields", "logging"),
+        ("Keep secrets out of logs", "logging"),
+    ] {
+        kb().args([
+            "record",
+            "backend",
+            "--type",
+            "convention",
+            content,
+            "--tags",
+            tags,
+        ])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    }
+    let api_id = get_record_id(&dir, "backend", 0);
+    let db_id = get_record_id(&dir, "backend", 3);
+    kb().args(["edit", "backend", &api_id, "--relates-to", &db_id])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+
+    kb().args(["split", "backend"])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "backend-api: 3 records sharing tag \"api\"",
+        ))
+        .stdout(predicate::str::contains("backend: 2 records stay"));
+
+    kb().args(["split", "backend", "--plan", "split.json"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let mut plan: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("split.json")).unwrap()).unwrap();
+    assert_eq!(plan["groups"].as_array().unwrap().len(), 2);
+    plan["groups"][1]["domain"] = "database".into();
+    fs::write(dir.path().join("split.json"), plan.to_string()).unwrap();
+
+    let output = kb()
+        .args(["--json", "split", "--apply", "split.json"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["success"], true);
+    assert_eq!(json["moved"], 6);
+
+    let config = fs::read_to_string(dir.path().join(".kb/kb.config.yaml")).unwrap();
+    assert!(config.contains("backend-api") && config.contains("database"));
+    let api = query_json(&dir, "backend-api")["domains"][0]["records"].clone();
+    assert_eq!(api[0]["id"], api_id.as_str());
+    assert_eq!(api[0]["relates_to"][0], db_id.as_str());
+    let database = query_json(&dir, "database")["domains"][0]["records"].clone();
+    assert_eq!(database[0]["id"], db_id.as_str());
+    let backend = query_json(&dir, "backend")["domains"][0]["records"].clone();
+    assert_eq!(backend.as_array().unwrap().len(), 2);
+
+    // Applying the plan again would move into the domains it just created
+    kb().args(["split", "--apply", "split.json"])
+        .current_dir(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "domain backend-api already exists",
+        ));
+}
+
+#[test]
+fn split_only_moves_into_new_domains_within_the_hard_limit() {
+    let dir = init_project_with_domain("backend");
+    for content in [
+        "Handlers return typed errors",
+        "Version every endpoint",
+        "Paginate list endpoints",
+        "Log with structured fields",
+        "Keep secrets out of logs",
+        "Queries go through the pool",
+        "Migrations are forward-only",
+    ] {
+        kb().args([
+            "record",
+            "backend",
+            "--type",
+            "convention",
+            content,
+            "--tags",
+            if content.contains("endpoint") || content.contains("Handlers") {
+                "api"
+            } else {
+                "misc"
+            },
+        ])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    }
+    kb().args(["add", "backend-api"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+
+    // The proposal steers clear of the existing domain
+    kb().args(["split", "backend", "--plan", "split.json"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let mut plan: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("split.json")).unwrap()).unwrap();
+    assert_eq!(plan["groups"][0]["domain"], "backend-api-2");
+
+    // A plan naming an existing domain is rejected and changes nothing
+    plan["groups"][0]["domain"] = "backend-api".into();
+    fs::write(dir.path().join("split.json"), plan.to_string()).unwrap();
+    kb().args(["split", "--apply", "split.json"])
+        .current_dir(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "domain backend-api already exists",
+        ));
+
+    // So is one that would take a new domain past the hard limit
+    plan["groups"][0]["domain"] = "api".into();
+    fs::write(dir.path().join("split.json"), plan.to_string()).unwrap();
+    set_hard_limit(&dir, 2);
+    kb().args(["split", "--apply", "split.json"])
+        .current_dir(dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("hard limit"));
+
+    let config = fs::read_to_string(dir.path().join(".kb/kb.config.yaml")).unwrap();
+    assert!(!config.contains("- api"));
+    assert!(!dir.path().join(".kb/expertise/api.jsonl").exists());
+    assert!(
+        query_json(&dir, "backend-api")["domains"][0]["records"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+    let backend = query_json(&dir, "backend")["domains"][0]["records"].clone();
+    assert_eq!(backend.as_array().unwrap().len(), 7);
+}
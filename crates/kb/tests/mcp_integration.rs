@@ -209,6 +209,8 @@ async fn record_query_search_delete_lifecycle() {
         .await
         .unwrap();
     assert_eq!(result_json(&r)["success"], true);
+    let archived = std::fs::read_to_string(dir.path().join(".kb/archive/test.jsonl")).unwrap();
+    assert!(archived.contains(&id));
 
     // Verify empty
     let r = client
@@ -483,3 +485,149 @@ async fn access_log_written_after_operations() {
         lines.len()
     );
 }
+
+#[tokio::test]
+async fn prime_budget_drops_records() {
+    let dir = init_project_with_domain("test");
+    for i in 0..5 {
+        std::process::Command::new(kb_bin())
+            .args([
+                "record",
+                "test",
+                "--type",
+                "convention",
+                &format!("Convention {i} with enough words to cost several tokens"),
+            ])
+            .current_dir(dir.path())
+            .status()
+            .unwrap();
+    }
+    let client = create_client(dir.path()).await;
+
+    let prime = |args: serde_json::Value| {
+        let client = client.clone();
+        async move {
+            let r = client.call_tool(tool_call("kb_prime", args)).await.unwrap();
+            let content = result_json(&r)["content"].as_str().unwrap().to_string();
+            serde_json::from_str::<serde_json::Value>(&content).unwrap()
+        }
+    };
+
+    let default = prime(json!({})).await;
+    assert_eq!(default["budget"], 4000);
+    assert_eq!(default["domains"][0]["kept"], 5);
+
+    let all = prime(json!({"no_limit": true})).await;
+    assert_eq!(all["budget"], serde_json::Value::Null);
+    assert_eq!(all["domains"][0]["kept"], 5);
+
+    let budgeted = prime(json!({"budget": 40})).await;
+    let domain = &budgeted["domains"][0];
+    assert_eq!(budgeted["budget"], 40);
+    assert_eq!(domain["entry_count"], 5);
+    assert_eq!(domain["kept"], 2);
+    assert_eq!(domain["dropped"], 3);
+    assert_eq!(domain["records"].as_array().unwrap().len(), 2);
+    assert!(domain["tokens"].as_u64().unwrap() <= 40);
+
+    // A template renders text instead of JSON, within the same budget
+    let r = client
+        .call_tool(tool_call(
+            "kb_prime",
+            json!({"budget": 40, "template": "markdown"}),
+        ))
+        .await
+        .unwrap();
+    let content = result_json(&r)["content"].as_str().unwrap().to_string();
+    assert!(content.contains("## test (2 records"), "{content}");
+    assert!(content.contains("3 more records"), "{content}");
+}
+
+#[tokio::test]
+async fn prime_files_skips_domains_without_matches() {
+    let dir = init_project_with_domain("api");
+    let kb = |args: &[&str]| {
+        std::process::Command::new(kb_bin())
+            .args(args)
+            .current_dir(dir.path())
+            .status()
+            .unwrap()
+    };
+    kb(&["add", "db"]);
+    kb(&[
+        "record",
+        "api",
+        "--type",
+        "pattern",
+        "--name",
+        "Handler layout",
+        "--description",
+        "One module per resource",
+        "--files",
+        "src/api/users.rs",
+    ]);
+    kb(&[
+        "record",
+        "db",
+        "--type",
+        "pattern",
+        "--name",
+        "Migrations",
+        "--description",
+        "Forward-only migrations",
+        "--files",
+        "src/db/migrate.rs",
+    ]);
+    let client = create_client(dir.path()).await;
+
+    let r = client
+        .call_tool(tool_call(
+            "kb_prime",
+            json!({"files": ["src/api/users.rs"]}),
+        ))
+        .await
+        .unwrap();
+    let content = result_json(&r)["content"].as_str().unwrap().to_string();
+    let primed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    let domains = primed["domains"].as_array().unwrap();
+    assert_eq!(domains.len(), 1);
+    assert_eq!(domains[0]["domain"], "api");
+}
+
+#[tokio::test]
+async fn record_respects_hard_limit_unless_forced() {
+    let dir = init_project_with_domain("test");
+    let config_path = dir.path().join(".kb/kb.config.yaml");
+    let config = std::fs::read_to_string(&config_path).unwrap();
+    std::fs::write(
+        &config_path,
+        config.replace("hard_limit: 200", "hard_limit: 1"),
+    )
+    .unwrap();
+    let client = create_client(dir.path()).await;
+
+    let record = |description: &str, force: bool| {
+        let client = client.clone();
+        let args = json!({
+            "domain": "test", "record_type": "convention",
+            "description": description, "force": force
+        });
+        async move { client.call_tool(tool_call("kb_record", args)).await }
+    };
+
+    let r = record("First convention", false).await.unwrap();
+    assert_eq!(result_json(&r)["success"], true);
+
+    match record("Second convention", false).await {
+        Err(e) => assert!(e.to_string().contains("hard limit"), "{e}"),
+        Ok(result) => assert!(
+            result.is_error.unwrap_or(false) && result_text(&result).contains("hard limit"),
+            "recording past the hard limit should fail"
+        ),
+    }
+
+    let r = record("Second convention", true).await.unwrap();
+    assert_eq!(result_json(&r)["success"], true);
+    let records = std::fs::read_to_string(dir.path().join(".kb/expertise/test.jsonl")).unwrap();
+    assert_eq!(records.lines().count(), 2);
+}